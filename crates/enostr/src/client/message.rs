@@ -24,6 +24,27 @@ pub enum ClientMessage {
     Close {
         sub_id: String,
     },
+    /// NIP-45 request for the number of events matching `filters`
+    Count {
+        sub_id: String,
+        filters: Vec<Filter>,
+    },
+    /// NIP-42 signed authentication event
+    Auth(EventClientMessage),
+    /// NIP-77 negentropy sync of the events matching `filter`, with our
+    /// hex encoded initial message
+    NegOpen {
+        sub_id: String,
+        filter: Filter,
+        message: String,
+    },
+    NegMsg {
+        sub_id: String,
+        message: String,
+    },
+    NegClose {
+        sub_id: String,
+    },
     Raw(String),
 }
 
@@ -38,6 +59,12 @@ impl ClientMessage {
         Ok(ClientMessage::Event(EventClientMessage { note_json }))
     }
 
+    pub fn auth(note: &Note) -> Result<Self, Error> {
+        Ok(ClientMessage::Auth(EventClientMessage {
+            note_json: note.json()?,
+        }))
+    }
+
     pub fn req(sub_id: String, filters: Vec<Filter>) -> Self {
         ClientMessage::Req { sub_id, filters }
     }
@@ -46,25 +73,60 @@ impl ClientMessage {
         ClientMessage::Close { sub_id }
     }
 
+    pub fn count(sub_id: String, filters: Vec<Filter>) -> Self {
+        ClientMessage::Count { sub_id, filters }
+    }
+
+    pub fn neg_open(sub_id: String, filter: Filter, message: String) -> Self {
+        ClientMessage::NegOpen {
+            sub_id,
+            filter,
+            message,
+        }
+    }
+
+    pub fn neg_msg(sub_id: String, message: String) -> Self {
+        ClientMessage::NegMsg { sub_id, message }
+    }
+
+    pub fn neg_close(sub_id: String) -> Self {
+        ClientMessage::NegClose { sub_id }
+    }
+
     pub fn to_json(&self) -> Result<String, Error> {
         Ok(match self {
             Self::Event(ecm) => ecm.to_json(),
+            Self::Auth(ecm) => format!("[\"AUTH\", {}]", ecm.note_json),
             Self::Raw(raw) => raw.clone(),
-            Self::Req { sub_id, filters } => {
-                if filters.is_empty() {
-                    format!("[\"REQ\",\"{sub_id}\",{{ }}]")
-                } else if filters.len() == 1 {
-                    let filters_json_str = filters[0].json()?;
-                    format!("[\"REQ\",\"{sub_id}\",{filters_json_str}]")
-                } else {
-                    let filters_json_str: Result<Vec<String>, Error> = filters
-                        .iter()
-                        .map(|f| f.json().map_err(Into::<Error>::into))
-                        .collect();
-                    format!("[\"REQ\",\"{}\",{}]", sub_id, filters_json_str?.join(","))
-                }
-            }
+            Self::Req { sub_id, filters } => filters_message("REQ", sub_id, filters)?,
+            Self::Count { sub_id, filters } => filters_message("COUNT", sub_id, filters)?,
             Self::Close { sub_id } => json!(["CLOSE", sub_id]).to_string(),
+            Self::NegOpen {
+                sub_id,
+                filter,
+                message,
+            } => format!(
+                "[\"NEG-OPEN\",\"{sub_id}\",{},\"{message}\"]",
+                filter.json()?
+            ),
+            Self::NegMsg { sub_id, message } => json!(["NEG-MSG", sub_id, message]).to_string(),
+            Self::NegClose { sub_id } => json!(["NEG-CLOSE", sub_id]).to_string(),
         })
     }
 }
+
+/// REQ and COUNT share the same format: `["<verb>", <sub_id>, <filters>...]`
+fn filters_message(verb: &str, sub_id: &str, filters: &[Filter]) -> Result<String, Error> {
+    Ok(if filters.is_empty() {
+        format!("[\"{verb}\",\"{sub_id}\",{{ }}]")
+    } else if filters.len() == 1 {
+        let filters_json_str = filters[0].json()?;
+        format!("[\"{verb}\",\"{sub_id}\",{filters_json_str}]")
+    } else {
+        let filters_json_str: Result<Vec<String>, Error> = filters
+            .iter()
+            .map(|f| f.json().map_err(Into::<Error>::into))
+            .collect();
+        format!("[\"{verb}\",\"{sub_id}\",{}]", filters_json_str?.join(","))
+    })
+}
@@ -28,6 +28,9 @@ pub enum Error {
     #[error("invalid relay url")]
     InvalidRelayUrl,
 
+    #[error("encryption failed: {0}")]
+    Encryption(String),
+
     // Secp(secp256k1::Error),
     #[error("json error: {0}")]
     Json(#[from] serde_json::Error),
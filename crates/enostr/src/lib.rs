@@ -2,6 +2,8 @@ mod client;
 mod error;
 mod filter;
 mod keypair;
+pub mod nip44;
+pub mod nip59;
 mod note;
 mod profile;
 mod pubkey;
@@ -16,9 +18,18 @@ pub use nostr::SecretKey;
 pub use note::{Note, NoteId};
 pub use profile::ProfileState;
 pub use pubkey::{Pubkey, PubkeyRef};
-pub use relay::message::{RelayEvent, RelayMessage};
+pub use relay::auth::{RelayAuth, RelayAuthState};
+pub use relay::count::CountTotal;
+pub use relay::health::RelayHealth;
+pub use relay::info::{RelayInformation, RelayLimitation};
+pub use relay::limits::{IncomingMessage, RelayLimits};
+pub use relay::message::{
+    ClosedResult, CommandResult, CountResult, MessagePrefix, RelayEvent, RelayMessage,
+};
+pub use relay::mock::MockRelay;
 pub use relay::pool::{PoolEvent, PoolRelay, RelayPool};
 pub use relay::subs_debug::{OwnedRelayEvent, RelayLogEvent, SubsDebug, TransferStats};
+pub use relay::sync::{SyncRequest, SyncSupport};
 pub use relay::{Relay, RelayStatus};
 
 pub type Result<T> = std::result::Result<T, error::Error>;
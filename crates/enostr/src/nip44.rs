@@ -0,0 +1,49 @@
+//! NIP-44 encrypted payloads
+
+use nostr::nips::nip44;
+
+use crate::{Error, Pubkey, SecretKey};
+
+/// Encrypt `content` from `secret_key` to `recipient`
+pub fn encrypt(secret_key: &SecretKey, recipient: &Pubkey, content: &str) -> Result<String, Error> {
+    let recipient =
+        nostr::PublicKey::from_slice(recipient.bytes()).map_err(|_| Error::InvalidPublicKey)?;
+
+    nip44::encrypt(secret_key, &recipient, content, nip44::Version::V2)
+        .map_err(|e| Error::Encryption(e.to_string()))
+}
+
+/// Decrypt a payload that `sender` encrypted to `secret_key`
+pub fn decrypt(secret_key: &SecretKey, sender: &Pubkey, payload: &str) -> Result<String, Error> {
+    let sender =
+        nostr::PublicKey::from_slice(sender.bytes()).map_err(|_| Error::InvalidPublicKey)?;
+
+    nip44::decrypt(secret_key, &sender, payload).map_err(|e| Error::Encryption(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FullKeypair;
+
+    #[test]
+    fn test_roundtrip() {
+        let alice = FullKeypair::generate();
+        let bob = FullKeypair::generate();
+
+        let payload = encrypt(&alice.secret_key, &bob.pubkey, "hello bob").unwrap();
+        assert_ne!(payload, "hello bob");
+
+        let plaintext = decrypt(&bob.secret_key, &alice.pubkey, &payload).unwrap();
+        assert_eq!(plaintext, "hello bob");
+
+        // encrypting to yourself
+        let payload = encrypt(&alice.secret_key, &alice.pubkey, "note to self").unwrap();
+        assert_eq!(
+            decrypt(&alice.secret_key, &alice.pubkey, &payload).unwrap(),
+            "note to self"
+        );
+
+        assert!(decrypt(&bob.secret_key, &bob.pubkey, &payload).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! NIP-59 gift wraps. A rumor, an event that is never signed, is sealed by
+//! its author (kind 13) and the seal is wrapped with a one-time key (kind
+//! 1059). Relays, and anyone else without the recipient's key, only see who
+//! the wrap is for.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nostr::JsonUtil;
+use nostrdb::NoteBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{nip44, Error, FilledKeypair, FullKeypair, Note, Pubkey};
+
+pub const KIND_SEAL: u32 = 13;
+pub const KIND_GIFT_WRAP: u32 = 1059;
+
+/// Seals and wraps are dated up to two days back, so their timestamps don't
+/// give away when the rumor was written
+const MAX_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+
+/// An unsigned event. Without a signature it can't be proven to anyone else
+/// who wrote it, should it leak.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rumor {
+    #[serde(default)]
+    pub id: String,
+    pub pubkey: Pubkey,
+    pub created_at: u64,
+    pub kind: u32,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+}
+
+impl Rumor {
+    pub fn new(
+        pubkey: Pubkey,
+        created_at: u64,
+        kind: u32,
+        tags: Vec<Vec<String>>,
+        content: String,
+    ) -> Self {
+        let id = event_id(&pubkey, created_at, kind, &tags, &content);
+
+        Self {
+            id,
+            pubkey,
+            created_at,
+            kind,
+            tags,
+            content,
+        }
+    }
+
+    /// The values of the rumor's `name` tags
+    pub fn tag_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.tags
+            .iter()
+            .filter(move |tag| tag.first().is_some_and(|n| n == name))
+            .filter_map(|tag| tag.get(1).map(String::as_str))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("rumor json")
+    }
+
+    fn computed_id(&self) -> String {
+        event_id(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        )
+    }
+}
+
+/// The NIP-01 id of an event
+fn event_id(
+    pubkey: &Pubkey,
+    created_at: u64,
+    kind: u32,
+    tags: &[Vec<String>],
+    content: &str,
+) -> String {
+    let serialized = serde_json::to_string(&(0, pubkey.hex(), created_at, kind, tags, content))
+        .expect("event json");
+
+    hex::encode(Sha256::digest(serialized.as_bytes()))
+}
+
+fn tweaked_now() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    now.saturating_sub(rand::random_range(0..MAX_TIMESTAMP_TWEAK))
+}
+
+/// Seal `rumor` with the key of its author and wrap it for `recipient`
+pub fn gift_wrap(
+    rumor: &Rumor,
+    author: FilledKeypair<'_>,
+    recipient: &Pubkey,
+) -> Result<nostrdb::Note<'static>, Error> {
+    if rumor.pubkey != *author.pubkey {
+        return Err(Error::Generic("can't seal someone else's rumor".to_owned()));
+    }
+
+    let sealed = nip44::encrypt(author.secret_key, recipient, &rumor.to_json())?;
+    let seal = NoteBuilder::new()
+        .kind(KIND_SEAL)
+        .content(&sealed)
+        .created_at(tweaked_now())
+        .sign(&author.secret_key.secret_bytes())
+        .build()
+        .ok_or_else(|| Error::Generic("could not build seal".to_owned()))?;
+
+    let wrapper = FullKeypair::generate();
+    let wrapped = nip44::encrypt(&wrapper.secret_key, recipient, &seal.json()?)?;
+
+    NoteBuilder::new()
+        .kind(KIND_GIFT_WRAP)
+        .content(&wrapped)
+        .created_at(tweaked_now())
+        .start_tag()
+        .tag_str("p")
+        .tag_id(recipient.bytes())
+        .sign(&wrapper.secret_key.secret_bytes())
+        .build()
+        .ok_or_else(|| Error::Generic("could not build gift wrap".to_owned()))
+}
+
+/// Open a gift wrap addressed to `recipient`. The seal's signature is
+/// checked, and that whoever signed it wrote the rumor.
+pub fn unwrap(wrap: &nostrdb::Note<'_>, recipient: FilledKeypair<'_>) -> Result<Rumor, Error> {
+    let invalid = |reason: &str| Error::DecodeFailed(reason.to_owned());
+
+    if wrap.kind() != KIND_GIFT_WRAP {
+        return Err(invalid("not a gift wrap"));
+    }
+
+    let wrapper = Pubkey::new(*wrap.pubkey());
+    let seal_json = nip44::decrypt(recipient.secret_key, &wrapper, wrap.content())?;
+
+    nostr::Event::from_json(&seal_json)
+        .map_err(|e| invalid(&e.to_string()))?
+        .verify()
+        .map_err(|_| Error::InvalidSignature)?;
+
+    let seal = Note::from_json(&seal_json)?;
+    if seal.kind != KIND_SEAL as u64 {
+        return Err(invalid("not a seal"));
+    }
+
+    let rumor_json = nip44::decrypt(recipient.secret_key, &seal.pubkey, &seal.content)?;
+    let mut rumor: Rumor = serde_json::from_str(&rumor_json)?;
+
+    if rumor.pubkey != seal.pubkey {
+        return Err(invalid("rumor isn't by the seal's author"));
+    }
+
+    let id = rumor.computed_id();
+    if rumor.id.is_empty() {
+        rumor.id = id;
+    } else if rumor.id != id {
+        return Err(invalid("rumor id doesn't match"));
+    }
+
+    Ok(rumor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rumor(author: &Pubkey, recipient: &Pubkey) -> Rumor {
+        Rumor::new(
+            *author,
+            1_700_000_000,
+            14,
+            vec![vec!["p".to_owned(), recipient.hex()]],
+            "hello bob".to_owned(),
+        )
+    }
+
+    #[test]
+    fn test_gift_wrap_roundtrip() {
+        let alice = FullKeypair::generate();
+        let bob = FullKeypair::generate();
+        let rumor = rumor(&alice.pubkey, &bob.pubkey);
+
+        let wrap = gift_wrap(&rumor, alice.to_filled(), &bob.pubkey).unwrap();
+        assert_eq!(wrap.kind(), KIND_GIFT_WRAP);
+        assert_ne!(wrap.pubkey(), alice.pubkey.bytes());
+        assert!(!wrap.content().contains("hello bob"));
+
+        let p = wrap
+            .tags()
+            .iter()
+            .find(|tag| tag.get_str(0) == Some("p"))
+            .and_then(|tag| tag.get_id(1));
+        assert_eq!(p, Some(bob.pubkey.bytes()));
+
+        let unwrapped = unwrap(&wrap, bob.to_filled()).unwrap();
+        assert_eq!(unwrapped, rumor);
+        assert_eq!(
+            unwrapped.tag_values("p").collect::<Vec<_>>(),
+            [bob.pubkey.hex()]
+        );
+
+        // only bob can open it
+        let carol = FullKeypair::generate();
+        assert!(unwrap(&wrap, carol.to_filled()).is_err());
+        assert!(unwrap(&wrap, alice.to_filled()).is_err());
+    }
+
+    #[test]
+    fn test_gift_wrap_author() {
+        let alice = FullKeypair::generate();
+        let bob = FullKeypair::generate();
+
+        // alice can't pass off a rumor as bob's
+        let forged = rumor(&bob.pubkey, &bob.pubkey);
+        assert!(gift_wrap(&forged, alice.to_filled(), &bob.pubkey).is_err());
+
+        // and a rumor that was changed after its id was computed is refused
+        let mut changed = rumor(&alice.pubkey, &bob.pubkey);
+        changed.content = "hello carol".to_owned();
+        let wrap = gift_wrap(&changed, alice.to_filled(), &bob.pubkey).unwrap();
+        assert!(unwrap(&wrap, bob.to_filled()).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{Error, Pubkey};
 
+use nostr::nips::nip19::{FromBech32, Nip19Event};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::hash::{Hash, Hasher};
@@ -46,6 +47,14 @@ impl NoteId {
 
         Some(NoteId::new(data.try_into().ok()?))
     }
+
+    /// Decode a NIP-19 `nevent`, returning the event id and its relay hints
+    pub fn from_nevent(bech: &str) -> Option<(Self, Vec<String>)> {
+        let event = Nip19Event::from_bech32(bech).ok()?;
+        let relays = event.relays.iter().map(|r| r.to_string()).collect();
+
+        Some((NoteId::new(event.event_id.to_bytes()), relays))
+    }
 }
 
 /// Event is the struct used to represent a Nostr event
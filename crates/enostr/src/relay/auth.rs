@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use nostrdb::{Filter, NoteBuilder};
+use tracing::{debug, error, warn};
+
+use crate::{ClientMessage, FilledKeypair, MessagePrefix};
+
+/// NIP-42 authentication event kind
+pub const KIND_CLIENT_AUTH: u32 = 22242;
+
+/// NIP-42 authentication state of a relay connection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RelayAuthState {
+    /// The relay hasn't asked us to authenticate
+    #[default]
+    None,
+
+    /// The relay sent a challenge we haven't answered yet, usually because
+    /// the selected account can't sign
+    Challenged(String),
+
+    /// Our signed response was sent, waiting for the relay's OK
+    Pending {
+        event_id: String,
+    },
+
+    Authenticated,
+
+    /// The relay rejected our response
+    Failed(String),
+}
+
+/// Tracks the auth handshake of a single relay connection, along with the
+/// REQs sent on it so they can be replayed once we're authenticated. Relays
+/// that require auth usually drop or CLOSE anything sent before that.
+#[derive(Debug, Default)]
+pub struct RelayAuth {
+    state: RelayAuthState,
+    challenge: Option<String>,
+    subs: HashMap<String, Vec<Filter>>,
+}
+
+impl RelayAuth {
+    pub fn state(&self) -> &RelayAuthState {
+        &self.state
+    }
+
+    /// Forget everything about the connection, called on disconnect
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Remember open subscriptions so they can be replayed after auth
+    pub fn track(&mut self, msg: &ClientMessage) {
+        match msg {
+            ClientMessage::Req { sub_id, filters } => {
+                self.subs.insert(sub_id.clone(), filters.clone());
+            }
+            ClientMessage::Close { sub_id } => {
+                self.subs.remove(sub_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// The relay closed a subscription. Ones closed for lack of auth are
+    /// kept so they get replayed, the rest are gone for good.
+    pub fn on_closed(&mut self, sub_id: &str, prefix: Option<MessagePrefix>) {
+        if prefix != Some(MessagePrefix::AuthRequired) {
+            self.subs.remove(sub_id);
+        }
+    }
+
+    pub fn on_challenge(&mut self, challenge: &str) {
+        self.challenge = Some(challenge.to_owned());
+        self.state = RelayAuthState::Challenged(challenge.to_owned());
+    }
+
+    /// Sign a response to the current challenge. Returns `None` when there
+    /// is no challenge waiting for an answer.
+    pub fn respond(&mut self, relay_url: &str, keypair: FilledKeypair) -> Option<ClientMessage> {
+        let RelayAuthState::Challenged(challenge) = self.state.clone() else {
+            return None;
+        };
+
+        let note = NoteBuilder::new()
+            .kind(KIND_CLIENT_AUTH)
+            .content("")
+            .start_tag()
+            .tag_str("relay")
+            .tag_str(relay_url)
+            .start_tag()
+            .tag_str("challenge")
+            .tag_str(&challenge)
+            .sign(&keypair.secret_key.secret_bytes())
+            .build()?;
+
+        let msg = match ClientMessage::auth(&note) {
+            Ok(msg) => msg,
+            Err(err) => {
+                error!("could not serialize auth event for {relay_url}: {err}");
+                return None;
+            }
+        };
+
+        debug!("answering auth challenge from {relay_url}");
+        self.state = RelayAuthState::Pending {
+            event_id: hex::encode(note.id()),
+        };
+
+        Some(msg)
+    }
+
+    /// Handle an OK from the relay. Returns the REQs to replay when it
+    /// accepted our auth event.
+    pub fn on_ok(&mut self, event_id: &str, status: bool, message: &str) -> Vec<ClientMessage> {
+        let RelayAuthState::Pending { event_id: pending } = &self.state else {
+            return vec![];
+        };
+
+        if pending != event_id {
+            return vec![];
+        }
+
+        if !status {
+            warn!("auth rejected: {message}");
+            self.state = RelayAuthState::Failed(message.to_owned());
+            return vec![];
+        }
+
+        self.state = RelayAuthState::Authenticated;
+        self.subs
+            .iter()
+            .map(|(sub_id, filters)| ClientMessage::req(sub_id.clone(), filters.clone()))
+            .collect()
+    }
+
+    /// The last challenge we were given, if any
+    pub fn challenge(&self) -> Option<&str> {
+        self.challenge.as_deref()
+    }
+
+    /// Answer the last challenge again, for example after switching to an
+    /// account that can sign
+    pub fn rechallenge(&mut self) {
+        if let Some(challenge) = &self.challenge {
+            if self.state != RelayAuthState::Authenticated {
+                self.state = RelayAuthState::Challenged(challenge.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FullKeypair;
+
+    const RELAY: &str = "wss://relay.example.com/";
+
+    fn pending_id(auth: &RelayAuth) -> String {
+        match auth.state() {
+            RelayAuthState::Pending { event_id } => event_id.clone(),
+            state => panic!("expected pending auth, got {state:?}"),
+        }
+    }
+
+    #[test]
+    fn test_auth_replays_subs() {
+        let kp = FullKeypair::generate();
+        let mut auth = RelayAuth::default();
+
+        auth.track(&ClientMessage::req(
+            "a".to_owned(),
+            vec![Filter::new().build()],
+        ));
+        auth.track(&ClientMessage::req(
+            "b".to_owned(),
+            vec![Filter::new().build()],
+        ));
+        auth.track(&ClientMessage::req(
+            "c".to_owned(),
+            vec![Filter::new().build()],
+        ));
+        auth.track(&ClientMessage::close("b".to_owned()));
+        auth.on_closed("a", Some(MessagePrefix::AuthRequired));
+        auth.on_closed("c", Some(MessagePrefix::Restricted));
+
+        // nothing to answer yet
+        assert!(auth.respond(RELAY, kp.to_filled()).is_none());
+
+        auth.on_challenge("challenge");
+        assert_eq!(
+            auth.state(),
+            &RelayAuthState::Challenged("challenge".to_owned())
+        );
+
+        let msg = auth.respond(RELAY, kp.to_filled()).expect("auth msg");
+        let json = msg.to_json().expect("json");
+        assert!(json.starts_with("[\"AUTH\", {"));
+        assert!(json.contains("\"kind\":22242"));
+        assert!(json.contains("[\"challenge\",\"challenge\"]"));
+        assert!(json.contains("[\"relay\","));
+
+        let event_id = pending_id(&auth);
+
+        // OKs for other events don't count
+        assert!(auth.on_ok(&"0".repeat(64), true, "").is_empty());
+        assert_eq!(pending_id(&auth), event_id);
+
+        let replay = auth.on_ok(&event_id, true, "");
+        assert_eq!(auth.state(), &RelayAuthState::Authenticated);
+        assert_eq!(replay.len(), 1);
+        assert!(matches!(&replay[0], ClientMessage::Req { sub_id, .. } if sub_id == "a"));
+    }
+
+    #[test]
+    fn test_auth_rejected() {
+        let kp = FullKeypair::generate();
+        let mut auth = RelayAuth::default();
+
+        auth.on_challenge("challenge");
+        auth.respond(RELAY, kp.to_filled()).expect("auth msg");
+        let event_id = pending_id(&auth);
+
+        assert!(auth
+            .on_ok(&event_id, false, "restricted: not a member")
+            .is_empty());
+        assert_eq!(
+            auth.state(),
+            &RelayAuthState::Failed("restricted: not a member".to_owned())
+        );
+
+        // switching accounts lets us answer again
+        auth.rechallenge();
+        assert_eq!(
+            auth.state(),
+            &RelayAuthState::Challenged("challenge".to_owned())
+        );
+
+        auth.reset();
+        assert_eq!(auth.state(), &RelayAuthState::None);
+        assert!(auth.challenge().is_none());
+    }
+}
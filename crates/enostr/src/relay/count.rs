@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::relay::message::CountResult;
+use crate::RelayMessage;
+
+/// Relays that haven't answered a COUNT by then probably don't support
+/// NIP-45 and just ignored it
+const COUNT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RelayCount {
+    Pending,
+    Counted {
+        count: u64,
+        approximate: bool,
+    },
+    /// The relay CLOSED the request or never answered
+    Unsupported,
+}
+
+#[derive(Debug)]
+struct CountQuery {
+    relays: HashMap<String, RelayCount>,
+    sent_at: Instant,
+}
+
+/// The answers of every relay a COUNT was sent to, combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CountTotal {
+    /// The highest count any relay reported. Relays can't tell which
+    /// events the others have, so this is a lower bound.
+    pub count: u64,
+
+    /// At least one relay estimated instead of counting
+    pub approximate: bool,
+
+    /// Relays that answered with a count
+    pub answered: usize,
+
+    /// Relays we're still waiting on
+    pub pending: usize,
+}
+
+impl CountTotal {
+    /// Every relay answered, refused or timed out
+    pub fn is_done(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Whether any relay could count. When this is false once done,
+    /// callers have to count events themselves.
+    pub fn is_supported(&self) -> bool {
+        self.answered > 0
+    }
+}
+
+/// NIP-45 COUNT requests in flight, by subscription id
+#[derive(Debug, Default)]
+pub struct CountQueries {
+    queries: HashMap<String, CountQuery>,
+}
+
+impl CountQueries {
+    pub fn start(&mut self, sub_id: String, relays: Vec<String>, now: Instant) {
+        let relays = relays
+            .into_iter()
+            .map(|relay| (relay, RelayCount::Pending))
+            .collect();
+
+        self.queries.insert(
+            sub_id,
+            CountQuery {
+                relays,
+                sent_at: now,
+            },
+        );
+    }
+
+    /// Record COUNT answers and CLOSED refusals for our queries
+    pub fn handle_message(&mut self, relay: &str, txt: &str) {
+        if self.queries.is_empty()
+            || (!txt.starts_with("[\"COUNT\"") && !txt.starts_with("[\"CLOSED\""))
+        {
+            return;
+        }
+
+        match RelayMessage::from_json(txt) {
+            Ok(RelayMessage::Count(result)) => self.on_count(relay, &result),
+            Ok(RelayMessage::Closed(closed)) => {
+                self.set(relay, closed.sub_id(), RelayCount::Unsupported)
+            }
+            _ => {}
+        }
+    }
+
+    fn on_count(&mut self, relay: &str, result: &CountResult) {
+        let count = RelayCount::Counted {
+            count: result.count(),
+            approximate: result.approximate(),
+        };
+        self.set(relay, result.sub_id(), count);
+    }
+
+    fn set(&mut self, relay: &str, sub_id: &str, count: RelayCount) {
+        if let Some(state) = self
+            .queries
+            .get_mut(sub_id)
+            .and_then(|query| query.relays.get_mut(relay))
+        {
+            *state = count;
+        }
+    }
+
+    pub fn result(&self, sub_id: &str, now: Instant) -> Option<CountTotal> {
+        let query = self.queries.get(sub_id)?;
+        let timed_out = now.saturating_duration_since(query.sent_at) >= COUNT_TIMEOUT;
+
+        let mut total = CountTotal::default();
+        for state in query.relays.values() {
+            match state {
+                RelayCount::Pending if !timed_out => total.pending += 1,
+                RelayCount::Pending | RelayCount::Unsupported => {}
+                RelayCount::Counted { count, approximate } => {
+                    total.answered += 1;
+                    total.count = total.count.max(*count);
+                    total.approximate |= *approximate;
+                }
+            }
+        }
+
+        Some(total)
+    }
+
+    pub fn forget(&mut self, sub_id: &str) {
+        self.queries.remove(sub_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_aggregation() {
+        let now = Instant::now();
+        let mut counts = CountQueries::default();
+        counts.start(
+            "replies".to_owned(),
+            vec![
+                "wss://a/".to_owned(),
+                "wss://b/".to_owned(),
+                "wss://c/".to_owned(),
+            ],
+            now,
+        );
+
+        counts.handle_message("wss://a/", r#"["COUNT","replies",{"count":12}]"#);
+        counts.handle_message(
+            "wss://b/",
+            r#"["COUNT","replies",{"count":30,"approximate":true}]"#,
+        );
+        // not ours
+        counts.handle_message("wss://c/", r#"["COUNT","other",{"count":500}]"#);
+        counts.handle_message("wss://d/", r#"["COUNT","replies",{"count":500}]"#);
+
+        let total = counts.result("replies", now).expect("query");
+        assert_eq!(total.count, 30);
+        assert!(total.approximate);
+        assert_eq!(total.answered, 2);
+        assert!(!total.is_done());
+
+        // relays without NIP-45 support give up after a while
+        let total = counts
+            .result("replies", now + COUNT_TIMEOUT)
+            .expect("query");
+        assert!(total.is_done());
+        assert!(total.is_supported());
+
+        counts.forget("replies");
+        assert!(counts.result("replies", now).is_none());
+    }
+
+    #[test]
+    fn test_count_unsupported() {
+        let now = Instant::now();
+        let mut counts = CountQueries::default();
+        counts.start("reactions".to_owned(), vec!["wss://a/".to_owned()], now);
+
+        counts.handle_message("wss://a/", r#"["CLOSED","reactions","unsupported: COUNT"]"#);
+
+        let total = counts.result("reactions", now).expect("query");
+        assert!(total.is_done());
+        assert!(!total.is_supported());
+    }
+}
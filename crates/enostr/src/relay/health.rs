@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+/// Delay before the first reconnect, doubled after every failed attempt
+const RECONNECT_BASE: Duration = Duration::from_secs(3);
+
+/// Upper bound for the reconnect delay
+const RECONNECT_MAX: Duration = Duration::from_secs(5 * 60);
+
+/// Connections that stay open this long reset the backoff. Anything
+/// shorter counts as a failed attempt, so flapping relays back off too.
+const STABLE_CONNECTION: Duration = Duration::from_secs(30);
+
+/// Failed connection attempts in a row before a relay counts as failing
+const FAILING_AFTER: u32 = 5;
+
+/// Relays rejecting more than this share of what we send count as failing
+const FAILING_ERROR_RATE: f32 = 0.5;
+
+/// Responses we need before trusting the error rate
+const MIN_RESPONSES: u64 = 20;
+
+/// How long to wait before reconnecting after `failures` failed attempts
+/// in a row. Exponential with a cap, and jittered so relays that went
+/// down together don't all get hammered at the same moment.
+pub fn reconnect_delay(failures: u32) -> Duration {
+    backoff(failures, rand::rng().random_range(0.0..=1.0))
+}
+
+/// "Equal jitter" backoff: half of the exponential delay is fixed, the
+/// other half is scaled by `jitter` (0.0 to 1.0)
+fn backoff(failures: u32, jitter: f64) -> Duration {
+    let exp = RECONNECT_BASE.saturating_mul(1 << failures.min(16));
+    let delay = exp.min(RECONNECT_MAX);
+    delay / 2 + (delay / 2).mul_f64(jitter.clamp(0.0, 1.0))
+}
+
+/// Connection health of a single relay: how often connecting fails, how
+/// fast it answers pings and REQs, and how often it refuses what we send
+#[derive(Debug, Default)]
+pub struct RelayHealth {
+    consecutive_failures: u32,
+    connect_failures: u32,
+    connected_at: Option<Instant>,
+
+    ping_sent_at: Option<Instant>,
+    latency: Option<Duration>,
+
+    reqs_sent: HashMap<String, Instant>,
+    eose_time: Option<Duration>,
+
+    responses: u64,
+    errors: u64,
+}
+
+impl RelayHealth {
+    pub fn on_opened(&mut self, now: Instant) {
+        self.connected_at = Some(now);
+    }
+
+    /// The connection closed or failed to open
+    pub fn on_disconnected(&mut self, now: Instant) {
+        if self.is_stable(now) {
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures += 1;
+            self.connect_failures += 1;
+        }
+
+        self.connected_at = None;
+        self.ping_sent_at = None;
+        self.reqs_sent.clear();
+    }
+
+    /// Forget past failures once the connection has been up for a while
+    pub fn settle(&mut self, now: Instant) {
+        if self.is_stable(now) {
+            self.consecutive_failures = 0;
+        }
+    }
+
+    fn is_stable(&self, now: Instant) -> bool {
+        self.uptime(now)
+            .is_some_and(|uptime| uptime >= STABLE_CONNECTION)
+    }
+
+    pub fn on_ping(&mut self, now: Instant) {
+        self.ping_sent_at = Some(now);
+    }
+
+    pub fn on_pong(&mut self, now: Instant) {
+        if let Some(sent) = self.ping_sent_at.take() {
+            self.latency = Some(smooth(self.latency, now.saturating_duration_since(sent)));
+        }
+    }
+
+    pub fn on_req(&mut self, sub_id: &str, now: Instant) {
+        self.reqs_sent.insert(sub_id.to_owned(), now);
+    }
+
+    pub fn on_eose(&mut self, sub_id: &str, now: Instant) {
+        self.responses += 1;
+        if let Some(sent) = self.reqs_sent.remove(sub_id) {
+            self.eose_time = Some(smooth(self.eose_time, now.saturating_duration_since(sent)));
+        }
+    }
+
+    /// An OK for an event we published, or a CLOSED for one of our REQs
+    pub fn on_response(&mut self, sub_id: Option<&str>, accepted: bool) {
+        self.responses += 1;
+        if !accepted {
+            self.errors += 1;
+        }
+        if let Some(sub_id) = sub_id {
+            self.reqs_sent.remove(sub_id);
+        }
+    }
+
+    /// Failed connection attempts since the last stable connection
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Failed connection attempts since the relay was added
+    pub fn connect_failures(&self) -> u32 {
+        self.connect_failures
+    }
+
+    /// How long the current connection has been open
+    pub fn uptime(&self, now: Instant) -> Option<Duration> {
+        self.connected_at
+            .map(|at| now.saturating_duration_since(at))
+    }
+
+    /// Smoothed ping round trip time
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Smoothed time from sending a REQ to its EOSE
+    pub fn eose_time(&self) -> Option<Duration> {
+        self.eose_time
+    }
+
+    /// Share of OKs and REQs the relay refused
+    pub fn error_rate(&self) -> f32 {
+        if self.responses == 0 {
+            0.0
+        } else {
+            self.errors as f32 / self.responses as f32
+        }
+    }
+
+    /// Chronically failing relays keep dropping connections or refusing
+    /// what we send. Callers should avoid relying on them.
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures >= FAILING_AFTER
+            || (self.responses >= MIN_RESPONSES && self.error_rate() > FAILING_ERROR_RATE)
+    }
+}
+
+/// Exponentially weighted moving average, so one slow answer doesn't
+/// dominate
+fn smooth(prev: Option<Duration>, sample: Duration) -> Duration {
+    match prev {
+        Some(prev) => prev.mul_f64(0.8) + sample.mul_f64(0.2),
+        None => sample,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0, 0.0), RECONNECT_BASE / 2);
+        assert_eq!(backoff(0, 1.0), RECONNECT_BASE);
+        assert_eq!(backoff(3, 1.0), RECONNECT_BASE * 8);
+        assert!(backoff(3, 0.5) < backoff(4, 0.5));
+
+        // capped, even for absurd failure counts
+        assert_eq!(backoff(20, 1.0), RECONNECT_MAX);
+        assert_eq!(backoff(u32::MAX, 0.0), RECONNECT_MAX / 2);
+
+        for failures in 0..10 {
+            let delay = reconnect_delay(failures);
+            assert!(delay >= backoff(failures, 0.0));
+            assert!(delay <= backoff(failures, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_flapping_relay_is_failing() {
+        let mut health = RelayHealth::default();
+        let start = Instant::now();
+
+        for i in 0..FAILING_AFTER {
+            let at = start + Duration::from_secs(i as u64);
+            health.on_opened(at);
+            health.on_disconnected(at + Duration::from_secs(1));
+        }
+        assert_eq!(health.consecutive_failures(), FAILING_AFTER);
+        assert!(health.is_failing());
+
+        // a connection that holds resets it
+        let at = start + Duration::from_secs(60);
+        health.on_opened(at);
+        health.settle(at + STABLE_CONNECTION);
+        assert_eq!(health.consecutive_failures(), 0);
+        assert_eq!(health.connect_failures(), FAILING_AFTER);
+        assert!(!health.is_failing());
+    }
+
+    #[test]
+    fn test_latency_and_error_rate() {
+        let mut health = RelayHealth::default();
+        let start = Instant::now();
+
+        health.on_ping(start);
+        health.on_pong(start + Duration::from_millis(100));
+        assert_eq!(health.latency(), Some(Duration::from_millis(100)));
+
+        // unsolicited pongs are ignored
+        health.on_pong(start + Duration::from_secs(10));
+        assert_eq!(health.latency(), Some(Duration::from_millis(100)));
+
+        health.on_req("sub", start);
+        health.on_eose("sub", start + Duration::from_millis(500));
+        assert_eq!(health.eose_time(), Some(Duration::from_millis(500)));
+
+        for _ in 0..MIN_RESPONSES {
+            health.on_response(None, false);
+        }
+        assert!(health.error_rate() > FAILING_ERROR_RATE);
+        assert!(health.is_failing());
+    }
+}
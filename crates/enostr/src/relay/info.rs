@@ -0,0 +1,136 @@
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use url::Url;
+
+use crate::Result;
+
+/// A relay's NIP-11 information document, fetched over HTTP from the relay
+/// url with an `Accept: application/nostr+json` header. Every field is
+/// optional in practice, missing ones are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RelayInformation {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub pubkey: Option<String>,
+    pub contact: Option<String>,
+    pub software: Option<String>,
+    pub version: Option<String>,
+    pub icon: Option<String>,
+
+    #[serde(deserialize_with = "nips")]
+    pub supported_nips: Vec<u32>,
+
+    pub limitation: RelayLimitation,
+}
+
+/// What a relay accepts, from the `limitation` block of its NIP-11 document
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RelayLimitation {
+    pub max_message_length: Option<u64>,
+    pub max_subscriptions: Option<u64>,
+    pub max_filters: Option<u64>,
+    pub max_limit: Option<u64>,
+    pub max_subid_length: Option<u64>,
+    pub max_event_tags: Option<u64>,
+    pub max_content_length: Option<u64>,
+    pub min_pow_difficulty: Option<u64>,
+    pub auth_required: bool,
+    pub payment_required: bool,
+    pub restricted_writes: bool,
+}
+
+impl RelayInformation {
+    pub const CONTENT_TYPE: &'static str = "application/nostr+json";
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Where to fetch the document of `relay_url`: the same url over
+    /// http(s)
+    pub fn document_url(relay_url: &str) -> Option<String> {
+        let mut url = Url::parse(relay_url).ok()?;
+        let scheme = match url.scheme() {
+            "wss" => "https",
+            "ws" => "http",
+            _ => return None,
+        };
+        url.set_scheme(scheme).ok()?;
+        Some(url.to_string())
+    }
+
+    pub fn supports(&self, nip: u32) -> bool {
+        self.supported_nips.contains(&nip)
+    }
+}
+
+/// Some relays list NIPs as strings, or include entries that aren't NIPs
+/// at all. Keep the numbers we can make sense of instead of rejecting the
+/// whole document.
+fn nips<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u32>, D::Error> {
+    let Value::Array(values) = Value::deserialize(deserializer)? else {
+        return Ok(vec![]);
+    };
+
+    Ok(values
+        .iter()
+        .filter_map(|value| match value {
+            Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relay_information() {
+        let info = RelayInformation::from_json(
+            r#"{
+                "name": "damus",
+                "description": "a relay",
+                "supported_nips": [1, "11", 42, "nip-50"],
+                "software": "strfry",
+                "limitation": {
+                    "max_subscriptions": 20,
+                    "max_filters": 10,
+                    "auth_required": false,
+                    "payment_required": true
+                },
+                "fees": {"admission": [{"amount": 1000, "unit": "msats"}]}
+            }"#,
+        )
+        .expect("document");
+
+        assert_eq!(info.name.as_deref(), Some("damus"));
+        assert_eq!(info.supported_nips, vec![1, 11, 42]);
+        assert!(info.supports(42));
+        assert_eq!(info.limitation.max_subscriptions, Some(20));
+        assert_eq!(info.limitation.max_filters, Some(10));
+        assert_eq!(info.limitation.max_limit, None);
+        assert!(info.limitation.payment_required);
+
+        assert_eq!(
+            RelayInformation::from_json("{}").expect("empty"),
+            RelayInformation::default()
+        );
+    }
+
+    #[test]
+    fn test_document_url() {
+        assert_eq!(
+            RelayInformation::document_url("wss://relay.damus.io/").as_deref(),
+            Some("https://relay.damus.io/")
+        );
+        assert_eq!(
+            RelayInformation::document_url("ws://localhost:8080").as_deref(),
+            Some("http://localhost:8080/")
+        );
+        assert_eq!(RelayInformation::document_url("multicast"), None);
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use nostrdb::{Filter, Ndb, Transaction};
+use rand::Rng;
+use serde_json::Value;
+use tracing::{debug, error};
+
+/// How often we announce ourselves to the other peers
+const HELLO_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Peers we haven't heard from in this long are forgotten
+const PEER_TIMEOUT: Duration = Duration::from_secs(3 * 60);
+
+/// Peers wait up to this long before answering a request, so the one that
+/// answers first lets the others skip what it already sent
+const MAX_ANSWER_DELAY: Duration = Duration::from_millis(500);
+
+/// The same request is answered at most once in this window
+const REPEAT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Events seen on the network in this window aren't sent again
+const SEEN_WINDOW: Duration = Duration::from_secs(60);
+
+/// Largest message that fits in a datagram, after the length header
+pub const MAX_MESSAGE_SIZE: usize = 65507 - 4;
+
+/// Most events sent in answer to a single request
+const MAX_EVENTS: i32 = 500;
+
+/// Most event bytes sent in answer to a single request
+const MAX_ANSWER_BYTES: usize = 4 * 1024 * 1024;
+
+/// Datagrams sent per [`LanSync::take_outgoing`], to avoid overflowing
+/// socket buffers
+const SEND_BATCH: usize = 64;
+
+/// Most requests waiting for an answer, from all peers and from one
+const MAX_REQUESTS: usize = 128;
+const MAX_PEER_REQUESTS: usize = 32;
+
+/// Most requests taken from one peer per [`RATE_WINDOW`]. A new peer makes
+/// everyone ask for all their subscriptions at once, so this leaves room.
+const PEER_REQUEST_RATE: u32 = 32;
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Most answer messages waiting to be sent, in all and for one peer
+const MAX_OUTGOING: usize = 4096;
+const MAX_PEER_OUTGOING: usize = 1024;
+
+/// Kinds never sent to peers: gift wrapped DMs (NIP-59) and app data
+/// (NIP-78) are meant for their recipient and app, not for whoever is on
+/// the network
+const PRIVATE_KINDS: [u32; 2] = [1059, 30078];
+
+struct PeerRequest {
+    peer: String,
+    key: String,
+    filters: Vec<Filter>,
+    answer_at: Instant,
+}
+
+/// How many requests a peer made in the current window
+struct RequestRate {
+    since: Instant,
+    count: u32,
+}
+
+struct Outgoing {
+    /// The peer we're answering, none for our own messages
+    peer: Option<String>,
+    msg: String,
+}
+
+/// A small sync protocol between notedeck instances on the same network,
+/// on top of the multicast relay. Peers announce themselves with
+/// `["LAN-HELLO",<peer>]`. Our subscriptions go out as
+/// `["LAN-REQ",<peer>,<filter>...]`, again whenever a new peer shows up,
+/// and peers answer them with `["EVENT",<event>]` messages from their
+/// nostrdb.
+pub struct LanSync {
+    peer_id: String,
+
+    /// Other peers, and when we last heard from them
+    peers: HashMap<String, Instant>,
+    last_hello: Option<Instant>,
+
+    /// Our subscriptions, asked again when a peer joins
+    subscriptions: HashMap<String, Vec<Filter>>,
+
+    /// Requests from peers we still have to answer
+    requests: Vec<PeerRequest>,
+
+    /// Recent requests by peer, to rate limit them
+    rates: HashMap<String, RequestRate>,
+
+    /// When we last answered a request, by its filters json
+    answered: HashMap<String, Instant>,
+
+    /// Ids of the events sent on the network lately, by anyone
+    seen: HashMap<String, Instant>,
+
+    /// Messages waiting to be sent
+    outgoing: VecDeque<Outgoing>,
+
+    /// How many of the outgoing messages answer each peer
+    queued: HashMap<String, usize>,
+}
+
+impl Default for LanSync {
+    fn default() -> Self {
+        Self::new(hex::encode(rand::rng().random::<[u8; 8]>()))
+    }
+}
+
+impl LanSync {
+    pub fn new(peer_id: String) -> Self {
+        Self {
+            peer_id,
+            peers: HashMap::new(),
+            last_hello: None,
+            subscriptions: HashMap::new(),
+            requests: vec![],
+            rates: HashMap::new(),
+            answered: HashMap::new(),
+            seen: HashMap::new(),
+            outgoing: VecDeque::new(),
+            queued: HashMap::new(),
+        }
+    }
+
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    /// The peers we heard from lately
+    pub fn peers(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Whether there are requests to answer or messages to send
+    pub fn is_busy(&self) -> bool {
+        !self.requests.is_empty() || !self.outgoing.is_empty()
+    }
+
+    /// Ask the peers for the events of one of our subscriptions
+    pub fn subscribe(&mut self, sub_id: String, filters: Vec<Filter>) {
+        self.request(&filters);
+        self.subscriptions.insert(sub_id, filters);
+    }
+
+    pub fn unsubscribe(&mut self, sub_id: &str) {
+        self.subscriptions.remove(sub_id);
+    }
+
+    /// An event we're about to broadcast ourselves
+    pub fn on_send_event(&mut self, note_json: &str, now: Instant) {
+        if let Some(id) = serde_json::from_str::<Value>(note_json)
+            .ok()
+            .and_then(|note| note["id"].as_str().map(str::to_owned))
+        {
+            self.seen.insert(id, now);
+        }
+    }
+
+    /// Handle a message received from the network. Returns true for our
+    /// own protocol messages, which aren't meant for the app.
+    pub fn handle_message(&mut self, txt: &str, now: Instant) -> bool {
+        if txt.starts_with("[\"EVENT\"") {
+            if let Some(id) = event_id(txt) {
+                self.seen.insert(id, now);
+            }
+            return false;
+        }
+
+        if !txt.starts_with("[\"LAN-") {
+            return false;
+        }
+
+        let Ok(Value::Array(msg)) = serde_json::from_str::<Value>(txt) else {
+            error!("lan: bad message {txt}");
+            return true;
+        };
+
+        let Some(peer) = msg.get(1).and_then(Value::as_str) else {
+            return true;
+        };
+
+        // multicast loops our own messages back to us
+        if peer == self.peer_id {
+            return true;
+        }
+
+        let is_new = self.peers.insert(peer.to_owned(), now).is_none();
+        if is_new {
+            debug!("lan: peer {peer} joined, asking for our subscriptions");
+            let subscriptions: Vec<Vec<Filter>> = self.subscriptions.values().cloned().collect();
+            for filters in subscriptions {
+                self.request(&filters);
+            }
+            // introduce ourselves too, so they ask us for theirs
+            self.hello(now);
+        }
+
+        if msg[0] == "LAN-REQ" {
+            let peer = peer.to_owned();
+            self.on_request(peer, &msg[2..], now);
+        }
+
+        true
+    }
+
+    fn on_request(&mut self, peer: String, filters: &[Value], now: Instant) {
+        let key = Value::Array(filters.to_vec()).to_string();
+        if self
+            .answered
+            .get(&key)
+            .is_some_and(|at| now.saturating_duration_since(*at) < REPEAT_WINDOW)
+            || self.requests.iter().any(|req| req.key == key)
+        {
+            return;
+        }
+
+        let rate = self.rates.entry(peer.clone()).or_insert(RequestRate {
+            since: now,
+            count: 0,
+        });
+        if now.saturating_duration_since(rate.since) >= RATE_WINDOW {
+            *rate = RequestRate {
+                since: now,
+                count: 0,
+            };
+        }
+        rate.count += 1;
+        if rate.count > PEER_REQUEST_RATE {
+            debug!("lan: {peer} is asking too often, dropping its request");
+            return;
+        }
+
+        let pending = self.requests.iter().filter(|req| req.peer == peer).count();
+        if self.requests.len() >= MAX_REQUESTS || pending >= MAX_PEER_REQUESTS {
+            debug!("lan: too many requests waiting, dropping one from {peer}");
+            return;
+        }
+
+        let filters: Vec<Filter> = filters
+            .iter()
+            .filter_map(|filter| Filter::from_json(&filter.to_string()).ok())
+            .collect();
+        if filters.is_empty() {
+            return;
+        }
+
+        let delay = rand::rng().random_range(0..=MAX_ANSWER_DELAY.as_millis() as u64);
+        self.requests.push(PeerRequest {
+            peer,
+            key,
+            filters,
+            answer_at: now + Duration::from_millis(delay),
+        });
+    }
+
+    /// Answer the requests that are due with events from `ndb`, and
+    /// announce ourselves when it's time
+    pub fn update(&mut self, ndb: &Ndb, now: Instant) {
+        let hello_due = match self.last_hello {
+            Some(at) => now.saturating_duration_since(at) >= HELLO_INTERVAL,
+            None => true,
+        };
+        if hello_due {
+            self.hello(now);
+        }
+
+        self.peers
+            .retain(|_, at| now.saturating_duration_since(*at) < PEER_TIMEOUT);
+        self.seen
+            .retain(|_, at| now.saturating_duration_since(*at) < SEEN_WINDOW);
+        self.answered
+            .retain(|_, at| now.saturating_duration_since(*at) < REPEAT_WINDOW);
+        self.rates
+            .retain(|_, rate| now.saturating_duration_since(rate.since) < RATE_WINDOW);
+
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.requests)
+            .into_iter()
+            .partition(|req| req.answer_at <= now);
+        self.requests = waiting;
+
+        for req in due {
+            self.answer(ndb, &req, now);
+            self.answered.insert(req.key, now);
+        }
+    }
+
+    fn answer(&mut self, ndb: &Ndb, req: &PeerRequest, now: Instant) {
+        let Ok(txn) = Transaction::new(ndb) else {
+            return;
+        };
+
+        let results = match ndb.query(&txn, &req.filters, MAX_EVENTS) {
+            Ok(results) => results,
+            Err(err) => {
+                error!("lan: could not answer request: {err}");
+                return;
+            }
+        };
+
+        let (mut sent, mut bytes) = (0, 0);
+        for result in results {
+            if PRIVATE_KINDS.contains(&result.note.kind()) {
+                continue;
+            }
+
+            let id = hex::encode(result.note.id());
+            if self.seen.contains_key(&id) {
+                continue;
+            }
+
+            let Ok(json) = result.note.json() else {
+                continue;
+            };
+            let msg = format!("[\"EVENT\", {json}]");
+            if msg.len() > MAX_MESSAGE_SIZE {
+                continue;
+            }
+
+            bytes += msg.len();
+            let queued = self.queued.get(&req.peer).copied().unwrap_or(0);
+            if bytes > MAX_ANSWER_BYTES
+                || self.outgoing.len() >= MAX_OUTGOING
+                || queued >= MAX_PEER_OUTGOING
+            {
+                break;
+            }
+
+            self.seen.insert(id, now);
+            self.outgoing.push_back(Outgoing {
+                peer: Some(req.peer.clone()),
+                msg,
+            });
+            *self.queued.entry(req.peer.clone()).or_default() += 1;
+            sent += 1;
+        }
+
+        debug!(
+            "lan: answering a request from {} with {sent} events",
+            req.peer
+        );
+    }
+
+    fn hello(&mut self, now: Instant) {
+        self.last_hello = Some(now);
+        let msg = serde_json::json!(["LAN-HELLO", self.peer_id]).to_string();
+        self.outgoing.push_back(Outgoing { peer: None, msg });
+    }
+
+    fn request(&mut self, filters: &[Filter]) {
+        let mut msg = vec![Value::from("LAN-REQ"), Value::from(self.peer_id.as_str())];
+        for filter in filters {
+            match filter
+                .json()
+                .map(|json| serde_json::from_str::<Value>(&json))
+            {
+                Ok(Ok(filter)) => msg.push(filter),
+                _ => return,
+            }
+        }
+
+        let msg = Value::Array(msg).to_string();
+        if msg.len() > MAX_MESSAGE_SIZE {
+            debug!("lan: request too large to send ({} bytes)", msg.len());
+            return;
+        }
+        self.outgoing.push_back(Outgoing { peer: None, msg });
+    }
+
+    /// The next batch of messages to send
+    pub fn take_outgoing(&mut self) -> Vec<String> {
+        let n = self.outgoing.len().min(SEND_BATCH);
+        let mut batch = Vec::with_capacity(n);
+        for out in self.outgoing.drain(..n) {
+            if let Some(peer) = out.peer {
+                if let Some(queued) = self.queued.get_mut(&peer) {
+                    *queued -= 1;
+                    if *queued == 0 {
+                        self.queued.remove(&peer);
+                    }
+                }
+            }
+            batch.push(out.msg);
+        }
+        batch
+    }
+}
+
+fn event_id(txt: &str) -> Option<String> {
+    let msg: Value = serde_json::from_str(txt).ok()?;
+    // ["EVENT", <event>] from peers, ["EVENT", <sub_id>, <event>] otherwise
+    let event = msg.as_array()?.last()?;
+    event["id"].as_str().map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lan: &mut LanSync) -> Vec<String> {
+        lan.take_outgoing()
+    }
+
+    #[test]
+    fn test_lan_requests() {
+        let now = Instant::now();
+        let mut lan = LanSync::new("aa".to_owned());
+
+        lan.subscribe("sub".to_owned(), vec![Filter::new().kinds([1]).build()]);
+        assert_eq!(texts(&mut lan), vec![r#"["LAN-REQ","aa",{"kinds":[1]}]"#]);
+
+        // our own messages come back, and are ignored
+        assert!(lan.handle_message(r#"["LAN-HELLO","aa"]"#, now));
+        assert_eq!(lan.peers(), 0);
+        assert!(texts(&mut lan).is_empty());
+
+        // a new peer gets our subscriptions and a hello
+        assert!(lan.handle_message(r#"["LAN-HELLO","bb"]"#, now));
+        assert_eq!(lan.peers(), 1);
+        assert_eq!(
+            texts(&mut lan),
+            vec![
+                r#"["LAN-REQ","aa",{"kinds":[1]}]"#.to_owned(),
+                r#"["LAN-HELLO","aa"]"#.to_owned()
+            ]
+        );
+
+        // known peers don't
+        assert!(lan.handle_message(r#"["LAN-HELLO","bb"]"#, now));
+        assert!(texts(&mut lan).is_empty());
+
+        // events are for the app
+        assert!(!lan.handle_message(r#"["EVENT", {"id":"01"}]"#, now));
+        assert!(lan.seen.contains_key("01"));
+    }
+
+    #[test]
+    fn test_lan_request_dedup() {
+        let now = Instant::now();
+        let mut lan = LanSync::new("aa".to_owned());
+
+        let req = r#"["LAN-REQ","bb",{"kinds":[1],"limit":10}]"#;
+        lan.handle_message(req, now);
+        lan.handle_message(req, now);
+        assert_eq!(lan.requests.len(), 1);
+
+        // garbage filters are not worth answering
+        lan.handle_message(r#"["LAN-REQ","cc",1]"#, now);
+        assert_eq!(lan.requests.len(), 1);
+
+        let filters = [serde_json::json!({"kinds":[1],"limit":10})];
+        lan.answered.insert(lan.requests[0].key.clone(), now);
+        lan.requests.clear();
+        lan.on_request("bb".to_owned(), &filters, now + Duration::from_secs(1));
+        assert!(lan.requests.is_empty());
+        lan.on_request("bb".to_owned(), &filters, now + REPEAT_WINDOW);
+        assert_eq!(lan.requests.len(), 1);
+    }
+
+    #[test]
+    fn test_lan_request_limits() {
+        let now = Instant::now();
+        let mut lan = LanSync::new("aa".to_owned());
+        let request = |limit: usize| [serde_json::json!({"kinds":[1],"limit":limit})];
+
+        // one peer can't fill the queue
+        for limit in 0..MAX_PEER_REQUESTS + 1 {
+            lan.on_request("bb".to_owned(), &request(limit), now);
+        }
+        assert_eq!(lan.requests.len(), MAX_PEER_REQUESTS);
+
+        // but all of them can
+        for (i, peer) in ["cc", "dd", "ee", "ff"].into_iter().enumerate() {
+            for limit in 0..MAX_PEER_REQUESTS {
+                let limit = 100 * (i + 1) + limit;
+                lan.on_request(peer.to_owned(), &request(limit), now);
+            }
+        }
+        assert_eq!(lan.requests.len(), MAX_REQUESTS);
+
+        // answered requests don't reset the rate limit
+        lan.requests.clear();
+        lan.on_request("bb".to_owned(), &request(1000), now);
+        assert!(lan.requests.is_empty());
+        lan.on_request("bb".to_owned(), &request(1000), now + RATE_WINDOW);
+        assert_eq!(lan.requests.len(), 1);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::{HashMap, VecDeque};
+
+use nostrdb::Filter;
+use tracing::debug;
+
+use crate::relay::info::RelayLimitation;
+use crate::{ClientMessage, RelayMessage};
+
+/// Keeps the REQs we send to a relay within the limits of its NIP-11
+/// document. REQs with more filters than the relay takes are split over
+/// extra subscriptions named `<sub_id>:1`, `<sub_id>:2`, and so on. What
+/// the relay sends for them is reported for `<sub_id>`, with their EOSEs
+/// reported as one. REQs and NIP-77 NEG-OPENs that would go
+/// over the subscription cap wait until one of ours closes.
+#[derive(Debug, Default)]
+pub struct RelayLimits {
+    limitation: RelayLimitation,
+
+    /// Our open subscriptions, and how many relay subscriptions each one
+    /// was split into
+    open: HashMap<String, usize>,
+
+    /// How many parts of a split subscription sent their EOSE
+    eosed: HashMap<String, usize>,
+
+    /// REQs and NEG-OPENs waiting for a free subscription slot, oldest first
+    queued: VecDeque<ClientMessage>,
+}
+
+impl RelayLimits {
+    pub fn limitation(&self) -> &RelayLimitation {
+        &self.limitation
+    }
+
+    pub fn set_limitation(&mut self, limitation: RelayLimitation) {
+        self.limitation = limitation;
+    }
+
+    /// Forget the subscriptions of a closed connection. Queued REQs are
+    /// dropped too, subscriptions are sent again when the relay reconnects.
+    pub fn reset(&mut self) {
+        self.open.clear();
+        self.eosed.clear();
+        self.queued.clear();
+    }
+
+    /// What to actually send the relay for `msg`, now
+    pub fn outgoing(&mut self, msg: &ClientMessage) -> Vec<ClientMessage> {
+        match msg {
+            ClientMessage::Req { sub_id, filters } => {
+                self.unqueue(sub_id);
+
+                let replaced = self.open.get(sub_id).copied().unwrap_or(0);
+                if !self.fits(self.in_use() - replaced, filters.len()) {
+                    debug!("queueing {sub_id}, {} subscriptions open", self.in_use());
+                    self.queued.push_back(msg.clone());
+                    return if replaced > 0 {
+                        self.close(sub_id)
+                    } else {
+                        vec![]
+                    };
+                }
+
+                self.req(sub_id, filters)
+            }
+
+            // a negentropy sync holds a subscription on the relay too
+            ClientMessage::NegOpen { sub_id, .. } => {
+                self.unqueue(sub_id);
+
+                let replaced = self.open.get(sub_id).copied().unwrap_or(0);
+                if !self.fits(self.in_use() - replaced, 1) {
+                    debug!(
+                        "queueing sync {sub_id}, {} subscriptions open",
+                        self.in_use()
+                    );
+                    self.queued.push_back(msg.clone());
+                    return vec![];
+                }
+
+                self.open.insert(sub_id.clone(), 1);
+                vec![msg.clone()]
+            }
+
+            ClientMessage::Close { sub_id } => {
+                self.unqueue(sub_id);
+                let mut msgs = self.close(sub_id);
+                msgs.extend(self.release());
+                msgs
+            }
+
+            ClientMessage::NegClose { sub_id } => {
+                self.unqueue(sub_id);
+                self.open.remove(sub_id);
+                let mut msgs = vec![msg.clone()];
+                msgs.extend(self.release());
+                msgs
+            }
+
+            _ => vec![msg.clone()],
+        }
+    }
+
+    /// The relay closed one of our subscriptions. Returns what to send in
+    /// turn: CLOSEs for the other parts of a split subscription, and the
+    /// queued REQs that fit now.
+    pub fn on_closed(&mut self, sub_id: &str) -> Vec<ClientMessage> {
+        let Some(base) = self.base_id(sub_id) else {
+            return vec![];
+        };
+
+        let mut msgs = self.close(base);
+        msgs.retain(|msg| !matches!(msg, ClientMessage::Close { sub_id: id } if id == sub_id));
+        msgs.extend(self.release());
+        msgs
+    }
+
+    /// What the app gets to see of a message from the relay. Messages for
+    /// the parts of a split subscription, whatever their type, are
+    /// rewritten for the subscription we asked for. Call it before
+    /// [`RelayLimits::on_closed`], which forgets the parts.
+    pub fn incoming(&mut self, txt: &str) -> IncomingMessage {
+        if !self.open.values().any(|parts| *parts > 1) {
+            return IncomingMessage::Unchanged;
+        }
+
+        let Ok(msg) = RelayMessage::from_json(txt) else {
+            return IncomingMessage::Unchanged;
+        };
+        let Some(sub_id) = msg.sub_id() else {
+            return IncomingMessage::Unchanged;
+        };
+
+        let base = if let RelayMessage::Eose(_) = msg {
+            let Some(base) = self.on_eose(sub_id) else {
+                return IncomingMessage::Held;
+            };
+            base
+        } else {
+            let Some(base) = self.base_id(sub_id) else {
+                return IncomingMessage::Unchanged;
+            };
+            base.to_owned()
+        };
+
+        if base == sub_id {
+            return IncomingMessage::Unchanged;
+        }
+
+        // the sub id is the first string after the type, and ours never
+        // need escaping
+        IncomingMessage::Rewritten(txt.replacen(
+            &format!("\"{sub_id}\""),
+            &format!("\"{base}\""),
+            1,
+        ))
+    }
+
+    /// The relay sent an EOSE for `sub_id`. Returns the subscription to
+    /// report it for: the one we asked for once every part of a split
+    /// subscription is done, and None for the parts before that.
+    pub fn on_eose(&mut self, sub_id: &str) -> Option<String> {
+        let Some(base) = self.base_id(sub_id) else {
+            return Some(sub_id.to_owned());
+        };
+
+        let parts = self.open.get(base).copied().unwrap_or(1);
+        if parts <= 1 {
+            return Some(sub_id.to_owned());
+        }
+
+        let eosed = self.eosed.entry(base.to_owned()).or_default();
+        *eosed += 1;
+        (*eosed == parts).then(|| base.to_owned())
+    }
+
+    /// The open subscription `sub_id` is, or is a part of
+    fn base_id<'a>(&self, sub_id: &'a str) -> Option<&'a str> {
+        if self.open.contains_key(sub_id) {
+            return Some(sub_id);
+        }
+
+        match sub_id.rsplit_once(':') {
+            Some((base, part)) if part.parse::<usize>().is_ok() && self.open.contains_key(base) => {
+                Some(base)
+            }
+            _ => None,
+        }
+    }
+
+    fn unqueue(&mut self, sub_id: &str) {
+        self.queued
+            .retain(|queued| queued_sub_id(queued) != Some(sub_id));
+    }
+
+    fn in_use(&self) -> usize {
+        self.open.values().sum()
+    }
+
+    /// Whether a REQ with `num_filters` filters fits next to `in_use`
+    /// subscriptions. A REQ that could never fit goes out once nothing
+    /// else is open, the relay can still refuse it.
+    fn fits(&self, in_use: usize, num_filters: usize) -> bool {
+        match self.limitation.max_subscriptions {
+            Some(max) => in_use == 0 || (in_use + self.chunks(num_filters)) as u64 <= max,
+            None => true,
+        }
+    }
+
+    fn chunks(&self, num_filters: usize) -> usize {
+        match self.limitation.max_filters {
+            Some(max) if max > 0 => num_filters.div_ceil(max as usize).max(1),
+            _ => 1,
+        }
+    }
+
+    fn req(&mut self, sub_id: &str, filters: &[Filter]) -> Vec<ClientMessage> {
+        let chunk_size = match self.limitation.max_filters {
+            Some(max) if max > 0 => max as usize,
+            _ => filters.len().max(1),
+        };
+
+        let mut msgs: Vec<ClientMessage> = if filters.is_empty() {
+            vec![ClientMessage::req(sub_id.to_owned(), vec![])]
+        } else {
+            filters
+                .chunks(chunk_size)
+                .enumerate()
+                .map(|(i, chunk)| ClientMessage::req(part_id(sub_id, i), chunk.to_vec()))
+                .collect()
+        };
+
+        // a replaced REQ that was split further than this one
+        self.eosed.remove(sub_id);
+        let before = self.open.insert(sub_id.to_owned(), msgs.len());
+        for i in msgs.len()..before.unwrap_or(0) {
+            msgs.push(ClientMessage::close(part_id(sub_id, i)));
+        }
+
+        msgs
+    }
+
+    fn close(&mut self, sub_id: &str) -> Vec<ClientMessage> {
+        self.eosed.remove(sub_id);
+        let parts = self.open.remove(sub_id).unwrap_or(1);
+        (0..parts)
+            .map(|i| ClientMessage::close(part_id(sub_id, i)))
+            .collect()
+    }
+
+    /// Send queued REQs and NEG-OPENs while they fit
+    fn release(&mut self) -> Vec<ClientMessage> {
+        let mut msgs = vec![];
+        while let Some(queued) = self.queued.front() {
+            let num_filters = match queued {
+                ClientMessage::Req { filters, .. } => filters.len(),
+                _ => 1,
+            };
+            if !self.fits(self.in_use(), num_filters) {
+                break;
+            }
+
+            match self.queued.pop_front() {
+                Some(ClientMessage::Req { sub_id, filters }) => {
+                    msgs.extend(self.req(&sub_id, &filters));
+                }
+                Some(msg @ ClientMessage::NegOpen { .. }) => {
+                    if let Some(sub_id) = queued_sub_id(&msg) {
+                        self.open.insert(sub_id.to_owned(), 1);
+                    }
+                    msgs.push(msg);
+                }
+                _ => {}
+            }
+        }
+        msgs
+    }
+}
+
+/// A relay message as the app gets to see it, see [`RelayLimits::incoming`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum IncomingMessage {
+    Unchanged,
+    /// The EOSE of one part of a split subscription, more are coming
+    Held,
+    /// The message for a part of a split subscription, for the whole one
+    Rewritten(String),
+}
+
+fn queued_sub_id(msg: &ClientMessage) -> Option<&str> {
+    match msg {
+        ClientMessage::Req { sub_id, .. } | ClientMessage::NegOpen { sub_id, .. } => Some(sub_id),
+        _ => None,
+    }
+}
+
+fn part_id(sub_id: &str, part: usize) -> String {
+    if part == 0 {
+        sub_id.to_owned()
+    } else {
+        format!("{sub_id}:{part}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(n: u64) -> Vec<Filter> {
+        (0..n)
+            .map(|kind| Filter::new().kinds([kind]).build())
+            .collect()
+    }
+
+    fn sub_ids(msgs: &[ClientMessage]) -> Vec<String> {
+        msgs.iter()
+            .map(|msg| match msg {
+                ClientMessage::Req { sub_id, filters } => format!("REQ {sub_id} {}", filters.len()),
+                ClientMessage::Close { sub_id } => format!("CLOSE {sub_id}"),
+                ClientMessage::NegOpen { sub_id, .. } => format!("NEG-OPEN {sub_id}"),
+                ClientMessage::NegClose { sub_id } => format!("NEG-CLOSE {sub_id}"),
+                _ => "other".to_owned(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_filters() {
+        let mut limits = RelayLimits::default();
+        limits.set_limitation(RelayLimitation {
+            max_filters: Some(2),
+            ..Default::default()
+        });
+
+        let msgs = limits.outgoing(&ClientMessage::req("a".to_owned(), filters(5)));
+        assert_eq!(sub_ids(&msgs), vec!["REQ a 2", "REQ a:1 2", "REQ a:2 1"]);
+
+        // replacing it with fewer filters closes the extra parts
+        let msgs = limits.outgoing(&ClientMessage::req("a".to_owned(), filters(2)));
+        assert_eq!(sub_ids(&msgs), vec!["REQ a 2", "CLOSE a:1", "CLOSE a:2"]);
+
+        let msgs = limits.outgoing(&ClientMessage::close("a".to_owned()));
+        assert_eq!(sub_ids(&msgs), vec!["CLOSE a"]);
+    }
+
+    #[test]
+    fn test_subscription_cap() {
+        let mut limits = RelayLimits::default();
+        limits.set_limitation(RelayLimitation {
+            max_subscriptions: Some(2),
+            ..Default::default()
+        });
+
+        limits.outgoing(&ClientMessage::req("a".to_owned(), filters(1)));
+        limits.outgoing(&ClientMessage::req("b".to_owned(), filters(1)));
+        assert!(limits
+            .outgoing(&ClientMessage::req("c".to_owned(), filters(1)))
+            .is_empty());
+
+        // updating an open subscription doesn't need a new slot
+        let msgs = limits.outgoing(&ClientMessage::req("b".to_owned(), filters(3)));
+        assert_eq!(sub_ids(&msgs), vec!["REQ b 3"]);
+
+        let msgs = limits.outgoing(&ClientMessage::close("a".to_owned()));
+        assert_eq!(sub_ids(&msgs), vec!["CLOSE a", "REQ c 1"]);
+
+        // the relay closing one frees a slot too
+        limits.outgoing(&ClientMessage::req("d".to_owned(), filters(1)));
+        let msgs = limits.on_closed("b");
+        assert_eq!(sub_ids(&msgs), vec!["REQ d 1"]);
+    }
+
+    #[test]
+    fn test_split_eose() {
+        let mut limits = RelayLimits::default();
+        limits.set_limitation(RelayLimitation {
+            max_filters: Some(2),
+            ..Default::default()
+        });
+
+        limits.outgoing(&ClientMessage::req("a".to_owned(), filters(5)));
+
+        // reported once, for the subscription we asked for, when every
+        // part is done
+        assert_eq!(limits.on_eose("a"), None);
+        assert_eq!(limits.on_eose("a:2"), None);
+        assert_eq!(limits.on_eose("a:1"), Some("a".to_owned()));
+
+        // replacing it starts over
+        limits.outgoing(&ClientMessage::req("a".to_owned(), filters(3)));
+        assert_eq!(limits.on_eose("a:1"), None);
+        assert_eq!(limits.on_eose("a"), Some("a".to_owned()));
+
+        // subscriptions that weren't split are left alone
+        limits.outgoing(&ClientMessage::req("b".to_owned(), filters(1)));
+        assert_eq!(limits.on_eose("b"), Some("b".to_owned()));
+        assert_eq!(limits.on_eose("unknown"), Some("unknown".to_owned()));
+    }
+
+    #[test]
+    fn test_sync_subscription_cap() {
+        let mut limits = RelayLimits::default();
+        limits.set_limitation(RelayLimitation {
+            max_subscriptions: Some(1),
+            ..Default::default()
+        });
+
+        let neg_open = |sub_id: &str| {
+            ClientMessage::neg_open(
+                sub_id.to_owned(),
+                Filter::new().kinds([1]).build(),
+                "".to_owned(),
+            )
+        };
+
+        let msgs = limits.outgoing(&neg_open("sync"));
+        assert_eq!(sub_ids(&msgs), vec!["NEG-OPEN sync"]);
+
+        // the sync holds the only slot
+        assert!(limits
+            .outgoing(&ClientMessage::req("a".to_owned(), filters(1)))
+            .is_empty());
+        assert!(limits.outgoing(&neg_open("other")).is_empty());
+
+        let msgs = limits.outgoing(&ClientMessage::neg_close("sync".to_owned()));
+        assert_eq!(sub_ids(&msgs), vec!["NEG-CLOSE sync", "REQ a 1"]);
+
+        let msgs = limits.outgoing(&ClientMessage::close("a".to_owned()));
+        assert_eq!(sub_ids(&msgs), vec!["CLOSE a", "NEG-OPEN other"]);
+    }
+
+    #[test]
+    fn test_split_incoming() {
+        let mut limits = RelayLimits::default();
+        limits.set_limitation(RelayLimitation {
+            max_filters: Some(2),
+            ..Default::default()
+        });
+
+        // nothing is split yet
+        let event = r#"["EVENT","a:1",{"kind":1}]"#;
+        assert_eq!(limits.incoming(event), IncomingMessage::Unchanged);
+
+        limits.outgoing(&ClientMessage::req("a".to_owned(), filters(5)));
+
+        assert_eq!(
+            limits.incoming(event),
+            IncomingMessage::Rewritten(r#"["EVENT","a",{"kind":1}]"#.to_owned())
+        );
+        assert_eq!(
+            limits.incoming(r#"["EVENT","a",{"kind":1}]"#),
+            IncomingMessage::Unchanged
+        );
+        assert_eq!(
+            limits.incoming(r#"["COUNT","a:2",{"count":3}]"#),
+            IncomingMessage::Rewritten(r#"["COUNT","a",{"count":3}]"#.to_owned())
+        );
+
+        assert_eq!(limits.incoming(r#"["EOSE","a:1"]"#), IncomingMessage::Held);
+        assert_eq!(limits.incoming(r#"["EOSE","a"]"#), IncomingMessage::Held);
+        assert_eq!(
+            limits.incoming(r#"["EOSE","a:2"]"#),
+            IncomingMessage::Rewritten(r#"["EOSE","a"]"#.to_owned())
+        );
+
+        let closed = r#"["CLOSED","a:2","error: shutting down"]"#;
+        assert_eq!(
+            limits.incoming(closed),
+            IncomingMessage::Rewritten(r#"["CLOSED","a","error: shutting down"]"#.to_owned())
+        );
+        let msgs = limits.on_closed("a:2");
+        assert_eq!(sub_ids(&msgs), vec!["CLOSE a", "CLOSE a:1"]);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{Error, Result};
 use ewebsock::{WsEvent, WsMessage};
+use serde::Deserialize;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct CommandResult<'a> {
@@ -8,16 +9,137 @@ pub struct CommandResult<'a> {
     message: &'a str,
 }
 
+impl<'a> CommandResult<'a> {
+    pub fn event_id(&self) -> &'a str {
+        self.event_id
+    }
+
+    pub fn status(&self) -> bool {
+        self.status
+    }
+
+    pub fn message(&self) -> &'a str {
+        self.message
+    }
+
+    pub fn prefix(&self) -> Option<MessagePrefix> {
+        MessagePrefix::parse(self.message)
+    }
+}
+
 pub fn calculate_command_result_size(result: &CommandResult) -> usize {
     std::mem::size_of_val(result) + result.event_id.len() + result.message.len()
 }
 
+/// A relay ended or refused one of our subscriptions
+#[derive(Debug, Eq, PartialEq)]
+pub struct ClosedResult<'a> {
+    sub_id: &'a str,
+    message: &'a str,
+}
+
+impl<'a> ClosedResult<'a> {
+    pub fn sub_id(&self) -> &'a str {
+        self.sub_id
+    }
+
+    pub fn message(&self) -> &'a str {
+        self.message
+    }
+
+    pub fn prefix(&self) -> Option<MessagePrefix> {
+        MessagePrefix::parse(self.message)
+    }
+}
+
+pub fn calculate_closed_result_size(result: &ClosedResult) -> usize {
+    std::mem::size_of_val(result) + result.sub_id.len() + result.message.len()
+}
+
+/// NIP-45 answer to a COUNT request
+#[derive(Debug, Eq, PartialEq)]
+pub struct CountResult<'a> {
+    sub_id: &'a str,
+    count: u64,
+    approximate: bool,
+}
+
+impl<'a> CountResult<'a> {
+    pub fn sub_id(&self) -> &'a str {
+        self.sub_id
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The relay estimated the count instead of counting every event
+    pub fn approximate(&self) -> bool {
+        self.approximate
+    }
+}
+
+#[derive(Deserialize)]
+struct CountPayload {
+    count: u64,
+    #[serde(default)]
+    approximate: bool,
+}
+
+/// Machine-readable prefix of OK and CLOSED messages, as in
+/// `"rate-limited: slow down"`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum MessagePrefix {
+    Duplicate,
+    Pow,
+    Blocked,
+    RateLimited,
+    Invalid,
+    Restricted,
+    Mute,
+    Error,
+    /// NIP-42, we need to authenticate first
+    AuthRequired,
+}
+
+impl MessagePrefix {
+    pub fn parse(message: &str) -> Option<Self> {
+        let (prefix, _) = message.split_once(':')?;
+        Some(match prefix {
+            "duplicate" => Self::Duplicate,
+            "pow" => Self::Pow,
+            "blocked" => Self::Blocked,
+            "rate-limited" => Self::RateLimited,
+            "invalid" => Self::Invalid,
+            "restricted" => Self::Restricted,
+            "mute" => Self::Mute,
+            "error" => Self::Error,
+            "auth-required" => Self::AuthRequired,
+            _ => return None,
+        })
+    }
+
+    /// Whether sending the same request again later could succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::RateLimited | Self::Error | Self::AuthRequired)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum RelayMessage<'a> {
     OK(CommandResult<'a>),
     Eose(&'a str),
     Event(&'a str, &'a str),
     Notice(&'a str),
+    /// NIP-42 authentication challenge
+    Auth(&'a str),
+    Closed(ClosedResult<'a>),
+    /// NIP-45 event count
+    Count(CountResult<'a>),
+    /// NIP-77 negentropy message: subscription id and hex encoded message
+    NegMsg(&'a str, &'a str),
+    /// NIP-77 negentropy sync refused or aborted
+    NegErr(ClosedResult<'a>),
 }
 
 #[derive(Debug)]
@@ -61,6 +183,30 @@ impl<'a> RelayMessage<'a> {
         RelayMessage::Notice(msg)
     }
 
+    pub fn auth(challenge: &'a str) -> Self {
+        RelayMessage::Auth(challenge)
+    }
+
+    pub fn closed(sub_id: &'a str, message: &'a str) -> Self {
+        RelayMessage::Closed(ClosedResult { sub_id, message })
+    }
+
+    pub fn count(sub_id: &'a str, count: u64, approximate: bool) -> Self {
+        RelayMessage::Count(CountResult {
+            sub_id,
+            count,
+            approximate,
+        })
+    }
+
+    pub fn neg_msg(sub_id: &'a str, message: &'a str) -> Self {
+        RelayMessage::NegMsg(sub_id, message)
+    }
+
+    pub fn neg_err(sub_id: &'a str, message: &'a str) -> Self {
+        RelayMessage::NegErr(ClosedResult { sub_id, message })
+    }
+
     pub fn ok(event_id: &'a str, status: bool, message: &'a str) -> Self {
         RelayMessage::OK(CommandResult {
             event_id,
@@ -73,6 +219,16 @@ impl<'a> RelayMessage<'a> {
         RelayMessage::Event(sub_id, ev)
     }
 
+    /// The subscription the message is about, for the types that have one
+    pub fn sub_id(&self) -> Option<&'a str> {
+        match self {
+            Self::Eose(sub_id) | Self::Event(sub_id, _) | Self::NegMsg(sub_id, _) => Some(sub_id),
+            Self::Closed(closed) | Self::NegErr(closed) => Some(closed.sub_id()),
+            Self::Count(count) => Some(count.sub_id()),
+            Self::OK(_) | Self::Notice(_) | Self::Auth(_) => None,
+        }
+    }
+
     pub fn from_json(msg: &'a str) -> Result<RelayMessage<'a>> {
         if msg.is_empty() {
             return Err(Error::Empty);
@@ -135,6 +291,50 @@ impl<'a> RelayMessage<'a> {
             ));
         }
 
+        // AUTH (NIP-42)
+        // Relay response format: ["AUTH", <challenge>]
+        if &msg[0..=7] == "[\"AUTH\"," {
+            let rest = msg[8..].trim_start();
+            if let Some(end) = rest.rfind(']') {
+                let challenge = rest[..end].trim();
+                if challenge.len() >= 2 && challenge.starts_with('"') && challenge.ends_with('"') {
+                    return Ok(Self::auth(&challenge[1..challenge.len() - 1]));
+                }
+            }
+            return Err(Error::DecodeFailed("Invalid AUTH format".into()));
+        }
+
+        // CLOSED
+        // Relay response format: ["CLOSED", <subscription_id>, <message>]
+        if msg.starts_with("[\"CLOSED\",") {
+            return Self::parse_sub_message(&msg[10..])
+                .map(|(sub_id, message)| Self::closed(sub_id, message))
+                .ok_or_else(|| Error::DecodeFailed("Invalid CLOSED format".into()));
+        }
+
+        // COUNT (NIP-45)
+        // Relay response format: ["COUNT", <subscription_id>, {"count": <integer>}]
+        if msg.starts_with("[\"COUNT\",") {
+            return Self::parse_count(&msg[9..])
+                .ok_or_else(|| Error::DecodeFailed("Invalid COUNT format".into()));
+        }
+
+        // NEG-MSG and NEG-ERR (NIP-77)
+        // Relay response format: ["NEG-MSG", <subscription_id>, <hex message>]
+        // or ["NEG-ERR", <subscription_id>, <reason>]
+        if msg.starts_with("[\"NEG-MSG\",") {
+            return Self::parse_sub_message(&msg[10..])
+                .filter(|(_, message)| !message.is_empty())
+                .map(|(sub_id, message)| Self::neg_msg(sub_id, message))
+                .ok_or_else(|| Error::DecodeFailed("Invalid NEG-MSG format".into()));
+        }
+
+        if msg.starts_with("[\"NEG-ERR\",") {
+            return Self::parse_sub_message(&msg[10..])
+                .map(|(sub_id, message)| Self::neg_err(sub_id, message))
+                .ok_or_else(|| Error::DecodeFailed("Invalid NEG-ERR format".into()));
+        }
+
         // OK (NIP-20)
         // Relay response format: ["OK",<event_id>, <true|false>, <message>]
         if &msg[0..=5] == "[\"OK\"," && msg.len() >= 78 {
@@ -156,6 +356,42 @@ impl<'a> RelayMessage<'a> {
             "unrecognized message type: '{msg}'"
         )))
     }
+
+    /// Parse the `<subscription_id>, <message>]` that follows the type of
+    /// CLOSED and NEG- messages
+    fn parse_sub_message(rest: &'a str) -> Option<(&'a str, &'a str)> {
+        let rest = rest.trim_start().strip_prefix('"')?;
+        let sub_id_end = rest.find('"')?;
+        let sub_id = &rest[..sub_id_end];
+
+        let rest = rest[sub_id_end + 1..].trim().strip_suffix(']')?.trim();
+
+        let message = if rest.is_empty() {
+            // some relays leave out the message
+            rest
+        } else {
+            let message = rest.strip_prefix(',')?.trim();
+            message.strip_prefix('"')?.strip_suffix('"')?
+        };
+
+        Some((sub_id, message))
+    }
+
+    /// Parse what follows `["COUNT",`
+    fn parse_count(rest: &'a str) -> Option<RelayMessage<'a>> {
+        let rest = rest.trim_start().strip_prefix('"')?;
+        let sub_id_end = rest.find('"')?;
+        let sub_id = &rest[..sub_id_end];
+
+        let payload = rest[sub_id_end + 1..]
+            .trim()
+            .strip_prefix(',')?
+            .trim()
+            .strip_suffix(']')?;
+        let payload: CountPayload = serde_json::from_str(payload).ok()?;
+
+        Some(Self::count(sub_id, payload.count, payload.approximate))
+    }
 }
 
 #[cfg(test)]
@@ -207,6 +443,42 @@ mod tests {
                     "pow: difficulty 25>=24",
                 )),
             ),
+            (
+                r#"["AUTH","challenge-string"]"#,
+                Ok(RelayMessage::auth("challenge-string")),
+            ),
+            (
+                r#"["AUTH", "challenge-string" ]"#,
+                Ok(RelayMessage::auth("challenge-string")),
+            ),
+            (
+                r#"["CLOSED","sub1","auth-required: we only serve members"]"#,
+                Ok(RelayMessage::closed("sub1", "auth-required: we only serve members")),
+            ),
+            (
+                r#"["CLOSED", "sub1", "" ]"#,
+                Ok(RelayMessage::closed("sub1", "")),
+            ),
+            (
+                r#"["CLOSED","sub1"]"#,
+                Ok(RelayMessage::closed("sub1", "")),
+            ),
+            (
+                r#"["COUNT","reactions",{"count":238}]"#,
+                Ok(RelayMessage::count("reactions", 238, false)),
+            ),
+            (
+                r#"["COUNT", "reactions", {"count": 93412452, "approximate": true} ]"#,
+                Ok(RelayMessage::count("reactions", 93412452, true)),
+            ),
+            (
+                r#"["NEG-MSG","sync","6100000200"]"#,
+                Ok(RelayMessage::neg_msg("sync", "6100000200")),
+            ),
+            (
+                r#"["NEG-ERR", "sync", "blocked: too many records"]"#,
+                Ok(RelayMessage::neg_err("sync", "blocked: too many records")),
+            ),
             // Invalid cases
             (
                 r#"["EVENT","random_string"]"#,
@@ -216,6 +488,22 @@ mod tests {
                 r#"["EOSE"]"#,
                 Err(Error::DecodeFailed("message too short".into())),
             ),
+            (
+                r#"["AUTH", 42]"#,
+                Err(Error::DecodeFailed("Invalid AUTH format".into())),
+            ),
+            (
+                r#"["CLOSED",sub1,"error"]"#,
+                Err(Error::DecodeFailed("Invalid CLOSED format".into())),
+            ),
+            (
+                r#"["COUNT","reactions",{"count":"many"}]"#,
+                Err(Error::DecodeFailed("Invalid COUNT format".into())),
+            ),
+            (
+                r#"["NEG-MSG","sync",""]"#,
+                Err(Error::DecodeFailed("Invalid NEG-MSG format".into())),
+            ),
             (
                 r#"["NOTICE"]"#,
                 Err(Error::DecodeFailed("message too short".into())),
@@ -267,6 +555,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_message_prefix() {
+        let closed = RelayMessage::closed("sub", "rate-limited: slow down");
+        let RelayMessage::Closed(closed) = closed else {
+            panic!("expected CLOSED");
+        };
+        assert_eq!(closed.prefix(), Some(MessagePrefix::RateLimited));
+        assert!(MessagePrefix::RateLimited.is_transient());
+
+        assert_eq!(
+            MessagePrefix::parse("restricted: members only"),
+            Some(MessagePrefix::Restricted)
+        );
+        assert!(!MessagePrefix::Restricted.is_transient());
+        assert_eq!(
+            MessagePrefix::parse("auth-required:"),
+            Some(MessagePrefix::AuthRequired)
+        );
+        assert_eq!(MessagePrefix::parse("no prefix here"), None);
+        assert_eq!(MessagePrefix::parse("unknown: prefix"), None);
+    }
+
     /*
     #[test]
     fn test_handle_valid_event() -> Result<()> {
@@ -0,0 +1,362 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use ewebsock::{WsEvent, WsMessage};
+use nostrdb::Filter;
+use serde_json::{json, Value};
+use tracing::{debug, error, info};
+
+use crate::relay::RelayStatus;
+use crate::{ClientMessage, Result};
+
+/// In-process relay serving events from a fixture directory, for running
+/// against deterministic data without network access. Events published to
+/// it are accepted and served to later REQs, and everything the client
+/// sends is recorded.
+pub struct MockRelay {
+    pub status: RelayStatus,
+
+    /// Events sorted newest first
+    events: Vec<Value>,
+
+    /// Messages the client sent, oldest first
+    sent: Vec<ClientMessage>,
+
+    /// Responses waiting for the next [`MockRelay::try_recv`]
+    queue: RefCell<VecDeque<WsEvent>>,
+
+    wakeup: Box<dyn Fn() + Send + Sync>,
+}
+
+impl MockRelay {
+    pub const URL: &'static str = "mock";
+
+    /// Load every `.json` (an event or an array of events) and `.jsonl`
+    /// (one event per line) file in `dir`
+    pub fn new(dir: impl AsRef<Path>, wakeup: impl Fn() + Send + Sync + 'static) -> Result<Self> {
+        let mut paths: Vec<_> = fs::read_dir(dir.as_ref())?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+
+        let mut events = vec![];
+        for path in paths {
+            let contents = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") | Some("jsonl") => fs::read_to_string(&path)?,
+                _ => continue,
+            };
+
+            let before = events.len();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                    match serde_json::from_str(line) {
+                        Ok(event) => events.push(event),
+                        Err(err) => error!("mock relay: bad event in {}: {err}", path.display()),
+                    }
+                }
+            } else {
+                match serde_json::from_str(&contents) {
+                    Ok(Value::Array(fixtures)) => events.extend(fixtures),
+                    Ok(event) => events.push(event),
+                    Err(err) => error!("mock relay: could not parse {}: {err}", path.display()),
+                }
+            }
+            debug!(
+                "mock relay: loaded {} events from {}",
+                events.len() - before,
+                path.display()
+            );
+        }
+
+        info!("mock relay: serving {} events", events.len());
+        Ok(Self::with_events(events, wakeup))
+    }
+
+    fn with_events(mut events: Vec<Value>, wakeup: impl Fn() + Send + Sync + 'static) -> Self {
+        events.retain(|event| event["id"].is_string());
+        sort_newest_first(&mut events);
+
+        Self {
+            status: RelayStatus::Connected,
+            events,
+            sent: vec![],
+            queue: RefCell::new(VecDeque::from([WsEvent::Opened])),
+            wakeup: Box::new(wakeup),
+        }
+    }
+
+    /// Everything the client sent so far
+    pub fn sent(&self) -> &[ClientMessage] {
+        &self.sent
+    }
+
+    pub fn try_recv(&self) -> Option<WsEvent> {
+        self.queue.borrow_mut().pop_front()
+    }
+
+    pub fn send(&mut self, msg: &ClientMessage) {
+        self.sent.push(msg.clone());
+
+        match msg {
+            ClientMessage::Req { sub_id, filters } => {
+                for event in self.query(&filters_json(filters)) {
+                    self.respond(json!(["EVENT", sub_id, event]));
+                }
+                self.respond(json!(["EOSE", sub_id]));
+            }
+
+            ClientMessage::Count { sub_id, filters } => {
+                let count = self.count(&filters_json(filters));
+                self.respond(json!(["COUNT", sub_id, { "count": count }]));
+            }
+
+            // syncing against fixtures isn't worth it, clients fall back to
+            // a plain REQ
+            ClientMessage::NegOpen { sub_id, .. } => {
+                self.respond(json!([
+                    "NEG-ERR",
+                    sub_id,
+                    "blocked: negentropy isn't supported"
+                ]));
+            }
+
+            ClientMessage::Event(ecm) | ClientMessage::Auth(ecm) => {
+                let Ok(event) = serde_json::from_str::<Value>(&ecm.note_json) else {
+                    return;
+                };
+
+                let id = event["id"].as_str().unwrap_or_default().to_owned();
+                if matches!(msg, ClientMessage::Event(_))
+                    && !self.events.iter().any(|have| have["id"] == event["id"])
+                {
+                    self.events.push(event);
+                    sort_newest_first(&mut self.events);
+                }
+                self.respond(json!(["OK", id, true, ""]));
+            }
+
+            _ => {}
+        }
+
+        (self.wakeup)();
+    }
+
+    /// The stored events matching any of `filters`, newest first, each
+    /// filter limited on its own
+    fn query(&self, filters: &[Value]) -> Vec<&Value> {
+        let mut seen = HashSet::new();
+        let mut results = vec![];
+
+        for filter in filters {
+            let limit = filter["limit"].as_u64().unwrap_or(u64::MAX) as usize;
+            let matching = self
+                .events
+                .iter()
+                .filter(|event| matches(filter, event))
+                .take(limit);
+
+            for event in matching {
+                if seen.insert(event["id"].as_str()) {
+                    results.push(event);
+                }
+            }
+        }
+
+        sort_newest_first(&mut results);
+        results
+    }
+
+    /// How many stored events match any of `filters`, limits aside
+    fn count(&self, filters: &[Value]) -> usize {
+        self.events
+            .iter()
+            .filter(|event| filters.iter().any(|filter| matches(filter, event)))
+            .count()
+    }
+
+    fn respond(&self, msg: Value) {
+        self.queue
+            .borrow_mut()
+            .push_back(WsEvent::Message(WsMessage::Text(msg.to_string())));
+    }
+}
+
+fn filters_json(filters: &[Filter]) -> Vec<Value> {
+    filters
+        .iter()
+        .filter_map(|filter| filter.json().ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect()
+}
+
+fn sort_newest_first<T: Borrow<Value>>(events: &mut [T]) {
+    events.sort_by(|a, b| {
+        let (a, b): (&Value, &Value) = (a.borrow(), b.borrow());
+        b["created_at"]
+            .as_u64()
+            .cmp(&a["created_at"].as_u64())
+            .then_with(|| a["id"].as_str().cmp(&b["id"].as_str()))
+    });
+}
+
+/// NIP-01 filter matching. Unknown fields, like NIP-50 search, match
+/// everything.
+fn matches(filter: &Value, event: &Value) -> bool {
+    let Some(filter) = filter.as_object() else {
+        return false;
+    };
+
+    filter.iter().all(|(field, value)| {
+        let any = |candidate: &Value| value.as_array().is_some_and(|vs| vs.contains(candidate));
+        let created_at = event["created_at"].as_u64().unwrap_or(0);
+
+        match field.as_str() {
+            "ids" => any(&event["id"]),
+            "authors" => any(&event["pubkey"]),
+            "kinds" => any(&event["kind"]),
+            "since" => value.as_u64().is_some_and(|since| created_at >= since),
+            "until" => value.as_u64().is_some_and(|until| created_at <= until),
+            tag if tag.len() == 2 && tag.starts_with('#') => event["tags"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|t| t[0].as_str() == Some(&tag[1..]) && any(&t[1])),
+            _ => true,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RelayPool;
+    use std::collections::BTreeSet;
+
+    fn event(id: &str, kind: u64, created_at: u64, tags: Value) -> Value {
+        json!({
+            "id": id,
+            "pubkey": "aa".repeat(32),
+            "created_at": created_at,
+            "kind": kind,
+            "tags": tags,
+            "content": "",
+            "sig": "bb".repeat(64),
+        })
+    }
+
+    fn received(relay: &MockRelay) -> Vec<String> {
+        std::iter::from_fn(|| relay.try_recv())
+            .filter_map(|ev| match ev {
+                WsEvent::Message(WsMessage::Text(txt)) => Some(txt),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mock_relay_serves_fixtures() {
+        let mut relay = MockRelay::with_events(
+            vec![
+                event("01", 1, 100, json!([["t", "nostr"]])),
+                event("02", 1, 300, json!([["t", "nostr"]])),
+                event("03", 1, 200, json!([["t", "bitcoin"]])),
+                event("04", 7, 400, json!([["t", "nostr"]])),
+            ],
+            || {},
+        );
+        assert!(matches!(relay.try_recv(), Some(WsEvent::Opened)));
+
+        let filter = Filter::new()
+            .kinds([1])
+            .tags(["nostr"], 't')
+            .limit(10)
+            .build();
+        relay.send(&ClientMessage::req("sub".to_owned(), vec![filter]));
+
+        let msgs = received(&relay);
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].contains(r#""id":"02""#));
+        assert!(msgs[1].contains(r#""id":"01""#));
+        assert_eq!(msgs[2], r#"["EOSE","sub"]"#);
+
+        // published events are served too
+        let published = event("05", 1, 500, json!([["t", "nostr"]]));
+        relay.send(&ClientMessage::event_json(published.to_string()).unwrap());
+        assert_eq!(received(&relay), vec![r#"["OK","05",true,""]"#.to_owned()]);
+
+        let filter = Filter::new().kinds([1]).since(250).build();
+        relay.send(&ClientMessage::req("new".to_owned(), vec![filter]));
+        let msgs = received(&relay);
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].contains(r#""id":"05""#));
+
+        assert_eq!(relay.sent().len(), 3);
+    }
+
+    #[test]
+    fn test_mock_relay_answers_count_and_negentropy() {
+        let mut relay = MockRelay::with_events(
+            vec![
+                event("01", 1, 100, json!([])),
+                event("02", 1, 200, json!([])),
+                event("03", 7, 300, json!([])),
+            ],
+            || {},
+        );
+        assert!(matches!(relay.try_recv(), Some(WsEvent::Opened)));
+
+        // limits don't apply to counts
+        let filter = Filter::new().kinds([1]).limit(1).build();
+        relay.send(&ClientMessage::count("likes".to_owned(), vec![filter]));
+        assert_eq!(received(&relay), vec![r#"["COUNT","likes",{"count":2}]"#]);
+
+        let filter = Filter::new().kinds([1]).build();
+        relay.send(&ClientMessage::neg_open(
+            "sync".to_owned(),
+            filter,
+            "6100000200".to_owned(),
+        ));
+        assert_eq!(
+            received(&relay),
+            vec![r#"["NEG-ERR","sync","blocked: negentropy isn't supported"]"#]
+        );
+    }
+
+    #[test]
+    fn test_mock_pool_answers_for_every_relay() {
+        let fixtures = format!("{}/../../fixtures/mock_relay", env!("CARGO_MANIFEST_DIR"));
+        let mut pool = RelayPool::new();
+        pool.add_mock_relay(fixtures, || {}).unwrap();
+
+        // nothing goes out to the network
+        pool.add_url("wss://relay.example.com".to_owned(), || {})
+            .unwrap();
+        assert_eq!(pool.urls(), BTreeSet::from([MockRelay::URL.to_owned()]));
+
+        let filter = Filter::new().kinds([9]).tags(["notedeck"], 'h').build();
+        pool.subscribe_to(
+            "wss://groups.example.com/",
+            "group".to_owned(),
+            vec![filter],
+        );
+        let filter = Filter::new().kinds([42]).build();
+        pool.count("messages".to_owned(), vec![filter]);
+
+        let mut msgs = vec![];
+        while let Some(ev) = pool.try_recv() {
+            if let WsEvent::Message(WsMessage::Text(txt)) = ev.event {
+                msgs.push(txt);
+            }
+        }
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].starts_with(r#"["EVENT","group""#));
+        assert_eq!(msgs[1], r#"["EOSE","group"]"#);
+        assert_eq!(msgs[2], r#"["COUNT","messages",{"count":2}]"#);
+
+        let total = pool.count_result("messages").unwrap();
+        assert_eq!((total.count, total.answered, total.pending), (2, 1, 0));
+    }
+}
@@ -5,15 +5,25 @@ use std::net::IpAddr;
 use std::net::{SocketAddr, SocketAddrV4};
 use std::time::{Duration, Instant};
 
+use crate::relay::lan::LanSync;
 use crate::{ClientMessage, EventClientMessage, Result};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::net::Ipv4Addr;
 use tracing::{debug, error};
 
+pub mod auth;
+pub mod count;
+pub mod health;
+pub mod info;
+pub mod lan;
+pub mod limits;
 pub mod message;
+pub mod mock;
+pub mod negentropy;
 pub mod pool;
 pub mod subs_debug;
+pub mod sync;
 
 #[derive(Debug, Copy, Clone)]
 pub enum RelayStatus {
@@ -28,6 +38,7 @@ pub struct MulticastRelay {
     address: SocketAddrV4,
     socket: UdpSocket,
     interface: Ipv4Addr,
+    pub lan: LanSync,
 }
 
 impl MulticastRelay {
@@ -40,6 +51,7 @@ impl MulticastRelay {
             socket,
             interface,
             last_join,
+            lan: LanSync::default(),
         }
     }
 
@@ -60,39 +72,58 @@ impl MulticastRelay {
         (Instant::now() - self.last_join) >= Duration::from_secs(200)
     }
 
-    pub fn try_recv(&self) -> Option<WsEvent> {
+    /// Receive the next message for the app. LAN sync messages are handled
+    /// on the way.
+    pub fn try_recv(&mut self) -> Option<WsEvent> {
         let mut buffer = [0u8; 65535];
-        // Read the size header
-        match self.socket.recv_from(&mut buffer) {
-            Ok((size, src)) => {
-                let parsed_size = u32::from_be_bytes(buffer[0..4].try_into().ok()?) as usize;
-                debug!("multicast: read size {} from start of header", size - 4);
-
-                if size != parsed_size + 4 {
-                    error!(
-                        "multicast: partial data received: expected {}, got {}",
-                        parsed_size, size
-                    );
+        loop {
+            // Read the size header
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, src)) => {
+                    let parsed_size = u32::from_be_bytes(buffer[0..4].try_into().ok()?) as usize;
+                    debug!("multicast: read size {} from start of header", size - 4);
+
+                    if size != parsed_size + 4 {
+                        error!(
+                            "multicast: partial data received: expected {}, got {}",
+                            parsed_size, size
+                        );
+                        return None;
+                    }
+
+                    let text = String::from_utf8_lossy(&buffer[4..size]);
+                    debug!("multicast: received {} bytes from {}: {}", size, src, &text);
+                    if self.lan.handle_message(&text, Instant::now()) {
+                        continue;
+                    }
+                    return Some(WsEvent::Message(WsMessage::Text(text.to_string())));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // No data available, continue
+                    return None;
+                }
+                Err(e) => {
+                    error!("multicast: error receiving data: {}", e);
                     return None;
                 }
-
-                let text = String::from_utf8_lossy(&buffer[4..size]);
-                debug!("multicast: received {} bytes from {}: {}", size, src, &text);
-                Some(WsEvent::Message(WsMessage::Text(text.to_string())))
-            }
-            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                // No data available, continue
-                None
-            }
-            Err(e) => {
-                error!("multicast: error receiving data: {}", e);
-                None
             }
         }
     }
 
-    pub fn send(&self, msg: &EventClientMessage) -> Result<()> {
-        let json = msg.to_json();
+    pub fn send(&mut self, msg: &EventClientMessage) -> Result<()> {
+        self.lan.on_send_event(&msg.note_json, Instant::now());
+        self.send_text(&msg.to_json())
+    }
+
+    /// Send what LAN sync has queued, a batch at a time
+    pub fn flush(&mut self) -> Result<()> {
+        for msg in self.lan.take_outgoing() {
+            self.send_text(&msg)?;
+        }
+        Ok(())
+    }
+
+    fn send_text(&self, json: &str) -> Result<()> {
         let len = json.len();
 
         debug!("writing to multicast relay");
@@ -118,7 +149,7 @@ pub fn setup_multicast_relay(
     let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
     let multicast_ip = Ipv4Addr::new(239, 19, 88, 1);
 
-    let mut socket = UdpSocket::bind(address)?;
+    let mut socket = bind_shared(address)?;
     let interface = Ipv4Addr::UNSPECIFIED;
     let multicast_address = SocketAddrV4::new(multicast_ip, port);
 
@@ -148,6 +179,21 @@ pub fn setup_multicast_relay(
     Ok(MulticastRelay::new(multicast_address, socket, interface))
 }
 
+/// Bind the multicast port so that other notedeck instances on this
+/// machine can bind it too, and sync with us over loopback
+fn bind_shared(address: SocketAddr) -> Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+
+    Ok(UdpSocket::from_std(socket.into()))
+}
+
 pub struct Relay {
     pub url: nostr::RelayUrl,
     pub status: RelayStatus,
@@ -0,0 +1,537 @@
+//! NIP-77 negentropy set reconciliation, client side only.
+//!
+//! A sync only tells us which events the relay has that we don't. The
+//! events only we have aren't collected: we never upload other people's
+//! events to a relay, and our own are already published by the outbox.
+
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+/// NIP-77 negentropy protocol version 1
+const PROTOCOL_VERSION: u8 = 0x61;
+
+/// How many fingerprinted ranges a range gets split into when it differs
+const BUCKETS: usize = 16;
+
+const ID_SIZE: usize = 32;
+const FINGERPRINT_SIZE: usize = 16;
+
+const MODE_SKIP: u64 = 0;
+const MODE_FINGERPRINT: u64 = 1;
+const MODE_ID_LIST: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Item {
+    timestamp: u64,
+    id: [u8; ID_SIZE],
+}
+
+/// Upper end of a range: a timestamp and the shortest id prefix that
+/// separates it from the previous item
+#[derive(Debug, Clone, Default)]
+struct Bound {
+    timestamp: u64,
+    id: Vec<u8>,
+}
+
+impl Bound {
+    fn infinity() -> Self {
+        Self {
+            timestamp: u64::MAX,
+            id: vec![],
+        }
+    }
+
+    /// The smallest bound that is above `prev` but not above `next`
+    fn between(prev: &Item, next: &Item) -> Self {
+        if prev.timestamp != next.timestamp {
+            return Self {
+                timestamp: next.timestamp,
+                id: vec![],
+            };
+        }
+
+        let shared = prev
+            .id
+            .iter()
+            .zip(next.id.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        Self {
+            timestamp: next.timestamp,
+            id: next.id[..(shared + 1).min(ID_SIZE)].to_vec(),
+        }
+    }
+
+    fn is_above(&self, item: &Item) -> bool {
+        (item.timestamp, &item.id[..]) < (self.timestamp, &self.id[..])
+    }
+}
+
+/// The client side of a NIP-77 negentropy sync: works out which events a
+/// relay has that we don't, and the other way around, while only sending
+/// fingerprints of the ranges both sides already agree on.
+pub struct Negentropy {
+    items: Vec<Item>,
+    initiator: bool,
+}
+
+impl Negentropy {
+    /// Start a sync over the events we have, as (created_at, id) pairs
+    pub fn new(items: impl IntoIterator<Item = (u64, [u8; 32])>) -> Self {
+        Self::with_role(items, true)
+    }
+
+    fn with_role(items: impl IntoIterator<Item = (u64, [u8; 32])>, initiator: bool) -> Self {
+        let mut items: Vec<Item> = items
+            .into_iter()
+            .map(|(timestamp, id)| Item { timestamp, id })
+            .collect();
+        items.sort();
+        items.dedup();
+
+        Self { items, initiator }
+    }
+
+    /// The first message, sent with NEG-OPEN
+    pub fn initiate(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.out.push(PROTOCOL_VERSION);
+        self.split_range(0, self.items.len(), &Bound::infinity(), &mut writer);
+        writer.out
+    }
+
+    /// Handle a message from the relay, adding the ids only the relay has
+    /// to `need`. Returns the next message to send, or `None` once both
+    /// sides are reconciled.
+    pub fn reconcile(&self, msg: &[u8], need: &mut Vec<[u8; 32]>) -> Result<Option<Vec<u8>>> {
+        let mut reader = Reader::new(msg);
+        if reader.byte()? != PROTOCOL_VERSION {
+            return Err(Error::DecodeFailed(
+                "unsupported negentropy protocol version".into(),
+            ));
+        }
+
+        let mut writer = Writer::default();
+        writer.out.push(PROTOCOL_VERSION);
+
+        let mut prev_bound = Bound::default();
+        let mut prev_index = 0;
+        let mut skip = false;
+
+        while !reader.is_empty() {
+            let curr_bound = reader.bound()?;
+            let mode = reader.varint()?;
+
+            let lower = prev_index;
+            let upper = self.range_end(lower, &curr_bound);
+
+            match mode {
+                MODE_SKIP => skip = true,
+
+                MODE_FINGERPRINT => {
+                    let theirs = reader.bytes(FINGERPRINT_SIZE)?;
+                    if theirs == fingerprint(&self.items[lower..upper]) {
+                        skip = true;
+                    } else {
+                        flush_skip(&mut skip, &prev_bound, &mut writer);
+                        self.split_range(lower, upper, &curr_bound, &mut writer);
+                    }
+                }
+
+                MODE_ID_LIST => {
+                    let num_ids = reader.varint()?;
+                    let mut theirs = HashSet::new();
+                    for _ in 0..num_ids {
+                        let id: [u8; ID_SIZE] = reader
+                            .bytes(ID_SIZE)?
+                            .try_into()
+                            .map_err(|_| Error::InvalidByteSize)?;
+                        theirs.insert(id);
+                    }
+
+                    if self.initiator {
+                        skip = true;
+                        for item in &self.items[lower..upper] {
+                            theirs.remove(&item.id);
+                        }
+                        need.extend(theirs);
+                    } else {
+                        flush_skip(&mut skip, &prev_bound, &mut writer);
+                        write_id_list(&self.items[lower..upper], &curr_bound, &mut writer);
+                    }
+                }
+
+                _ => {
+                    return Err(Error::DecodeFailed(format!(
+                        "unknown negentropy mode {mode}"
+                    )))
+                }
+            }
+
+            prev_index = upper;
+            prev_bound = curr_bound;
+        }
+
+        // trailing skips are implied
+        if self.initiator && writer.out.len() == 1 {
+            Ok(None)
+        } else {
+            Ok(Some(writer.out))
+        }
+    }
+
+    /// Index of the first item at or above `bound`, starting from `lower`
+    fn range_end(&self, lower: usize, bound: &Bound) -> usize {
+        lower + self.items[lower..].partition_point(|item| bound.is_above(item))
+    }
+
+    fn split_range(&self, lower: usize, upper: usize, upper_bound: &Bound, writer: &mut Writer) {
+        let items = &self.items[lower..upper];
+        if items.len() < BUCKETS * 2 {
+            write_id_list(items, upper_bound, writer);
+            return;
+        }
+
+        let per_bucket = items.len() / BUCKETS;
+        let with_extra = items.len() % BUCKETS;
+        let mut curr = 0;
+
+        for bucket in 0..BUCKETS {
+            let size = per_bucket + usize::from(bucket < with_extra);
+            let fp = fingerprint(&items[curr..curr + size]);
+            curr += size;
+
+            let bound = if curr == items.len() {
+                upper_bound.clone()
+            } else {
+                Bound::between(&items[curr - 1], &items[curr])
+            };
+
+            writer.bound(&bound);
+            writer.varint(MODE_FINGERPRINT);
+            writer.out.extend_from_slice(&fp);
+        }
+    }
+}
+
+fn flush_skip(skip: &mut bool, prev_bound: &Bound, writer: &mut Writer) {
+    if *skip {
+        *skip = false;
+        writer.bound(prev_bound);
+        writer.varint(MODE_SKIP);
+    }
+}
+
+fn write_id_list(items: &[Item], bound: &Bound, writer: &mut Writer) {
+    writer.bound(bound);
+    writer.varint(MODE_ID_LIST);
+    writer.varint(items.len() as u64);
+    for item in items {
+        writer.out.extend_from_slice(&item.id);
+    }
+}
+
+/// sha256 of the ids summed as little-endian 256-bit numbers (mod 2^256)
+/// followed by the item count, truncated
+fn fingerprint(items: &[Item]) -> [u8; FINGERPRINT_SIZE] {
+    let mut sum = [0u8; ID_SIZE];
+    for item in items {
+        let mut carry = 0u16;
+        for (acc, byte) in sum.iter_mut().zip(item.id.iter()) {
+            let total = *acc as u16 + *byte as u16 + carry;
+            *acc = total as u8;
+            carry = total >> 8;
+        }
+    }
+
+    let mut input = Writer::default();
+    input.out.extend_from_slice(&sum);
+    input.varint(items.len() as u64);
+
+    let hash = Sha256::digest(&input.out);
+    let mut fp = [0u8; FINGERPRINT_SIZE];
+    fp.copy_from_slice(&hash[..FINGERPRINT_SIZE]);
+    fp
+}
+
+/// Timestamps are delta encoded against the previous bound in the same
+/// message, so both ends keep track of the last one
+#[derive(Default)]
+struct Writer {
+    out: Vec<u8>,
+    last_timestamp: u64,
+}
+
+impl Writer {
+    fn varint(&mut self, mut n: u64) {
+        let mut bytes = vec![(n & 0x7f) as u8];
+        n >>= 7;
+        while n > 0 {
+            bytes.push((n & 0x7f) as u8 | 0x80);
+            n >>= 7;
+        }
+        self.out.extend(bytes.iter().rev());
+    }
+
+    fn bound(&mut self, bound: &Bound) {
+        if bound.timestamp == u64::MAX {
+            self.last_timestamp = u64::MAX;
+            self.varint(0);
+        } else {
+            let delta = bound.timestamp.saturating_sub(self.last_timestamp);
+            self.last_timestamp = bound.timestamp;
+            self.varint(delta + 1);
+        }
+
+        self.varint(bound.id.len() as u64);
+        self.out.extend_from_slice(&bound.id);
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    last_timestamp: u64,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            last_timestamp: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(Error::DecodeFailed("negentropy message too short".into()));
+        }
+        let (bytes, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut n: u64 = 0;
+        loop {
+            let byte = self.byte()?;
+            n = n
+                .checked_mul(128)
+                .ok_or_else(|| Error::DecodeFailed("negentropy varint overflow".into()))?
+                | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+    }
+
+    fn bound(&mut self) -> Result<Bound> {
+        let encoded = self.varint()?;
+        let timestamp = if encoded == 0 || self.last_timestamp == u64::MAX {
+            u64::MAX
+        } else {
+            self.last_timestamp.saturating_add(encoded - 1)
+        };
+        self.last_timestamp = timestamp;
+
+        let len = self.varint()? as usize;
+        if len > ID_SIZE {
+            return Err(Error::DecodeFailed("negentropy bound too long".into()));
+        }
+        let id = self.bytes(len)?.to_vec();
+
+        Ok(Bound { timestamp, id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u32) -> [u8; 32] {
+        let mut id = [0u8; 32];
+        id[..4].copy_from_slice(&n.to_be_bytes());
+        id[4..8].copy_from_slice(&n.wrapping_mul(2654435761).to_le_bytes());
+        id
+    }
+
+    /// Run a full sync between us and a relay, returning what we need,
+    /// sorted, and how many rounds it took
+    fn sync(ours: &[u32], theirs: &[u32]) -> (Vec<[u8; 32]>, usize) {
+        let client = Negentropy::new(ours.iter().map(|n| (1_700_000_000 + *n as u64 / 7, id(*n))));
+        let relay = Negentropy::with_role(
+            theirs
+                .iter()
+                .map(|n| (1_700_000_000 + *n as u64 / 7, id(*n))),
+            false,
+        );
+
+        let mut need = vec![];
+        let mut msg = client.initiate();
+        let mut rounds = 0;
+
+        loop {
+            rounds += 1;
+            assert!(rounds < 20, "sync didn't converge");
+
+            let reply = relay
+                .reconcile(&msg, &mut vec![])
+                .expect("relay reconcile")
+                .expect("relay always answers");
+
+            match client
+                .reconcile(&reply, &mut need)
+                .expect("client reconcile")
+            {
+                Some(next) => msg = next,
+                None => break,
+            }
+        }
+
+        need.sort();
+        (need, rounds)
+    }
+
+    fn ids(ns: impl IntoIterator<Item = u32>) -> Vec<[u8; 32]> {
+        let mut ids: Vec<[u8; 32]> = ns.into_iter().map(id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for n in [
+            0,
+            1,
+            127,
+            128,
+            300,
+            16383,
+            16384,
+            u32::MAX as u64,
+            u64::MAX >> 1,
+        ] {
+            let mut writer = Writer::default();
+            writer.varint(n);
+            assert_eq!(Reader::new(&writer.out).varint().unwrap(), n);
+        }
+
+        let mut writer = Writer::default();
+        writer.varint(300);
+        assert_eq!(writer.out, vec![0x82, 0x2c]);
+    }
+
+    // The vectors below were worked out by hand from the NIP-77 spec,
+    // independently of this implementation, so they catch encoding bugs
+    // that a sync against ourselves can't.
+
+    #[test]
+    fn test_fingerprint_vectors() {
+        let item = |id: [u8; 32]| Item { timestamp: 0, id };
+
+        // sha256 of 32 zero bytes and a zero count
+        assert_eq!(
+            hex::encode(fingerprint(&[])),
+            "7f9c9e31ac8256ca2f258583df262dbc"
+        );
+        assert_eq!(
+            hex::encode(fingerprint(&[item([1; 32]), item([2; 32])])),
+            "12a7b248579deb04f68dac6d3db20efd"
+        );
+
+        // the sum carries across bytes and wraps around at 2^256
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        assert_eq!(
+            hex::encode(fingerprint(&[item([0xff; 32]), item(one)])),
+            "58cc2f44d3a27866874701fbad573da9"
+        );
+    }
+
+    #[test]
+    fn test_initiate_vectors() {
+        assert_eq!(hex::encode(Negentropy::new([]).initiate()), "6100000200");
+
+        let two = Negentropy::new([(1_700_000_010, [2; 32]), (1_700_000_000, [1; 32])]);
+        assert_eq!(
+            hex::encode(two.initiate()),
+            format!("6100000202{}{}", "01".repeat(32), "02".repeat(32))
+        );
+
+        // enough items for fingerprinted buckets, three per timestamp so
+        // some bounds need an id prefix
+        let items = (0..32u32).map(|n| {
+            let id: [u8; 32] = Sha256::digest(n.to_string().as_bytes()).into();
+            (1_700_000_000 + n as u64 / 3, id)
+        });
+        let expected = concat!(
+            "6186aacfe20101d40171db9738080f9ed48a9329f559b0ce5602014e01cd075719676dabdfe642b3",
+            "5ea18c4b3e020001db0980d87d65ccbc0a44e763f87767fa0101e70140c4390414772a6575f4fd02",
+            "bf9f79dd02014a016a1be1d0129963dbf60beb754dbb3b3d0200013b57869ecff42f0aae60c2c370",
+            "acbe84010185018aa314f7900aa3a012d967ac11c430430201b101ec792fe2a89c7c7d396e7c14ce",
+            "0efaf6020001acf94fe844fb5dc4c5002071422df0bd0101f501953994d77dcaaaaabaa153dc390c",
+            "247802016f01778fcae738368d9f5b3cd99f10c884700200010b46ca4276fd99649e7fe73f0a42c8",
+            "180101c201db9e68295e265b5fe8d93bf1ca2be4c402015901f75d4dd64ee8ade09be0de2b141919",
+            "100200016de0f08ec0d36149dcb7ba2c420cd1b000000162a4e875a0e6a907a24b674b07ba757d",
+        );
+        assert_eq!(hex::encode(Negentropy::new(items).initiate()), expected);
+    }
+
+    #[test]
+    fn test_reconcile_vector() {
+        let client = Negentropy::new([(1_700_000_000, [1; 32]), (1_700_000_010, [2; 32])]);
+
+        // skip everything before 1700000005, then the relay lists its ids
+        let reply = hex::decode(format!("6186aacfe206000000000201{}", "03".repeat(32))).unwrap();
+        let mut need = vec![];
+        assert_eq!(client.reconcile(&reply, &mut need).unwrap(), None);
+        assert_eq!(need, vec![[3; 32]]);
+    }
+
+    #[test]
+    fn test_sync_small_sets() {
+        let (need, _) = sync(&[1, 2, 3, 4], &[3, 4, 5]);
+        assert_eq!(need, ids([5]));
+
+        let (need, _) = sync(&[3, 4, 5], &[1, 2, 3, 4]);
+        assert_eq!(need, ids([1, 2]));
+
+        let (need, _) = sync(&[], &[1, 2]);
+        assert_eq!(need, ids([1, 2]));
+    }
+
+    #[test]
+    fn test_sync_large_sets() {
+        let ours: Vec<u32> = (0..5000).filter(|n| n % 97 != 0).collect();
+        let theirs: Vec<u32> = (0..5000).filter(|n| n % 89 != 0).collect();
+
+        let (need, _) = sync(&ours, &theirs);
+        assert_eq!(need, ids((0..5000).filter(|n| n % 97 == 0 && n % 89 != 0)));
+
+        // identical sets agree after a single round of fingerprints
+        let (need, rounds) = sync(&theirs, &theirs);
+        assert!(need.is_empty());
+        assert_eq!(rounds, 1);
+    }
+
+    #[test]
+    fn test_bad_message() {
+        let client = Negentropy::new([]);
+        assert!(client.reconcile(&[0x62], &mut vec![]).is_err());
+        assert!(client
+            .reconcile(&[PROTOCOL_VERSION, 0x00, 0x00, 0x01, 0xab], &mut vec![])
+            .is_err());
+    }
+}
@@ -1,8 +1,16 @@
+use crate::relay::auth::{RelayAuth, RelayAuthState};
+use crate::relay::count::{CountQueries, CountTotal};
+use crate::relay::health::{reconnect_delay, RelayHealth};
+use crate::relay::info::RelayInformation;
+use crate::relay::limits::{IncomingMessage, RelayLimits};
+use crate::relay::mock::MockRelay;
+use crate::relay::sync::{RelaySync, SyncRequest, SyncSupport};
 use crate::relay::{setup_multicast_relay, MulticastRelay, Relay, RelayStatus};
-use crate::{ClientMessage, Error, Result};
-use nostrdb::Filter;
+use crate::{ClientMessage, Error, FilledKeypair, RelayMessage, Result};
+use nostrdb::{Filter, Ndb};
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
 use std::time::{Duration, Instant};
 
 use url::Url;
@@ -35,6 +43,7 @@ pub struct PoolEventBuf {
 pub enum PoolRelay {
     Websocket(WebsocketRelay),
     Multicast(MulticastRelay),
+    Mock(MockRelay),
 }
 
 pub struct WebsocketRelay {
@@ -42,6 +51,11 @@ pub struct WebsocketRelay {
     pub last_ping: Instant,
     pub last_connect_attempt: Instant,
     pub retry_connect_after: Duration,
+    pub auth: RelayAuth,
+    pub health: RelayHealth,
+    pub sync: RelaySync,
+    pub info: Option<RelayInformation>,
+    pub limits: RelayLimits,
 }
 
 impl PoolRelay {
@@ -49,22 +63,46 @@ impl PoolRelay {
         match self {
             Self::Websocket(wsr) => wsr.relay.url.as_str(),
             Self::Multicast(_wsr) => "multicast",
+            Self::Mock(_) => MockRelay::URL,
         }
     }
 
     pub fn set_status(&mut self, status: RelayStatus) {
         match self {
             Self::Websocket(wsr) => {
+                let now = Instant::now();
+                match status {
+                    // errors are usually followed by a close, only count
+                    // the first one
+                    RelayStatus::Disconnected
+                        if !matches!(wsr.relay.status, RelayStatus::Disconnected) =>
+                    {
+                        wsr.auth.reset();
+                        wsr.sync.reset();
+                        wsr.limits.reset();
+                        wsr.health.on_disconnected(now);
+                        wsr.retry_connect_after =
+                            reconnect_delay(wsr.health.consecutive_failures());
+                        debug!(
+                            "{} disconnected, reconnecting in {:?}",
+                            wsr.relay.url, wsr.retry_connect_after
+                        );
+                    }
+                    RelayStatus::Connected => wsr.health.on_opened(now),
+                    _ => {}
+                }
                 wsr.relay.status = status;
             }
             Self::Multicast(_mcr) => {}
+            Self::Mock(mock) => mock.status = status,
         }
     }
 
-    pub fn try_recv(&self) -> Option<WsEvent> {
+    pub fn try_recv(&mut self) -> Option<WsEvent> {
         match self {
             Self::Websocket(recvr) => recvr.relay.receiver.try_recv(),
             Self::Multicast(recvr) => recvr.try_recv(),
+            Self::Mock(mock) => mock.try_recv(),
         }
     }
 
@@ -72,23 +110,73 @@ impl PoolRelay {
         match self {
             Self::Websocket(wsr) => wsr.relay.status,
             Self::Multicast(mcr) => mcr.status,
+            Self::Mock(mock) => mock.status,
+        }
+    }
+
+    /// Connection health, not tracked for multicast and mock relays
+    pub fn health(&self) -> Option<&RelayHealth> {
+        match self {
+            Self::Websocket(wsr) => Some(&wsr.health),
+            Self::Multicast(_) | Self::Mock(_) => None,
+        }
+    }
+
+    /// Chronically failing relays, see [`RelayHealth::is_failing`]
+    pub fn is_failing(&self) -> bool {
+        self.health().is_some_and(RelayHealth::is_failing)
+    }
+
+    /// NIP-42 auth state, multicast and mock relays never authenticate
+    pub fn auth_state(&self) -> Option<&RelayAuthState> {
+        match self {
+            Self::Websocket(wsr) => Some(wsr.auth.state()),
+            Self::Multicast(_) | Self::Mock(_) => None,
+        }
+    }
+
+    /// The relay's NIP-11 document, once fetched
+    pub fn info(&self) -> Option<&RelayInformation> {
+        match self {
+            Self::Websocket(wsr) => wsr.info.as_ref(),
+            Self::Multicast(_) | Self::Mock(_) => None,
+        }
+    }
+
+    /// Whether the relay can reconcile with NIP-77, multicast and mock
+    /// relays can't
+    pub fn sync_support(&self) -> SyncSupport {
+        match self {
+            Self::Websocket(wsr) => wsr.sync.support(),
+            Self::Multicast(_) | Self::Mock(_) => SyncSupport::Unsupported,
         }
     }
 
     pub fn send(&mut self, msg: &ClientMessage) -> Result<()> {
         match self {
             Self::Websocket(wsr) => {
-                wsr.relay.send(msg);
+                wsr.send(msg);
                 Ok(())
             }
 
             Self::Multicast(mcr) => {
-                // we only send event client messages at the moment
-                if let ClientMessage::Event(ecm) = msg {
-                    mcr.send(ecm)?;
+                // subscriptions are asked from LAN peers
+                match msg {
+                    ClientMessage::Event(ecm) => mcr.send(ecm)?,
+                    ClientMessage::Req { sub_id, filters } => {
+                        mcr.lan.subscribe(sub_id.clone(), filters.clone());
+                        mcr.flush()?;
+                    }
+                    ClientMessage::Close { sub_id } => mcr.lan.unsubscribe(sub_id),
+                    _ => {}
                 }
                 Ok(())
             }
+
+            Self::Mock(mock) => {
+                mock.send(msg);
+                Ok(())
+            }
         }
     }
 
@@ -96,6 +184,17 @@ impl PoolRelay {
         self.send(&ClientMessage::req(subid, filter))
     }
 
+    /// Fetch the events of `request` we're missing with a NIP-77 negentropy
+    /// sync, then subscribe to them and the followup filters. Relays
+    /// without NIP-77 get the fallback filters instead.
+    pub fn sync(&mut self, subid: String, request: SyncRequest) -> Result<()> {
+        let msg = match self {
+            Self::Websocket(wsr) => wsr.sync.start(subid, request, Instant::now()),
+            Self::Multicast(_) | Self::Mock(_) => ClientMessage::req(subid, request.fallback),
+        };
+        self.send(&msg)
+    }
+
     pub fn websocket(relay: Relay) -> Self {
         Self::Websocket(WebsocketRelay::new(relay))
     }
@@ -103,6 +202,10 @@ impl PoolRelay {
     pub fn multicast(wakeup: impl Fn() + Send + Sync + Clone + 'static) -> Result<Self> {
         Ok(Self::Multicast(setup_multicast_relay(wakeup)?))
     }
+
+    pub fn mock(dir: impl AsRef<Path>, wakeup: impl Fn() + Send + Sync + 'static) -> Result<Self> {
+        Ok(Self::Mock(MockRelay::new(dir, wakeup)?))
+    }
 }
 
 impl WebsocketRelay {
@@ -112,6 +215,81 @@ impl WebsocketRelay {
             last_ping: Instant::now(),
             last_connect_attempt: Instant::now(),
             retry_connect_after: Self::initial_reconnect_duration(),
+            auth: RelayAuth::default(),
+            health: RelayHealth::default(),
+            sync: RelaySync::default(),
+            info: None,
+            limits: RelayLimits::default(),
+        }
+    }
+
+    /// Send `msg`, split or held back as needed to stay within the relay's
+    /// NIP-11 limits
+    pub fn send(&mut self, msg: &ClientMessage) {
+        for msg in self.limits.outgoing(msg) {
+            self.send_now(&msg);
+        }
+    }
+
+    fn send_now(&mut self, msg: &ClientMessage) {
+        self.auth.track(msg);
+        if let ClientMessage::Req { sub_id, .. } = msg {
+            self.health.on_req(sub_id, Instant::now());
+        }
+        self.relay.send(msg);
+    }
+
+    pub fn set_info(&mut self, info: RelayInformation) {
+        self.limits.set_limitation(info.limitation.clone());
+        self.info = Some(info);
+    }
+
+    /// Update the auth state, health stats and negentropy syncs from AUTH,
+    /// OK, CLOSED, EOSE and NEG- messages. Returns what to send back: REQs
+    /// to replay when authentication just succeeded, or the next step of a
+    /// sync.
+    fn handle_message(&mut self, txt: &str) -> Vec<ClientMessage> {
+        let synced = self.sync.handle_message(txt, Instant::now());
+        if !synced.is_empty() {
+            return synced;
+        }
+
+        if !txt.starts_with("[\"AUTH\"")
+            && !txt.starts_with("[\"OK\"")
+            && !txt.starts_with("[\"CLOSED\"")
+            && !txt.starts_with("[\"EOSE\"")
+        {
+            return vec![];
+        }
+
+        match RelayMessage::from_json(txt) {
+            Ok(RelayMessage::Auth(challenge)) => {
+                self.auth.on_challenge(challenge);
+                vec![]
+            }
+            Ok(RelayMessage::Closed(closed)) => {
+                self.health.on_response(Some(closed.sub_id()), false);
+                self.auth.on_closed(closed.sub_id(), closed.prefix());
+                for msg in self.limits.on_closed(closed.sub_id()) {
+                    debug!(
+                        "{} closed {}, sending {:?}",
+                        self.relay.url,
+                        closed.sub_id(),
+                        msg
+                    );
+                    self.send_now(&msg);
+                }
+                vec![]
+            }
+            Ok(RelayMessage::OK(cr)) => {
+                self.health.on_response(None, cr.status());
+                self.auth.on_ok(cr.event_id(), cr.status(), cr.message())
+            }
+            Ok(RelayMessage::Eose(sub_id)) => {
+                self.health.on_eose(sub_id, Instant::now());
+                vec![]
+            }
+            _ => vec![],
         }
     }
 
@@ -124,6 +302,12 @@ pub struct RelayPool {
     pub relays: Vec<PoolRelay>,
     pub ping_rate: Duration,
     pub debug: Option<SubsDebug>,
+    counts: CountQueries,
+
+    /// Relays connected to outside of the account's relay list, like the
+    /// relays of a channel, and who needs them. [`RelayPool::remove_urls`]
+    /// leaves them alone until they are unpinned.
+    pinned: HashMap<String, HashSet<&'static str>>,
 }
 
 impl Default for RelayPool {
@@ -139,6 +323,8 @@ impl RelayPool {
             relays: vec![],
             ping_rate: Duration::from_secs(45),
             debug: None,
+            counts: CountQueries::default(),
+            pinned: HashMap::new(),
         }
     }
 
@@ -151,6 +337,39 @@ impl RelayPool {
         Ok(())
     }
 
+    /// Serve events from the fixture files in `dir` instead of the network,
+    /// see [`MockRelay`]
+    pub fn add_mock_relay(
+        &mut self,
+        dir: impl AsRef<Path>,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> Result<()> {
+        let mock_relay = PoolRelay::mock(dir, wakeup)?;
+        self.relays.push(mock_relay);
+        Ok(())
+    }
+
+    /// Answer the history requests of LAN peers from `ndb`. Returns true
+    /// while there's more to do, callers should come back shortly.
+    pub fn lan_sync(&mut self, ndb: &Ndb) -> bool {
+        let now = Instant::now();
+        let mut busy = false;
+
+        for relay in &mut self.relays {
+            let PoolRelay::Multicast(mcr) = relay else {
+                continue;
+            };
+
+            mcr.lan.update(ndb, now);
+            if let Err(err) = mcr.flush() {
+                error!("multicast: lan sync error: {err}");
+            }
+            busy |= mcr.lan.is_busy();
+        }
+
+        busy
+    }
+
     pub fn use_debug(&mut self) {
         self.debug = Some(SubsDebug::default());
     }
@@ -160,6 +379,28 @@ impl RelayPool {
         self
     }
 
+    /// Whether the pool runs against a [`MockRelay`], which then answers
+    /// for every relay url
+    pub fn is_mock(&self) -> bool {
+        self.relays
+            .iter()
+            .any(|relay| matches!(relay, PoolRelay::Mock(_)))
+    }
+
+    /// The relay at `url`, or the mock relay standing in for it
+    fn relay_mut(&mut self, url: &str) -> Option<&mut PoolRelay> {
+        let index = self
+            .relays
+            .iter()
+            .position(|relay| relay.url() == url)
+            .or_else(|| {
+                self.relays
+                    .iter()
+                    .position(|relay| matches!(relay, PoolRelay::Mock(_)))
+            })?;
+        self.relays.get_mut(index)
+    }
+
     pub fn has(&self, url: &str) -> bool {
         for relay in &self.relays {
             if relay.url() == url {
@@ -204,8 +445,16 @@ impl RelayPool {
         }
     }
 
+    /// Subscribe on every relay. Chronically failing relays are skipped as
+    /// long as a healthy one is connected.
     pub fn subscribe(&mut self, subid: String, filter: Vec<Filter>) {
+        let skip_failing = self.has_healthy_relay();
         for relay in &mut self.relays {
+            if skip_failing && relay.is_failing() {
+                debug!("skipping {subid} on failing relay {}", relay.url());
+                continue;
+            }
+
             if let Some(debug) = &mut self.debug {
                 debug.send_cmd(
                     relay.url().to_owned(),
@@ -219,24 +468,75 @@ impl RelayPool {
         }
     }
 
-    /// Subscribe to a specific relay by URL
-    pub fn subscribe_to(&mut self, relay_url: &str, subid: String, filter: Vec<Filter>) {
+    /// Ask every connected relay how many events match `filters` (NIP-45).
+    /// Poll the combined answer with [`RelayPool::count_result`].
+    pub fn count(&mut self, sub_id: String, filters: Vec<Filter>) {
+        let cmd = ClientMessage::count(sub_id.clone(), filters);
+        let skip_failing = self.has_healthy_relay();
+        let mut sent_to = vec![];
+
         for relay in &mut self.relays {
-            if relay.url() == relay_url {
-                if let Some(debug) = &mut self.debug {
-                    debug.send_cmd(
-                        relay.url().to_owned(),
-                        &ClientMessage::req(subid.clone(), filter.clone()),
-                    );
+            let wsr = match relay {
+                PoolRelay::Websocket(wsr) => wsr,
+                PoolRelay::Mock(mock) => {
+                    mock.send(&cmd);
+                    sent_to.push(MockRelay::URL.to_owned());
+                    continue;
                 }
+                PoolRelay::Multicast(_) => continue,
+            };
 
-                if let Err(err) = relay.send(&ClientMessage::req(subid, filter)) {
-                    error!("error subscribing to {}: {err}", relay.url());
-                }
-                return;
+            if !matches!(wsr.relay.status, RelayStatus::Connected)
+                || (skip_failing && wsr.health.is_failing())
+            {
+                continue;
             }
+
+            if let Some(debug) = &mut self.debug {
+                debug.send_cmd(wsr.relay.url.to_string(), &cmd);
+            }
+            wsr.relay.send(&cmd);
+            sent_to.push(wsr.relay.url.as_str().to_owned());
+        }
+
+        self.counts.start(sub_id, sent_to, Instant::now());
+    }
+
+    /// The counts relays sent for a [`RelayPool::count`] request so far
+    pub fn count_result(&self, sub_id: &str) -> Option<CountTotal> {
+        self.counts.result(sub_id, Instant::now())
+    }
+
+    /// Stop tracking a count request once its result was used
+    pub fn forget_count(&mut self, sub_id: &str) {
+        self.counts.forget(sub_id);
+    }
+
+    /// Whether a connected relay is doing fine
+    pub fn has_healthy_relay(&self) -> bool {
+        self.relays.iter().any(|relay| {
+            matches!(relay, PoolRelay::Websocket(_) | PoolRelay::Mock(_))
+                && matches!(relay.status(), RelayStatus::Connected)
+                && !relay.is_failing()
+        })
+    }
+
+    /// Subscribe to a specific relay by URL
+    pub fn subscribe_to(&mut self, relay_url: &str, subid: String, filter: Vec<Filter>) {
+        let cmd = ClientMessage::req(subid, filter);
+        let Some(relay) = self.relay_mut(relay_url) else {
+            error!("relay {} not found in pool", relay_url);
+            return;
+        };
+        let url = relay.url().to_owned();
+
+        let result = relay.send(&cmd);
+        if let Some(debug) = &mut self.debug {
+            debug.send_cmd(url.clone(), &cmd);
+        }
+        if let Err(err) = result {
+            error!("error subscribing to {url}: {err}");
         }
-        error!("relay {} not found in pool", relay_url);
     }
 
     /// Keep relay connectiongs alive by pinging relays that haven't been
@@ -246,7 +546,7 @@ impl RelayPool {
             let now = std::time::Instant::now();
 
             match relay {
-                PoolRelay::Multicast(_) => {}
+                PoolRelay::Multicast(_) | PoolRelay::Mock(_) => {}
                 PoolRelay::Websocket(relay) => {
                     match relay.relay.status {
                         RelayStatus::Disconnected => {
@@ -254,12 +554,11 @@ impl RelayPool {
                                 relay.last_connect_attempt + relay.retry_connect_after;
                             if now > reconnect_at {
                                 relay.last_connect_attempt = now;
-                                let next_duration = Duration::from_millis(3000);
                                 debug!(
-                                    "bumping reconnect duration from {:?} to {:?} and retrying connect",
-                                    relay.retry_connect_after, next_duration
+                                    "reconnecting to {} after {} failed attempts",
+                                    relay.relay.url,
+                                    relay.health.consecutive_failures()
                                 );
-                                relay.retry_connect_after = next_duration;
                                 if let Err(err) = relay.relay.connect(wakeup.clone()) {
                                     error!("error connecting to relay: {}", err);
                                 }
@@ -269,14 +568,21 @@ impl RelayPool {
                         }
 
                         RelayStatus::Connected => {
-                            relay.retry_connect_after =
-                                WebsocketRelay::initial_reconnect_duration();
+                            relay.health.settle(now);
+
+                            for msg in relay.sync.expire(now) {
+                                if let Some(debug) = &mut self.debug {
+                                    debug.send_cmd(relay.relay.url.to_string(), &msg);
+                                }
+                                relay.send(&msg);
+                            }
 
                             let should_ping = now - relay.last_ping > self.ping_rate;
                             if should_ping {
                                 trace!("pinging {}", relay.relay.url);
                                 relay.relay.ping();
                                 relay.last_ping = Instant::now();
+                                relay.health.on_ping(relay.last_ping);
                             }
                         }
 
@@ -290,16 +596,56 @@ impl RelayPool {
     }
 
     pub fn send_to(&mut self, cmd: &ClientMessage, relay_url: &str) {
+        let Some(relay) = self.relay_mut(relay_url) else {
+            return;
+        };
+        let url = relay.url().to_owned();
+
+        let result = relay.send(cmd);
+        if let Some(debug) = &mut self.debug {
+            debug.send_cmd(url, cmd);
+        }
+        if let Err(err) = result {
+            error!("send_to err: {err}");
+        }
+    }
+
+    /// Answer a relay's NIP-42 auth challenge with the given account
+    pub fn authenticate(&mut self, relay_url: &str, keypair: FilledKeypair) {
         for relay in &mut self.relays {
-            if relay.url() == relay_url {
-                if let Some(debug) = &mut self.debug {
-                    debug.send_cmd(relay.url().to_owned(), cmd);
-                }
-                if let Err(err) = relay.send(cmd) {
-                    error!("send_to err: {err}");
+            if relay.url() != relay_url {
+                continue;
+            }
+
+            if let PoolRelay::Websocket(wsr) = relay {
+                if let Some(msg) = wsr.auth.respond(relay_url, keypair) {
+                    if let Some(debug) = &mut self.debug {
+                        debug.send_cmd(relay_url.to_owned(), &msg);
+                    }
+                    wsr.relay.send(&msg);
                 }
-                return;
             }
+            return;
+        }
+    }
+
+    /// Answer every outstanding auth challenge again with a new account,
+    /// called when the selected account changes
+    pub fn reauthenticate(&mut self, keypair: FilledKeypair) {
+        let urls: Vec<String> = self
+            .relays
+            .iter_mut()
+            .filter_map(|relay| match relay {
+                PoolRelay::Websocket(wsr) if wsr.auth.challenge().is_some() => {
+                    wsr.auth.rechallenge();
+                    Some(wsr.relay.url.to_string())
+                }
+                _ => None,
+            })
+            .collect();
+
+        for url in urls {
+            self.authenticate(&url, keypair);
         }
     }
 
@@ -328,8 +674,9 @@ impl RelayPool {
         wakeup: impl Fn() + Send + Sync + Clone + 'static,
     ) -> Result<()> {
         let url = Self::canonicalize_url(url);
-        // Check if the URL already exists in the pool.
-        if self.has(&url) {
+        // Check if the URL already exists in the pool. The mock relay
+        // stands in for every url, nothing goes out to the network.
+        if self.has(&url) || self.is_mock() {
             return Ok(());
         }
         let relay = Relay::new(
@@ -354,9 +701,39 @@ impl RelayPool {
         Ok(())
     }
 
+    /// Remove relays from the pool, except the pinned ones
     pub fn remove_urls(&mut self, urls: &BTreeSet<String>) {
-        self.relays
-            .retain(|pool_relay| !urls.contains(pool_relay.url()));
+        let pinned = &self.pinned;
+        self.relays.retain(|pool_relay| {
+            let url = pool_relay.url();
+            !urls.contains(url) || pinned.contains_key(&Self::canonicalize_url(url.to_owned()))
+        });
+    }
+
+    /// Keep `url` in the pool, whatever the account's relay list says,
+    /// until `owner` unpins it
+    pub fn pin_url(&mut self, url: &str, owner: &'static str) {
+        self.pinned
+            .entry(Self::canonicalize_url(url.to_owned()))
+            .or_default()
+            .insert(owner);
+    }
+
+    pub fn unpin_url(&mut self, url: &str, owner: &'static str) {
+        let url = Self::canonicalize_url(url.to_owned());
+        let Some(owners) = self.pinned.get_mut(&url) else {
+            return;
+        };
+
+        owners.remove(owner);
+        if owners.is_empty() {
+            self.pinned.remove(&url);
+        }
+    }
+
+    pub fn is_pinned(&self, url: &str) -> bool {
+        self.pinned
+            .contains_key(&Self::canonicalize_url(url.to_owned()))
     }
 
     // standardize the format (ie, trailing slashes)
@@ -382,7 +759,8 @@ impl RelayPool {
                 }
             }
 
-            if let Some(event) = relay.try_recv() {
+            while let Some(mut event) = relay.try_recv() {
+                let mut incoming = IncomingMessage::Unchanged;
                 match &event {
                     WsEvent::Opened => {
                         relay.set_status(RelayStatus::Connected);
@@ -395,6 +773,30 @@ impl RelayPool {
                         relay.set_status(RelayStatus::Disconnected);
                     }
                     WsEvent::Message(ev) => {
+                        if let (PoolRelay::Mock(_), WsMessage::Text(txt)) = (&*relay, ev) {
+                            self.counts.handle_message(MockRelay::URL, txt);
+                        }
+
+                        if let (PoolRelay::Websocket(wsr), WsMessage::Text(txt)) = (&mut *relay, ev)
+                        {
+                            // before handling it, a CLOSED forgets the parts
+                            // of a split subscription
+                            incoming = wsr.limits.incoming(txt);
+                            self.counts.handle_message(wsr.relay.url.as_str(), txt);
+
+                            for msg in wsr.handle_message(txt) {
+                                debug!("answering {} with {:?}", wsr.relay.url, msg);
+                                if let Some(debug) = &mut self.debug {
+                                    debug.send_cmd(wsr.relay.url.to_string(), &msg);
+                                }
+                                wsr.send(&msg);
+                            }
+                        }
+
+                        if let (PoolRelay::Websocket(wsr), WsMessage::Pong(_)) = (&mut *relay, ev) {
+                            wsr.health.on_pong(Instant::now());
+                        }
+
                         // let's just handle pongs here.
                         // We only need to do this natively.
                         #[cfg(not(target_arch = "wasm32"))]
@@ -404,7 +806,7 @@ impl RelayPool {
                                 PoolRelay::Websocket(wsr) => {
                                     wsr.relay.sender.send(WsMessage::Pong(bs.to_owned()));
                                 }
-                                PoolRelay::Multicast(_mcr) => {}
+                                PoolRelay::Multicast(_mcr) | PoolRelay::Mock(_) => {}
                             }
                         }
                     }
@@ -414,6 +816,17 @@ impl RelayPool {
                     debug.receive_cmd(relay.url().to_owned(), (&event).into());
                 }
 
+                match incoming {
+                    IncomingMessage::Unchanged => {}
+                    IncomingMessage::Held => {
+                        trace!("{} holding back EOSE of a split subscription", relay.url());
+                        continue;
+                    }
+                    IncomingMessage::Rewritten(txt) => {
+                        event = WsEvent::Message(WsMessage::Text(txt))
+                    }
+                }
+
                 let pool_event = PoolEvent {
                     event,
                     relay: relay.url(),
@@ -5,7 +5,7 @@ use nostrdb::Filter;
 
 use crate::{ClientMessage, Error, RelayEvent, RelayMessage};
 
-use super::message::calculate_command_result_size;
+use super::message::{calculate_closed_result_size, calculate_command_result_size};
 
 type RelayId = String;
 type SubId = String;
@@ -60,6 +60,11 @@ impl From<RelayEvent<'_>> for OwnedRelayEvent {
                     RelayMessage::Eose(s) => format!("EOSE:{s}"),
                     RelayMessage::Event(_, s) => format!("EVENT:{s}"),
                     RelayMessage::Notice(s) => format!("NOTICE:{s}"),
+                    RelayMessage::Auth(s) => format!("AUTH:{s}"),
+                    RelayMessage::Closed(c) => format!("CLOSED:{}:{}", c.sub_id(), c.message()),
+                    RelayMessage::Count(c) => format!("COUNT:{}:{}", c.sub_id(), c.count()),
+                    RelayMessage::NegMsg(s, _) => format!("NEG-MSG:{s}"),
+                    RelayMessage::NegErr(c) => format!("NEG-ERR:{}:{}", c.sub_id(), c.message()),
                 };
                 OwnedRelayEvent::Message(relay_msg)
             }
@@ -185,15 +190,25 @@ fn inc_data_count(c: &mut TransferStats) {
 fn calculate_client_message_size(message: &ClientMessage) -> usize {
     match message {
         ClientMessage::Event(note) => note.note_json.len() + 10, // 10 is ["EVENT",]
-        ClientMessage::Req { sub_id, filters } => {
+        ClientMessage::Auth(note) => note.note_json.len() + 9,   // 9 is ["AUTH",]
+        ClientMessage::Req { sub_id, filters } | ClientMessage::Count { sub_id, filters } => {
             mem::size_of_val(message)
                 + mem::size_of_val(sub_id)
                 + sub_id.len()
                 + filters.iter().map(mem::size_of_val).sum::<usize>()
         }
-        ClientMessage::Close { sub_id } => {
+        ClientMessage::Close { sub_id } | ClientMessage::NegClose { sub_id } => {
             mem::size_of_val(message) + mem::size_of_val(sub_id) + sub_id.len()
         }
+        ClientMessage::NegOpen {
+            sub_id,
+            filter,
+            message: msg,
+        } => mem::size_of_val(message) + sub_id.len() + mem::size_of_val(filter) + msg.len(),
+        ClientMessage::NegMsg {
+            sub_id,
+            message: msg,
+        } => mem::size_of_val(message) + sub_id.len() + msg.len(),
         ClientMessage::Raw(data) => mem::size_of_val(message) + data.len(),
     }
 }
@@ -246,9 +261,15 @@ fn calculate_error_size(error: &Error) -> usize {
 fn calculate_relay_message_size(message: &RelayMessage) -> usize {
     match message {
         RelayMessage::OK(result) => calculate_command_result_size(result),
+        RelayMessage::Closed(result) | RelayMessage::NegErr(result) => {
+            calculate_closed_result_size(result)
+        }
+        RelayMessage::NegMsg(sub_id, msg) => mem::size_of_val(message) + sub_id.len() + msg.len(),
+        RelayMessage::Count(result) => mem::size_of_val(message) + result.sub_id().len(),
         RelayMessage::Eose(str_ref)
         | RelayMessage::Event(str_ref, _)
-        | RelayMessage::Notice(str_ref) => mem::size_of_val(message) + str_ref.len(),
+        | RelayMessage::Notice(str_ref)
+        | RelayMessage::Auth(str_ref) => mem::size_of_val(message) + str_ref.len(),
     }
 }
 
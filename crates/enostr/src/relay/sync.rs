@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nostrdb::Filter;
+use tracing::{debug, warn};
+
+use crate::relay::negentropy::Negentropy;
+use crate::{ClientMessage, Error, RelayMessage};
+
+/// Relays that haven't answered a NEG-OPEN by then probably don't support
+/// NIP-77 and just ignored it
+const SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Missing events are fetched with filters of at most this many ids
+const IDS_PER_FILTER: usize = 256;
+
+/// What to reconcile with a relay, and what to subscribe to afterwards
+#[derive(Debug, Clone)]
+pub struct SyncRequest {
+    /// The events to reconcile
+    pub filter: Filter,
+
+    /// Created at and id of the events matching `filter` we already have
+    pub have: Vec<(u64, [u8; 32])>,
+
+    /// Subscribed to along with the missing events once synced, usually
+    /// to keep getting new events
+    pub followup: Vec<Filter>,
+
+    /// Subscribed to instead when the relay can't sync
+    pub fallback: Vec<Filter>,
+}
+
+/// Whether a relay speaks NIP-77, as far as we know
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncSupport {
+    #[default]
+    Unknown,
+    Supported,
+    Unsupported,
+}
+
+struct SyncSession {
+    negentropy: Negentropy,
+    need: Vec<[u8; 32]>,
+    followup: Vec<Filter>,
+    fallback: Vec<Filter>,
+    last_message: Instant,
+}
+
+/// NIP-77 negentropy syncs running on a single relay connection. Once a
+/// sync is done, the missing events and the followup filters are requested
+/// with a REQ under the same subscription id. Relays that refuse the sync,
+/// or never answer it, get the fallback REQ instead.
+#[derive(Default)]
+pub struct RelaySync {
+    support: SyncSupport,
+    sessions: HashMap<String, SyncSession>,
+}
+
+impl RelaySync {
+    pub fn support(&self) -> SyncSupport {
+        self.support
+    }
+
+    /// Forget the syncs of a closed connection. What we learned about
+    /// NIP-77 support is kept.
+    pub fn reset(&mut self) {
+        self.sessions.clear();
+    }
+
+    /// Start a sync. Returns the NEG-OPEN to send, or the fallback REQ if
+    /// the relay is known not to support NIP-77.
+    pub fn start(&mut self, sub_id: String, request: SyncRequest, now: Instant) -> ClientMessage {
+        if self.support == SyncSupport::Unsupported {
+            return ClientMessage::req(sub_id, request.fallback);
+        }
+
+        let negentropy = Negentropy::new(request.have);
+        let message = hex::encode(negentropy.initiate());
+
+        self.sessions.insert(
+            sub_id.clone(),
+            SyncSession {
+                negentropy,
+                need: vec![],
+                followup: request.followup,
+                fallback: request.fallback,
+                last_message: now,
+            },
+        );
+
+        ClientMessage::neg_open(sub_id, request.filter, message)
+    }
+
+    /// Handle NEG-MSG and NEG-ERR messages for our syncs. Returns what to
+    /// send back to the relay.
+    pub fn handle_message(&mut self, txt: &str, now: Instant) -> Vec<ClientMessage> {
+        if self.sessions.is_empty() || !txt.starts_with("[\"NEG-") {
+            return vec![];
+        }
+
+        match RelayMessage::from_json(txt) {
+            Ok(RelayMessage::NegMsg(sub_id, message)) => self.on_message(sub_id, message, now),
+            Ok(RelayMessage::NegErr(err)) => {
+                let Some(session) = self.sessions.remove(err.sub_id()) else {
+                    return vec![];
+                };
+
+                warn!("sync {} refused: {}", err.sub_id(), err.message());
+                vec![ClientMessage::req(
+                    err.sub_id().to_owned(),
+                    session.fallback,
+                )]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn on_message(&mut self, sub_id: &str, message: &str, now: Instant) -> Vec<ClientMessage> {
+        let Some(session) = self.sessions.get_mut(sub_id) else {
+            return vec![];
+        };
+
+        self.support = SyncSupport::Supported;
+        session.last_message = now;
+
+        let reconciled = hex::decode(message)
+            .map_err(|_| Error::HexDecodeFailed)
+            .and_then(|msg| session.negentropy.reconcile(&msg, &mut session.need));
+
+        match reconciled {
+            Ok(Some(next)) => vec![ClientMessage::neg_msg(sub_id.to_owned(), hex::encode(next))],
+
+            Ok(None) => {
+                let Some(session) = self.sessions.remove(sub_id) else {
+                    return vec![];
+                };
+
+                debug!("synced {sub_id}, missing {} events", session.need.len());
+                let mut filters: Vec<Filter> = session
+                    .need
+                    .chunks(IDS_PER_FILTER)
+                    .map(|ids| Filter::new().ids(ids).limit(ids.len() as u64).build())
+                    .collect();
+                filters.extend(session.followup);
+
+                let mut msgs = vec![ClientMessage::neg_close(sub_id.to_owned())];
+                if !filters.is_empty() {
+                    msgs.push(ClientMessage::req(sub_id.to_owned(), filters));
+                }
+                msgs
+            }
+
+            Err(err) => {
+                let Some(session) = self.sessions.remove(sub_id) else {
+                    return vec![];
+                };
+
+                warn!("sync {sub_id} failed: {err}");
+                vec![
+                    ClientMessage::neg_close(sub_id.to_owned()),
+                    ClientMessage::req(sub_id.to_owned(), session.fallback),
+                ]
+            }
+        }
+    }
+
+    /// Give up on syncs the relay stopped answering. Returns the fallback
+    /// REQs to send instead.
+    pub fn expire(&mut self, now: Instant) -> Vec<ClientMessage> {
+        let expired: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| {
+                now.saturating_duration_since(session.last_message) >= SYNC_TIMEOUT
+            })
+            .map(|(sub_id, _)| sub_id.clone())
+            .collect();
+
+        let mut msgs = vec![];
+        for sub_id in expired {
+            let Some(session) = self.sessions.remove(&sub_id) else {
+                continue;
+            };
+
+            if self.support == SyncSupport::Unknown {
+                debug!("no answer to sync {sub_id}, assuming no NIP-77 support");
+                self.support = SyncSupport::Unsupported;
+            }
+
+            msgs.push(ClientMessage::neg_close(sub_id.clone()));
+            msgs.push(ClientMessage::req(sub_id, session.fallback));
+        }
+
+        msgs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> SyncRequest {
+        SyncRequest {
+            filter: Filter::new().kinds([1]).build(),
+            have: vec![(1_700_000_000, [1; 32])],
+            followup: vec![Filter::new().kinds([1]).since(1_700_000_000).build()],
+            fallback: vec![Filter::new().kinds([1]).limit(250).build()],
+        }
+    }
+
+    fn is_req(msg: &ClientMessage, id: &str, num_filters: usize) -> bool {
+        matches!(msg, ClientMessage::Req { sub_id, filters }
+            if sub_id == id && filters.len() == num_filters)
+    }
+
+    #[test]
+    fn test_sync_needs_events() {
+        let now = Instant::now();
+        let mut sync = RelaySync::default();
+
+        let open = sync.start("sync".to_owned(), request(), now);
+        assert!(matches!(open, ClientMessage::NegOpen { .. }));
+
+        // the relay has one event we don't
+        let reply = format!(
+            r#"["NEG-MSG","sync","{}"]"#,
+            hex::encode([&[0x61, 0x00, 0x00, 0x02, 0x01][..], &[2; 32]].concat())
+        );
+        let msgs = sync.handle_message(&reply, now);
+        assert_eq!(sync.support(), SyncSupport::Supported);
+        assert_eq!(msgs.len(), 2);
+        assert!(matches!(&msgs[0], ClientMessage::NegClose { sub_id } if sub_id == "sync"));
+        // the missing id and the followup
+        assert!(is_req(&msgs[1], "sync", 2));
+
+        // done, nothing left to time out
+        assert!(sync.expire(now + SYNC_TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn test_sync_fallback() {
+        let now = Instant::now();
+        let mut sync = RelaySync::default();
+
+        sync.start("a".to_owned(), request(), now);
+        let msgs = sync.handle_message(r#"["NEG-ERR","a","blocked: too many records"]"#, now);
+        assert_eq!(msgs.len(), 1);
+        assert!(is_req(&msgs[0], "a", 1));
+        assert_eq!(sync.support(), SyncSupport::Unknown);
+
+        // relays that never answer don't support it
+        sync.start("b".to_owned(), request(), now);
+        assert!(sync.expire(now).is_empty());
+        let msgs = sync.expire(now + SYNC_TIMEOUT);
+        assert!(is_req(&msgs[1], "b", 1));
+        assert_eq!(sync.support(), SyncSupport::Unsupported);
+
+        let msg = sync.start("c".to_owned(), request(), now);
+        assert!(is_req(&msg, "c", 1));
+    }
+}
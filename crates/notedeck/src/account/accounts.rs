@@ -1,20 +1,28 @@
 use uuid::Uuid;
 
+use crate::account::app_data::AccountAppData;
 use crate::account::cache::AccountCache;
 use crate::account::contacts::Contacts;
-use crate::account::mute::AccountMutedData;
+use crate::account::dms::{dm_relays_note, AccountDmData};
+use crate::account::mute::{
+    mute_list_event, plain_content, AccountMutedData, MuteCrypto, MuteEdit,
+};
 use crate::account::relay::{
     modify_advertised_relays, update_relay_configuration, AccountRelayData, RelayAction,
     RelayDefaults,
 };
-use crate::storage::AccountStorageWriter;
+use crate::deletions::account_deletions_filter;
+use crate::storage::{AccountStorageReader, AccountStorageWriter};
 use crate::user_account::UserAccountSerializable;
 use crate::{
-    AccountStorage, MuteFun, SingleUnkIdAction, UnifiedSubscription, UnknownIds, UserAccount,
-    ZapWallet,
+    AccountStorage, Error, JobPool, LocalSigner, MuteFun, MuteItem, Muted, Outbox, RelaySpec,
+    RemoteLogin, RemoteSigner, RemoteSignerInfo, SignResult, Signer, SignerError,
+    SingleUnkIdAction, UnifiedSubscription, UnknownIds, UnsignedEvent, UserAccount, ZapWallet,
 };
-use enostr::{ClientMessage, FilledKeypair, Keypair, Pubkey, RelayPool};
+use enostr::{ClientMessage, FilledKeypair, Keypair, KeypairUnowned, Pubkey, RelayPool};
 use nostrdb::{Ndb, Note, Transaction};
+use poll_promise::Promise;
+use std::collections::{HashMap, HashSet};
 
 // TODO: remove this
 use std::sync::Arc;
@@ -23,9 +31,27 @@ use std::sync::Arc;
 /// Represents all user-facing operations related to account management.
 pub struct Accounts {
     pub cache: AccountCache,
+    storage_reader: Option<AccountStorageReader>,
     storage_writer: Option<AccountStorageWriter>,
+
+    /// Whether the stored accounts were loaded. They aren't until the
+    /// first unlock when the keystore has a passphrase.
+    storage_loaded: bool,
+
+    /// The stored keys being unlocked in the background, see
+    /// [`Accounts::start_unlock`]
+    unlocking: Option<Promise<crate::Result<UnlockedKeys>>>,
+
     relay_defaults: RelayDefaults,
     subs: AccountSubs,
+
+    /// Connections to the remote signers of accounts without a secret
+    /// key, made when they first sign
+    remote_signers: HashMap<Pubkey, RemoteSigner>,
+
+    /// The signer encrypting and decrypting the selected account's private
+    /// mutes
+    mute_crypto: MuteCrypto,
 }
 
 impl Accounts {
@@ -47,27 +73,35 @@ impl Accounts {
 
         unknown_id.process_action(unknown_ids, ndb, txn);
 
+        let mut storage_reader = None;
         let mut storage_writer = None;
+        let mut storage_loaded = false;
         if let Some(keystore) = key_store {
             let (reader, writer) = keystore.rw();
-            match reader.get_accounts() {
-                Ok(accounts) => {
-                    for account in accounts {
-                        add_account_from_storage(&mut cache, account).process_action(
-                            unknown_ids,
-                            ndb,
-                            txn,
-                        )
+            if writer.is_locked() {
+                tracing::info!("keystore is locked, loading accounts once unlocked");
+            } else {
+                match reader.get_accounts() {
+                    Ok(accounts) => {
+                        for account in accounts {
+                            add_account_from_storage(&mut cache, account).process_action(
+                                unknown_ids,
+                                ndb,
+                                txn,
+                            )
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("could not get keys: {e}");
                     }
                 }
-                Err(e) => {
-                    tracing::error!("could not get keys: {e}");
+                if let Some(selected) = reader.get_selected_key().ok().flatten() {
+                    cache.select(selected);
                 }
-            }
-            if let Some(selected) = reader.get_selected_key().ok().flatten() {
-                cache.select(selected);
+                storage_loaded = true;
             }
 
+            storage_reader = Some(reader);
             storage_writer = Some(writer);
         };
 
@@ -91,9 +125,14 @@ impl Accounts {
 
         Accounts {
             cache,
+            storage_reader,
             storage_writer,
+            storage_loaded,
+            unlocking: None,
             relay_defaults,
             subs,
+            remote_signers: HashMap::new(),
+            mute_crypto: MuteCrypto::default(),
         }
     }
 
@@ -107,6 +146,7 @@ impl Accounts {
         let Some(resp) = self.cache.remove(pk) else {
             return false;
         };
+        self.remote_signers.remove(pk);
 
         if pk != self.cache.fallback() {
             if let Some(key_store) = &self.storage_writer {
@@ -149,7 +189,9 @@ impl Accounts {
         };
 
         if let Some(key_store) = &self.storage_writer {
-            if let Err(e) = key_store.write_account(&acc.get_acc().into()) {
+            if key_store.is_locked() {
+                // written once the keystore is unlocked
+            } else if let Err(e) = key_store.write_account(&acc.get_acc().into()) {
                 tracing::error!("Could not add key for {:?}: {e}", kp.pubkey);
             }
         }
@@ -160,6 +202,45 @@ impl Accounts {
         })
     }
 
+    /// Add an account that signs with a NIP-46 remote signer, or connect an
+    /// existing account without a secret key to one
+    #[must_use = "UnknownIdAction's must be handled. Use .process_unknown_id_action()"]
+    pub fn add_remote_account(&mut self, login: RemoteLogin) -> Option<AddAccountResponse> {
+        let RemoteLogin { pubkey, info } = login;
+
+        let acc = if let Some(acc) = self.cache.get_mut(&pubkey) {
+            if acc.key.secret_key.is_some() {
+                tracing::info!("Already have the secret key of {pubkey}, not adding signer");
+                return None;
+            }
+
+            set_signer_relays(&mut acc.data, &info);
+            acc.remote_signer = Some(info);
+            AccType::Acc(&*acc)
+        } else {
+            let mut data = AccountData::new(pubkey.bytes());
+            set_signer_relays(&mut data, &info);
+            let acc = UserAccount::new(Keypair::only_pubkey(pubkey), data).with_remote_signer(info);
+            AccType::Entry(self.cache.add(acc))
+        };
+
+        if let Some(key_store) = &self.storage_writer {
+            if key_store.is_locked() {
+                // written once the keystore is unlocked
+            } else if let Err(e) = key_store.write_account(&acc.get_acc().into()) {
+                tracing::error!("Could not add account {pubkey}: {e}");
+            }
+        }
+
+        // connect again with the new signer
+        self.remote_signers.remove(&pubkey);
+
+        Some(AddAccountResponse {
+            switch_to: pubkey,
+            unk_id_action: SingleUnkIdAction::pubkey(pubkey),
+        })
+    }
+
     /// Update the `UserAccount` via callback and save the result to disk.
     /// return true if the update was successful
     pub fn update_current_account(&mut self, update: impl FnOnce(&mut UserAccount)) -> bool {
@@ -185,6 +266,54 @@ impl Accounts {
         self.get_selected_account().key.to_full()
     }
 
+    /// The selected account's keys, when we can sign for it, with its
+    /// secret key or through its remote signer
+    pub fn selected_poster(&self) -> Option<KeypairUnowned<'_>> {
+        let acc = self.get_selected_account();
+        (acc.key.secret_key.is_some() || acc.remote_signer.is_some()).then(|| acc.keypair())
+    }
+
+    /// Sign `event` for the selected account. Local keys sign right away,
+    /// remote signers answer when they get to it.
+    pub fn sign(&mut self, event: UnsignedEvent) -> Promise<SignResult> {
+        let pubkey = *self.selected_account_pubkey();
+        self.with_signer(&pubkey, |signer| signer.sign(event))
+    }
+
+    /// Ask the signer of `pubkey`'s account, its secret key or its remote
+    /// signer, to do `work`
+    fn with_signer<T: Send + 'static>(
+        &mut self,
+        pubkey: &Pubkey,
+        work: impl FnOnce(&mut dyn Signer) -> Promise<Result<T, SignerError>>,
+    ) -> Promise<Result<T, SignerError>> {
+        if let Some(keypair) = self.get_full(pubkey) {
+            return work(&mut LocalSigner::new(keypair));
+        }
+
+        let Some(info) = self
+            .cache
+            .get(pubkey)
+            .and_then(|acc| acc.remote_signer.as_ref())
+        else {
+            return Promise::from_ready(Err(SignerError::NoSigner));
+        };
+
+        work(
+            self.remote_signers
+                .entry(*pubkey)
+                .or_insert_with(|| RemoteSigner::new(*pubkey, info)),
+        )
+    }
+
+    /// Hand an event from a relay to the remote signers. Returns true when
+    /// it was the answer to one of their requests.
+    pub fn handle_signer_event(&mut self, sub_id: &str, event_json: &str) -> bool {
+        self.remote_signers
+            .values_mut()
+            .any(|signer| signer.handle_event(sub_id, event_json))
+    }
+
     /// Get the selected account's pubkey as bytes. Common operation so
     /// we make it a helper here.
     pub fn selected_account_pubkey_bytes(&self) -> &[u8; 32] {
@@ -249,7 +378,10 @@ impl Accounts {
             }
         }
 
-        self.get_selected_account_mut().data.query(ndb, txn);
+        let acc = self.cache.selected_mut();
+        acc.data.query(ndb, txn);
+        // until the new subscription tells us what the relays have
+        acc.data.muted.fetched = false;
         self.subs.swap_to(
             ndb,
             pool,
@@ -258,6 +390,11 @@ impl Accounts {
             &self.cache.selected().data,
             create_wakeup(ctx),
         );
+
+        // relays that asked the previous account to authenticate
+        if let Some(keypair) = self.selected_filled() {
+            pool.reauthenticate(keypair);
+        }
     }
 
     pub fn mutefun(&self) -> Box<MuteFun> {
@@ -272,6 +409,162 @@ impl Accounts {
         Box::new(Arc::clone(&account_data.muted.muted))
     }
 
+    /// Whether new mutes can be kept private. That needs a signer, and
+    /// that it read the private mutes already on the list.
+    pub fn can_mute_privately(&self) -> bool {
+        let muted = &self.get_selected_account().data.muted.muted;
+        self.selected_poster().is_some() && muted.unreadable.is_none()
+    }
+
+    /// Add `item` to the selected account's mute list and publish it. It is
+    /// published once a relay sent us the list it changes.
+    pub fn mute_item(&mut self, outbox: &mut Outbox, item: MuteItem, private: bool) {
+        let private = private && self.can_mute_privately();
+        self.update_mute_list(outbox, MuteEdit::Mute(item, private));
+    }
+
+    /// Take `item` off the selected account's mute list and publish it
+    pub fn unmute_item(&mut self, outbox: &mut Outbox, item: &MuteItem) {
+        self.update_mute_list(outbox, MuteEdit::Unmute(item.clone()));
+    }
+
+    fn update_mute_list(&mut self, outbox: &mut Outbox, edit: MuteEdit) {
+        if self.selected_poster().is_none() {
+            return;
+        }
+
+        // mute right away, the list we publish comes back to us later
+        let acc = self.cache.selected_mut();
+        let mut muted = Muted::clone(&acc.data.muted.muted);
+        edit.apply(&mut muted);
+        acc.data.muted.muted = Arc::new(muted);
+        acc.data.muted.pending.push(edit);
+
+        self.publish_mute_list(outbox);
+    }
+
+    /// Publish the selected account's unpublished mute list changes. They
+    /// wait until a relay sent us the list they go on top of, and for the
+    /// signer to encrypt the private entries.
+    fn publish_mute_list(&mut self, outbox: &mut Outbox) {
+        loop {
+            self.encrypt_mute_list();
+
+            let Some((pubkey, muted, promise)) = self.mute_crypto.encrypting.take() else {
+                return;
+            };
+
+            let content = match promise.try_take() {
+                Ok(Ok(content)) => content,
+                Ok(Err(e)) => {
+                    tracing::error!("could not encrypt private mutes: {e}");
+                    continue;
+                }
+                Err(promise) => {
+                    self.mute_crypto.encrypting = Some((pubkey, muted, promise));
+                    return;
+                }
+            };
+
+            if muted.unreadable.is_none() && !muted.private.is_empty() {
+                self.mute_crypto.encrypted = Some((content.clone(), muted.private_json()));
+            }
+
+            match mute_list_event(&muted, &pubkey, &content) {
+                Ok(event) => {
+                    let promise = self.with_signer(&pubkey, |signer| signer.sign(event));
+                    outbox.sign_and_send(promise, vec![]);
+                }
+                Err(e) => tracing::error!("could not build mute list: {e}"),
+            }
+        }
+    }
+
+    /// Have the signer encrypt the private entries of the selected
+    /// account's mute list, when it has changes to publish
+    fn encrypt_mute_list(&mut self) {
+        if self.mute_crypto.encrypting.is_some() {
+            return;
+        }
+
+        let acc = self.cache.selected_mut();
+        if !acc.data.muted.fetched || acc.data.muted.pending.is_empty() {
+            return;
+        }
+        acc.data.muted.pending.clear();
+
+        let pubkey = acc.key.pubkey;
+        let muted = Muted::clone(&acc.data.muted.muted);
+        let promise = match plain_content(&muted) {
+            Some(content) => Promise::from_ready(Ok(content)),
+            None => {
+                let json = muted.private_json();
+                self.with_signer(&pubkey, |signer| signer.nip44_encrypt(&pubkey, &json))
+            }
+        };
+
+        self.mute_crypto.encrypting = Some((pubkey, muted, promise));
+    }
+
+    /// Have the signer read the private entries of the selected account's
+    /// mute list
+    fn decrypt_mute_list(&mut self) {
+        let acc = self.cache.selected();
+        let Some(content) = &acc.data.muted.muted.unreadable else {
+            return;
+        };
+        let pubkey = acc.key.pubkey;
+
+        if self.mute_crypto.decrypted.as_ref() != Some(&(pubkey, content.clone())) {
+            if self.selected_poster().is_none() {
+                return;
+            }
+
+            let content = content.clone();
+            let ours = (self.mute_crypto.encrypted.as_ref())
+                .filter(|(encrypted, _)| *encrypted == content)
+                .map(|(_, json)| json.clone());
+            let promise = match ours {
+                Some(json) => Promise::from_ready(Ok(json)),
+                None => self.with_signer(&pubkey, |signer| signer.nip44_decrypt(&pubkey, &content)),
+            };
+            self.mute_crypto.decrypted = Some((pubkey, content));
+            self.mute_crypto.decrypting = Some(promise);
+        }
+
+        let Some(promise) = self.mute_crypto.decrypting.take() else {
+            return;
+        };
+        let json = match promise.try_take() {
+            Ok(Ok(json)) => json,
+            Ok(Err(e)) => {
+                tracing::warn!("could not decrypt private mutes: {e}");
+                return;
+            }
+            Err(promise) => {
+                self.mute_crypto.decrypting = Some(promise);
+                return;
+            }
+        };
+
+        let acc = self.cache.selected_mut();
+        let mut muted = Muted::clone(&acc.data.muted.muted);
+        muted.unreadable = None;
+        if let Err(e) = muted.add_private_json(&json) {
+            tracing::error!("bad private mutes: {e}");
+            return;
+        }
+        acc.data.muted.muted = Arc::new(acc.data.muted.with_pending(muted));
+    }
+
+    /// A relay finished sending what it has for `sub_id`. Mute list
+    /// changes are published once it did for the mute list.
+    pub fn on_eose(&mut self, sub_id: &str) {
+        if sub_id == self.subs.mute.remote {
+            self.cache.selected_mut().data.muted.fetched = true;
+        }
+    }
+
     pub fn send_initial_filters(&mut self, pool: &mut RelayPool, relay_url: &str) {
         let data = &self.get_selected_account().data;
         // send the active account's relay list subscription
@@ -297,18 +590,61 @@ impl Accounts {
             ),
             relay_url,
         );
+        pool.send_to(
+            &ClientMessage::req(
+                self.subs.channels.remote.clone(),
+                vec![data.app_data.channels_filter.clone()],
+            ),
+            relay_url,
+        );
+        pool.send_to(
+            &ClientMessage::req(
+                self.subs.dm_relays.remote.clone(),
+                vec![data.dms.relays_filter().clone()],
+            ),
+            relay_url,
+        );
+        pool.send_to(
+            &ClientMessage::req(
+                self.subs.gift_wraps.remote.clone(),
+                vec![data.dms.wraps_filter.clone()],
+            ),
+            relay_url,
+        );
+        pool.send_to(
+            &ClientMessage::req(
+                self.subs.deletions.clone(),
+                vec![account_deletions_filter(
+                    self.selected_account_pubkey_bytes(),
+                )],
+            ),
+            relay_url,
+        );
     }
 
-    pub fn update(&mut self, ndb: &mut Ndb, pool: &mut RelayPool, ctx: &egui::Context) {
+    pub fn update(
+        &mut self,
+        ndb: &mut Ndb,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        ctx: &egui::Context,
+    ) {
         // IMPORTANT - This function is called in the UI update loop,
         // make sure it is fast when idle
 
-        let Some(update) = self
+        for signer in self.remote_signers.values_mut() {
+            signer.update(pool);
+        }
+
+        let update = self
             .cache
             .selected_mut()
             .data
-            .poll_for_updates(ndb, &self.subs)
-        else {
+            .poll_for_updates(ndb, &self.subs);
+        self.decrypt_mute_list();
+        self.publish_mute_list(outbox);
+
+        let Some(update) = update else {
             return;
         };
 
@@ -320,7 +656,7 @@ impl Accounts {
                     pool,
                     &self.relay_defaults,
                     &acc.key.pubkey,
-                    &acc.data.relay,
+                    &acc.data,
                     create_wakeup(ctx),
                 );
             }
@@ -335,18 +671,237 @@ impl Accounts {
         &mut self,
         ctx: &egui::Context,
         pool: &mut RelayPool,
+        outbox: &mut Outbox,
         action: RelayAction,
     ) {
         let acc = self.cache.selected_mut();
-        modify_advertised_relays(&acc.key, action, pool, &self.relay_defaults, &mut acc.data);
+        modify_advertised_relays(action, &self.relay_defaults, &mut acc.data);
+
+        update_relay_configuration(
+            pool,
+            &self.relay_defaults,
+            &acc.key.pubkey,
+            &acc.data,
+            create_wakeup(ctx),
+        );
+
+        // If we can sign for the account publish the NIP-65 relay list
+        if self.selected_poster().is_none() {
+            return;
+        }
+
+        let acc = self.cache.selected();
+        let event = UnsignedEvent::new(&acc.key.pubkey, acc.data.relay.nip65_note());
+        match event {
+            Ok(event) => {
+                let promise = self.sign(event);
+                outbox.sign_and_send(promise, vec![]);
+            }
+            Err(e) => tracing::error!("could not build relay list: {e}"),
+        }
+    }
+
+    /// Set the relays the selected account receives direct messages on and
+    /// publish them as its kind 10050
+    pub fn set_dm_relays(
+        &mut self,
+        ctx: &egui::Context,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        relays: Vec<String>,
+    ) {
+        let acc = self.cache.selected_mut();
+        acc.data.dms.relays = relays
+            .iter()
+            .map(|url| RelaySpec::new(AccountRelayData::canonicalize_url(url), false, false))
+            .collect();
 
         update_relay_configuration(
             pool,
             &self.relay_defaults,
             &acc.key.pubkey,
-            &acc.data.relay,
+            &acc.data,
             create_wakeup(ctx),
         );
+
+        if self.selected_poster().is_none() {
+            return;
+        }
+
+        let acc = self.cache.selected();
+        let note = dm_relays_note(acc.data.dms.relays.iter().map(|spec| spec.url.as_str()));
+        match UnsignedEvent::new(&acc.key.pubkey, note) {
+            Ok(event) => {
+                let promise = self.sign(event);
+                outbox.sign_and_send(promise, vec![]);
+            }
+            Err(e) => tracing::error!("could not build dm relay list: {e}"),
+        }
+    }
+
+    /// Whether secret keys are stored locked with a passphrase
+    pub fn has_passphrase(&self) -> bool {
+        self.storage_writer
+            .as_ref()
+            .is_some_and(|writer| writer.has_passphrase())
+    }
+
+    /// Whether the keystore waits for its passphrase. The stored accounts
+    /// have no secret keys until then, or aren't loaded at all on startup.
+    pub fn is_locked(&self) -> bool {
+        self.storage_writer
+            .as_ref()
+            .is_some_and(|writer| writer.is_locked())
+    }
+
+    /// Whether secret keys added now are saved right away
+    pub fn can_store_keys(&self) -> bool {
+        self.storage_writer.is_some() && !self.is_locked()
+    }
+
+    /// Lock the stored secret keys with `passphrase`, or store them without
+    /// one again with `None`
+    pub fn set_passphrase(&mut self, passphrase: Option<&str>) -> crate::Result<()> {
+        let Some(writer) = &mut self.storage_writer else {
+            return Err(Error::Generic("no keystore to protect".to_owned()));
+        };
+
+        let fallback = *self.cache.fallback();
+        let accounts: Vec<UserAccountSerializable> = (&self.cache)
+            .into_iter()
+            .filter(|(pk, _)| **pk != fallback)
+            .map(|(_, acc)| acc.into())
+            .collect();
+
+        writer.set_passphrase(passphrase, &accounts)
+    }
+
+    /// Forget the passphrase and the secret keys of the stored accounts,
+    /// until [`Accounts::start_unlock`]
+    pub fn lock(&mut self) {
+        let (Some(reader), Some(writer)) = (&self.storage_reader, &mut self.storage_writer) else {
+            return;
+        };
+        if !writer.has_passphrase() {
+            return;
+        }
+
+        writer.lock();
+
+        let stored = match reader.get_accounts() {
+            Ok(stored) => stored,
+            Err(e) => {
+                tracing::error!("could not get keys to lock: {e}");
+                return;
+            }
+        };
+        for account in stored {
+            if let Some(acc) = self.cache.get_mut(&account.key.pubkey) {
+                acc.key.secret_key = None;
+            }
+        }
+    }
+
+    /// Start unlocking the keystore with `passphrase`. NIP-49 keys take a
+    /// while to unlock, so it happens on the job pool, and
+    /// [`Accounts::poll_unlock`] finishes it.
+    pub fn start_unlock(&mut self, passphrase: String, job_pool: &JobPool) {
+        let Some(reader) = &self.storage_reader else {
+            return;
+        };
+        if self.unlocking.is_some() {
+            return;
+        }
+
+        let reader = reader.clone();
+        let unsaved: Vec<UserAccountSerializable> = (&self.cache)
+            .into_iter()
+            .filter(|(_, acc)| acc.key.secret_key.is_some())
+            .map(|(_, acc)| acc.into())
+            .collect();
+
+        self.unlocking = Some(Promise::spawn_async(
+            job_pool.schedule(move || unlock_keys(&reader, passphrase, unsaved)),
+        ));
+    }
+
+    /// Whether [`Accounts::start_unlock`] is still working
+    pub fn is_unlocking(&self) -> bool {
+        self.unlocking.is_some()
+    }
+
+    /// Finish unlocking the keystore, once the keys are unlocked. The stored
+    /// accounts are loaded on the first unlock, and get their secret keys
+    /// back on later ones.
+    #[allow(clippy::too_many_arguments)]
+    pub fn poll_unlock(
+        &mut self,
+        ndb: &mut Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        ctx: &egui::Context,
+        unknown_ids: &mut UnknownIds,
+    ) -> Option<crate::Result<()>> {
+        let promise = self.unlocking.take()?;
+        let unlocked = match promise.try_take() {
+            Ok(unlocked) => unlocked,
+            Err(promise) => {
+                self.unlocking = Some(promise);
+                return None;
+            }
+        };
+
+        Some(unlocked.map(|unlocked| self.unlocked(unlocked, ndb, txn, pool, ctx, unknown_ids)))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn unlocked(
+        &mut self,
+        unlocked: UnlockedKeys,
+        ndb: &mut Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        ctx: &egui::Context,
+        unknown_ids: &mut UnknownIds,
+    ) {
+        let (Some(reader), Some(writer)) = (&self.storage_reader, &mut self.storage_writer) else {
+            return;
+        };
+
+        writer.unlocked(unlocked.passphrase);
+        let selected = if self.storage_loaded {
+            None
+        } else {
+            reader.get_selected_key().ok().flatten()
+        };
+
+        for account in unlocked.stored {
+            if let Some(acc) = self.cache.get_mut(&account.key.pubkey) {
+                if acc.key.secret_key.is_none() {
+                    acc.key.secret_key = account.key.secret_key;
+                }
+            } else {
+                add_account_from_storage(&mut self.cache, account).process_action(
+                    unknown_ids,
+                    ndb,
+                    txn,
+                );
+            }
+        }
+
+        if let Some(writer) = &self.storage_writer {
+            for account in &unlocked.relocked {
+                if let Err(e) = writer.write_account(account) {
+                    tracing::error!("Could not lock key for {}: {e}", account.key.pubkey);
+                }
+            }
+        }
+
+        // the private mutes can be read now, see Accounts::update
+        self.storage_loaded = true;
+        if let Some(selected) = selected {
+            self.select_account(&selected, ndb, txn, pool, ctx);
+        }
     }
 
     pub fn get_subs(&self) -> &AccountSubs {
@@ -359,6 +914,61 @@ impl Accounts {
     }
 }
 
+/// The keys unlocked by [`Accounts::start_unlock`]
+struct UnlockedKeys {
+    /// The checked passphrase
+    passphrase: String,
+
+    /// The stored accounts, with their secret keys
+    stored: Vec<UserAccountSerializable>,
+
+    /// Keys that weren't locked with the passphrase yet, from older key
+    /// files or added while locked, locked now and ready to be written
+    relocked: Vec<UserAccountSerializable>,
+}
+
+/// Unlock the stored keys, and lock the ones that should be. Runs on the
+/// job pool.
+fn unlock_keys(
+    reader: &AccountStorageReader,
+    passphrase: String,
+    unsaved: Vec<UserAccountSerializable>,
+) -> crate::Result<UnlockedKeys> {
+    let stored = reader.get_unlocked_accounts(&passphrase)?;
+
+    let mut locked = HashSet::new();
+    let mut to_lock = Vec::new();
+    for account in &stored {
+        if account.key.secret_key.is_none() {
+            continue;
+        }
+        if account.locked_key.is_some() {
+            locked.insert(account.key.pubkey);
+        } else {
+            to_lock.push(account.clone());
+        }
+    }
+    to_lock.extend(unsaved);
+
+    let mut relocked = Vec::new();
+    for mut account in to_lock {
+        if !locked.insert(account.key.pubkey) {
+            continue;
+        }
+
+        match reader.lock_account(&mut account, &passphrase) {
+            Ok(()) => relocked.push(account),
+            Err(e) => tracing::error!("Could not lock key for {}: {e}", account.key.pubkey),
+        }
+    }
+
+    Ok(UnlockedKeys {
+        passphrase,
+        stored,
+        relocked,
+    })
+}
+
 enum AccType<'a> {
     Entry(hashbrown::hash_map::OccupiedEntry<'a, Pubkey, UserAccount>),
     Acc(&'a UserAccount),
@@ -396,7 +1006,10 @@ fn add_account_from_storage(
 
 fn get_acc_from_storage(user_account_serializable: UserAccountSerializable) -> Option<UserAccount> {
     let keypair = user_account_serializable.key;
-    let new_account_data = AccountData::new(keypair.pubkey.bytes());
+    let mut new_account_data = AccountData::new(keypair.pubkey.bytes());
+    if let Some(info) = &user_account_serializable.remote_signer {
+        set_signer_relays(&mut new_account_data, info);
+    }
 
     let mut wallet = None;
     if let Some(wallet_s) = user_account_serializable.wallet {
@@ -412,15 +1025,27 @@ fn get_acc_from_storage(user_account_serializable: UserAccountSerializable) -> O
     Some(UserAccount {
         key: keypair,
         wallet,
+        remote_signer: user_account_serializable.remote_signer,
         data: new_account_data,
     })
 }
 
+/// Stay connected to the relays the account's remote signer listens on
+fn set_signer_relays(data: &mut AccountData, info: &RemoteSignerInfo) {
+    data.relay.signer = info
+        .relays
+        .iter()
+        .map(|url| RelaySpec::new(url.clone(), false, false))
+        .collect();
+}
+
 #[derive(Clone)]
 pub struct AccountData {
     pub(crate) relay: AccountRelayData,
     pub(crate) muted: AccountMutedData,
     pub contacts: Contacts,
+    pub app_data: AccountAppData,
+    pub dms: AccountDmData,
 }
 
 impl AccountData {
@@ -429,6 +1054,8 @@ impl AccountData {
             relay: AccountRelayData::new(pubkey),
             muted: AccountMutedData::new(pubkey),
             contacts: Contacts::new(pubkey),
+            app_data: AccountAppData::new(pubkey),
+            dms: AccountDmData::new(pubkey),
         }
     }
 
@@ -443,6 +1070,10 @@ impl AccountData {
             resp = Some(AccountDataUpdate::Relay);
         }
 
+        if self.dms.poll_for_updates(ndb, &txn, subs.dm_relays.local) {
+            resp = Some(AccountDataUpdate::Relay);
+        }
+
         self.muted.poll_for_updates(ndb, &txn, subs.mute.local);
         self.contacts
             .poll_for_updates(ndb, &txn, subs.contacts.local);
@@ -455,6 +1086,7 @@ impl AccountData {
         self.relay.query(ndb, txn);
         self.muted.query(ndb, txn);
         self.contacts.query(ndb, txn);
+        self.dms.query(ndb, txn);
     }
 }
 
@@ -471,6 +1103,15 @@ pub struct AccountSubs {
     relay: UnifiedSubscription,
     mute: UnifiedSubscription,
     pub contacts: UnifiedSubscription,
+    /// The synced channel list. Polled by the app that owns the channels.
+    pub channels: UnifiedSubscription,
+    dm_relays: UnifiedSubscription,
+    /// Gift wraps addressed to the account. Polled by the app that shows
+    /// the direct messages.
+    pub gift_wraps: UnifiedSubscription,
+    /// The account's deletions. Only remote, `Deletions` reads every
+    /// deletion nostrdb gets.
+    deletions: String,
 }
 
 impl AccountSubs {
@@ -485,12 +1126,26 @@ impl AccountSubs {
         let relay = subscribe(ndb, pool, &data.relay.filter);
         let mute = subscribe(ndb, pool, &data.muted.filter);
         let contacts = subscribe(ndb, pool, &data.contacts.filter);
-        update_relay_configuration(pool, relay_defaults, pk, &data.relay, wakeup);
+        let channels = subscribe(ndb, pool, &data.app_data.channels_filter);
+        let dm_relays = subscribe(ndb, pool, data.dms.relays_filter());
+        let gift_wraps = subscribe(ndb, pool, &data.dms.wraps_filter);
+
+        let deletions = Uuid::new_v4().to_string();
+        pool.subscribe(
+            deletions.clone(),
+            vec![account_deletions_filter(pk.bytes())],
+        );
+
+        update_relay_configuration(pool, relay_defaults, pk, data, wakeup);
 
         Self {
             relay,
             mute,
             contacts,
+            channels,
+            dm_relays,
+            gift_wraps,
+            deletions,
         }
     }
 
@@ -506,13 +1161,23 @@ impl AccountSubs {
         unsubscribe(ndb, pool, &self.relay);
         unsubscribe(ndb, pool, &self.mute);
         unsubscribe(ndb, pool, &self.contacts);
+        unsubscribe(ndb, pool, &self.channels);
+        unsubscribe(ndb, pool, &self.dm_relays);
+        unsubscribe(ndb, pool, &self.gift_wraps);
+        pool.unsubscribe(self.deletions.clone());
 
         *self = AccountSubs::new(ndb, pool, relay_defaults, pk, new_selection_data, wakeup);
     }
 
     /// Check if a subscription ID belongs to any of the account subscriptions
     pub fn is_account_sub(&self, subid: &str) -> bool {
-        self.relay.remote == subid || self.mute.remote == subid || self.contacts.remote == subid
+        self.relay.remote == subid
+            || self.mute.remote == subid
+            || self.contacts.remote == subid
+            || self.channels.remote == subid
+            || self.dm_relays.remote == subid
+            || self.gift_wraps.remote == subid
+            || self.deletions == subid
     }
 }
 
@@ -0,0 +1,26 @@
+use nostrdb::Filter;
+
+/// NIP-78 application data, a parameterized replaceable event
+pub const KIND_APP_DATA: u32 = 30078;
+
+/// `d` tag of the app data event holding the account's channel list
+pub const CHANNELS_APP_DATA_ID: &str = "notedeck/channels";
+
+/// Application data the account syncs between its devices
+#[derive(Clone)]
+pub struct AccountAppData {
+    pub channels_filter: Filter,
+}
+
+impl AccountAppData {
+    pub fn new(pubkey: &[u8; 32]) -> Self {
+        let channels_filter = Filter::new()
+            .authors([pubkey])
+            .kinds([KIND_APP_DATA as u64])
+            .tags([CHANNELS_APP_DATA_ID], 'd')
+            .limit(1)
+            .build();
+
+        Self { channels_filter }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+
+use enostr::nip59::KIND_GIFT_WRAP;
+use nostrdb::{Filter, Ndb, Note, NoteBuilder, Subscription, Transaction};
+use tracing::debug;
+
+use crate::account::relay::AccountRelayData;
+use crate::RelaySpec;
+
+/// NIP-17 list of the relays an account receives direct messages on
+pub const KIND_DM_RELAYS: u32 = 10050;
+
+/// NIP-17 direct messages come in NIP-59 gift wraps, on the relays the
+/// account lists in its kind 10050
+#[derive(Clone)]
+pub struct AccountDmData {
+    /// Gift wraps addressed to the account. Polled by the app that shows
+    /// the messages.
+    pub wraps_filter: Filter,
+    relays_filter: Filter,
+    /// Where the account receives direct messages. Used locally, like the
+    /// relays of a remote signer.
+    pub relays: BTreeSet<RelaySpec>,
+}
+
+impl AccountDmData {
+    pub fn new(pubkey: &[u8; 32]) -> Self {
+        let wraps_filter = Filter::new()
+            .kinds([KIND_GIFT_WRAP as u64])
+            .pubkeys([pubkey])
+            .build();
+
+        Self {
+            wraps_filter,
+            relays_filter: dm_relays_filter(pubkey),
+            relays: BTreeSet::new(),
+        }
+    }
+
+    pub(super) fn relays_filter(&self) -> &Filter {
+        &self.relays_filter
+    }
+
+    pub(super) fn query(&mut self, ndb: &Ndb, txn: &Transaction) {
+        let Ok(results) = ndb.query(txn, std::slice::from_ref(&self.relays_filter), 1) else {
+            return;
+        };
+
+        if let Some(result) = results.first() {
+            self.relays = relay_specs(&result.note);
+            debug!("initial dm relays {:?}", self.relays);
+        }
+    }
+
+    /// Returns true if the account's kind 10050 changed
+    pub(super) fn poll_for_updates(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        sub: Subscription,
+    ) -> bool {
+        let Some(note) = ndb
+            .poll_for_notes(sub, 1)
+            .into_iter()
+            .filter_map(|key| ndb.get_note_by_key(txn, key).ok())
+            .max_by_key(|note| note.created_at())
+        else {
+            return false;
+        };
+
+        self.relays = relay_specs(&note);
+        debug!("updated dm relays {:?}", self.relays);
+        true
+    }
+}
+
+fn dm_relays_filter(pubkey: &[u8; 32]) -> Filter {
+    Filter::new()
+        .authors([pubkey])
+        .kinds([KIND_DM_RELAYS as u64])
+        .limit(1)
+        .build()
+}
+
+fn relay_specs(note: &Note) -> BTreeSet<RelaySpec> {
+    harvest_dm_relays(note)
+        .into_iter()
+        .map(|url| RelaySpec::new(url, false, false))
+        .collect()
+}
+
+/// The `relay` tags of a kind 10050
+fn harvest_dm_relays(note: &Note) -> Vec<String> {
+    note.tags()
+        .iter()
+        .filter(|tag| tag.count() >= 2 && tag.get_str(0) == Some("relay"))
+        .filter_map(|tag| tag.get_str(1))
+        .map(AccountRelayData::canonicalize_url)
+        .collect()
+}
+
+/// The relays `pubkey` receives direct messages on, if we have their kind
+/// 10050
+pub fn dm_inbox_relays(ndb: &Ndb, txn: &Transaction, pubkey: &[u8; 32]) -> Vec<String> {
+    let filter = dm_relays_filter(pubkey);
+    ndb.query(txn, std::slice::from_ref(&filter), 1)
+        .ok()
+        .and_then(|results| results.into_iter().next())
+        .map(|result| harvest_dm_relays(&result.note))
+        .unwrap_or_default()
+}
+
+/// A kind 10050 listing `relays`, for the account's signer to sign
+pub fn dm_relays_note<'a>(relays: impl IntoIterator<Item = &'a str>) -> NoteBuilder<'a> {
+    let mut builder = NoteBuilder::new().kind(KIND_DM_RELAYS).content("");
+    for relay in relays {
+        builder = builder.start_tag().tag_str("relay").tag_str(relay);
+    }
+    builder
+}
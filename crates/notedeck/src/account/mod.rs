@@ -1,6 +1,8 @@
 pub mod accounts;
+pub mod app_data;
 pub mod cache;
 pub mod contacts;
+pub mod dms;
 pub mod mute;
 pub mod relay;
 
@@ -1,14 +1,27 @@
 use std::sync::Arc;
 
-use nostrdb::{Filter, Ndb, NoteKey, Subscription, Transaction};
+use enostr::Pubkey;
+use nostrdb::{Filter, Ndb, NoteBuilder, NoteKey, Subscription, Transaction};
+use poll_promise::Promise;
 use tracing::{debug, error};
 
-use crate::Muted;
+use crate::{CryptResult, MuteItem, Muted, UnsignedEvent};
+
+/// NIP-51 mute list
+pub const KIND_MUTE_LIST: u32 = 10000;
 
 #[derive(Clone)]
 pub(crate) struct AccountMutedData {
     pub filter: Filter,
     pub muted: Arc<Muted>,
+
+    /// Whether a relay sent us the list, or told us it has none.
+    /// Publishing before that would replace a list we haven't seen.
+    pub fetched: bool,
+
+    /// Changes that aren't published yet. They are made again to every
+    /// list that comes in until they are.
+    pub pending: Vec<MuteEdit>,
 }
 
 impl AccountMutedData {
@@ -16,13 +29,15 @@ impl AccountMutedData {
         // Construct a filter for the user's NIP-51 muted list
         let filter = Filter::new()
             .authors([pubkey])
-            .kinds([10000])
+            .kinds([KIND_MUTE_LIST as u64])
             .limit(1)
             .build();
 
         AccountMutedData {
             filter,
             muted: Arc::new(Muted::default()),
+            fetched: false,
+            pending: vec![],
         }
     }
 
@@ -38,16 +53,21 @@ impl AccountMutedData {
             .iter()
             .map(|qr| qr.note_key)
             .collect::<Vec<NoteKey>>();
-        let muted = Self::harvest_nip51_muted(ndb, txn, &nks);
+        let muted = self.with_pending(Self::harvest_nip51_muted(ndb, txn, &nks));
         debug!("initial muted {:?}", muted);
 
         self.muted = Arc::new(muted);
     }
 
+    /// The private entries are left encrypted in [`Muted::unreadable`] for
+    /// the account's signer to read
     pub(crate) fn harvest_nip51_muted(ndb: &Ndb, txn: &Transaction, nks: &[NoteKey]) -> Muted {
         let mut muted = Muted::default();
         for nk in nks.iter() {
             if let Ok(note) = ndb.get_note_by_key(txn, *nk) {
+                if !note.content().is_empty() {
+                    muted.unreadable = Some(note.content().to_owned());
+                }
                 for tag in note.tags() {
                     match tag.get(0).and_then(|t| t.variant().str()) {
                         Some("p") => {
@@ -57,12 +77,12 @@ impl AccountMutedData {
                         }
                         Some("t") => {
                             if let Some(str) = tag.get(1).and_then(|f| f.variant().str()) {
-                                muted.hashtags.insert(str.to_string());
+                                muted.insert(MuteItem::hashtag(str), false);
                             }
                         }
                         Some("word") => {
                             if let Some(str) = tag.get(1).and_then(|f| f.variant().str()) {
-                                muted.words.insert(str.to_string());
+                                muted.insert(MuteItem::word(str), false);
                             }
                         }
                         Some("e") => {
@@ -92,8 +112,120 @@ impl AccountMutedData {
             return;
         }
 
-        let muted = AccountMutedData::harvest_nip51_muted(ndb, txn, &nks);
+        let muted = self.with_pending(AccountMutedData::harvest_nip51_muted(ndb, txn, &nks));
         debug!("updated muted {:?}", muted);
         self.muted = Arc::new(muted);
     }
+
+    /// Make the unpublished changes to `muted`
+    pub(super) fn with_pending(&self, mut muted: Muted) -> Muted {
+        for edit in &self.pending {
+            edit.apply(&mut muted);
+        }
+        muted
+    }
+}
+
+/// A change to the mute list
+#[derive(Debug, Clone)]
+pub(crate) enum MuteEdit {
+    /// Mute an item, privately or not
+    Mute(MuteItem, bool),
+    Unmute(MuteItem),
+}
+
+impl MuteEdit {
+    pub fn apply(&self, muted: &mut Muted) {
+        match self {
+            MuteEdit::Mute(item, private) => muted.insert(item.clone(), *private),
+            MuteEdit::Unmute(item) => muted.remove(item),
+        }
+    }
+}
+
+/// NIP-44 work the account's signer does for the selected account's mute
+/// list. Remote signers take their time, so it's polled every frame.
+#[derive(Default)]
+pub(crate) struct MuteCrypto {
+    /// The private entries being encrypted, and the list they belong to
+    pub encrypting: Option<(Pubkey, Muted, Promise<CryptResult>)>,
+
+    /// The last private entries we read or tried to, and the signer
+    /// reading them until it answers
+    pub decrypted: Option<(Pubkey, String)>,
+    pub decrypting: Option<Promise<CryptResult>>,
+
+    /// The private entries we encrypted last, so our own list coming back
+    /// doesn't need the signer again
+    pub encrypted: Option<(String, String)>,
+}
+
+/// The content of a mute list when it doesn't need encrypting: the private
+/// entries we couldn't read, published again as they were, or nothing
+pub(crate) fn plain_content(muted: &Muted) -> Option<String> {
+    match &muted.unreadable {
+        Some(unreadable) => Some(unreadable.clone()),
+        None if muted.private.is_empty() => Some(String::new()),
+        None => None,
+    }
+}
+
+/// A kind 10000 with the public entries of `muted` as tags and the
+/// encrypted private entries as its content, ready for the account's
+/// signer
+pub(crate) fn mute_list_event(
+    muted: &Muted,
+    author: &Pubkey,
+    content: &str,
+) -> crate::Result<UnsignedEvent> {
+    let tags = muted.public_tags();
+    let mut builder = NoteBuilder::new().kind(KIND_MUTE_LIST).content(content);
+    for [name, value] in &tags {
+        builder = builder.start_tag().tag_str(name).tag_str(value);
+    }
+
+    Ok(UnsignedEvent::new(author, builder)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_edits_survive_incoming_lists() {
+        let mut data = AccountMutedData::new(&[1; 32]);
+        data.pending
+            .push(MuteEdit::Mute(MuteItem::hashtag("nsfw"), true));
+        data.pending
+            .push(MuteEdit::Unmute(MuteItem::Pubkey([2; 32])));
+
+        // the list a relay sent us, from before our changes
+        let mut remote = Muted::default();
+        remote.insert(MuteItem::Pubkey([2; 32]), false);
+        remote.insert(MuteItem::word("gm"), false);
+
+        let muted = data.with_pending(remote);
+        assert!(muted.is_private(&MuteItem::hashtag("nsfw")));
+        assert!(!muted.is_pk_muted(&[2; 32]));
+        assert!(muted.contains(&MuteItem::word("gm")));
+
+        // the private entry needs the signer before publishing
+        assert_eq!(plain_content(&muted), None);
+    }
+
+    #[test]
+    fn test_unreadable_entries_are_kept() {
+        let mut muted = Muted::default();
+        muted.insert(MuteItem::word("gm"), false);
+        assert_eq!(plain_content(&muted).as_deref(), Some(""));
+
+        muted.unreadable = Some("nip04?iv=".to_owned());
+        assert_eq!(plain_content(&muted).as_deref(), Some("nip04?iv="));
+
+        let author = Pubkey::new([3; 32]);
+        let event = mute_list_event(&muted, &author, "nip04?iv=").expect("event");
+        assert_eq!(event.kind, KIND_MUTE_LIST);
+        assert_eq!(event.content, "nip04?iv=");
+        assert_eq!(event.tags, vec![vec!["word".to_owned(), "gm".to_owned()]]);
+    }
 }
@@ -1,7 +1,7 @@
 use std::collections::BTreeSet;
 
 use crate::{AccountData, RelaySpec};
-use enostr::{Keypair, Pubkey, RelayPool};
+use enostr::{Pubkey, RelayPool};
 use nostrdb::{Filter, Ndb, NoteBuilder, NoteKey, Subscription, Transaction};
 use tracing::{debug, error, info};
 use url::Url;
@@ -11,6 +11,7 @@ pub(crate) struct AccountRelayData {
     pub filter: Filter,
     pub local: BTreeSet<RelaySpec>, // used locally but not advertised
     pub advertised: BTreeSet<RelaySpec>, // advertised via NIP-65
+    pub signer: BTreeSet<RelaySpec>, // where the remote signer listens
 }
 
 impl AccountRelayData {
@@ -26,6 +27,7 @@ impl AccountRelayData {
             filter,
             local: BTreeSet::new(),
             advertised: BTreeSet::new(),
+            signer: BTreeSet::new(),
         }
     }
 
@@ -96,7 +98,8 @@ impl AccountRelayData {
         relays
     }
 
-    pub fn publish_nip65_relays(&self, seckey: &[u8; 32], pool: &mut RelayPool) {
+    /// Our NIP-65 relay list, for the account's signer to sign
+    pub fn nip65_note(&self) -> NoteBuilder<'_> {
         let mut builder = NoteBuilder::new().kind(10002).content("");
         for rs in &self.advertised {
             builder = builder.start_tag().tag_str("r").tag_str(&rs.url);
@@ -106,8 +109,7 @@ impl AccountRelayData {
                 builder = builder.tag_str("write");
             }
         }
-        let note = builder.sign(seckey).build().expect("note build");
-        pool.send(&enostr::ClientMessage::event(&note).expect("note client message"));
+        builder
     }
 
     pub fn poll_for_updates(&mut self, ndb: &Ndb, txn: &Transaction, sub: Subscription) -> bool {
@@ -125,6 +127,22 @@ impl AccountRelayData {
     }
 }
 
+/// The relays `pubkey` publishes to according to their NIP-65 relay list,
+/// if we have it. This is where their notes are most likely to be found.
+pub fn nip65_write_relays(ndb: &Ndb, txn: &Transaction, pubkey: &[u8; 32]) -> Vec<String> {
+    let filter = AccountRelayData::new(pubkey).filter;
+    let Ok(results) = ndb.query(txn, std::slice::from_ref(&filter), 1) else {
+        return vec![];
+    };
+
+    let nks: Vec<NoteKey> = results.iter().map(|qr| qr.note_key).collect();
+    AccountRelayData::harvest_nip65_relays(ndb, txn, &nks)
+        .into_iter()
+        .filter(|spec| spec.is_writable())
+        .map(|spec| spec.url)
+        .collect()
+}
+
 pub(crate) struct RelayDefaults {
     pub forced_relays: BTreeSet<RelaySpec>,
     pub bootstrap_relays: BTreeSet<RelaySpec>,
@@ -159,7 +177,7 @@ pub(super) fn update_relay_configuration(
     pool: &mut RelayPool,
     relay_defaults: &RelayDefaults,
     pk: &Pubkey,
-    data: &AccountRelayData,
+    data: &AccountData,
     wakeup: impl Fn() + Send + Sync + Clone + 'static,
 ) {
     debug!(
@@ -172,8 +190,8 @@ pub(super) fn update_relay_configuration(
 
     // Compose the desired relay lists from the selected account
     if desired_relays.is_empty() {
-        desired_relays.extend(data.local.iter().cloned());
-        desired_relays.extend(data.advertised.iter().cloned());
+        desired_relays.extend(data.relay.local.iter().cloned());
+        desired_relays.extend(data.relay.advertised.iter().cloned());
     }
 
     // If no relays are specified at this point use the bootstrap list
@@ -181,6 +199,14 @@ pub(super) fn update_relay_configuration(
         desired_relays = relay_defaults.bootstrap_relays.clone();
     }
 
+    // the remote signer can't sign without its relays, forced or not
+    desired_relays.extend(data.relay.signer.iter().cloned());
+
+    // and the ones we receive direct messages on, unless relays are forced
+    if relay_defaults.forced_relays.is_empty() {
+        desired_relays.extend(data.dms.relays.iter().cloned());
+    }
+
     debug!("current relays: {:?}", pool.urls());
     debug!("desired relays: {:?}", desired_relays);
 
@@ -223,9 +249,7 @@ impl RelayAction {
 }
 
 pub(super) fn modify_advertised_relays(
-    kp: &Keypair,
     action: RelayAction,
-    pool: &mut RelayPool,
     relay_defaults: &RelayDefaults,
     account_data: &mut AccountData,
 ) {
@@ -251,11 +275,51 @@ pub(super) fn modify_advertised_relays(
             advertised.remove(&RelaySpec::new(relay_url, false, false));
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected(pool: &RelayPool, url: &str) -> bool {
+        pool.urls()
+            .iter()
+            .any(|pool_url| pool_url.trim_end_matches('/') == url)
+    }
+
+    #[test]
+    fn test_pinned_relay_survives_update() {
+        let channel_relay = "ws://127.0.0.1:1";
+        let stale_relay = "ws://127.0.0.1:2";
+        let forced_relay = "ws://127.0.0.1:3";
+
+        let mut pool = RelayPool::new();
+        pool.add_url(channel_relay.to_owned(), || {}).unwrap();
+        pool.pin_url(channel_relay, "channels");
+        pool.add_url(stale_relay.to_owned(), || {}).unwrap();
+
+        let pk = Pubkey::new([1; 32]);
+        update_relay_configuration(
+            &mut pool,
+            &RelayDefaults::new(vec![forced_relay.to_owned()]),
+            &pk,
+            &AccountData::new(pk.bytes()),
+            || {},
+        );
+
+        assert!(connected(&pool, channel_relay));
+        assert!(!connected(&pool, stale_relay));
+        assert!(connected(&pool, forced_relay));
 
-    // If we have the secret key publish the NIP-65 relay list
-    if let Some(secretkey) = &kp.secret_key {
-        account_data
-            .relay
-            .publish_nip65_relays(&secretkey.to_secret_bytes(), pool);
+        // dropped by the next update once nothing needs it
+        pool.unpin_url(channel_relay, "channels");
+        update_relay_configuration(
+            &mut pool,
+            &RelayDefaults::new(vec![forced_relay.to_owned()]),
+            &pk,
+            &AccountData::new(pk.bytes()),
+            || {},
+        );
+        assert!(!connected(&pool, channel_relay));
     }
 }
@@ -1,19 +1,21 @@
 use crate::account::FALLBACK_PUBKEY;
 use crate::i18n::Localization;
+use crate::key_lock::KeyLock;
 use crate::persist::{AppSizeHandler, SettingsHandler};
 use crate::wallet::GlobalWallet;
 use crate::zaps::Zaps;
-use crate::Error;
 use crate::JobPool;
 use crate::NotedeckOptions;
 use crate::{
     frame_history::FrameHistory, AccountStorage, Accounts, AppContext, Args, DataPath,
-    DataPathType, Directory, Images, NoteAction, NoteCache, RelayDebugView, UnknownIds,
+    DataPathType, Deletions, Directory, Images, NoteAction, NoteCache, Outbox, RelayDebugView,
+    RelayInfoCache, UnknownIds,
 };
+use crate::{tr, Error, KeystoreError};
 use egui::Margin;
 use egui::ThemePreference;
 use egui_winit::clipboard::Clipboard;
-use enostr::RelayPool;
+use enostr::{MockRelay, RelayPool};
 use nostrdb::{Config, Ndb, Transaction};
 use std::cell::RefCell;
 use std::collections::BTreeSet;
@@ -78,6 +80,10 @@ pub struct Notedeck {
     frame_history: FrameHistory,
     job_pool: JobPool,
     i18n: Localization,
+    outbox: Outbox,
+    deletions: Deletions,
+    relay_info: RelayInfoCache,
+    key_lock: KeyLock,
 
     #[cfg(target_os = "android")]
     android_app: Option<AndroidApp>,
@@ -116,6 +122,48 @@ fn render_notedeck(notedeck: &mut Notedeck, ctx: &egui::Context) {
     });
 }
 
+/// The unlock screen, instead of the app
+fn render_key_lock(notedeck: &mut Notedeck, ctx: &egui::Context) {
+    main_panel(&ctx.style()).show(ctx, |ui| {
+        let unlocking = notedeck.accounts.is_unlocking();
+        if let Some(passphrase) = notedeck.key_lock.ui(ui, &mut notedeck.i18n, unlocking) {
+            notedeck
+                .accounts
+                .start_unlock(passphrase, &notedeck.job_pool);
+        }
+        if !notedeck.accounts.is_unlocking() {
+            return;
+        }
+
+        let txn = Transaction::new(&notedeck.ndb).expect("txn");
+        let Some(result) = notedeck.accounts.poll_unlock(
+            &mut notedeck.ndb,
+            &txn,
+            &mut notedeck.pool,
+            ctx,
+            &mut notedeck.unknown_ids,
+        ) else {
+            return;
+        };
+
+        match result {
+            Ok(()) => notedeck.key_lock.on_unlocked(),
+            Err(Error::Keystore(KeystoreError::WrongPassphrase)) => {
+                let msg = tr!(
+                    notedeck.i18n,
+                    "Wrong passphrase",
+                    "Error on the unlock screen when the passphrase is wrong"
+                );
+                notedeck.key_lock.set_error(msg);
+            }
+            Err(err) => {
+                error!("could not unlock keystore: {err}");
+                notedeck.key_lock.set_error(err.to_string());
+            }
+        }
+    });
+}
+
 impl eframe::App for Notedeck {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         profiling::finish_frame!();
@@ -123,12 +171,41 @@ impl eframe::App for Notedeck {
             .on_new_frame(ctx.input(|i| i.time), frame.info().cpu_usage);
 
         // handle account updates
-        self.accounts.update(&mut self.ndb, &mut self.pool, ctx);
+        self.accounts
+            .update(&mut self.ndb, &mut self.pool, &mut self.outbox, ctx);
+
+        // publish notes as they get signed, and resend unconfirmed notes
+        // to relays that came back
+        if self.outbox.update(&self.ndb, &mut self.pool) {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        // notes deleted since the last frame
+        if self.deletions.update(&self.ndb) {
+            ctx.request_repaint();
+        }
+
+        // share our history with notedeck instances on the local network
+        if self.pool.lan_sync(&self.ndb) {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        // fetch NIP-11 documents of new relays
+        {
+            let ctx = ctx.clone();
+            self.relay_info
+                .update(&mut self.pool, move || ctx.request_repaint());
+        }
 
         self.zaps
             .process(&mut self.accounts, &mut self.global_wallet, &self.ndb);
 
-        render_notedeck(self, ctx);
+        // the app waits while the keystore is locked
+        if self.key_lock.update(ctx, &mut self.accounts) {
+            render_key_lock(self, ctx);
+        } else {
+            render_notedeck(self, ctx);
+        }
 
         self.settings.update_batch(|settings| {
             settings.zoom_factor = ctx.zoom_factor();
@@ -210,9 +287,11 @@ impl Notedeck {
         let keystore = if parsed_args.options.contains(NotedeckOptions::UseKeystore) {
             let keys_path = path.path(DataPathType::Keys);
             let selected_key_path = path.path(DataPathType::SelectedKey);
+            let key_lock_path = path.path(DataPathType::KeyLock);
             Some(AccountStorage::new(
                 Directory::new(keys_path),
                 Directory::new(selected_key_path),
+                Directory::new(key_lock_path),
             ))
         } else {
             None
@@ -220,7 +299,16 @@ impl Notedeck {
 
         // AccountManager will setup the pool on first update
         let mut pool = RelayPool::new();
-        {
+        let mut forced_relays = parsed_args.relays.clone();
+        if let Some(dir) = &parsed_args.mock_relay {
+            // only talk to the mock relay, it stays the sole relay since
+            // it's forced
+            let ctx = ctx.clone();
+            if let Err(err) = pool.add_mock_relay(dir, move || ctx.request_repaint()) {
+                error!("error setting up mock relay from {dir}: {err}");
+            }
+            forced_relays = vec![MockRelay::URL.to_owned()];
+        } else {
             let ctx = ctx.clone();
             if let Err(err) = pool.add_multicast_relay(move || ctx.request_repaint()) {
                 error!("error setting up multicast relay: {err}");
@@ -233,7 +321,7 @@ impl Notedeck {
 
         let mut accounts = Accounts::new(
             keystore,
-            parsed_args.relays.clone(),
+            forced_relays,
             FALLBACK_PUBKEY(),
             &mut ndb,
             &txn,
@@ -269,6 +357,8 @@ impl Notedeck {
         let global_wallet = GlobalWallet::new(&path);
         let zaps = Zaps::default();
         let job_pool = JobPool::default();
+        let outbox = Outbox::new(&path);
+        let deletions = Deletions::new(&ndb, &job_pool);
 
         // Initialize localization
         let mut i18n = Localization::new();
@@ -307,6 +397,10 @@ impl Notedeck {
             zaps,
             job_pool,
             i18n,
+            outbox,
+            deletions,
+            relay_info: RelayInfoCache::default(),
+            key_lock: KeyLock::default(),
             #[cfg(target_os = "android")]
             android_app: None,
         }
@@ -372,6 +466,8 @@ impl Notedeck {
             frame_history: &mut self.frame_history,
             job_pool: &mut self.job_pool,
             i18n: &mut self.i18n,
+            outbox: &mut self.outbox,
+            deletions: &self.deletions,
             #[cfg(target_os = "android")]
             android: self.android_app.as_ref().unwrap().clone(),
         }
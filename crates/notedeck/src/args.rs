@@ -12,6 +12,9 @@ pub struct Args {
     pub options: NotedeckOptions,
     pub dbpath: Option<String>,
     pub datapath: Option<String>,
+    /// Fixture directory to serve events from instead of connecting to
+    /// relays, see [`enostr::MockRelay`]
+    pub mock_relay: Option<String>,
 }
 
 impl Args {
@@ -24,6 +27,7 @@ impl Args {
             options: NotedeckOptions::default(),
             dbpath: None,
             datapath: None,
+            mock_relay: None,
             locale: None,
         };
 
@@ -120,6 +124,15 @@ impl Args {
                     continue;
                 };
                 res.relays.push(relay.clone());
+            } else if arg == "--mock-relay" {
+                i += 1;
+                let dir = if let Some(next_arg) = args.get(i) {
+                    next_arg
+                } else {
+                    error!("mock-relay argument missing?");
+                    continue;
+                };
+                res.mock_relay = Some(dir.clone());
             } else if arg == "--no-keystore" {
                 res.options.set(NotedeckOptions::UseKeystore, true);
             } else if arg == "--relay-debug" {
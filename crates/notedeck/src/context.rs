@@ -1,7 +1,7 @@
 use crate::{
     account::accounts::Accounts, frame_history::FrameHistory, i18n::Localization,
-    wallet::GlobalWallet, zaps::Zaps, Args, DataPath, Images, JobPool, NoteCache, SettingsHandler,
-    UnknownIds,
+    wallet::GlobalWallet, zaps::Zaps, Args, DataPath, Deletions, Images, JobPool, NoteCache,
+    Outbox, SettingsHandler, UnknownIds,
 };
 use egui_winit::clipboard::Clipboard;
 
@@ -29,6 +29,8 @@ pub struct AppContext<'a> {
     pub frame_history: &'a mut FrameHistory,
     pub job_pool: &'a mut JobPool,
     pub i18n: &'a mut Localization,
+    pub outbox: &'a mut Outbox,
+    pub deletions: &'a Deletions,
 
     #[cfg(target_os = "android")]
     pub android: AndroidApp,
@@ -0,0 +1,329 @@
+//! NIP-09 deletions. A kind 5 lists the ids of notes its author wants
+//! gone, and the addresses of replaceable notes whose versions up to the
+//! deletion are gone. Relays may keep them, so we hide them ourselves. A
+//! deletion only counts for notes by its own author.
+
+use std::collections::{HashMap, HashSet};
+
+use enostr::Pubkey;
+use nostrdb::{Filter, Ndb, Note, NoteBuilder, Subscription, Transaction};
+use poll_promise::Promise;
+use tracing::{debug, error};
+
+use crate::{JobPool, SignerError, UnsignedEvent};
+
+pub const KIND_DELETION: u32 = 5;
+
+/// How many deletions are read from nostrdb on startup
+const MAX_INITIAL_DELETIONS: i32 = 10000;
+
+/// Our own deletions we ask relays for, so notes deleted on another device
+/// disappear here too
+const MAX_ACCOUNT_DELETIONS: u64 = 500;
+
+/// The notes deleted by every kind 5 nostrdb has
+#[derive(Default)]
+pub struct Deletions {
+    sub: Option<Subscription>,
+    /// The deletions nostrdb had on startup, read in the background
+    initial: Option<Promise<Deletions>>,
+    /// Note ids with the pubkey that deleted them
+    deleted: HashSet<([u8; 32], [u8; 32])>,
+    /// `kind:pubkey:d` addresses with the newest deletion of them
+    deleted_addresses: HashMap<(u32, [u8; 32], String), u64>,
+    /// Bumped whenever more notes get deleted
+    generation: u64,
+}
+
+impl Deletions {
+    pub fn new(ndb: &Ndb, job_pool: &JobPool) -> Self {
+        let mut deletions = Self::default();
+
+        // subscribe first, so deletions arriving while the stored ones are
+        // read aren't missed
+        match ndb.subscribe(&[deletions_filter()]) {
+            Ok(sub) => deletions.sub = Some(sub),
+            Err(err) => error!("could not subscribe to deletions: {err}"),
+        }
+
+        // thousands of them take a while, don't hold up the first frame
+        let ndb = ndb.clone();
+        deletions.initial = Some(Promise::spawn_async(
+            job_pool.schedule(move || stored_deletions(&ndb)),
+        ));
+
+        deletions
+    }
+
+    /// Read the deletions that arrived since the last frame. Returns true
+    /// when there were some.
+    pub fn update(&mut self, ndb: &Ndb) -> bool {
+        let loaded = self.merge_initial();
+
+        let Some(sub) = self.sub else {
+            return loaded;
+        };
+
+        let keys = ndb.poll_for_notes(sub, 100);
+        if keys.is_empty() {
+            return loaded;
+        }
+
+        let Ok(txn) = Transaction::new(ndb) else {
+            return loaded;
+        };
+
+        for key in keys {
+            if let Ok(note) = ndb.get_note_by_key(&txn, key) {
+                self.add(&note);
+            }
+        }
+        self.generation += 1;
+
+        true
+    }
+
+    /// Take in the stored deletions once they were read. Returns true when
+    /// that happened.
+    fn merge_initial(&mut self) -> bool {
+        let Some(promise) = self.initial.take() else {
+            return false;
+        };
+
+        let stored = match promise.try_take() {
+            Ok(stored) => stored,
+            Err(promise) => {
+                self.initial = Some(promise);
+                return false;
+            }
+        };
+
+        self.deleted.extend(stored.deleted);
+        for (address, deleted_at) in stored.deleted_addresses {
+            let created_at = self.deleted_addresses.entry(address).or_default();
+            *created_at = (*created_at).max(deleted_at);
+        }
+        self.generation += 1;
+
+        true
+    }
+
+    /// Changes whenever more notes get deleted, so views holding on to notes
+    /// know when to drop the deleted ones
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn add(&mut self, deletion: &Note) {
+        for tag in deletion.tags() {
+            if tag.count() < 2 {
+                continue;
+            }
+
+            match tag.get_str(0) {
+                Some("e") => {
+                    if let Some(id) = tag.get_id(1) {
+                        self.deleted.insert((*id, *deletion.pubkey()));
+                    }
+                }
+
+                Some("a") => {
+                    let Some(address) = tag.get_str(1).and_then(parse_address) else {
+                        continue;
+                    };
+
+                    // someone else's notes can't be deleted
+                    if address.1 != *deletion.pubkey() {
+                        continue;
+                    }
+
+                    let created_at = self.deleted_addresses.entry(address).or_default();
+                    *created_at = (*created_at).max(deletion.created_at());
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the author of `note` deleted it
+    pub fn is_deleted(&self, note: &Note) -> bool {
+        if !self.deleted.is_empty() && self.deleted.contains(&(*note.id(), *note.pubkey())) {
+            return true;
+        }
+
+        if self.deleted_addresses.is_empty() {
+            return false;
+        }
+
+        let address = (note.kind(), *note.pubkey(), d_tag(note).to_owned());
+        self.deleted_addresses
+            .get(&address)
+            .is_some_and(|&deleted_at| note.created_at() <= deleted_at)
+    }
+}
+
+fn deletions_filter() -> Filter {
+    Filter::new().kinds([KIND_DELETION as u64]).build()
+}
+
+/// The deletions in nostrdb, up to [`MAX_INITIAL_DELETIONS`]
+fn stored_deletions(ndb: &Ndb) -> Deletions {
+    let mut deletions = Deletions::default();
+    let Ok(txn) = Transaction::new(ndb) else {
+        return deletions;
+    };
+
+    if let Ok(results) = ndb.query(&txn, &[deletions_filter()], MAX_INITIAL_DELETIONS) {
+        for result in results {
+            deletions.add(&result.note);
+        }
+    }
+    debug!(
+        "{} deleted notes, {} deleted addresses",
+        deletions.deleted.len(),
+        deletions.deleted_addresses.len()
+    );
+
+    deletions
+}
+
+/// `kind:pubkey:d` from an `a` tag
+fn parse_address(address: &str) -> Option<(u32, [u8; 32], String)> {
+    let mut parts = address.splitn(3, ':');
+    let kind = parts.next()?.parse().ok()?;
+
+    let mut pubkey = [0; 32];
+    hex::decode_to_slice(parts.next()?, &mut pubkey).ok()?;
+
+    Some((kind, pubkey, parts.next().unwrap_or_default().to_owned()))
+}
+
+/// The `d` tag of a replaceable note, empty when it has none
+fn d_tag<'a>(note: &Note<'a>) -> &'a str {
+    note.tags()
+        .into_iter()
+        .find(|tag| tag.count() >= 2 && tag.get_str(0) == Some("d"))
+        .and_then(|tag| tag.get_str(1))
+        .unwrap_or_default()
+}
+
+/// The deletions `pubkey` published
+pub(crate) fn account_deletions_filter(pubkey: &[u8; 32]) -> Filter {
+    Filter::new()
+        .authors([pubkey])
+        .kinds([KIND_DELETION as u64])
+        .limit(MAX_ACCOUNT_DELETIONS)
+        .build()
+}
+
+/// A kind 5 asking to delete `note`, for its author's signer to sign
+pub fn deletion_event(author: &Pubkey, note: &Note) -> Result<UnsignedEvent, SignerError> {
+    let kind = note.kind().to_string();
+    let builder = NoteBuilder::new()
+        .kind(KIND_DELETION)
+        .content("")
+        .start_tag()
+        .tag_str("e")
+        .tag_id(note.id())
+        .start_tag()
+        .tag_str("k")
+        .tag_str(&kind);
+
+    UnsignedEvent::new(author, builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+
+    fn sign(builder: NoteBuilder<'_>, kp: &FullKeypair) -> Note<'static> {
+        let seckey = kp.secret_key.secret_bytes();
+        builder.sign(&seckey).build().expect("note")
+    }
+
+    fn text_note(kp: &FullKeypair) -> Note<'static> {
+        sign(NoteBuilder::new().kind(1).content("oops"), kp)
+    }
+
+    fn delete_ids(ids: &[&[u8; 32]], kp: &FullKeypair) -> Note<'static> {
+        let mut builder = NoteBuilder::new().kind(KIND_DELETION).content("");
+        for id in ids {
+            builder = builder.start_tag().tag_str("e").tag_id(id);
+        }
+        sign(builder, kp)
+    }
+
+    #[test]
+    fn test_author_deletion() {
+        let author = FullKeypair::generate();
+        let note = text_note(&author);
+        let kept = text_note(&author);
+
+        let mut deletions = Deletions::default();
+        assert!(!deletions.is_deleted(&note));
+
+        deletions.add(&delete_ids(&[note.id()], &author));
+        assert!(deletions.is_deleted(&note));
+        assert!(!deletions.is_deleted(&kept));
+    }
+
+    #[test]
+    fn test_deletion_by_someone_else() {
+        let author = FullKeypair::generate();
+        let other = FullKeypair::generate();
+        let note = text_note(&author);
+
+        let mut deletions = Deletions::default();
+        deletions.add(&delete_ids(&[note.id()], &other));
+        assert!(!deletions.is_deleted(&note));
+
+        // nor can they delete it by its address
+        let address = format!("1:{}:", author.pubkey.hex());
+        let builder = NoteBuilder::new()
+            .kind(KIND_DELETION)
+            .content("")
+            .start_tag()
+            .tag_str("a")
+            .tag_str(&address);
+        deletions.add(&sign(builder, &other));
+        assert!(!deletions.is_deleted(&note));
+    }
+
+    #[test]
+    fn test_address_deletion() {
+        let author = FullKeypair::generate();
+        let article = |d: &str, created_at: u64| {
+            let builder = NoteBuilder::new()
+                .kind(30023)
+                .content("article")
+                .created_at(created_at)
+                .start_tag()
+                .tag_str("d")
+                .tag_str(d);
+            sign(builder, &author)
+        };
+
+        let old = article("post", 100);
+        let other_post = article("other", 100);
+
+        let address = format!("30023:{}:post", author.pubkey.hex());
+        let builder = NoteBuilder::new()
+            .kind(KIND_DELETION)
+            .content("")
+            .created_at(200)
+            .start_tag()
+            .tag_str("a")
+            .tag_str(&address);
+
+        let mut deletions = Deletions::default();
+        deletions.add(&sign(builder, &author));
+
+        assert!(deletions.is_deleted(&old));
+        assert!(!deletions.is_deleted(&other_post));
+
+        // versions published after the deletion stay
+        assert!(!deletions.is_deleted(&article("post", 300)));
+    }
+}
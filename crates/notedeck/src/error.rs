@@ -26,6 +26,12 @@ pub enum Error {
 
     #[error("zaps error: {0}")]
     Zap(#[from] ZapError),
+
+    #[error("keystore error: {0}")]
+    Keystore(#[from] KeystoreError),
+
+    #[error("signer error: {0}")]
+    Signer(#[from] SignerError),
 }
 
 #[derive(Debug, thiserror::Error, Clone)]
@@ -80,6 +86,41 @@ pub enum SubscriptionError {
     UnexpectedSubscriptionCount(i32),
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("wrong passphrase")]
+    WrongPassphrase,
+
+    /// Secret keys can't be written until the keystore is unlocked
+    #[error("keystore is locked")]
+    Locked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignerError {
+    /// Neither a secret key nor a remote signer for the account
+    #[error("can't sign for this account")]
+    NoSigner,
+
+    #[error("could not build the note")]
+    Build,
+
+    #[error("invalid signer uri: {0}")]
+    InvalidUri(String),
+
+    #[error("remote signer error: {0}")]
+    Remote(String),
+
+    #[error("bad remote signer response: {0}")]
+    BadResponse(String),
+
+    #[error("remote signer didn't answer in time")]
+    Timeout,
+
+    #[error("could not encrypt or decrypt: {0}")]
+    Crypto(String),
+}
+
 impl Error {
     pub fn unexpected_sub_count(c: i32) -> Self {
         Error::SubscriptionError(SubscriptionError::UnexpectedSubscriptionCount(c))
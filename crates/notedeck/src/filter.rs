@@ -2,6 +2,7 @@ use crate::error::{Error, FilterError};
 use crate::note::NoteRef;
 use nostrdb::{Filter, FilterBuilder, Note, Subscription};
 use std::collections::HashMap;
+use std::time::Instant;
 use tracing::{debug, warn};
 
 /// A unified subscription has a local and remote component. The remote subid
@@ -93,6 +94,67 @@ impl FilterStates {
             *cur_state = state.clone();
         }
     }
+
+    /// The relay closed our subscription. The state it was in is kept so
+    /// it can be restored with [`FilterStates::reopen`].
+    pub fn set_relay_closed(&mut self, relay: &str, reason: String, retry_at: Option<Instant>) {
+        let previous = match self.states.remove(relay) {
+            Some(FilterState::Closed(closed)) => closed.previous,
+            Some(state) => Box::new(state),
+            None => Box::new(self.initial_state.clone()),
+        };
+
+        let closed = SubscriptionClosed {
+            reason,
+            retry_at,
+            previous,
+        };
+        self.set_relay_state(relay.to_owned(), FilterState::Closed(closed));
+    }
+
+    /// Restore the state a relay was in before it closed our subscription
+    pub fn reopen(&mut self, relay: &str) {
+        if let Some(FilterState::Closed(closed)) = self.states.remove(relay) {
+            self.set_relay_state(relay.to_owned(), *closed.previous);
+        }
+    }
+
+    /// Relays that closed our subscription, and why
+    pub fn closed(&self) -> impl Iterator<Item = (&str, &SubscriptionClosed)> {
+        self.states.iter().filter_map(|(relay, state)| match state {
+            FilterState::Closed(closed) => Some((relay.as_str(), closed)),
+            _ => None,
+        })
+    }
+
+    /// Relays with a closed subscription that is due for a retry
+    pub fn due_retries(&self, now: Instant) -> Vec<String> {
+        self.closed()
+            .filter(|(_, closed)| closed.retry_at.is_some_and(|at| at <= now))
+            .map(|(relay, _)| relay.to_owned())
+            .collect()
+    }
+
+    /// Retry every closed subscription on the next frame, even the ones
+    /// relays told us not to bother with
+    pub fn retry_all_now(&mut self) {
+        let now = Instant::now();
+        for state in self.states.values_mut() {
+            if let FilterState::Closed(closed) = state {
+                closed.retry_at = Some(now);
+            }
+        }
+    }
+}
+
+/// A relay closed or refused one of our subscriptions
+#[derive(Debug, Clone)]
+pub struct SubscriptionClosed {
+    /// The relay's message, including its machine readable prefix
+    pub reason: String,
+    /// When to subscribe again, `None` if retrying won't help
+    pub retry_at: Option<Instant>,
+    previous: Box<FilterState>,
 }
 
 /// We may need to fetch some data from relays before our filter is ready.
@@ -104,6 +166,8 @@ pub enum FilterState {
     GotRemote(GotRemoteType),
     Ready(HybridFilter),
     Broken(FilterError),
+    /// The relay closed our subscription
+    Closed(SubscriptionClosed),
 }
 
 pub enum GotRemoteResult {
@@ -502,3 +566,88 @@ pub fn make_filters_since(raw: &[Filter], since: u64) -> Vec<Filter> {
     }
     filters
 }
+
+/// Page back through history: `raw` limited to notes created at or before
+/// `until`, at most `limit` of them per filter
+pub fn make_filters_until(raw: &[Filter], until: u64, limit: u64) -> Vec<Filter> {
+    let mut filters = Vec::with_capacity(raw.len());
+    for builder in raw {
+        filters.push(
+            Filter::copy_from(builder)
+                .until(until)
+                .build()
+                .limit_mut(limit),
+        );
+    }
+    filters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_relay_closed_and_reopen() {
+        let mut states = FilterStates::new(FilterState::needs_remote());
+        states.set_relay_state(
+            "wss://a".to_owned(),
+            FilterState::GotRemote(GotRemoteType::Contact),
+        );
+
+        states.set_relay_closed("wss://a", "auth-required: sign in".to_owned(), None);
+        let closed: Vec<_> = states.closed().collect();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].0, "wss://a");
+        assert_eq!(closed[0].1.reason, "auth-required: sign in");
+
+        // closing again replaces the reason but keeps the state from before
+        // the first close
+        states.set_relay_closed("wss://a", "rate-limited: slow down".to_owned(), None);
+        assert_eq!(
+            states.closed().next().unwrap().1.reason,
+            "rate-limited: slow down"
+        );
+        states.reopen("wss://a");
+        assert!(matches!(
+            states.states["wss://a"],
+            FilterState::GotRemote(GotRemoteType::Contact)
+        ));
+        assert_eq!(states.closed().count(), 0);
+
+        // relays without a state of their own go back to the initial one
+        states.set_relay_closed("wss://b", "error: shutting down".to_owned(), None);
+        states.reopen("wss://b");
+        assert!(matches!(states.states["wss://b"], FilterState::NeedsRemote));
+
+        // reopening an open subscription changes nothing
+        states.reopen("wss://a");
+        assert!(matches!(
+            states.states["wss://a"],
+            FilterState::GotRemote(GotRemoteType::Contact)
+        ));
+    }
+
+    #[test]
+    fn test_due_retries() {
+        let now = Instant::now();
+        let mut states = FilterStates::new(FilterState::ready(vec![]));
+        states.set_relay_closed(
+            "wss://a",
+            "rate-limited:".to_owned(),
+            Some(now + Duration::from_secs(5)),
+        );
+        states.set_relay_closed("wss://b", "blocked:".to_owned(), None);
+        states.set_relay_closed("wss://c", "error:".to_owned(), Some(now));
+
+        assert_eq!(states.due_retries(now), vec!["wss://c".to_owned()]);
+
+        let mut due = states.due_retries(now + Duration::from_secs(5));
+        due.sort();
+        assert_eq!(due, vec!["wss://a".to_owned(), "wss://c".to_owned()]);
+
+        // the user can ask to retry even what the relay refused
+        states.retry_all_now();
+        assert_eq!(states.due_retries(Instant::now()).len(), 3);
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use egui::{Align, Key, Layout, RichText, TextEdit};
+use tracing::info;
+
+use crate::{tr, Accounts, Localization, NotedeckTextStyle};
+
+/// Keys are locked again after this long without input
+const AUTO_LOCK_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// The screen asking for the keystore passphrase, shown instead of the app
+/// while locked, and the inactivity timer that locks it again.
+pub struct KeyLock {
+    last_activity: Instant,
+    passphrase: String,
+    error: Option<String>,
+}
+
+impl Default for KeyLock {
+    fn default() -> Self {
+        Self {
+            last_activity: Instant::now(),
+            passphrase: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl KeyLock {
+    /// Lock the keys when the user has been away for a while. Returns
+    /// whether the keystore is locked.
+    pub fn update(&mut self, ctx: &egui::Context, accounts: &mut Accounts) -> bool {
+        if accounts.is_locked() {
+            return true;
+        }
+
+        let now = Instant::now();
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.last_activity = now;
+        }
+
+        if !accounts.has_passphrase() {
+            return false;
+        }
+
+        let idle = now.saturating_duration_since(self.last_activity);
+        if idle >= AUTO_LOCK_AFTER {
+            info!("locking keys after {}s without input", idle.as_secs());
+            accounts.lock();
+            return true;
+        }
+
+        ctx.request_repaint_after(AUTO_LOCK_AFTER - idle);
+        false
+    }
+
+    /// The unlock form. Returns the passphrase once submitted. The form is
+    /// disabled while `unlocking`.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &mut Localization,
+        unlocking: bool,
+    ) -> Option<String> {
+        let mut submitted = None;
+
+        ui.with_layout(Layout::top_down(Align::Center), |ui| {
+            ui.add_space(ui.available_height() / 4.0);
+            ui.set_max_width(320.0);
+            ui.spacing_mut().item_spacing.y = 12.0;
+
+            ui.label(
+                RichText::new(tr!(
+                    i18n,
+                    "Notedeck is locked",
+                    "Title of the unlock screen"
+                ))
+                .text_style(NotedeckTextStyle::Heading2.text_style()),
+            );
+            ui.label(tr!(
+                i18n,
+                "Enter your passphrase to unlock your keys.",
+                "Explanation on the unlock screen"
+            ));
+
+            let response = ui.add_enabled(
+                !unlocking,
+                TextEdit::singleline(&mut self.passphrase)
+                    .password(true)
+                    .hint_text(tr!(
+                        i18n,
+                        "Passphrase",
+                        "Hint for the passphrase field on the unlock screen"
+                    ))
+                    .desired_width(f32::INFINITY),
+            );
+            if !unlocking && !response.has_focus() && !response.lost_focus() {
+                response.request_focus();
+            }
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+
+            if unlocking {
+                ui.horizontal(|ui| {
+                    ui.add(egui::Spinner::new());
+                    ui.label(tr!(
+                        i18n,
+                        "Unlocking…",
+                        "Shown on the unlock screen while the keys are being unlocked"
+                    ));
+                });
+                return;
+            }
+
+            let unlock = ui.button(tr!(i18n, "Unlock", "Button to unlock the keystore"));
+            if (entered || unlock.clicked()) && !self.passphrase.is_empty() {
+                submitted = Some(std::mem::take(&mut self.passphrase));
+                self.error = None;
+            }
+
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+        submitted
+    }
+
+    pub fn on_unlocked(&mut self) {
+        self.error = None;
+        self.last_activity = Instant::now();
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+}
@@ -7,6 +7,7 @@ mod args;
 pub mod contacts;
 mod context;
 pub mod debouncer;
+mod deletions;
 mod error;
 pub mod filter;
 pub mod fonts;
@@ -15,6 +16,7 @@ pub mod i18n;
 mod imgcache;
 mod job_pool;
 mod jobs;
+mod key_lock;
 pub mod media;
 mod muted;
 pub mod name;
@@ -22,14 +24,17 @@ mod nip51_set;
 pub mod note;
 mod notecache;
 mod options;
+mod outbox;
 mod persist;
 pub mod platform;
 pub mod profile;
 pub mod relay_debug;
+mod relay_info;
 pub mod relayspec;
 mod result;
 mod route;
 mod setup;
+mod signer;
 pub mod storage;
 mod style;
 pub mod theme;
@@ -44,14 +49,17 @@ mod wallet;
 mod zaps;
 
 pub use account::accounts::{AccountData, AccountSubs, Accounts};
+pub use account::app_data::{CHANNELS_APP_DATA_ID, KIND_APP_DATA};
 pub use account::contacts::{ContactState, IsFollowing};
-pub use account::relay::RelayAction;
+pub use account::dms::{dm_inbox_relays, dm_relays_note, KIND_DM_RELAYS};
+pub use account::relay::{nip65_write_relays, RelayAction};
 pub use account::FALLBACK_PUBKEY;
 pub use app::{App, AppAction, AppResponse, Notedeck};
 pub use args::Args;
 pub use context::{AppContext, SoftKeyboardContext};
-pub use error::{show_one_error_message, Error, FilterError, ZapError};
-pub use filter::{FilterState, FilterStates, UnifiedSubscription};
+pub use deletions::{deletion_event, Deletions, KIND_DELETION};
+pub use error::{show_one_error_message, Error, FilterError, KeystoreError, SignerError, ZapError};
+pub use filter::{FilterState, FilterStates, SubscriptionClosed, UnifiedSubscription};
 pub use fonts::NamedFontFamily;
 pub use i18n::{CacheStats, FluentArgs, FluentValue, LanguageIdentifier, Localization};
 pub use imgcache::{
@@ -67,7 +75,7 @@ pub use media::{
     compute_blurhash, update_imeta_blurhashes, ImageMetadata, ImageType, MediaAction,
     ObfuscationType, PixelDimensions, PointDimensions, RenderableMedia,
 };
-pub use muted::{MuteFun, Muted};
+pub use muted::{MuteFun, MuteItem, Muted};
 pub use name::NostrName;
 pub use nip51_set::{create_nip51_set, Nip51Set, Nip51SetCache};
 pub use note::{
@@ -76,12 +84,18 @@ pub use note::{
 };
 pub use notecache::{CachedNote, NoteCache};
 pub use options::NotedeckOptions;
+pub use outbox::{Delivery, DeliveryStatus, Outbox};
 pub use persist::*;
 pub use profile::*;
 pub use relay_debug::RelayDebugView;
+pub use relay_info::RelayInfoCache;
 pub use relayspec::RelaySpec;
 pub use result::Result;
 pub use route::DrawerRouter;
+pub use signer::{
+    nostrconnect_uri, BunkerUri, CryptResult, LocalSigner, RemoteLogin, RemoteSigner,
+    RemoteSignerInfo, SignResult, SignedEvent, Signer, UnsignedEvent, KIND_NOSTR_CONNECT,
+};
 pub use storage::{AccountStorage, DataPath, DataPathType, Directory};
 pub use style::NotedeckTextStyle;
 pub use theme::ColorTheme;
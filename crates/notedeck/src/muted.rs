@@ -6,13 +6,64 @@ use std::collections::BTreeSet;
 // If the note is muted return a reason string, otherwise None
 pub type MuteFun = dyn Fn(&Note, &[u8; 32]) -> bool;
 
-#[derive(Default)]
+/// One entry of a NIP-51 mute list
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MuteItem {
+    Pubkey([u8; 32]),
+    Thread([u8; 32]),
+    /// Lowercase, without the `#`
+    Hashtag(String),
+    /// Lowercase. Matches whole words only, and can be a phrase.
+    Word(String),
+}
+
+impl MuteItem {
+    pub fn hashtag(hashtag: &str) -> Self {
+        MuteItem::Hashtag(hashtag.trim().trim_start_matches('#').to_lowercase())
+    }
+
+    pub fn word(word: &str) -> Self {
+        MuteItem::Word(word.trim().to_lowercase())
+    }
+
+    /// Parse a mute list tag, like `["t", "nsfw"]`
+    pub fn from_tag(name: &str, value: &str) -> Option<Self> {
+        let id = |value: &str| {
+            let mut id = [0u8; 32];
+            hex::decode_to_slice(value, &mut id).ok().map(|_| id)
+        };
+
+        match name {
+            "p" => id(value).map(MuteItem::Pubkey),
+            "e" => id(value).map(MuteItem::Thread),
+            "t" => Some(MuteItem::hashtag(value)),
+            "word" => Some(MuteItem::word(value)),
+            _ => None,
+        }
+    }
+
+    pub fn tag(&self) -> [String; 2] {
+        match self {
+            MuteItem::Pubkey(pk) => ["p".to_owned(), hex::encode(pk)],
+            MuteItem::Thread(id) => ["e".to_owned(), hex::encode(id)],
+            MuteItem::Hashtag(hashtag) => ["t".to_owned(), hashtag.clone()],
+            MuteItem::Word(word) => ["word".to_owned(), word.clone()],
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct Muted {
-    // TODO - implement private mutes
     pub pubkeys: BTreeSet<[u8; 32]>,
     pub hashtags: BTreeSet<String>,
     pub words: BTreeSet<String>,
     pub threads: BTreeSet<[u8; 32]>,
+    /// Entries kept in the list's encrypted content rather than its tags
+    pub private: BTreeSet<MuteItem>,
+    /// Encrypted content we haven't read: the account's signer is still
+    /// at it, or couldn't, like for lists encrypted with NIP-04. It is
+    /// published again unchanged so its entries aren't lost.
+    pub unreadable: Option<String>,
 }
 
 impl std::fmt::Debug for Muted {
@@ -28,6 +79,8 @@ impl std::fmt::Debug for Muted {
                 "threads",
                 &self.threads.iter().map(hex::encode).collect::<Vec<_>>(),
             )
+            .field("private", &self.private.len())
+            .field("unreadable", &self.unreadable.is_some())
             .finish()
     }
 }
@@ -53,19 +106,17 @@ impl Muted {
             */
             return true;
         }
-        // FIXME - Implement hashtag muting here
-
-        // TODO - let's not add this for now, we will likely need to
-        // have an optimized data structure in nostrdb to properly
-        // mute words. this mutes substrings which is not ideal.
-        //
-        // let content = note.content().to_lowercase();
-        // for word in &self.words {
-        //     if content.contains(&word.to_lowercase()) {
-        //         debug!("{}: MUTED word: {}", hex::encode(note.id()), word);
-        //         return Some(format!("muted word {}", word));
-        //     }
-        // }
+
+        if !self.hashtags.is_empty() && self.has_muted_hashtag(note) {
+            return true;
+        }
+
+        if !self.words.is_empty() {
+            let content = note.content().to_lowercase();
+            if self.words.iter().any(|word| contains_word(&content, word)) {
+                return true;
+            }
+        }
 
         if self.threads.contains(thread) {
             /*
@@ -84,4 +135,154 @@ impl Muted {
     pub fn is_pk_muted(&self, pk: &[u8; 32]) -> bool {
         self.pubkeys.contains(pk)
     }
+
+    fn has_muted_hashtag(&self, note: &Note) -> bool {
+        note.tags().into_iter().any(|tag| {
+            tag.count() >= 2
+                && tag.get_str(0) == Some("t")
+                && tag
+                    .get_str(1)
+                    .is_some_and(|t| self.hashtags.contains(&t.to_lowercase()))
+        })
+    }
+
+    pub fn contains(&self, item: &MuteItem) -> bool {
+        match item {
+            MuteItem::Pubkey(pk) => self.pubkeys.contains(pk),
+            MuteItem::Thread(id) => self.threads.contains(id),
+            MuteItem::Hashtag(hashtag) => self.hashtags.contains(hashtag),
+            MuteItem::Word(word) => self.words.contains(word),
+        }
+    }
+
+    pub fn insert(&mut self, item: MuteItem, private: bool) {
+        if private {
+            self.private.insert(item.clone());
+        } else {
+            self.private.remove(&item);
+        }
+
+        match item {
+            MuteItem::Pubkey(pk) => self.pubkeys.insert(pk),
+            MuteItem::Thread(id) => self.threads.insert(id),
+            MuteItem::Hashtag(hashtag) => self.hashtags.insert(hashtag),
+            MuteItem::Word(word) => self.words.insert(word),
+        };
+    }
+
+    pub fn remove(&mut self, item: &MuteItem) {
+        self.private.remove(item);
+
+        match item {
+            MuteItem::Pubkey(pk) => self.pubkeys.remove(pk),
+            MuteItem::Thread(id) => self.threads.remove(id),
+            MuteItem::Hashtag(hashtag) => self.hashtags.remove(hashtag),
+            MuteItem::Word(word) => self.words.remove(word),
+        };
+    }
+
+    /// Everything on the list, users first
+    pub fn items(&self) -> Vec<MuteItem> {
+        let pubkeys = self.pubkeys.iter().copied().map(MuteItem::Pubkey);
+        let threads = self.threads.iter().copied().map(MuteItem::Thread);
+        let hashtags = self.hashtags.iter().cloned().map(MuteItem::Hashtag);
+        let words = self.words.iter().cloned().map(MuteItem::Word);
+
+        pubkeys
+            .chain(threads)
+            .chain(hashtags)
+            .chain(words)
+            .collect()
+    }
+
+    pub fn is_private(&self, item: &MuteItem) -> bool {
+        self.private.contains(item)
+    }
+
+    /// The tags of the public entries
+    pub fn public_tags(&self) -> Vec<[String; 2]> {
+        self.items()
+            .into_iter()
+            .filter(|item| !self.is_private(item))
+            .map(|item| item.tag())
+            .collect()
+    }
+
+    /// The private entries, as the JSON tag array that NIP-51 encrypts into
+    /// the list's content
+    pub fn private_json(&self) -> String {
+        let tags: Vec<[String; 2]> = self.private.iter().map(MuteItem::tag).collect();
+        serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_owned())
+    }
+
+    /// Add the private entries of a decrypted list content
+    pub fn add_private_json(&mut self, json: &str) -> Result<(), serde_json::Error> {
+        let tags: Vec<Vec<String>> = serde_json::from_str(json)?;
+        for tag in tags {
+            let (Some(name), Some(value)) = (tag.first(), tag.get(1)) else {
+                continue;
+            };
+
+            if let Some(item) = MuteItem::from_tag(name, value) {
+                self.insert(item, true);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `word` appears in `content` as a whole word or phrase, so that
+/// muting "art" doesn't hide notes about parties. Both must be lowercase.
+fn contains_word(content: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+
+    let is_boundary = |c: Option<char>| !matches!(c, Some(c) if c.is_alphanumeric());
+
+    content.match_indices(word).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let after = content[start + word.len()..].chars().next();
+        is_boundary(before) && is_boundary(after)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_word() {
+        assert!(contains_word("gm, frens", "gm"));
+        assert!(contains_word("price talk again", "price talk"));
+        assert!(contains_word("modern art", "art"));
+        assert!(!contains_word("what a party", "art"));
+        assert!(!contains_word("gmgm", "gm"));
+        assert!(!contains_word("anything", ""));
+    }
+
+    #[test]
+    fn test_private_entries_roundtrip() {
+        let pk = [7u8; 32];
+        let mut muted = Muted::default();
+        muted.insert(MuteItem::Pubkey(pk), true);
+        muted.insert(MuteItem::hashtag("#Bitcoin"), false);
+
+        assert!(muted.contains(&MuteItem::Hashtag("bitcoin".to_owned())));
+        assert_eq!(
+            muted.public_tags(),
+            vec![["t".to_owned(), "bitcoin".to_owned()]]
+        );
+
+        let mut read = Muted::default();
+        read.add_private_json(&muted.private_json()).unwrap();
+        assert!(read.is_pk_muted(&pk));
+        assert!(read.is_private(&MuteItem::Pubkey(pk)));
+
+        // muting publicly takes it out of the private entries
+        read.insert(MuteItem::Pubkey(pk), false);
+        assert!(!read.is_private(&MuteItem::Pubkey(pk)));
+        assert_eq!(read.private_json(), "[]");
+    }
 }
@@ -2,6 +2,8 @@ use enostr::{ClientMessage, NoteId, Pubkey, RelayPool};
 use nostrdb::{Note, NoteKey};
 use tracing::error;
 
+use crate::MuteItem;
+
 /// When broadcasting notes, this determines whether to broadcast
 /// over the local network via multicast, or globally
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -19,6 +21,11 @@ pub enum NoteContextSelection {
     CopyNoteJSON,
     Broadcast(BroadcastContext),
     CopyLink,
+    /// Add something about the note to the account's mute list. Handled by
+    /// the app, which can sign the updated list.
+    Mute(MuteItem),
+    /// Ask for our own note to be deleted (NIP-09). Handled by the app too.
+    Delete,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -84,6 +91,7 @@ impl NoteContextSelection {
                     ui.ctx().copy_text(damus_url(bech));
                 }
             }
+            NoteContextSelection::Mute(_) | NoteContextSelection::Delete => {}
         }
     }
 }
@@ -5,9 +5,11 @@ pub use action::{NoteAction, ReactAction, ScrollInfo, ZapAction, ZapTargetAmount
 pub use context::{BroadcastContext, ContextSelection, NoteContextSelection};
 
 use crate::Accounts;
+use crate::Deletions;
 use crate::GlobalWallet;
 use crate::JobPool;
 use crate::Localization;
+use crate::Outbox;
 use crate::UnknownIds;
 use crate::{notecache::NoteCache, zaps::Zaps, Images};
 use enostr::{NoteId, RelayPool};
@@ -30,6 +32,8 @@ pub struct NoteContext<'d> {
     pub job_pool: &'d mut JobPool,
     pub unknown_ids: &'d mut UnknownIds,
     pub clipboard: &'d mut egui_winit::clipboard::Clipboard,
+    pub outbox: &'d Outbox,
+    pub deletions: &'d Deletions,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
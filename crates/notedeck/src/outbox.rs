@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use enostr::{
+    ClientMessage, EventClientMessage, MessagePrefix, PoolRelay, RelayAuthState, RelayPool,
+    RelayStatus,
+};
+use nostrdb::{IngestMetadata, Ndb};
+use poll_promise::Promise;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::debouncer::Debouncer;
+use crate::signer::{SignResult, SignedEvent};
+use crate::{storage, DataPath, DataPathType, Directory, SignerError};
+
+const OUTBOX_FILE: &str = "outbox.json";
+
+/// Notes are forgotten a week after they were published, delivered or not
+const OUTBOX_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+/// Where a published note stands on a single relay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "reason", rename_all = "snake_case")]
+pub enum Delivery {
+    /// Sent, or waiting to be sent, without an OK yet
+    Pending,
+    Accepted,
+    /// The relay refused it, with the relay's message
+    Rejected(String),
+}
+
+/// How a published note is doing across all the relays it was sent to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryStatus {
+    pub pending: usize,
+    pub accepted: usize,
+    /// Relay urls and their reasons
+    pub rejected: Vec<(String, String)>,
+}
+
+impl DeliveryStatus {
+    /// At least one relay has it
+    pub fn is_delivered(&self) -> bool {
+        self.accepted > 0
+    }
+
+    /// Every relay answered and none of them took it
+    pub fn is_failed(&self) -> bool {
+        self.accepted == 0 && self.pending == 0 && !self.rejected.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct OutboxEntry {
+    note_json: String,
+    created_at: u64,
+    relays: BTreeMap<String, Delivery>,
+}
+
+/// A note waiting for its signature, and where it goes once signed
+struct Signing {
+    /// Set for notes whose signing outcome someone asked to hear about
+    id: Option<u64>,
+    promise: Promise<SignResult>,
+    relays: Vec<String>,
+}
+
+/// Notes we published that relays haven't confirmed with an OK yet, along
+/// with what each relay said about the ones they did. Pending notes are
+/// sent again whenever their relay (re)connects or finishes NIP-42 auth,
+/// and the whole thing is saved to disk so it survives restarts.
+pub struct Outbox {
+    /// Keyed by hex note id
+    entries: HashMap<String, OutboxEntry>,
+
+    /// Note and relay pairs sent on the current connection, so we only
+    /// resend after a reconnect
+    sent: HashSet<(String, String)>,
+
+    /// Note and relay pairs the relay refused until we authenticate
+    needs_auth: HashSet<(String, String)>,
+
+    /// Notes published before their signer answered
+    signing: Vec<Signing>,
+
+    /// How tracked signing went, until [`Self::take_sign_result`] reads it
+    sign_results: HashMap<u64, Result<(), SignerError>>,
+    next_sign_id: u64,
+
+    directory: Option<Directory>,
+    save_debouncer: Debouncer,
+    dirty: bool,
+}
+
+impl Outbox {
+    pub fn new(path: &DataPath) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Setting));
+
+        let entries = match directory.get_file(OUTBOX_FILE.to_owned()) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                error!("could not parse outbox: {err}");
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        let mut outbox = Self::with_entries(entries);
+        outbox.directory = Some(directory);
+        outbox.prune(now_secs());
+        outbox
+    }
+
+    /// An outbox that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::with_entries(HashMap::new())
+    }
+
+    fn with_entries(entries: HashMap<String, OutboxEntry>) -> Self {
+        Self {
+            entries,
+            sent: HashSet::new(),
+            needs_auth: HashSet::new(),
+            signing: vec![],
+            sign_results: HashMap::new(),
+            next_sign_id: 0,
+            directory: None,
+            save_debouncer: Debouncer::new(Duration::from_secs(2)),
+            dirty: false,
+        }
+    }
+
+    /// Publish a note once its signer signed it, see [`Self::send`]
+    pub fn sign_and_send(&mut self, promise: Promise<SignResult>, relays: Vec<String>) {
+        self.signing.push(Signing {
+            id: None,
+            promise,
+            relays,
+        });
+    }
+
+    /// Like [`Self::sign_and_send`], for callers that need to know whether
+    /// the signer signed it. Returns the id to pass to
+    /// [`Self::take_sign_result`].
+    pub fn sign_and_track(&mut self, promise: Promise<SignResult>, relays: Vec<String>) -> u64 {
+        let id = self.next_sign_id;
+        self.next_sign_id += 1;
+        self.signing.push(Signing {
+            id: Some(id),
+            promise,
+            relays,
+        });
+        id
+    }
+
+    /// Whether the note tracked as `id` got signed, or the signer's error.
+    /// None while the signer hasn't answered yet.
+    pub fn take_sign_result(&mut self, id: u64) -> Option<Result<(), SignerError>> {
+        self.sign_results.remove(&id)
+    }
+
+    /// Publish a signed note to `relays`, or to every relay in the pool
+    /// when empty, and track it until the relays confirm it
+    pub fn send(&mut self, pool: &mut RelayPool, event: &SignedEvent, relays: &[String]) {
+        let note_json = event.json.clone();
+        let msg = ClientMessage::Event(EventClientMessage {
+            note_json: note_json.clone(),
+        });
+        let id = hex::encode(event.id);
+
+        let urls: Vec<String> = if relays.is_empty() {
+            pool.send(&msg);
+            pool.relays
+                .iter()
+                // multicast never answers with an OK
+                .filter(|relay| !matches!(relay, PoolRelay::Multicast(_)))
+                .map(|relay| relay.url().to_owned())
+                .collect()
+        } else {
+            for relay in relays {
+                pool.send_to(&msg, relay);
+            }
+            relays.to_vec()
+        };
+
+        let mut entry = OutboxEntry {
+            note_json,
+            created_at: event.created_at,
+            relays: BTreeMap::new(),
+        };
+
+        for url in urls {
+            if is_connected(pool, &url) {
+                self.sent.insert((id.clone(), url.clone()));
+            }
+            entry.relays.insert(url, Delivery::Pending);
+        }
+
+        self.entries.insert(id, entry);
+        self.dirty = true;
+    }
+
+    /// Handle an OK from `relay`. Returns whether it was for one of ours.
+    pub fn handle_ok(
+        &mut self,
+        relay: &str,
+        event_id: &str,
+        accepted: bool,
+        message: &str,
+    ) -> bool {
+        let Some(entry) = self.entries.get_mut(event_id) else {
+            return false;
+        };
+
+        let Some(delivery) = entry.relays.get_mut(relay) else {
+            return false;
+        };
+
+        let prefix = MessagePrefix::parse(message);
+        let key = (event_id.to_owned(), relay.to_owned());
+
+        *delivery = if accepted || prefix == Some(MessagePrefix::Duplicate) {
+            Delivery::Accepted
+        } else if prefix == Some(MessagePrefix::AuthRequired) {
+            // sent again once we're authenticated
+            self.needs_auth.insert(key);
+            return true;
+        } else {
+            warn!("{relay} rejected {event_id}: {message}");
+            Delivery::Rejected(message.to_owned())
+        };
+
+        self.dirty = true;
+        true
+    }
+
+    pub fn status(&self, note_id: &[u8; 32]) -> Option<DeliveryStatus> {
+        let entry = self.entries.get(&hex::encode(note_id))?;
+
+        let mut status = DeliveryStatus::default();
+        for (relay, delivery) in &entry.relays {
+            match delivery {
+                Delivery::Pending => status.pending += 1,
+                Delivery::Accepted => status.accepted += 1,
+                Delivery::Rejected(reason) => status.rejected.push((relay.clone(), reason.clone())),
+            }
+        }
+
+        Some(status)
+    }
+
+    /// Publish notes that got their signature, resend pending notes to
+    /// relays that (re)connected or finished authenticating since we last
+    /// sent them, and save changes. Called every frame. Returns whether
+    /// notes are still waiting for their signer.
+    pub fn update(&mut self, ndb: &Ndb, pool: &mut RelayPool) -> bool {
+        self.send_signed(ndb, pool);
+
+        for relay in &pool.relays {
+            if !matches!(relay.status(), RelayStatus::Connected) {
+                let url = relay.url();
+                self.sent.retain(|(_, sent_to)| sent_to != url);
+            }
+        }
+
+        let mut to_send = vec![];
+        for (id, entry) in &self.entries {
+            for (url, delivery) in &entry.relays {
+                if *delivery != Delivery::Pending || !is_connected(pool, url) {
+                    continue;
+                }
+
+                let key = (id.clone(), url.clone());
+                let resend = if self.needs_auth.contains(&key) {
+                    is_authenticated(pool, url)
+                } else {
+                    !self.sent.contains(&key)
+                };
+
+                if resend {
+                    to_send.push((key, entry.note_json.clone()));
+                }
+            }
+        }
+
+        for ((id, url), note_json) in to_send {
+            let msg = ClientMessage::Event(EventClientMessage { note_json });
+            debug!("outbox: sending {id} to {url}");
+            pool.send_to(&msg, &url);
+            self.needs_auth.remove(&(id.clone(), url.clone()));
+            self.sent.insert((id, url));
+        }
+
+        if self.dirty && self.save_debouncer.should_act() {
+            self.prune(now_secs());
+            self.save();
+        }
+
+        !self.signing.is_empty()
+    }
+
+    fn send_signed(&mut self, ndb: &Ndb, pool: &mut RelayPool) {
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.signing)
+            .into_iter()
+            .partition(|signing| signing.promise.ready().is_some());
+        self.signing = waiting;
+
+        for signing in ready {
+            let Ok(result) = signing.promise.try_take() else {
+                continue;
+            };
+
+            let event = match result {
+                Ok(event) => event,
+                Err(err) => {
+                    error!("outbox: could not sign note: {err}");
+                    if let Some(id) = signing.id {
+                        self.sign_results.insert(id, Err(err));
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(id) = signing.id {
+                self.sign_results.insert(id, Ok(()));
+            }
+
+            // our own notes show up right away, before any relay has them
+            let msg = ClientMessage::Event(EventClientMessage {
+                note_json: event.json.clone(),
+            });
+            if let Ok(json) = msg.to_json() {
+                let _ = ndb.process_event_with(&json, IngestMetadata::new().client(true));
+            }
+
+            self.send(pool, &event, &signing.relays);
+        }
+    }
+
+    fn prune(&mut self, now: u64) {
+        let before = self.entries.len();
+        let max_age = OUTBOX_MAX_AGE.as_secs();
+        self.entries
+            .retain(|_, entry| now.saturating_sub(entry.created_at) < max_age);
+
+        if self.entries.len() != before {
+            info!("outbox: forgot {} old notes", before - self.entries.len());
+            self.dirty = true;
+        }
+    }
+
+    fn save(&mut self) {
+        self.save_debouncer.bounce();
+        self.dirty = false;
+
+        let Some(directory) = &self.directory else {
+            return;
+        };
+
+        let json = match serde_json::to_string(&self.entries) {
+            Ok(json) => json,
+            Err(err) => {
+                error!("could not serialize outbox: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = storage::write_file(&directory.file_path, OUTBOX_FILE.to_owned(), &json) {
+            error!("could not save outbox: {err}");
+        }
+    }
+}
+
+pub(crate) fn is_connected(pool: &RelayPool, url: &str) -> bool {
+    pool.relays
+        .iter()
+        .any(|relay| relay.url() == url && matches!(relay.status(), RelayStatus::Connected))
+}
+
+fn is_authenticated(pool: &RelayPool, url: &str) -> bool {
+    pool.relays.iter().any(|relay| {
+        relay.url() == url && relay.auth_state() == Some(&RelayAuthState::Authenticated)
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(created_at: u64, relays: &[(&str, Delivery)]) -> OutboxEntry {
+        OutboxEntry {
+            note_json: "{}".to_owned(),
+            created_at,
+            relays: relays
+                .iter()
+                .map(|(url, delivery)| (url.to_string(), delivery.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_handle_ok() {
+        let id = "ab".repeat(32);
+        let mut outbox = Outbox::with_entries(HashMap::from([(
+            id.clone(),
+            entry(
+                now_secs(),
+                &[
+                    ("wss://a/", Delivery::Pending),
+                    ("wss://b/", Delivery::Pending),
+                    ("wss://c/", Delivery::Pending),
+                ],
+            ),
+        )]));
+
+        assert!(!outbox.handle_ok("wss://a/", &"cd".repeat(32), true, ""));
+        assert!(!outbox.handle_ok("wss://d/", &id, true, ""));
+
+        assert!(outbox.handle_ok("wss://a/", &id, false, "duplicate: have it"));
+        assert!(outbox.handle_ok("wss://b/", &id, false, "blocked: go away"));
+        assert!(outbox.handle_ok("wss://c/", &id, false, "auth-required: members only"));
+
+        let status = outbox.status(&[0xab; 32]).expect("status");
+        assert!(status.is_delivered());
+        assert_eq!(status.accepted, 1);
+        assert_eq!(status.pending, 1);
+        assert_eq!(
+            status.rejected,
+            vec![("wss://b/".to_owned(), "blocked: go away".to_owned())]
+        );
+        assert!(outbox
+            .needs_auth
+            .contains(&(id.clone(), "wss://c/".to_owned())));
+    }
+
+    #[test]
+    fn test_delivery_failed() {
+        let status = DeliveryStatus {
+            pending: 0,
+            accepted: 0,
+            rejected: vec![("wss://a/".to_owned(), "invalid: bad".to_owned())],
+        };
+        assert!(status.is_failed());
+        assert!(!status.is_delivered());
+    }
+
+    #[test]
+    fn test_prune_and_roundtrip() {
+        let now = now_secs();
+        let old = now - OUTBOX_MAX_AGE.as_secs() - 1;
+        let mut outbox = Outbox::with_entries(HashMap::from([
+            (
+                "01".repeat(32),
+                entry(old, &[("wss://a/", Delivery::Pending)]),
+            ),
+            (
+                "02".repeat(32),
+                entry(
+                    now,
+                    &[("wss://a/", Delivery::Rejected("pow: 20".to_owned()))],
+                ),
+            ),
+        ]));
+
+        outbox.prune(now);
+        assert!(outbox.dirty);
+        assert!(outbox.status(&[0x01; 32]).is_none());
+
+        let json = serde_json::to_string(&outbox.entries).expect("json");
+        let parsed: HashMap<String, OutboxEntry> = serde_json::from_str(&json).expect("parse");
+        assert_eq!(parsed, outbox.entries);
+    }
+}
@@ -85,6 +85,11 @@ impl RelayDebugView<'_> {
                                     enostr::ClientMessage::Event { .. } => "Event",
                                     enostr::ClientMessage::Req { .. } => "Req",
                                     enostr::ClientMessage::Close { .. } => "Close",
+                                    enostr::ClientMessage::Count { .. } => "Count",
+                                    enostr::ClientMessage::NegOpen { .. } => "NegOpen",
+                                    enostr::ClientMessage::NegMsg { .. } => "NegMsg",
+                                    enostr::ClientMessage::NegClose { .. } => "NegClose",
+                                    enostr::ClientMessage::Auth(_) => "Auth",
                                     enostr::ClientMessage::Raw(_) => "Raw",
                                 };
 
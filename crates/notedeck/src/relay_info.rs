@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use enostr::{PoolRelay, RelayInformation, RelayPool};
+use poll_promise::Promise;
+use tracing::{debug, error};
+
+/// Relays that didn't serve a usable document are asked again after this
+const RETRY_AFTER: Duration = Duration::from_secs(60 * 30);
+
+type InfoResult = Result<RelayInformation, String>;
+
+enum InfoState {
+    Fetching(Promise<InfoResult>),
+    Fetched(RelayInformation),
+    Failed(Instant),
+}
+
+/// Fetches the NIP-11 document of every websocket relay in the pool once,
+/// and hands it to the pool so it can respect the relay's limits. Relays
+/// that are dropped and added again get the cached document right away.
+#[derive(Default)]
+pub struct RelayInfoCache {
+    relays: HashMap<String, InfoState>,
+}
+
+impl RelayInfoCache {
+    pub fn update(
+        &mut self,
+        pool: &mut RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        let now = Instant::now();
+
+        for relay in &mut pool.relays {
+            let PoolRelay::Websocket(wsr) = relay else {
+                continue;
+            };
+            let url = wsr.relay.url.as_str();
+
+            let Some(state) = self.relays.get_mut(url) else {
+                if let Some(promise) = fetch(url, wakeup.clone()) {
+                    self.relays
+                        .insert(url.to_owned(), InfoState::Fetching(promise));
+                }
+                continue;
+            };
+
+            match state {
+                InfoState::Fetching(promise) => {
+                    let Some(result) = promise.ready() else {
+                        continue;
+                    };
+
+                    let next = match result {
+                        Ok(info) => {
+                            debug!("fetched relay info for {url}");
+                            wsr.set_info(info.clone());
+                            InfoState::Fetched(info.clone())
+                        }
+                        Err(err) => {
+                            error!("could not fetch relay info for {url}: {err}");
+                            InfoState::Failed(now)
+                        }
+                    };
+                    *state = next;
+                }
+
+                InfoState::Fetched(info) => {
+                    if wsr.info.is_none() {
+                        wsr.set_info(info.clone());
+                    }
+                }
+
+                InfoState::Failed(failed_at) => {
+                    if now.saturating_duration_since(*failed_at) >= RETRY_AFTER {
+                        if let Some(promise) = fetch(url, wakeup.clone()) {
+                            *state = InfoState::Fetching(promise);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn fetch(
+    relay_url: &str,
+    wakeup: impl Fn() + Send + Sync + 'static,
+) -> Option<Promise<InfoResult>> {
+    let url = RelayInformation::document_url(relay_url)?;
+    let request = ehttp::Request {
+        headers: ehttp::Headers::new(&[("Accept", RelayInformation::CONTENT_TYPE)]),
+        ..ehttp::Request::get(url)
+    };
+
+    let (sender, promise) = Promise::new();
+    ehttp::fetch(request, move |response: Result<ehttp::Response, String>| {
+        let result = response.and_then(|resp| {
+            if !resp.ok {
+                return Err(format!("bad http response: {}", resp.status_text));
+            }
+
+            let text = resp.text().ok_or("response is not text")?;
+            RelayInformation::from_json(text).map_err(|e| e.to_string())
+        });
+
+        sender.send(result);
+        wakeup();
+    });
+
+    Some(promise)
+}
@@ -0,0 +1,284 @@
+mod nip46;
+
+pub use nip46::{
+    nostrconnect_uri, BunkerUri, RemoteLogin, RemoteSigner, RemoteSignerInfo, KIND_NOSTR_CONNECT,
+};
+
+use enostr::{FilledKeypair, Pubkey};
+use nostr::JsonUtil;
+use nostrdb::{NdbStrVariant, Note, NoteBuilder};
+use poll_promise::Promise;
+use serde::{Deserialize, Serialize};
+
+use crate::SignerError;
+
+pub type SignResult = Result<SignedEvent, SignerError>;
+
+/// A NIP-44 payload, or the text in it
+pub type CryptResult = Result<String, SignerError>;
+
+/// Signs notes for an account. Local keys sign right away, remote signers
+/// answer whenever they get to it, possibly after the user approved the
+/// request on another device.
+pub trait Signer {
+    /// The pubkey notes are signed for
+    fn pubkey(&self) -> &Pubkey;
+
+    fn sign(&mut self, event: UnsignedEvent) -> Promise<SignResult>;
+
+    /// Encrypt `plaintext` for `peer` with NIP-44
+    fn nip44_encrypt(&mut self, peer: &Pubkey, plaintext: &str) -> Promise<CryptResult>;
+
+    /// Read the NIP-44 `payload` exchanged with `peer`
+    fn nip44_decrypt(&mut self, peer: &Pubkey, payload: &str) -> Promise<CryptResult>;
+}
+
+/// Signs with a secret key we hold
+pub struct LocalSigner<'a> {
+    keypair: FilledKeypair<'a>,
+}
+
+impl<'a> LocalSigner<'a> {
+    pub fn new(keypair: FilledKeypair<'a>) -> Self {
+        Self { keypair }
+    }
+}
+
+impl Signer for LocalSigner<'_> {
+    fn pubkey(&self) -> &Pubkey {
+        self.keypair.pubkey
+    }
+
+    fn sign(&mut self, event: UnsignedEvent) -> Promise<SignResult> {
+        let result = if event.pubkey == *self.keypair.pubkey {
+            event.sign(&self.keypair.secret_key.secret_bytes())
+        } else {
+            Err(SignerError::NoSigner)
+        };
+
+        Promise::from_ready(result)
+    }
+
+    fn nip44_encrypt(&mut self, peer: &Pubkey, plaintext: &str) -> Promise<CryptResult> {
+        let result = enostr::nip44::encrypt(self.keypair.secret_key, peer, plaintext)
+            .map_err(|e| SignerError::Crypto(e.to_string()));
+        Promise::from_ready(result)
+    }
+
+    fn nip44_decrypt(&mut self, peer: &Pubkey, payload: &str) -> Promise<CryptResult> {
+        let result = enostr::nip44::decrypt(self.keypair.secret_key, peer, payload)
+            .map_err(|e| SignerError::Crypto(e.to_string()));
+        Promise::from_ready(result)
+    }
+}
+
+/// A note waiting for its signature, in the form NIP-46 `sign_event`
+/// requests carry it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsignedEvent {
+    pub pubkey: Pubkey,
+    pub created_at: u64,
+    pub kind: u32,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+}
+
+impl UnsignedEvent {
+    /// The note `builder` describes, by `pubkey`, without signing it
+    pub fn new(pubkey: &Pubkey, builder: NoteBuilder<'_>) -> Result<Self, SignerError> {
+        let note = builder
+            .pubkey(pubkey.bytes())
+            .build()
+            .ok_or(SignerError::Build)?;
+
+        Ok(Self::from_note(&note))
+    }
+
+    pub fn from_note(note: &Note<'_>) -> Self {
+        let tags = note
+            .tags()
+            .into_iter()
+            .map(|tag| {
+                tag.into_iter()
+                    .map(|item| match item.variant() {
+                        NdbStrVariant::Id(id) => hex::encode(id),
+                        NdbStrVariant::Str(s) => s.to_owned(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            pubkey: Pubkey::new(*note.pubkey()),
+            created_at: note.created_at(),
+            kind: note.kind(),
+            tags,
+            content: note.content().to_owned(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("unsigned event json")
+    }
+
+    fn builder(&self) -> NoteBuilder<'_> {
+        let mut builder = NoteBuilder::new()
+            .kind(self.kind)
+            .content(&self.content)
+            .pubkey(self.pubkey.bytes())
+            .created_at(self.created_at);
+
+        for tag in &self.tags {
+            builder = builder.start_tag();
+            for item in tag {
+                builder = builder.tag_str(item);
+            }
+        }
+
+        builder
+    }
+
+    fn sign(&self, seckey: &[u8; 32]) -> SignResult {
+        let note = self
+            .builder()
+            .sign(seckey)
+            .build()
+            .ok_or(SignerError::Build)?;
+
+        SignedEvent::from_note(&note)
+    }
+}
+
+/// A signed note, ready to be published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedEvent {
+    pub id: [u8; 32],
+    pub created_at: u64,
+    pub json: String,
+}
+
+#[derive(Deserialize)]
+struct SignedFields {
+    id: String,
+    pubkey: Pubkey,
+    created_at: u64,
+    kind: u32,
+    tags: Vec<Vec<String>>,
+    content: String,
+}
+
+impl SignedEvent {
+    pub fn from_note(note: &Note<'_>) -> SignResult {
+        let json = note.json().map_err(|_| SignerError::Build)?;
+
+        Ok(Self {
+            id: *note.id(),
+            created_at: note.created_at(),
+            json,
+        })
+    }
+
+    /// A note signed by someone else for `event`. The signature is checked,
+    /// and that it is the note we asked for.
+    pub fn from_remote(json: String, event: &UnsignedEvent) -> SignResult {
+        let bad = |e: String| SignerError::BadResponse(e);
+
+        nostr::Event::from_json(&json)
+            .map_err(|e| bad(e.to_string()))?
+            .verify()
+            .map_err(|e| bad(e.to_string()))?;
+
+        let fields: SignedFields = serde_json::from_str(&json).map_err(|e| bad(e.to_string()))?;
+        if fields.pubkey != event.pubkey
+            || fields.created_at != event.created_at
+            || fields.kind != event.kind
+            || fields.tags != event.tags
+            || fields.content != event.content
+        {
+            return Err(bad("signed a different note".to_owned()));
+        }
+
+        let mut id = [0; 32];
+        hex::decode_to_slice(&fields.id, &mut id).map_err(|e| bad(e.to_string()))?;
+
+        Ok(Self {
+            id,
+            created_at: fields.created_at,
+            json,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+
+    fn unsigned(pubkey: &Pubkey) -> UnsignedEvent {
+        let builder = NoteBuilder::new()
+            .kind(1)
+            .content("hello")
+            .start_tag()
+            .tag_str("p")
+            .tag_str(&pubkey.hex())
+            .start_tag()
+            .tag_str("t")
+            .tag_str("nostr");
+
+        UnsignedEvent::new(pubkey, builder).expect("unsigned event")
+    }
+
+    #[test]
+    fn test_unsigned_event() {
+        let kp = FullKeypair::generate();
+        let event = unsigned(&kp.pubkey);
+
+        assert_eq!(event.pubkey, kp.pubkey);
+        assert_eq!(event.kind, 1);
+        assert_eq!(event.content, "hello");
+        assert_eq!(
+            event.tags,
+            vec![
+                vec!["p".to_owned(), kp.pubkey.hex()],
+                vec!["t".to_owned(), "nostr".to_owned()]
+            ]
+        );
+        assert!(event.created_at > 0);
+
+        let json: serde_json::Value = serde_json::from_str(&event.to_json()).expect("json");
+        assert_eq!(json["pubkey"], kp.pubkey.hex());
+        assert_eq!(json["tags"][1][1], "nostr");
+    }
+
+    #[test]
+    fn test_local_signer() {
+        let kp = FullKeypair::generate();
+        let event = unsigned(&kp.pubkey);
+
+        let mut signer = LocalSigner::new(kp.to_filled());
+        let signed = signer.sign(event.clone()).block_and_take().expect("signed");
+
+        // what a remote signer would have answered
+        let checked = SignedEvent::from_remote(signed.json.clone(), &event).expect("valid");
+        assert_eq!(checked, signed);
+        assert_eq!(signed.created_at, event.created_at);
+
+        // a note for another pubkey is refused
+        let other = FullKeypair::generate();
+        let result = signer.sign(unsigned(&other.pubkey)).block_and_take();
+        assert_eq!(result, Err(SignerError::NoSigner));
+
+        // and so is a note that isn't the one we asked for
+        let mut changed = event.clone();
+        changed.content = "bye".to_owned();
+        assert!(SignedEvent::from_remote(signed.json.clone(), &changed).is_err());
+
+        let mut changed = event.clone();
+        changed.tags.push(vec!["t".to_owned(), "nostr".to_owned()]);
+        assert!(SignedEvent::from_remote(signed.json.clone(), &changed).is_err());
+
+        let mut changed = event;
+        changed.created_at += 1;
+        assert!(SignedEvent::from_remote(signed.json, &changed).is_err());
+    }
+}
@@ -0,0 +1,849 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use enostr::{
+    ClientMessage, EventClientMessage, FullKeypair, Pubkey, RelayEvent, RelayMessage, RelayPool,
+    SecretKey,
+};
+use nostr::nips::nip44;
+use nostrdb::{Filter, NoteBuilder};
+use poll_promise::{Promise, Sender};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+use super::{CryptResult, SignResult, SignedEvent, Signer, UnsignedEvent};
+use crate::account::relay::AccountRelayData;
+use crate::outbox::is_connected;
+use crate::SignerError;
+
+/// NIP-46 requests and responses
+pub const KIND_NOSTR_CONNECT: u32 = 24133;
+
+/// Remote signers may wait for the user to approve a request elsewhere
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long logging in with a remote signer may take, scanning a
+/// nostrconnect:// uri on a phone included
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A `bunker://<remote-signer-pubkey>?relay=<url>&secret=<secret>` uri, as
+/// remote signers hand them out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BunkerUri {
+    pub remote_pubkey: Pubkey,
+    pub relays: Vec<String>,
+    pub secret: Option<String>,
+}
+
+impl BunkerUri {
+    pub fn parse(uri: &str) -> Result<Self, SignerError> {
+        let invalid = |reason: &str| SignerError::InvalidUri(reason.to_owned());
+
+        let url = Url::parse(uri.trim()).map_err(|e| invalid(&e.to_string()))?;
+        if url.scheme() != "bunker" {
+            return Err(invalid("not a bunker:// uri"));
+        }
+
+        let remote_pubkey = url
+            .host_str()
+            .and_then(|host| Pubkey::from_hex(host).ok())
+            .ok_or_else(|| invalid("bad remote signer pubkey"))?;
+
+        let mut relays = vec![];
+        let mut secret = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => relays.push(AccountRelayData::canonicalize_url(&value)),
+                "secret" => secret = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        if relays.is_empty() {
+            return Err(invalid("no relays"));
+        }
+
+        Ok(Self {
+            remote_pubkey,
+            relays,
+            secret,
+        })
+    }
+}
+
+impl fmt::Display for BunkerUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut url = Url::parse(&format!("bunker://{}", self.remote_pubkey.hex()))
+            .map_err(|_| fmt::Error)?;
+        {
+            let mut query = url.query_pairs_mut();
+            for relay in &self.relays {
+                query.append_pair("relay", relay);
+            }
+            if let Some(secret) = &self.secret {
+                query.append_pair("secret", secret);
+            }
+        }
+        write!(f, "{url}")
+    }
+}
+
+/// The `nostrconnect://` uri a remote signer scans to connect to us. It
+/// answers with `secret` so we know it's the one we asked.
+pub fn nostrconnect_uri(client: &Pubkey, relays: &[String], secret: &str) -> String {
+    let mut url = Url::parse(&format!("nostrconnect://{}", client.hex())).expect("client url");
+    {
+        let mut query = url.query_pairs_mut();
+        for relay in relays {
+            query.append_pair("relay", relay);
+        }
+        query.append_pair("secret", secret);
+        query.append_pair("name", "Notedeck");
+    }
+    url.to_string()
+}
+
+/// What we need to talk to an account's remote signer again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSignerInfo {
+    pub remote_pubkey: Pubkey,
+    pub relays: Vec<String>,
+
+    /// Our side of the connection, the remote signer knows us by its
+    /// pubkey
+    pub client_key: SecretKey,
+}
+
+impl RemoteSignerInfo {
+    /// The uri to store next to the client key, without a secret
+    pub fn uri(&self) -> BunkerUri {
+        BunkerUri {
+            remote_pubkey: self.remote_pubkey,
+            relays: self.relays.clone(),
+            secret: None,
+        }
+    }
+}
+
+/// Signs for an account through its NIP-46 remote signer. Requests go out
+/// as kind 24133 events on the signer's relays, and the answers come back
+/// on our own subscription there, see [`RemoteSigner::handle_event`].
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    conn: Connection,
+}
+
+impl RemoteSigner {
+    pub fn new(pubkey: Pubkey, info: &RemoteSignerInfo) -> Self {
+        let client = FullKeypair::new(
+            enostr::Keypair::from_secret(info.client_key.clone()).pubkey,
+            info.client_key.clone(),
+        );
+
+        Self {
+            pubkey,
+            conn: Connection::new(client, Some(info.remote_pubkey), &info.relays),
+        }
+    }
+
+    pub fn relays(&self) -> &[String] {
+        &self.conn.relays
+    }
+
+    /// Whether requests are waiting for an answer
+    pub fn is_busy(&self) -> bool {
+        !self.conn.requests.is_empty()
+    }
+
+    /// Subscribe and send requests to the signer's relays as they connect,
+    /// and give up on requests that went unanswered for too long
+    pub fn update(&mut self, pool: &mut RelayPool) {
+        self.conn.update(pool, Instant::now());
+    }
+
+    /// Handle an event from a relay. Returns true when it came in on our
+    /// subscription.
+    pub fn handle_event(&mut self, sub_id: &str, event_json: &str) -> bool {
+        self.conn.handle_event(sub_id, event_json)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    fn sign(&mut self, event: UnsignedEvent) -> Promise<SignResult> {
+        let (sender, promise) = Promise::new();
+        if event.pubkey != self.pubkey {
+            sender.send(Err(SignerError::NoSigner));
+            return promise;
+        }
+
+        let params = vec![event.to_json()];
+        self.conn
+            .request("sign_event", params, Reply::Signed(event, sender));
+        promise
+    }
+
+    fn nip44_encrypt(&mut self, peer: &Pubkey, plaintext: &str) -> Promise<CryptResult> {
+        let (sender, promise) = Promise::new();
+        let params = vec![peer.hex(), plaintext.to_owned()];
+        self.conn
+            .request("nip44_encrypt", params, Reply::Result(sender));
+        promise
+    }
+
+    fn nip44_decrypt(&mut self, peer: &Pubkey, payload: &str) -> Promise<CryptResult> {
+        let (sender, promise) = Promise::new();
+        let params = vec![peer.hex(), payload.to_owned()];
+        self.conn
+            .request("nip44_decrypt", params, Reply::Result(sender));
+        promise
+    }
+}
+
+/// A remote signer we are connected to, and the user it signs for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteLogin {
+    pub pubkey: Pubkey,
+    pub info: RemoteSignerInfo,
+}
+
+type LoginResult = Result<RemoteLogin, SignerError>;
+
+impl RemoteLogin {
+    /// Connect to the remote signer of a `bunker://` uri and ask it which
+    /// pubkey it signs for, in the background
+    pub fn bunker(
+        uri: BunkerUri,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> Promise<LoginResult> {
+        let mut conn = Connection::new(
+            FullKeypair::generate(),
+            Some(uri.remote_pubkey),
+            &uri.relays,
+        );
+
+        let mut params = vec![uri.remote_pubkey.hex()];
+        params.extend(uri.secret);
+
+        let (sender, connected) = Promise::new();
+        conn.request("connect", params, Reply::Result(sender));
+
+        spawn_login(conn, connected, wakeup)
+    }
+
+    /// Wait in the background for a remote signer to connect through the
+    /// returned `nostrconnect://` uri
+    pub fn nostrconnect(
+        relays: &[String],
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> (String, Promise<LoginResult>) {
+        let client = FullKeypair::generate();
+        let secret = hex::encode(rand::random::<[u8; 16]>());
+        let uri = nostrconnect_uri(&client.pubkey, relays, &secret);
+
+        let mut conn = Connection::new(client, None, relays);
+        let (sender, connected) = Promise::new();
+        conn.awaiting = Some((secret, sender));
+
+        (uri, spawn_login(conn, connected, wakeup))
+    }
+}
+
+fn spawn_login(
+    conn: Connection,
+    connected: Promise<RawResult>,
+    wakeup: impl Fn() + Send + Sync + 'static,
+) -> Promise<LoginResult> {
+    let (sender, promise) = Promise::new();
+    std::thread::spawn(move || {
+        sender.send(login(conn, connected));
+        wakeup();
+    });
+    promise
+}
+
+/// The login handshake, on its own connections to the signer's relays:
+/// wait for the connection, then ask for the user's pubkey
+fn login(mut conn: Connection, connected: Promise<RawResult>) -> LoginResult {
+    let mut pool = RelayPool::new();
+    for relay in &conn.relays {
+        if let Err(err) = pool.add_url(relay.clone(), || {}) {
+            warn!("remote signer relay {relay}: {err}");
+        }
+    }
+
+    let deadline = Instant::now() + LOGIN_TIMEOUT;
+    let mut step = connected;
+    let mut asked_pubkey = false;
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(SignerError::Timeout);
+        }
+
+        while let Some(ev) = pool.try_recv() {
+            let ev = ev.into_owned();
+            if let RelayEvent::Message(RelayMessage::Event(sub_id, json)) = (&ev.event).into() {
+                conn.handle_event(sub_id, json);
+            }
+        }
+        conn.update(&mut pool, now);
+
+        if let Some(result) = step.ready() {
+            let result = result.clone()?;
+
+            if asked_pubkey {
+                let pubkey = Pubkey::from_hex(result.trim())
+                    .map_err(|e| SignerError::BadResponse(e.to_string()))?;
+                let remote_pubkey = conn.remote.ok_or(SignerError::NoSigner)?;
+                info!("logged in to {pubkey} with remote signer {remote_pubkey}");
+
+                return Ok(RemoteLogin {
+                    pubkey,
+                    info: RemoteSignerInfo {
+                        remote_pubkey,
+                        relays: conn.relays.clone(),
+                        client_key: conn.client.secret_key.clone(),
+                    },
+                });
+            }
+
+            let (sender, promise) = Promise::new();
+            conn.request("get_public_key", vec![], Reply::Result(sender));
+            step = promise;
+            asked_pubkey = true;
+            continue;
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+type RawResult = Result<String, SignerError>;
+
+enum Reply {
+    /// A `sign_event` request, checked against the note we asked for
+    Signed(UnsignedEvent, Sender<SignResult>),
+    Result(Sender<RawResult>),
+}
+
+impl Reply {
+    fn resolve(self, result: RawResult) {
+        match self {
+            Reply::Signed(event, sender) => {
+                sender.send(result.and_then(|json| SignedEvent::from_remote(json, &event)))
+            }
+            Reply::Result(sender) => sender.send(result),
+        }
+    }
+}
+
+struct Request {
+    event_json: String,
+    created: Instant,
+
+    /// Relays that have it on their current connection
+    sent_to: HashSet<String>,
+
+    reply: Reply,
+}
+
+#[derive(Deserialize)]
+struct ResponseEvent {
+    pubkey: Pubkey,
+    kind: u32,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: String,
+    #[serde(default)]
+    result: String,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// The NIP-46 plumbing between our client key and a remote signer
+struct Connection {
+    client: FullKeypair,
+
+    /// Unknown until a signer accepts our nostrconnect:// uri
+    remote: Option<Pubkey>,
+
+    relays: Vec<String>,
+    sub_id: String,
+
+    /// Relays we subscribed to on their current connection
+    subscribed: HashSet<String>,
+
+    /// Requests waiting for an answer, by request id
+    requests: HashMap<String, Request>,
+
+    /// The secret of our nostrconnect:// uri, and who waits for a signer
+    /// to answer with it
+    awaiting: Option<(String, Sender<RawResult>)>,
+}
+
+impl Connection {
+    fn new(client: FullKeypair, remote: Option<Pubkey>, relays: &[String]) -> Self {
+        Self {
+            client,
+            remote,
+            relays: relays
+                .iter()
+                .map(|relay| AccountRelayData::canonicalize_url(relay))
+                .collect(),
+            sub_id: format!("nip46-{}", Uuid::new_v4()),
+            subscribed: HashSet::new(),
+            requests: HashMap::new(),
+            awaiting: None,
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Vec<String>, reply: Reply) {
+        let Some(remote) = self.remote else {
+            reply.resolve(Err(SignerError::NoSigner));
+            return;
+        };
+
+        let id = Uuid::new_v4().to_string();
+        let payload = serde_json::json!({ "id": id, "method": method, "params": params });
+
+        match self.request_event(&remote, &payload.to_string()) {
+            Ok(event_json) => {
+                debug!("nip46: {method} request {id}");
+                self.requests.insert(
+                    id,
+                    Request {
+                        event_json,
+                        created: Instant::now(),
+                        sent_to: HashSet::new(),
+                        reply,
+                    },
+                );
+            }
+            Err(err) => reply.resolve(Err(err)),
+        }
+    }
+
+    fn request_event(&self, remote: &Pubkey, payload: &str) -> Result<String, SignerError> {
+        let remote_key = nostr::PublicKey::from_slice(remote.bytes())
+            .map_err(|e| SignerError::Remote(e.to_string()))?;
+        let content = nip44::encrypt(
+            &self.client.secret_key,
+            &remote_key,
+            payload,
+            nip44::Version::V2,
+        )
+        .map_err(|e| SignerError::Remote(e.to_string()))?;
+
+        let note = NoteBuilder::new()
+            .kind(KIND_NOSTR_CONNECT)
+            .content(&content)
+            .start_tag()
+            .tag_str("p")
+            .tag_str(&remote.hex())
+            .sign(&self.client.secret_key.secret_bytes())
+            .build()
+            .ok_or(SignerError::Build)?;
+
+        note.json().map_err(|_| SignerError::Build)
+    }
+
+    fn filter(&self) -> Filter {
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Filter::new()
+            .kinds([KIND_NOSTR_CONNECT as u64])
+            .pubkeys([self.client.pubkey.bytes()])
+            // a little slack for clocks that disagree
+            .since(since.saturating_sub(60))
+            .build()
+    }
+
+    fn update(&mut self, pool: &mut RelayPool, now: Instant) {
+        for relay in &self.relays {
+            if !is_connected(pool, relay) {
+                self.subscribed.remove(relay);
+                for request in self.requests.values_mut() {
+                    request.sent_to.remove(relay);
+                }
+                continue;
+            }
+
+            if self.subscribed.insert(relay.clone()) {
+                let req = ClientMessage::req(self.sub_id.clone(), vec![self.filter()]);
+                pool.send_to(&req, relay);
+            }
+
+            for request in self.requests.values_mut() {
+                if request.sent_to.insert(relay.clone()) {
+                    let msg = ClientMessage::Event(EventClientMessage {
+                        note_json: request.event_json.clone(),
+                    });
+                    pool.send_to(&msg, relay);
+                }
+            }
+        }
+
+        let expired: Vec<String> = self
+            .requests
+            .iter()
+            .filter(|(_, request)| {
+                now.saturating_duration_since(request.created) >= REQUEST_TIMEOUT
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in expired {
+            if let Some(request) = self.requests.remove(&id) {
+                warn!("nip46: request {id} timed out");
+                request.reply.resolve(Err(SignerError::Timeout));
+            }
+        }
+    }
+
+    fn handle_event(&mut self, sub_id: &str, event_json: &str) -> bool {
+        if sub_id != self.sub_id {
+            return false;
+        }
+
+        let Ok(event) = serde_json::from_str::<ResponseEvent>(event_json) else {
+            return true;
+        };
+        if event.kind != KIND_NOSTR_CONNECT {
+            return true;
+        }
+        if self.remote.is_some_and(|remote| remote != event.pubkey) {
+            return true;
+        }
+
+        // only the signer we talk to can encrypt for our client key, so
+        // decrypting also tells us it's really from them
+        let Some(response) = self.decrypt(&event) else {
+            debug!("nip46: could not read event from {}", event.pubkey);
+            return true;
+        };
+
+        if self.remote.is_none() {
+            if let Some((secret, _)) = &self.awaiting {
+                if response.result == *secret {
+                    let (_, sender) = self.awaiting.take().expect("awaiting");
+                    self.remote = Some(event.pubkey);
+                    sender.send(Ok(response.result));
+                }
+            }
+            return true;
+        }
+
+        if response.result == "auth_url" {
+            warn!(
+                "nip46: remote signer wants request {} approved at {}",
+                response.id,
+                response.error.unwrap_or_default()
+            );
+            return true;
+        }
+
+        let Some(request) = self.requests.remove(&response.id) else {
+            return true;
+        };
+
+        let result = match response.error {
+            Some(error) if !error.is_empty() => Err(SignerError::Remote(error)),
+            _ => Ok(response.result),
+        };
+        request.reply.resolve(result);
+
+        true
+    }
+
+    fn decrypt(&self, event: &ResponseEvent) -> Option<Response> {
+        let author = nostr::PublicKey::from_slice(event.pubkey.bytes()).ok()?;
+        let content = nip44::decrypt(&self.client.secret_key, &author, &event.content).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+
+    const REMOTE: &str = "fa984bd7dbb282f07e16e7ae87b26a2a7b9b90b7246a44771f0cf5ae58018f52";
+
+    #[test]
+    fn test_bunker_uri() {
+        let uri = BunkerUri::parse(&format!(
+            "bunker://{REMOTE}?relay=wss%3A%2F%2Frelay.nsec.app&relay=wss://nos.lol&secret=abc"
+        ))
+        .expect("uri");
+
+        assert_eq!(uri.remote_pubkey.hex(), REMOTE);
+        assert_eq!(uri.relays, vec!["wss://relay.nsec.app/", "wss://nos.lol/"]);
+        assert_eq!(uri.secret.as_deref(), Some("abc"));
+        assert_eq!(BunkerUri::parse(&uri.to_string()), Ok(uri));
+
+        assert!(BunkerUri::parse(&format!("nostrconnect://{REMOTE}?relay=wss://a.b")).is_err());
+        assert!(BunkerUri::parse(&format!("bunker://{REMOTE}")).is_err());
+        assert!(BunkerUri::parse("bunker://npub?relay=wss://nos.lol").is_err());
+    }
+
+    /// A remote signer stand-in: answers the requests of `conn` with the
+    /// user's local key
+    struct Bunker {
+        remote: FullKeypair,
+        user: FullKeypair,
+    }
+
+    impl Bunker {
+        fn answer(&self, conn: &Connection, request_json: &str) -> String {
+            #[derive(Deserialize)]
+            struct Payload {
+                id: String,
+                method: String,
+                params: Vec<String>,
+            }
+
+            let request: ResponseEvent = serde_json::from_str(request_json).expect("request");
+            let client = nostr::PublicKey::from_slice(request.pubkey.bytes()).expect("client");
+            let payload = nip44::decrypt(&self.remote.secret_key, &client, &request.content)
+                .expect("decrypt");
+            let payload: Payload = serde_json::from_str(&payload).expect("payload");
+
+            let result = match payload.method.as_str() {
+                "connect" => "ack".to_owned(),
+                "get_public_key" => self.user.pubkey.hex(),
+                "sign_event" => {
+                    let event: UnsignedEvent =
+                        serde_json::from_str(&payload.params[0]).expect("event");
+                    LocalSigner::new(self.user.to_filled())
+                        .sign(event)
+                        .block_and_take()
+                        .expect("signed")
+                        .json
+                }
+                "nip44_encrypt" | "nip44_decrypt" => {
+                    let peer = Pubkey::from_hex(&payload.params[0]).expect("peer");
+                    let mut signer = LocalSigner::new(self.user.to_filled());
+                    let result = if payload.method == "nip44_encrypt" {
+                        signer.nip44_encrypt(&peer, &payload.params[1])
+                    } else {
+                        signer.nip44_decrypt(&peer, &payload.params[1])
+                    };
+                    result.block_and_take().expect("crypt")
+                }
+                method => panic!("unexpected {method}"),
+            };
+
+            let response = serde_json::json!({ "id": payload.id, "result": result });
+            let content = nip44::encrypt(
+                &self.remote.secret_key,
+                &client,
+                response.to_string(),
+                nip44::Version::V2,
+            )
+            .expect("encrypt");
+
+            assert_eq!(request.pubkey, conn.client.pubkey);
+            NoteBuilder::new()
+                .kind(KIND_NOSTR_CONNECT)
+                .content(&content)
+                .start_tag()
+                .tag_str("p")
+                .tag_str(&request.pubkey.hex())
+                .sign(&self.remote.secret_key.secret_bytes())
+                .build()
+                .expect("response")
+                .json()
+                .expect("json")
+        }
+
+        fn answer_all(&self, signer: &mut RemoteSigner) {
+            let requests: Vec<String> = signer
+                .conn
+                .requests
+                .values()
+                .map(|request| request.event_json.clone())
+                .collect();
+
+            for request in requests {
+                let response = self.answer(&signer.conn, &request);
+                let sub_id = signer.conn.sub_id.clone();
+                assert!(signer.handle_event(&sub_id, &response));
+            }
+        }
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let bunker = Bunker {
+            remote: FullKeypair::generate(),
+            user: FullKeypair::generate(),
+        };
+        let info = RemoteSignerInfo {
+            remote_pubkey: bunker.remote.pubkey,
+            relays: vec!["wss://relay.nsec.app".to_owned()],
+            client_key: FullKeypair::generate().secret_key,
+        };
+        let mut signer = RemoteSigner::new(bunker.user.pubkey, &info);
+        assert_eq!(signer.relays(), ["wss://relay.nsec.app/"]);
+
+        let builder = NoteBuilder::new().kind(7).content("🤙");
+        let event = UnsignedEvent::new(&bunker.user.pubkey, builder).expect("event");
+        let promise = signer.sign(event.clone());
+        assert!(promise.ready().is_none());
+        assert!(signer.is_busy());
+
+        // events on other subscriptions aren't ours
+        assert!(!signer.handle_event("other", "{}"));
+
+        bunker.answer_all(&mut signer);
+        let signed = promise.block_and_take().expect("signed");
+        assert!(signed.json.contains(&bunker.user.pubkey.hex()));
+        assert!(!signer.is_busy());
+
+        // responses from anyone else are ignored
+        let impostor = Bunker {
+            remote: FullKeypair::generate(),
+            user: FullKeypair::generate(),
+        };
+        let promise = signer.sign(event);
+        impostor.answer_all(&mut signer);
+        assert!(promise.ready().is_none());
+
+        // notes for other pubkeys are refused right away
+        let other =
+            UnsignedEvent::new(&impostor.user.pubkey, NoteBuilder::new().kind(1)).expect("event");
+        assert_eq!(
+            signer.sign(other).block_and_take(),
+            Err(SignerError::NoSigner)
+        );
+    }
+
+    #[test]
+    fn test_remote_signer_nip44() {
+        let bunker = Bunker {
+            remote: FullKeypair::generate(),
+            user: FullKeypair::generate(),
+        };
+        let info = RemoteSignerInfo {
+            remote_pubkey: bunker.remote.pubkey,
+            relays: vec!["wss://relay.nsec.app".to_owned()],
+            client_key: FullKeypair::generate().secret_key,
+        };
+        let mut signer = RemoteSigner::new(bunker.user.pubkey, &info);
+        let user = bunker.user.pubkey;
+
+        let promise = signer.nip44_encrypt(&user, "[[\"t\",\"nsfw\"]]");
+        bunker.answer_all(&mut signer);
+        let payload = promise.block_and_take().expect("encrypted");
+
+        // readable with the user's own key, as NIP-51 private entries are
+        let mut local = LocalSigner::new(bunker.user.to_filled());
+        let json = local.nip44_decrypt(&user, &payload).block_and_take();
+        assert_eq!(json.as_deref(), Ok("[[\"t\",\"nsfw\"]]"));
+
+        let promise = signer.nip44_decrypt(&user, &payload);
+        bunker.answer_all(&mut signer);
+        assert_eq!(promise.block_and_take(), json);
+    }
+
+    #[test]
+    fn test_remote_signer_error() {
+        let remote = FullKeypair::generate();
+        let user = FullKeypair::generate();
+        let info = RemoteSignerInfo {
+            remote_pubkey: remote.pubkey,
+            relays: vec!["wss://relay.nsec.app".to_owned()],
+            client_key: FullKeypair::generate().secret_key,
+        };
+        let mut signer = RemoteSigner::new(user.pubkey, &info);
+
+        let event = UnsignedEvent::new(&user.pubkey, NoteBuilder::new().kind(1)).expect("event");
+        let promise = signer.sign(event);
+        let id = signer.conn.requests.keys().next().expect("request").clone();
+
+        let client = nostr::PublicKey::from_slice(signer.conn.client.pubkey.bytes()).expect("pk");
+        let response = serde_json::json!({ "id": id, "result": "", "error": "denied" });
+        let content = nip44::encrypt(
+            &remote.secret_key,
+            &client,
+            response.to_string(),
+            nip44::Version::V2,
+        )
+        .expect("encrypt");
+        let json = NoteBuilder::new()
+            .kind(KIND_NOSTR_CONNECT)
+            .content(&content)
+            .sign(&remote.secret_key.secret_bytes())
+            .build()
+            .expect("note")
+            .json()
+            .expect("json");
+
+        let sub_id = signer.conn.sub_id.clone();
+        signer.handle_event(&sub_id, &json);
+        assert_eq!(
+            promise.block_and_take(),
+            Err(SignerError::Remote("denied".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_nostrconnect() {
+        let client = FullKeypair::generate();
+        let relays = vec!["wss://relay.nsec.app/".to_owned()];
+        let uri = nostrconnect_uri(&client.pubkey, &relays, "s3cret");
+        assert!(uri.starts_with(&format!("nostrconnect://{}?relay=", client.pubkey.hex())));
+        assert!(uri.contains("secret=s3cret"));
+
+        let mut conn = Connection::new(client, None, &relays);
+        let (sender, connected) = Promise::new();
+        conn.awaiting = Some(("s3cret".to_owned(), sender));
+
+        // requests wait for a signer to connect
+        let (sender, promise) = Promise::new();
+        conn.request("get_public_key", vec![], Reply::Result(sender));
+        assert_eq!(promise.block_and_take(), Err(SignerError::NoSigner));
+
+        let remote = FullKeypair::generate();
+        let client_pk = nostr::PublicKey::from_slice(conn.client.pubkey.bytes()).expect("pk");
+        let answer = |result: &str| {
+            let response = serde_json::json!({ "id": "x", "result": result });
+            let content = nip44::encrypt(
+                &remote.secret_key,
+                &client_pk,
+                response.to_string(),
+                nip44::Version::V2,
+            )
+            .expect("encrypt");
+            NoteBuilder::new()
+                .kind(KIND_NOSTR_CONNECT)
+                .content(&content)
+                .sign(&remote.secret_key.secret_bytes())
+                .build()
+                .expect("note")
+                .json()
+                .expect("json")
+        };
+
+        let sub_id = conn.sub_id.clone();
+        conn.handle_event(&sub_id, &answer("wrong"));
+        assert!(connected.ready().is_none());
+
+        conn.handle_event(&sub_id, &answer("s3cret"));
+        assert_eq!(connected.block_and_take(), Ok("s3cret".to_owned()));
+        assert_eq!(conn.remote, Some(remote.pubkey));
+    }
+}
@@ -1,26 +1,60 @@
-use crate::{user_account::UserAccountSerializable, Result};
-use enostr::{Keypair, Pubkey, SerializableKeypair};
+use crate::{user_account::UserAccountSerializable, Error, KeystoreError, Result};
+use enostr::{FullKeypair, Keypair, Pubkey, SecretKey, SerializableKeypair};
+use nostr::nips::nip19::{FromBech32, ToBech32};
+use nostr::nips::nip49::{EncryptedSecretKey, KeySecurity};
 use tokenator::{TokenParser, TokenSerializable, TokenWriter};
 
 use super::file_storage::{delete_file, write_file, Directory};
 
 static SELECTED_PUBKEY_FILE_NAME: &str = "selected_pubkey";
+static PASSPHRASE_CHECK_FILE_NAME: &str = "passphrase_check";
+
+/// NIP-49 scrypt cost of keys locked with the user's passphrase, each
+/// unlock takes a noticeable moment per key
+const PASSPHRASE_LOG_N: u8 = 16;
 
 /// An OS agnostic file key storage implementation
+///
+/// Secret keys are stored with a fixed password by default. Once the user
+/// sets a passphrase, they are locked with it instead, and a random key
+/// locked with the same passphrase is kept in the key lock directory to
+/// check it on unlock.
 #[derive(Debug, PartialEq, Clone)]
 pub struct AccountStorage {
     accounts_directory: Directory,
     selected_key_directory: Directory,
+    key_lock_directory: Directory,
+    log_n: u8,
 }
 
 impl AccountStorage {
-    pub fn new(accounts_directory: Directory, selected_key_directory: Directory) -> Self {
+    pub fn new(
+        accounts_directory: Directory,
+        selected_key_directory: Directory,
+        key_lock_directory: Directory,
+    ) -> Self {
         Self {
             accounts_directory,
             selected_key_directory,
+            key_lock_directory,
+            log_n: PASSPHRASE_LOG_N,
         }
     }
 
+    /// Whether the stored secret keys are locked with a passphrase
+    pub fn has_passphrase(&self) -> bool {
+        self.key_lock_directory
+            .get_file(PASSPHRASE_CHECK_FILE_NAME.to_owned())
+            .is_ok()
+    }
+
+    pub fn check_passphrase(&self, passphrase: &str) -> Result<()> {
+        let check = self
+            .key_lock_directory
+            .get_file(PASSPHRASE_CHECK_FILE_NAME.to_owned())?;
+        unlock_key(check.trim(), passphrase).map(|_| ())
+    }
+
     pub fn rw(self) -> (AccountStorageReader, AccountStorageWriter) {
         (
             AccountStorageReader::new(self.clone()),
@@ -31,16 +65,115 @@ impl AccountStorage {
 
 pub struct AccountStorageWriter {
     storage: AccountStorage,
+
+    /// The passphrase keys are locked with, while unlocked
+    passphrase: Option<String>,
+
+    has_passphrase: bool,
 }
 
 impl AccountStorageWriter {
     pub fn new(storage: AccountStorage) -> Self {
-        Self { storage }
+        let has_passphrase = storage.has_passphrase();
+        Self {
+            storage,
+            passphrase: None,
+            has_passphrase,
+        }
+    }
+
+    /// Whether there is a passphrase we don't know yet. Nothing can be
+    /// written until [`Self::unlock`], or we would lose the locked keys.
+    pub fn is_locked(&self) -> bool {
+        self.passphrase.is_none() && self.has_passphrase
+    }
+
+    pub fn has_passphrase(&self) -> bool {
+        self.has_passphrase
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<()> {
+        self.storage.check_passphrase(passphrase)?;
+        self.passphrase = Some(passphrase.to_owned());
+        Ok(())
+    }
+
+    /// Unlock with a passphrase [`AccountStorageReader::get_unlocked_accounts`]
+    /// already checked, without paying for the check again
+    pub fn unlocked(&mut self, passphrase: String) {
+        self.passphrase = Some(passphrase);
+    }
+
+    /// Forget the passphrase
+    pub fn lock(&mut self) {
+        self.passphrase = None;
+    }
+
+    /// Lock the secret keys of `accounts` with a new passphrase, or store
+    /// them the default way again with `None`
+    pub fn set_passphrase(
+        &mut self,
+        passphrase: Option<&str>,
+        accounts: &[UserAccountSerializable],
+    ) -> Result<()> {
+        if self.is_locked() {
+            return Err(KeystoreError::Locked.into());
+        }
+
+        // the check file goes first and away last, so keys written in
+        // between are readable either way
+        if let Some(passphrase) = passphrase {
+            let check = lock_key(
+                &FullKeypair::generate().secret_key,
+                passphrase,
+                self.storage.log_n,
+            )?;
+            write_file(
+                &self.storage.key_lock_directory.file_path,
+                PASSPHRASE_CHECK_FILE_NAME.to_owned(),
+                &check,
+            )?;
+        }
+
+        for account in accounts {
+            self.write(account, passphrase)?;
+        }
+        self.passphrase = passphrase.map(str::to_owned);
+
+        if passphrase.is_none() && self.has_passphrase {
+            delete_file(
+                &self.storage.key_lock_directory.file_path,
+                PASSPHRASE_CHECK_FILE_NAME.to_owned(),
+            )?;
+        }
+        self.has_passphrase = passphrase.is_some();
+
+        Ok(())
     }
 
     pub fn write_account(&self, account: &UserAccountSerializable) -> Result<()> {
+        if self.is_locked() {
+            return Err(KeystoreError::Locked.into());
+        }
+
+        self.write(account, self.passphrase.as_deref())
+    }
+
+    fn write(&self, account: &UserAccountSerializable, passphrase: Option<&str>) -> Result<()> {
         let mut writer = TokenWriter::new("\t");
-        account.serialize_tokens(&mut writer);
+        match (passphrase, &account.key.secret_key) {
+            // keys already locked by `AccountStorageReader::lock_account` are kept
+            (Some(passphrase), Some(secret_key)) if account.locked_key.is_none() => {
+                let locked = lock_key(secret_key, passphrase, self.storage.log_n)?;
+                let mut locked_account =
+                    UserAccountSerializable::new(Keypair::only_pubkey(account.key.pubkey))
+                        .with_locked_key(locked);
+                locked_account.wallet = account.wallet.clone();
+                locked_account.serialize_tokens(&mut writer);
+            }
+            _ => account.serialize_tokens(&mut writer),
+        }
+
         write_file(
             &self.storage.accounts_directory.file_path,
             account.key.pubkey.hex(),
@@ -76,6 +209,7 @@ impl AccountStorageWriter {
     }
 }
 
+#[derive(Clone)]
 pub struct AccountStorageReader {
     storage: AccountStorage,
 }
@@ -96,6 +230,46 @@ impl AccountStorageReader {
         Ok(keys)
     }
 
+    /// The stored accounts, with their locked secret keys unlocked. Their
+    /// `locked_key` is kept, accounts without one still need to be locked.
+    pub fn get_unlocked_accounts(&self, passphrase: &str) -> Result<Vec<UserAccountSerializable>> {
+        self.storage.check_passphrase(passphrase)?;
+
+        let mut accounts = self.get_accounts()?;
+        for account in &mut accounts {
+            let Some(locked) = &account.locked_key else {
+                continue;
+            };
+
+            match unlock_key(locked, passphrase) {
+                Ok(secret_key)
+                    if Keypair::from_secret(secret_key.clone()).pubkey == account.key.pubkey =>
+                {
+                    account.key.secret_key = Some(secret_key);
+                }
+                Ok(_) => {
+                    tracing::error!("locked key of {} is for another pubkey", account.key.pubkey)
+                }
+                Err(e) => tracing::error!("could not unlock key of {}: {e}", account.key.pubkey),
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    /// Lock the secret key of `account` with `passphrase`, ahead of writing
+    /// it, since that takes a while
+    pub fn lock_account(
+        &self,
+        account: &mut UserAccountSerializable,
+        passphrase: &str,
+    ) -> Result<()> {
+        if let Some(secret_key) = &account.key.secret_key {
+            account.locked_key = Some(lock_key(secret_key, passphrase, self.storage.log_n)?);
+        }
+        Ok(())
+    }
+
     pub fn get_selected_key(&self) -> Result<Option<Pubkey>> {
         match self
             .storage
@@ -127,6 +301,20 @@ fn old_deserialization(serialized: &str) -> Result<Keypair> {
     Ok(serde_json::from_str::<SerializableKeypair>(serialized)?.to_keypair(""))
 }
 
+fn lock_key(secret_key: &SecretKey, passphrase: &str, log_n: u8) -> Result<String> {
+    EncryptedSecretKey::new(secret_key, passphrase, log_n, KeySecurity::Medium)
+        .map_err(|e| Error::Generic(format!("could not lock key: {e}")))?
+        .to_bech32()
+        .map_err(|e| Error::Generic(format!("could not encode locked key: {e}")))
+}
+
+fn unlock_key(ncryptsec: &str, passphrase: &str) -> Result<SecretKey> {
+    EncryptedSecretKey::from_bech32(ncryptsec)
+        .map_err(|e| Error::Generic(format!("bad locked key: {e}")))?
+        .to_secret_key(passphrase)
+        .map_err(|_| KeystoreError::WrongPassphrase.into())
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -142,6 +330,9 @@ mod tests {
             Ok(Self {
                 accounts_directory: Directory::new(CREATE_TMP_DIR()?),
                 selected_key_directory: Directory::new(CREATE_TMP_DIR()?),
+                key_lock_directory: Directory::new(CREATE_TMP_DIR()?),
+                // cheap, the real cost makes tests crawl
+                log_n: 4,
             })
         }
     }
@@ -196,4 +387,66 @@ mod tests {
             other => panic!("Expected Ok(None), got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_passphrase() {
+        let kp = enostr::FullKeypair::generate().to_keypair();
+        let storage = AccountStorage::mock().unwrap();
+        let (reader, mut writer) = storage.clone().rw();
+        let account = UserAccountSerializable::new(kp.clone());
+        writer.write_account(&account).unwrap();
+
+        writer.set_passphrase(Some("hunter2"), &[account]).unwrap();
+        assert!(storage.has_passphrase());
+
+        // only the pubkey is readable without the passphrase
+        let accounts = reader.get_accounts().unwrap();
+        assert_eq!(accounts[0].key, Keypair::only_pubkey(kp.pubkey));
+        assert!(accounts[0].locked_key.is_some());
+
+        assert!(matches!(
+            reader.get_unlocked_accounts("hunter3"),
+            Err(Error::Keystore(KeystoreError::WrongPassphrase))
+        ));
+        let accounts = reader.get_unlocked_accounts("hunter2").unwrap();
+        assert_eq!(accounts[0].key, kp);
+
+        // a locked writer can't touch the keys
+        writer.lock();
+        assert!(writer.is_locked());
+        assert!(writer
+            .write_account(&UserAccountSerializable::new(kp.clone()))
+            .is_err());
+        assert!(writer.unlock("hunter3").is_err());
+        writer.unlock("hunter2").unwrap();
+
+        writer
+            .set_passphrase(None, &[UserAccountSerializable::new(kp.clone())])
+            .unwrap();
+        assert!(!storage.has_passphrase());
+        let accounts = reader.get_accounts().unwrap();
+        assert_eq!(accounts[0].key, kp);
+        assert!(accounts[0].locked_key.is_none());
+    }
+
+    #[test]
+    fn test_lock_account() {
+        let kp = enostr::FullKeypair::generate().to_keypair();
+        let (reader, mut writer) = AccountStorage::mock().unwrap().rw();
+        writer.set_passphrase(Some("hunter2"), &[]).unwrap();
+        writer.lock();
+
+        // locked off the writer, and written once unlocked
+        let mut account = UserAccountSerializable::new(kp.clone());
+        reader.lock_account(&mut account, "hunter2").unwrap();
+        assert!(account.locked_key.is_some());
+        assert!(writer.write_account(&account).is_err());
+        writer.unlocked("hunter2".to_owned());
+        writer.write_account(&account).unwrap();
+
+        let accounts = reader.get_accounts().unwrap();
+        assert_eq!(accounts[0].key, Keypair::only_pubkey(kp.pubkey));
+        let accounts = reader.get_unlocked_accounts("hunter2").unwrap();
+        assert_eq!(accounts[0].key, kp);
+    }
 }
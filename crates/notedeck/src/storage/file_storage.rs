@@ -34,6 +34,7 @@ impl DataPath {
             DataPathType::Setting => PathBuf::from("settings"),
             DataPathType::Keys => PathBuf::from("storage").join("accounts"),
             DataPathType::SelectedKey => PathBuf::from("storage").join("selected_account"),
+            DataPathType::KeyLock => PathBuf::from("storage").join("key_lock"),
             DataPathType::Db => PathBuf::from("db"),
             DataPathType::Cache => PathBuf::from("cache"),
         }
@@ -55,6 +56,7 @@ pub enum DataPathType {
     Setting,
     Keys,
     SelectedKey,
+    KeyLock,
     Db,
     Cache,
 }
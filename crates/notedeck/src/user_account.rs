@@ -3,13 +3,18 @@ use tokenator::{ParseError, TokenParser, TokenSerializable};
 
 use crate::{
     wallet::{WalletSerializable, ZapWallet},
-    AccountData, IsFollowing,
+    AccountData, BunkerUri, IsFollowing, RemoteSignerInfo,
 };
 
 #[derive(Clone)]
 pub struct UserAccount {
     pub key: Keypair,
     pub wallet: Option<ZapWallet>,
+
+    /// The NIP-46 remote signer that signs for this account, when we
+    /// don't have its secret key
+    pub remote_signer: Option<RemoteSignerInfo>,
+
     pub data: AccountData,
 }
 
@@ -18,10 +23,16 @@ impl UserAccount {
         Self {
             key,
             wallet: None,
+            remote_signer: None,
             data,
         }
     }
 
+    pub fn with_remote_signer(mut self, remote_signer: RemoteSignerInfo) -> Self {
+        self.remote_signer = Some(remote_signer);
+        self
+    }
+
     pub fn keypair(&self) -> KeypairUnowned<'_> {
         KeypairUnowned {
             pubkey: &self.key.pubkey,
@@ -39,34 +50,82 @@ impl UserAccount {
     }
 }
 
+#[derive(Clone)]
 pub struct UserAccountSerializable {
     pub key: Keypair,
+
+    /// The secret key as an ncryptsec locked with the keystore passphrase,
+    /// when there is one. `key` only holds the pubkey then.
+    pub locked_key: Option<String>,
+
     pub wallet: Option<WalletSerializable>,
+
+    pub remote_signer: Option<RemoteSignerInfo>,
 }
 
 impl UserAccountSerializable {
     pub fn new(key: Keypair) -> Self {
-        Self { key, wallet: None }
+        Self {
+            key,
+            locked_key: None,
+            wallet: None,
+            remote_signer: None,
+        }
+    }
+
+    pub fn with_locked_key(mut self, ncryptsec: String) -> Self {
+        self.locked_key = Some(ncryptsec);
+        self
     }
 
     pub fn with_wallet(mut self, wallet: WalletSerializable) -> Self {
         self.wallet = Some(wallet);
         self
     }
+
+    pub fn with_remote_signer(mut self, remote_signer: RemoteSignerInfo) -> Self {
+        self.remote_signer = Some(remote_signer);
+        self
+    }
 }
 
 impl From<&UserAccount> for UserAccountSerializable {
     fn from(value: &UserAccount) -> Self {
         Self {
             key: value.key.clone(),
+            locked_key: None,
             wallet: value.wallet.as_ref().map(|z| z.into()),
+            remote_signer: value.remote_signer.clone(),
         }
     }
 }
 
+const LOCKED_KEY_TOKEN: &str = "lockedkey";
+const REMOTE_SIGNER_TOKEN: &str = "remotesigner";
+
 enum UserAccountRoute {
     Key(Keypair),
+    LockedKey(String),
     Wallet(WalletSerializable),
+    RemoteSigner(RemoteSignerInfo),
+}
+
+/// `remotesigner <bunker-uri> <client-keypair>`. The client key is stored
+/// like wallet uris are: it only lets us talk to the remote signer, which
+/// still decides what to sign.
+fn parse_remote_signer<'a>(
+    parser: &mut TokenParser<'a>,
+) -> Result<RemoteSignerInfo, ParseError<'a>> {
+    parser.parse_token(REMOTE_SIGNER_TOKEN)?;
+    let uri = BunkerUri::parse(parser.pull_token()?).map_err(|_| ParseError::DecodeFailed)?;
+    let client = Keypair::parse_from_tokens(parser)?;
+    let client_key = client.secret_key.ok_or(ParseError::DecodeFailed)?;
+
+    Ok(RemoteSignerInfo {
+        remote_pubkey: uri.remote_pubkey,
+        relays: uri.relays,
+        client_key,
+    })
 }
 
 impl TokenSerializable for UserAccountSerializable {
@@ -74,29 +133,38 @@ impl TokenSerializable for UserAccountSerializable {
         parser: &mut tokenator::TokenParser<'a>,
     ) -> Result<Self, tokenator::ParseError<'a>> {
         let mut m_key = None;
+        let mut m_locked_key = None;
         let mut m_wallet = None;
+        let mut m_remote_signer = None;
 
         loop {
             let res = TokenParser::alt(
                 parser,
                 &[
                     |p| Ok(UserAccountRoute::Key(Keypair::parse_from_tokens(p)?)),
+                    |p| {
+                        p.parse_token(LOCKED_KEY_TOKEN)?;
+                        Ok(UserAccountRoute::LockedKey(p.pull_token()?.to_owned()))
+                    },
                     |p| {
                         Ok(UserAccountRoute::Wallet(
                             WalletSerializable::parse_from_tokens(p)?,
                         ))
                     },
+                    |p| Ok(UserAccountRoute::RemoteSigner(parse_remote_signer(p)?)),
                 ],
             );
 
             match res {
                 Ok(UserAccountRoute::Key(key)) => m_key = Some(key),
+                Ok(UserAccountRoute::LockedKey(locked)) => m_locked_key = Some(locked),
                 Ok(UserAccountRoute::Wallet(wallet)) => m_wallet = Some(wallet),
+                Ok(UserAccountRoute::RemoteSigner(info)) => m_remote_signer = Some(info),
                 Err(ParseError::AltAllFailed) => break,
                 Err(_) => {}
             }
 
-            if m_key.is_some() && m_wallet.is_some() {
+            if m_key.is_some() && m_locked_key.is_some() && m_wallet.is_some() {
                 break;
             }
         }
@@ -107,15 +175,36 @@ impl TokenSerializable for UserAccountSerializable {
 
         let mut user_acc = UserAccountSerializable::new(key);
 
+        if let Some(locked_key) = m_locked_key {
+            user_acc = user_acc.with_locked_key(locked_key);
+        }
+
         if let Some(wallet) = m_wallet {
             user_acc = user_acc.with_wallet(wallet);
         };
 
+        if let Some(remote_signer) = m_remote_signer {
+            user_acc = user_acc.with_remote_signer(remote_signer);
+        }
+
         Ok(user_acc)
     }
 
     fn serialize_tokens(&self, writer: &mut tokenator::TokenWriter) {
-        self.key.serialize_tokens(writer);
+        if let Some(locked_key) = &self.locked_key {
+            // never store the secret key next to its locked copy
+            self.key.pubkey.serialize_tokens(writer);
+            writer.write_token(LOCKED_KEY_TOKEN);
+            writer.write_token(locked_key);
+        } else {
+            self.key.serialize_tokens(writer);
+        }
+
+        if let Some(info) = &self.remote_signer {
+            writer.write_token(REMOTE_SIGNER_TOKEN);
+            writer.write_token(&info.uri().to_string());
+            Keypair::from_secret(info.client_key.clone()).serialize_tokens(writer);
+        }
 
         let Some(wallet) = &self.wallet else {
             return;
@@ -130,7 +219,9 @@ mod tests {
     use enostr::FullKeypair;
     use tokenator::{TokenParser, TokenSerializable, TokenWriter};
 
-    use crate::{user_account::UserAccountSerializable, wallet::WalletSerializable};
+    use crate::{
+        user_account::UserAccountSerializable, wallet::WalletSerializable, RemoteSignerInfo,
+    };
 
     const URI: &str = "nostr+walletconnect://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4?relay=wss%3A%2F%2Frelay.damus.io&secret=71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c&lud16=nostr%40nostr.com";
 
@@ -160,4 +251,32 @@ mod tests {
 
         assert_eq!(wallet.uri, URI);
     }
+
+    #[test]
+    fn test_remote_signer_account_roundtrip() {
+        let user = FullKeypair::generate();
+        let info = RemoteSignerInfo {
+            remote_pubkey: FullKeypair::generate().pubkey,
+            relays: vec![
+                "wss://relay.nsec.app/".to_owned(),
+                "wss://nos.lol/".to_owned(),
+            ],
+            client_key: FullKeypair::generate().secret_key,
+        };
+        let acc = UserAccountSerializable::new(enostr::Keypair::only_pubkey(user.pubkey))
+            .with_remote_signer(info.clone())
+            .with_wallet(WalletSerializable::new(URI.to_owned()));
+
+        let mut writer = TokenWriter::new("\t");
+        acc.serialize_tokens(&mut writer);
+
+        let serialized = writer.str();
+        let data = &serialized.split("\t").collect::<Vec<&str>>();
+        let mut parser = TokenParser::new(data);
+        let new_acc = UserAccountSerializable::parse_from_tokens(&mut parser).expect("account");
+
+        assert_eq!(new_acc.key, acc.key);
+        assert_eq!(new_acc.remote_signer, Some(info));
+        assert_eq!(new_acc.wallet.expect("wallet").uri, URI);
+    }
 }
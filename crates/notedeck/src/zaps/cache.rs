@@ -11,9 +11,12 @@ use crate::{
     get_wallet_for,
     zaps::{
         get_users_zap_address,
-        networking::{fetch_invoice_promise, FetchedInvoiceResponse, LNUrlPayResponse, PayEntry},
+        networking::{
+            fetch_invoice_promise, make_kind_9734, FetchedInvoiceResponse, LNUrlPayResponse,
+            PayEntry, ZapEndpoint,
+        },
     },
-    Accounts, GlobalWallet, ZapError,
+    Accounts, GlobalWallet, SignResult, SignedEvent, SignerError, UnsignedEvent, ZapError,
 };
 
 use super::{networking::FetchingInvoice, zap::Zap};
@@ -64,7 +67,18 @@ fn process_event(
         ZapEvent::FetchInvoice {
             zap_ctx,
             sender_relays,
-        } => process_new_zap_event(cache, zap_ctx, accounts, ndb, txn, sender_relays),
+        } => process_new_zap_event(zap_ctx, accounts, ndb, txn, sender_relays),
+        ZapEvent::RequestSigned {
+            zap_ctx,
+            endpoint,
+            request,
+        } => {
+            let promise = fetch_invoice_promise(cache, endpoint, zap_ctx.msats, request);
+            NextState::Transition(ZapPromise::FetchingInvoice {
+                ctx: zap_ctx,
+                promise: Box::new(promise),
+            })
+        }
         ZapEvent::SendNWC {
             zap_ctx,
             req_noteid,
@@ -101,19 +115,18 @@ fn process_event(
 }
 
 fn process_new_zap_event(
-    cache: &PayCache,
     zap_ctx: ZapCtx,
-    accounts: &Accounts,
+    accounts: &mut Accounts,
     ndb: &Ndb,
     txn: &Transaction,
     sender_relays: Vec<String>,
 ) -> NextState {
-    let Some(full_kp) = accounts.get_selected_account().key.to_full() else {
+    if accounts.selected_poster().is_none() {
         return NextState::Event(EventResponse {
             id: zap_ctx.id,
             event: Err(ZappingError::InvalidAccount),
         });
-    };
+    }
 
     // TODO(kernelkind): support ZapTarget::Profile
     let ZapTargetOwned::Note(note_target) = zap_ctx.key.target.clone() else {
@@ -124,22 +137,11 @@ fn process_new_zap_event(
     };
 
     let id = zap_ctx.id;
-    let m_promise = send_note_zap(
-        cache,
-        ndb,
-        txn,
-        note_target,
-        zap_ctx.msats,
-        &full_kp.secret_key.secret_bytes(),
-        sender_relays,
-    )
-    .map(|promise| ZapPromise::FetchingInvoice {
-        ctx: zap_ctx,
-        promise: Box::new(promise),
-    });
+    let sender = zap_ctx.key.sender;
+    let m_request = zap_request(ndb, txn, &sender, note_target, zap_ctx.msats, sender_relays);
 
-    let promise = match m_promise {
-        Ok(promise) => promise,
+    let (endpoint, request) = match m_request {
+        Ok(request) => request,
         Err(e) => {
             return NextState::Event(EventResponse {
                 id,
@@ -148,28 +150,37 @@ fn process_new_zap_event(
         }
     };
 
-    NextState::Transition(promise)
+    // remote signers may take a while, the invoice is fetched once signed
+    let promise = accounts.sign(request);
+    NextState::Transition(ZapPromise::SigningRequest {
+        ctx: zap_ctx,
+        endpoint,
+        promise,
+    })
 }
 
-fn send_note_zap(
-    cache: &PayCache,
+fn zap_request(
     ndb: &Ndb,
     txn: &Transaction,
+    sender: &Pubkey,
     note_target: NoteZapTargetOwned,
     msats: u64,
-    nsec: &[u8; 32],
     relays: Vec<String>,
-) -> Result<FetchingInvoice, ZapError> {
+) -> Result<(ZapEndpoint, UnsignedEvent), ZapError> {
     let address = get_users_zap_address(txn, ndb, &note_target.zap_recipient)?;
+    let endpoint = ZapEndpoint::new(address)?;
 
-    fetch_invoice_promise(
-        cache,
-        address,
+    let comment = "";
+    let request = make_kind_9734(
+        &endpoint.lnurl,
         msats,
-        *nsec,
-        ZapTargetOwned::Note(note_target),
+        comment,
+        sender,
         relays,
-    )
+        ZapTargetOwned::Note(note_target),
+    )?;
+
+    Ok((endpoint, request))
 }
 
 fn try_get_promise_response(
@@ -183,6 +194,19 @@ fn try_get_promise_response(
     let promise = promises.remove(promise_index);
 
     match promise {
+        ZapPromise::SigningRequest {
+            ctx,
+            endpoint,
+            promise,
+        } => {
+            let result = promise.block_and_take();
+
+            Some(PromiseResponse::SigningRequest {
+                ctx,
+                endpoint,
+                result,
+            })
+        }
         ZapPromise::FetchingInvoice { ctx, promise } => {
             let result = Box::new(promise.block_and_take());
 
@@ -198,6 +222,7 @@ fn try_get_promise_response(
 
 fn is_promise_ready(zap_promise: &ZapPromise) -> bool {
     match zap_promise {
+        ZapPromise::SigningRequest { promise, .. } => promise.ready().is_some(),
         ZapPromise::FetchingInvoice { ctx: _, promise } => promise.ready().is_some(),
         ZapPromise::SendingNWCInvoice { ctx: _, promise } => promise.ready().is_some(),
     }
@@ -475,6 +500,11 @@ pub enum ZapEvent {
         zap_ctx: ZapCtx,
         sender_relays: Vec<String>,
     },
+    RequestSigned {
+        zap_ctx: ZapCtx,
+        endpoint: ZapEndpoint,
+        request: SignedEvent,
+    },
     SendNWC {
         zap_ctx: ZapCtx,
         req_noteid: NoteId,
@@ -490,6 +520,7 @@ pub enum ZapEvent {
 pub enum ZappingError {
     InvoiceFetchFailed(ZapError),
     InvalidAccount,
+    SigningFailed(SignerError),
     UnsupportedOperation, // TODO(kernelkind): support profile zaps
     InvalidZapAddress,
     SenderNoWallet,
@@ -502,6 +533,7 @@ impl std::fmt::Display for ZappingError {
         match self {
             ZappingError::InvoiceFetchFailed(err) => write!(f, "Failed to fetch invoice: {err}"),
             ZappingError::InvalidAccount => write!(f, "Invalid account"),
+            ZappingError::SigningFailed(err) => write!(f, "Failed to sign zap request: {err}"),
             ZappingError::UnsupportedOperation => {
                 write!(f, "Unsupported operation (e.g. profile zaps)")
             }
@@ -514,6 +546,11 @@ impl std::fmt::Display for ZappingError {
 }
 
 enum ZapPromise {
+    SigningRequest {
+        ctx: ZapCtx,
+        endpoint: ZapEndpoint,
+        promise: Promise<SignResult>,
+    },
     FetchingInvoice {
         ctx: ZapCtx,
         promise: Box<Promise<Result<FetchedInvoiceResponse, JoinError>>>,
@@ -525,6 +562,11 @@ enum ZapPromise {
 }
 
 enum PromiseResponse {
+    SigningRequest {
+        ctx: ZapCtx,
+        endpoint: ZapEndpoint,
+        result: SignResult,
+    },
     FetchingInvoice {
         ctx: ZapCtx,
         result: Box<Result<FetchedInvoiceResponse, JoinError>>,
@@ -538,6 +580,23 @@ enum PromiseResponse {
 impl PromiseResponse {
     pub fn take_as_event_response(self) -> EventResponse {
         match self {
+            PromiseResponse::SigningRequest {
+                ctx,
+                endpoint,
+                result,
+            } => {
+                let id = ctx.id;
+                let event = match result {
+                    Ok(request) => Ok(ZapEvent::RequestSigned {
+                        zap_ctx: ctx,
+                        endpoint,
+                        request,
+                    }),
+                    Err(e) => Err(ZappingError::SigningFailed(e)),
+                };
+
+                EventResponse { id, event }
+            }
             PromiseResponse::FetchingInvoice { ctx, result } => {
                 let id = ctx.id;
                 let event = match *result {
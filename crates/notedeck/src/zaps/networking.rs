@@ -1,7 +1,7 @@
 use crate::{
     error::EndpointError,
     zaps::{cache::PayCache, ZapAddress, ZapTargetOwned},
-    ZapError,
+    SignedEvent, UnsignedEvent, ZapError,
 };
 use enostr::{NoteId, Pubkey};
 use nostrdb::NoteBuilder;
@@ -54,14 +54,37 @@ fn endpoint_url_to_lnurl(endpoint_url: &Url) -> Result<String, ZapError> {
     bech32::encode::<bech32::Bech32>(HRP_LNURL, data).map_err(|e| ZapError::Bech(e.to_string()))
 }
 
-fn make_kind_9734<'a>(
+/// The LNURL endpoint zap requests go to, and its bech32 lnurl
+#[derive(Clone, Debug)]
+pub struct ZapEndpoint {
+    pub url: Url,
+    pub lnurl: String,
+}
+
+impl ZapEndpoint {
+    pub fn new(zap_address: ZapAddress) -> Result<Self, ZapError> {
+        let (url, lnurl) = match zap_address {
+            ZapAddress::Lud16(lud16) => {
+                let url = generate_endpoint_url(&lud16)?;
+                let lnurl = endpoint_url_to_lnurl(&url)?;
+                (url, lnurl)
+            }
+            ZapAddress::Lud06(lnurl) => (convert_lnurl_to_endpoint_url(&lnurl)?, lnurl),
+        };
+
+        Ok(Self { url, lnurl })
+    }
+}
+
+/// The kind 9734 zap request, for the sender's signer to sign
+pub fn make_kind_9734(
     lnurl: &str,
     msats: u64,
     comment: &str,
-    sender_nsec: &[u8; 32],
+    sender: &Pubkey,
     relays: Vec<String>,
     target: ZapTargetOwned,
-) -> nostrdb::Note<'a> {
+) -> Result<UnsignedEvent, ZapError> {
     let mut builder = NoteBuilder::new().kind(9734);
 
     builder = builder.content(comment).start_tag().tag_str("relays");
@@ -94,7 +117,7 @@ fn make_kind_9734<'a>(
         }
     }
 
-    builder.sign(sender_nsec).build().expect("note")
+    UnsignedEvent::new(sender, builder).map_err(|e| ZapError::Serialization(e.to_string()))
 }
 
 #[derive(Debug, Deserialize)]
@@ -162,57 +185,38 @@ fn endpoint_query_for_invoice(
     endpoint_base_url: &Url,
     msats: u64,
     lnurl: &str,
-    note: nostrdb::Note,
-) -> Result<Url, ZapError> {
+    request: &SignedEvent,
+) -> Url {
     let mut new_url = endpoint_base_url.clone();
-    let nostr = note
-        .json()
-        .map_err(|e| ZapError::Serialization(format!("failed note to json: {e}")))?;
 
     new_url
         .query_pairs_mut()
         .append_pair("amount", &msats.to_string())
         .append_pair("lnurl", lnurl)
-        .append_pair("nostr", &nostr)
+        .append_pair("nostr", &request.json)
         .finish();
 
-    Ok(new_url)
+    new_url
 }
 
+/// Fetch an invoice for the signed zap `request` from `endpoint`
 pub fn fetch_invoice_promise(
     cache: &PayCache,
-    zap_address: ZapAddress,
+    endpoint: ZapEndpoint,
     msats: u64,
-    sender_nsec: [u8; 32],
-    target: ZapTargetOwned,
-    relays: Vec<String>,
-) -> Result<FetchingInvoice, ZapError> {
-    let (url, lnurl) = match zap_address {
-        ZapAddress::Lud16(lud16) => {
-            let url = generate_endpoint_url(&lud16)?;
-            let lnurl = endpoint_url_to_lnurl(&url)?;
-            (url, lnurl)
-        }
-        ZapAddress::Lud06(lnurl) => (convert_lnurl_to_endpoint_url(&lnurl)?, lnurl),
-    };
+    request: SignedEvent,
+) -> FetchingInvoice {
+    let ZapEndpoint { url, lnurl } = endpoint;
 
     match cache.get_response(&url) {
         Some(endpoint_resp) => {
             tracing::info!("Using existing endpoint response for {url}");
             let response = endpoint_resp.clone();
-            Ok(Promise::spawn_async(tokio::spawn(async move {
-                fetch_invoice_lnurl_async(
-                    &lnurl,
-                    PayEntry { url, response },
-                    msats,
-                    &sender_nsec,
-                    relays,
-                    target,
-                )
-                .await
-            })))
+            Promise::spawn_async(tokio::spawn(async move {
+                fetch_invoice_lnurl_async(&lnurl, PayEntry { url, response }, msats, request).await
+            }))
         }
-        None => Ok(Promise::spawn_async(tokio::spawn(async move {
+        None => Promise::spawn_async(tokio::spawn(async move {
             tracing::info!("querying ln endpoint: {url}");
             let pay_req = match fetch_pay_req_async(&url).await {
                 Ok(p) => PayEntry {
@@ -227,8 +231,8 @@ pub fn fetch_invoice_promise(
                 }
             };
 
-            fetch_invoice_lnurl_async(&lnurl, pay_req, msats, &sender_nsec, relays, target).await
-        }))),
+            fetch_invoice_lnurl_async(&lnurl, pay_req, msats, request).await
+        })),
     }
 }
 
@@ -246,9 +250,7 @@ async fn fetch_invoice_lnurl_async(
     lnurl: &str,
     pay_entry: PayEntry,
     msats: u64,
-    sender_nsec: &[u8; 32],
-    relays: Vec<String>,
-    target: ZapTargetOwned,
+    request: SignedEvent,
 ) -> FetchedInvoiceResponse {
     if !pay_entry.response.allow_nostr {
         return FetchedInvoiceResponse {
@@ -296,21 +298,8 @@ async fn fetch_invoice_lnurl_async(
         }
     };
 
-    let (query, noteid) = {
-        let comment: &str = "";
-        let note = make_kind_9734(lnurl, msats, comment, sender_nsec, relays, target);
-        let noteid = NoteId::new(*note.id());
-        let query = match endpoint_query_for_invoice(&base_url, msats, lnurl, note) {
-            Ok(u) => u,
-            Err(e) => {
-                return FetchedInvoiceResponse {
-                    invoice: Err(e),
-                    pay_entry: Some(pay_entry),
-                }
-            }
-        };
-        (query, noteid)
-    };
+    let noteid = NoteId::new(request.id);
+    let query = endpoint_query_for_invoice(&base_url, msats, lnurl, &request);
 
     let res = fetch_ln_invoice(&query).await;
     FetchedInvoiceResponse {
@@ -381,7 +370,7 @@ fn generate_endpoint_url(lud16: &str) -> Result<Url, ZapError> {
 
 #[cfg(test)]
 mod tests {
-    use enostr::{FullKeypair, NoteId};
+    use enostr::{FullKeypair, NoteId, Pubkey};
 
     use crate::zaps::{
         cache::PayCache,
@@ -390,8 +379,34 @@ mod tests {
             generate_endpoint_url,
         },
     };
-
-    use super::fetch_invoice_promise;
+    use crate::{LocalSigner, SignedEvent, Signer, ZapError};
+
+    use super::{fetch_invoice_promise, make_kind_9734, ZapEndpoint};
+
+    fn sign_zap_request(
+        endpoint: &ZapEndpoint,
+        sender: &FullKeypair,
+        recipient: Pubkey,
+        relays: Vec<String>,
+    ) -> SignedEvent {
+        let request = make_kind_9734(
+            &endpoint.lnurl,
+            1000,
+            "",
+            &sender.pubkey,
+            relays,
+            crate::zaps::ZapTargetOwned::Note(crate::NoteZapTargetOwned {
+                note_id: NoteId::new([0; 32]),
+                zap_recipient: recipient,
+            }),
+        )
+        .expect("zap request");
+
+        LocalSigner::new(sender.to_filled())
+            .sign(request)
+            .block_and_take()
+            .expect("signed zap request")
+    }
 
     #[ignore] // don't run this test automatically since it sends real http
     #[tokio::test(flavor = "multi_thread")]
@@ -442,18 +457,17 @@ mod tests {
         let kp = FullKeypair::generate();
         let mut cache = PayCache::default();
         let maybe_invoice = rt.block_on(async {
-            fetch_invoice_promise(
-                &mut cache,
-                crate::zaps::ZapAddress::Lud16("jb55@sendsats.lol".to_owned()),
-                1000,
-                FullKeypair::generate().secret_key.to_secret_bytes(),
-                crate::zaps::ZapTargetOwned::Note(crate::NoteZapTargetOwned {
-                    note_id: NoteId::new([0; 32]),
-                    zap_recipient: kp.pubkey,
-                }),
+            let endpoint = ZapEndpoint::new(crate::zaps::ZapAddress::Lud16(
+                "jb55@sendsats.lol".to_owned(),
+            ))?;
+            let request = sign_zap_request(
+                &endpoint,
+                &FullKeypair::generate(),
+                kp.pubkey,
                 vec!["wss://relay.damus.io".to_owned()],
-            )
-            .map(|p| p.block_and_take())
+            );
+            Ok::<_, ZapError>(fetch_invoice_promise(&mut cache, endpoint, 1000, request))
+                .map(|p| p.block_and_take())
         });
 
         assert!(maybe_invoice.is_ok());
@@ -489,18 +503,10 @@ mod tests {
 
         let mut cache = PayCache::default();
         let maybe_invoice = rt.block_on(async {
-            fetch_invoice_promise(
-                &mut cache,
-                crate::zaps::ZapAddress::Lud06(lnurl.to_owned()),
-                1000,
-                kp.secret_key.to_secret_bytes(),
-                crate::zaps::ZapTargetOwned::Note(crate::NoteZapTargetOwned {
-                    note_id: NoteId::new([0; 32]),
-                    zap_recipient: kp.pubkey,
-                }),
-                [relay.to_owned()].to_vec(),
-            )
-            .map(|p| p.block_and_take())
+            let endpoint = ZapEndpoint::new(crate::zaps::ZapAddress::Lud06(lnurl.to_owned()))?;
+            let request = sign_zap_request(&endpoint, &kp, kp.pubkey, [relay.to_owned()].to_vec());
+            Ok::<_, ZapError>(fetch_invoice_promise(&mut cache, endpoint, 1000, request))
+                .map(|p| p.block_and_take())
         });
 
         assert!(maybe_invoice.is_ok());
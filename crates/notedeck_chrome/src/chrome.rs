@@ -478,6 +478,7 @@ fn chrome_handle_app_action(
                 &mut columns.threads,
                 ctx.note_cache,
                 ctx.pool,
+                ctx.outbox,
                 &txn,
                 ctx.unknown_ids,
                 ctx.accounts,
@@ -535,6 +536,7 @@ fn columns_route_to_profile(
         &mut columns.threads,
         ctx.note_cache,
         ctx.pool,
+        ctx.outbox,
         &txn,
         ctx.unknown_ids,
         ctx.accounts,
@@ -54,6 +54,8 @@ pub fn note_hover_ui(
             clipboard: ctx.clipboard,
             i18n: ctx.i18n,
             global_wallet: ctx.global_wallet,
+            outbox: ctx.outbox,
+            deletions: ctx.deletions,
         };
 
         let mut jobs = notedeck::JobsCache::default();
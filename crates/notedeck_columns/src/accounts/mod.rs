@@ -168,6 +168,10 @@ pub fn process_login_view_response(
             cur_router.go_back();
             app_ctx.accounts.add_account(keypair)
         }
+        AccountLoginResponse::LoginWithSigner(login) => {
+            cur_router.go_back();
+            app_ctx.accounts.add_remote_account(login)
+        }
         AccountLoginResponse::CreatingNew => {
             cur_router.route_to(Route::Accounts(AccountsRoute::Onboarding));
 
@@ -12,13 +12,14 @@ use crate::{
 };
 
 use egui_nav::Percent;
-use enostr::{FilledKeypair, NoteId, Pubkey, RelayPool};
-use nostrdb::{IngestMetadata, Ndb, NoteBuilder, NoteKey, Transaction};
+use enostr::{NoteId, Pubkey, RelayPool};
+use nostrdb::{Ndb, Note, NoteBuilder, NoteKey, Transaction};
 use notedeck::{
-    get_wallet_for,
+    deletion_event, get_wallet_for,
     note::{reaction_sent_id, ReactAction, ZapTargetAmount},
-    Accounts, GlobalWallet, Images, NoteAction, NoteCache, NoteZapTargetOwned, UnknownIds,
-    ZapAction, ZapTarget, ZappingError, Zaps,
+    Accounts, ContextSelection, GlobalWallet, Images, NoteAction, NoteCache, NoteContextSelection,
+    NoteZapTargetOwned, Outbox, UnknownIds, UnsignedEvent, ZapAction, ZapTarget, ZappingError,
+    Zaps,
 };
 use notedeck_ui::media::MediaViewerFlags;
 use tracing::error;
@@ -53,6 +54,7 @@ fn execute_note_action(
     threads: &mut Threads,
     note_cache: &mut NoteCache,
     pool: &mut RelayPool,
+    outbox: &mut Outbox,
     txn: &Transaction,
     accounts: &mut Accounts,
     global_wallet: &mut GlobalWallet,
@@ -65,7 +67,7 @@ fn execute_note_action(
 ) -> NoteActionResponse {
     let mut timeline_res = None;
     let mut router_action = None;
-    let can_post = accounts.get_selected_account().key.secret_key.is_some();
+    let can_post = accounts.selected_poster().is_some();
 
     match action {
         NoteAction::Scroll(ref scroll_info) => {
@@ -80,16 +82,12 @@ fn execute_note_action(
             }
         }
         NoteAction::React(react_action) => {
-            if let Some(filled) = accounts.selected_filled() {
-                if let Err(err) = send_reaction_event(ndb, txn, pool, filled, &react_action) {
+            if let Some(poster) = accounts.selected_poster() {
+                let sent_id = reaction_sent_id(poster.pubkey, react_action.note_id.bytes());
+                if let Err(err) = send_reaction_event(ndb, txn, accounts, outbox, &react_action) {
                     tracing::error!("Failed to send reaction: {err}");
                 }
-                ui.ctx().data_mut(|d| {
-                    d.insert_temp(
-                        reaction_sent_id(filled.pubkey, react_action.note_id.bytes()),
-                        true,
-                    )
-                });
+                ui.ctx().data_mut(|d| d.insert_temp(sent_id, true));
             } else {
                 router_action = Some(RouterAction::route_to(Route::accounts()));
             }
@@ -187,6 +185,23 @@ fn execute_note_action(
                 }
             }
         }
+        NoteAction::Context(ContextSelection {
+            action: NoteContextSelection::Mute(item),
+            ..
+        }) => {
+            if can_post {
+                accounts.mute_item(outbox, item, false);
+            } else {
+                router_action = Some(RouterAction::route_to(Route::accounts()));
+            }
+        }
+        NoteAction::Context(ContextSelection {
+            note_key,
+            action: NoteContextSelection::Delete,
+        }) => match ndb.get_note_by_key(txn, note_key) {
+            Err(err) => tracing::error!("{err}"),
+            Ok(note) => send_deletion_event(accounts, outbox, &note),
+        },
         NoteAction::Context(context) => match ndb.get_note_by_key(txn, context.note_key) {
             Err(err) => tracing::error!("{err}"),
             Ok(note) => {
@@ -230,6 +245,7 @@ pub fn execute_and_process_note_action(
     threads: &mut Threads,
     note_cache: &mut NoteCache,
     pool: &mut RelayPool,
+    outbox: &mut Outbox,
     txn: &Transaction,
     unknown_ids: &mut UnknownIds,
     accounts: &mut Accounts,
@@ -257,6 +273,7 @@ pub fn execute_and_process_note_action(
         threads,
         note_cache,
         pool,
+        outbox,
         txn,
         accounts,
         global_wallet,
@@ -282,11 +299,31 @@ pub fn execute_and_process_note_action(
     resp.router_action
 }
 
+/// Ask relays to delete `note`, one of our own, with a kind 5 (NIP-09).
+/// The outbox adds it to nostrdb once signed, which hides the note here.
+pub fn send_deletion_event(accounts: &mut Accounts, outbox: &mut Outbox, note: &Note) {
+    let author = *accounts.selected_account_pubkey();
+    if author.bytes() != note.pubkey() {
+        error!("can't delete someone else's note");
+        return;
+    }
+
+    match deletion_event(&author, note) {
+        Ok(event) => {
+            let promise = accounts.sign(event);
+            outbox.sign_and_send(promise, vec![]);
+        }
+        Err(err) => error!("could not build deletion: {err}"),
+    }
+}
+
+/// Sign a reaction with the selected account's signer, and publish it once
+/// signed
 pub fn send_reaction_event(
-    ndb: &mut Ndb,
+    ndb: &Ndb,
     txn: &Transaction,
-    pool: &mut RelayPool,
-    kp: FilledKeypair<'_>,
+    accounts: &mut Accounts,
+    outbox: &mut Outbox,
     reaction: &ReactAction,
 ) -> Result<(), String> {
     let Ok(note) = ndb.get_note_by_id(txn, reaction.note_id.bytes()) else {
@@ -332,22 +369,13 @@ pub fn send_reaction_event(
         .tag_str("k")
         .tag_str(&target_kind.to_string());
 
-    let note = builder
-        .sign(&kp.secret_key.secret_bytes())
-        .build()
-        .ok_or_else(|| "failed to build reaction event".to_owned())?;
-
-    let Ok(event) = &enostr::ClientMessage::event(&note) else {
-        return Err("failed to convert reaction note into client message".to_owned());
+    let Ok(event) = UnsignedEvent::new(accounts.selected_account_pubkey(), builder) else {
+        return Err("failed to build reaction event".to_owned());
     };
 
-    let Ok(json) = event.to_json() else {
-        return Err("failed to serialize reaction event to json".to_owned());
-    };
-
-    let _ = ndb.process_event_with(&json, IngestMetadata::new().client(true));
-
-    pool.send(event);
+    // shows up in nostrdb once signed, see Outbox::update
+    let signed = accounts.sign(event);
+    outbox.sign_and_send(signed, vec![]);
 
     Ok(())
 }
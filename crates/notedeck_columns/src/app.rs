@@ -1,11 +1,15 @@
 use crate::{
     args::{ColumnsArgs, ColumnsFlag},
+    channels::ChannelEdit,
     column::Columns,
     decks::{Decks, DecksCache},
+    dms::{self, DirectMessages},
     draft::Drafts,
     nav::{self, ProcessNavResult},
+    nip28, nip29,
     onboarding::Onboarding,
     options::AppOptions,
+    relay_config::canonical_relay_url,
     route::Route,
     storage,
     subscriptions::{SubKind, Subscriptions},
@@ -14,7 +18,8 @@ use crate::{
     toolbar::unseen_notification,
     ui::{
         self,
-        channel_sidebar::CHANNEL_SIDEBAR_WIDTH,
+        channel_sidebar::{DmRow, CHANNEL_SIDEBAR_WIDTH},
+        dm_view::conversation_name,
         toolbar::toolbar,
         ChannelSidebar, ChannelSidebarAction, DesktopSidePanel, SidePanelAction,
     },
@@ -22,12 +27,15 @@ use crate::{
     Result,
 };
 use egui_extras::{Size, StripBuilder};
-use enostr::{ClientMessage, PoolRelay, Pubkey, RelayEvent, RelayMessage, RelayPool};
+use enostr::{
+    ClientMessage, ClosedResult, MessagePrefix, PoolRelay, Pubkey, RelayEvent, RelayMessage,
+    RelayPool, RelayStatus,
+};
 use nostrdb::Transaction;
 use notedeck::{
-    tr, ui::is_narrow, Accounts, AppAction, AppContext, AppResponse, DataPath, DataPathType,
-    FilterState, Images, JobsCache, Localization, NoteAction, NotedeckOptions, SettingsHandler,
-    UnknownIds,
+    tr, ui::is_narrow, Accounts, AppAction, AppContext, AppResponse, ContextSelection, DataPath,
+    DataPathType, FilterState, Images, JobsCache, Localization, NoteAction, NoteContextSelection,
+    NotedeckOptions, SettingsHandler, UnknownIds,
 };
 use notedeck_ui::{
     media::{MediaViewer, MediaViewerFlags, MediaViewerState},
@@ -35,6 +43,7 @@ use notedeck_ui::{
 };
 use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, trace, warn};
 use uuid::Uuid;
 
@@ -54,12 +63,21 @@ pub struct Damus {
     /// Flag to show relay dialog if no relays are configured
     need_relay_prompt: bool,
 
+    /// The startup config nsec is dropped from disk once its key is in the
+    /// keystore, which may have to be unlocked first
+    forget_startup_nsec: bool,
+
     pub decks_cache: DecksCache,
     pub channels_cache: crate::channels::ChannelsCache,
+    channels_cache_saver: storage::ChannelsCacheSaver,
+    pub channel_sync: crate::channel_sync::ChannelSync,
+    pub dms: DirectMessages,
     pub relay_config: crate::relay_config::RelayConfig,
     pub channel_dialog: ui::ChannelDialog,
     pub relay_dialog: ui::RelayDialog,
     pub thread_panel: ui::ThreadPanel,
+    /// Scroll state of each channel's chat
+    pub chat_views: HashMap<TimelineKind, ui::ChatViewState>,
     pub view_state: ViewState,
     pub drafts: Drafts,
     pub timeline_cache: TimelineCache,
@@ -150,6 +168,7 @@ fn try_process_event(
 
                 timeline::send_initial_timeline_filters(
                     damus.options.contains(AppOptions::SinceOptimize),
+                    app_ctx.ndb,
                     &mut damus.timeline_cache,
                     &mut damus.subscriptions,
                     app_ctx.pool,
@@ -167,6 +186,9 @@ fn try_process_event(
         }
     }
 
+    retry_closed_subscriptions(damus, app_ctx);
+
+    let mut updated_timelines = Vec::new();
     for (kind, timeline) in &mut damus.timeline_cache {
         let is_ready = timeline::is_timeline_ready(
             &mut damus.subscriptions,
@@ -182,6 +204,7 @@ fn try_process_event(
             let txn = Transaction::new(app_ctx.ndb).expect("txn");
             // only thread timelines are reversed
             let reversed = false;
+            let prev_latest = timeline.current_view().units.latest().copied();
 
             if let Err(err) = timeline.poll_notes_into_view(
                 app_ctx.ndb,
@@ -192,6 +215,11 @@ fn try_process_event(
             ) {
                 error!("poll_notes_into_view: {err}");
             }
+            timeline.prune_deleted(app_ctx.ndb, &txn, app_ctx.deletions);
+
+            if timeline.current_view().units.latest().copied() != prev_latest {
+                updated_timelines.push(kind.clone());
+            }
         } else {
             // TODO: show loading?
             if matches!(kind, TimelineKind::List(ListKind::Contact(_))) {
@@ -200,6 +228,38 @@ fn try_process_event(
         }
     }
 
+    if !updated_timelines.is_empty() {
+        let channels = damus
+            .channels_cache
+            .active_channels_mut(app_ctx.i18n, app_ctx.accounts);
+        channels.on_timelines_updated(&updated_timelines, &damus.timeline_cache);
+
+        // public chat and group metadata usually arrives along with the
+        // first messages
+        let chat_updated = updated_timelines.iter().any(|kind| {
+            matches!(kind.unscoped(), TimelineKind::PublicChat(_) | TimelineKind::Group(_))
+        });
+        if chat_updated {
+            let txn = Transaction::new(app_ctx.ndb).expect("txn");
+            if channels.refresh_chat_names(app_ctx.ndb, &txn) {
+                storage::save_channels_cache(app_ctx.path, &damus.channels_cache);
+            }
+        }
+    }
+
+    damus.channel_sync.update(
+        &mut damus.channels_cache,
+        &mut damus.timeline_cache,
+        &mut damus.subscriptions,
+        app_ctx,
+    );
+    damus
+        .channels_cache_saver
+        .update(app_ctx.path, &damus.channels_cache);
+
+    damus.dms.update(app_ctx);
+    damus.drafts.update(app_ctx.outbox);
+
     if let Some(follow_packs) = damus.onboarding.get_follow_packs_mut() {
         follow_packs.poll_for_notes(app_ctx.ndb, app_ctx.unknown_ids);
     }
@@ -245,6 +305,11 @@ fn update_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ctx: &egui::Con
         }
     });
 
+    if damus.forget_startup_nsec && app_ctx.accounts.can_store_keys() {
+        crate::storage::forget_startup_nsec(app_ctx.path);
+        damus.forget_startup_nsec = false;
+    }
+
     if damus.columns(app_ctx.accounts).columns().is_empty() {
         damus
             .columns_mut(app_ctx.i18n, app_ctx.accounts)
@@ -328,11 +393,21 @@ fn handle_eose(
         return Ok(());
     };
 
+    // the relay is serving us again if it closed this timeline's
+    // subscription before, e.g. after we authenticated
+    if let SubKind::Timeline(kind) | SubKind::Initial(kind) | SubKind::FetchingContactList(kind) =
+        sub_kind
+    {
+        if let Some(timeline) = timeline_cache.get_mut(kind) {
+            timeline.filter.reopen(relay_url);
+        }
+    }
+
     match sub_kind {
         SubKind::Timeline(_) => {
             // eose on timeline? whatevs
         }
-        SubKind::Initial => {
+        SubKind::Initial(_) => {
             //let txn = Transaction::new(ctx.ndb)?;
             //unknowns::update_from_columns(
             //    &txn,
@@ -397,9 +472,101 @@ fn handle_eose(
     Ok(())
 }
 
+/// How long to wait before subscribing again on a relay that closed a
+/// timeline subscription for a transient reason
+fn closed_retry_delay(prefix: MessagePrefix) -> Duration {
+    match prefix {
+        MessagePrefix::RateLimited => Duration::from_secs(60),
+        _ => Duration::from_secs(15),
+    }
+}
+
+/// A relay refused or ended one of our subscriptions. Record why on the
+/// timeline it belongs to so the UI can show it, and when to try again.
+fn handle_closed(
+    subscriptions: &Subscriptions,
+    timeline_cache: &mut TimelineCache,
+    closed: &ClosedResult,
+    relay_url: &str,
+) {
+    let subid = closed.sub_id();
+    warn!(
+        "{relay_url} closed subscription {subid}: {}",
+        closed.message()
+    );
+
+    let kind = match subscriptions.subs.get(subid) {
+        Some(SubKind::Timeline(kind))
+        | Some(SubKind::Initial(kind))
+        | Some(SubKind::FetchingContactList(kind)) => kind,
+        Some(SubKind::OneShot) | None => return,
+    };
+
+    let Some(timeline) = timeline_cache.get_mut(kind) else {
+        return;
+    };
+
+    let retry_at = match closed.prefix() {
+        // the pool replays it once we're authenticated
+        Some(MessagePrefix::AuthRequired) => None,
+        // we only know how to resend subscriptions of ready timelines
+        Some(prefix) if prefix.is_transient() && timeline.filter.get_any_ready().is_some() => {
+            Some(Instant::now() + closed_retry_delay(prefix))
+        }
+        _ => None,
+    };
+
+    timeline
+        .filter
+        .set_relay_closed(relay_url, closed.message().to_owned(), retry_at);
+}
+
+/// Subscribe again on relays whose closed timeline subscriptions are due
+/// for a retry
+fn retry_closed_subscriptions(damus: &mut Damus, ctx: &mut AppContext<'_>) {
+    let now = Instant::now();
+    let since_optimize = damus.options.contains(AppOptions::SinceOptimize);
+
+    for (_kind, timeline) in &mut damus.timeline_cache {
+        for relay_url in timeline.filter.due_retries(now) {
+            timeline.filter.reopen(&relay_url);
+
+            // the initial filters get sent again when it reconnects
+            let Some(relay) = ctx.pool.relays.iter_mut().find(|r| r.url() == relay_url) else {
+                continue;
+            };
+            if !matches!(relay.status(), RelayStatus::Connected) {
+                continue;
+            }
+
+            if timeline.filter.get_any_ready().is_none() {
+                continue;
+            }
+
+            debug!(
+                "retrying closed subscription for {:?} on {relay_url}",
+                timeline.kind
+            );
+            timeline::send_initial_timeline_filter(
+                since_optimize,
+                ctx.ndb,
+                &mut damus.subscriptions,
+                relay,
+                timeline,
+                ctx.accounts,
+            );
+        }
+    }
+}
+
 fn process_message(damus: &mut Damus, ctx: &mut AppContext<'_>, relay: &str, msg: &RelayMessage) {
     match msg {
-        RelayMessage::Event(_subid, ev) => {
+        RelayMessage::Event(subid, ev) => {
+            // answers from remote signers are for them only
+            if ctx.accounts.handle_signer_event(subid, ev) {
+                return;
+            }
+
             let relay = if let Some(relay) = ctx.pool.relays.iter().find(|r| r.url() == relay) {
                 relay
             } else {
@@ -408,7 +575,7 @@ fn process_message(damus: &mut Damus, ctx: &mut AppContext<'_>, relay: &str, msg
             };
 
             match relay {
-                PoolRelay::Websocket(_) => {
+                PoolRelay::Websocket(_) | PoolRelay::Mock(_) => {
                     //info!("processing event {}", event);
                     if let Err(err) = ctx.ndb.process_event_with(
                         ev,
@@ -433,8 +600,47 @@ fn process_message(damus: &mut Damus, ctx: &mut AppContext<'_>, relay: &str, msg
             }
         }
         RelayMessage::Notice(msg) => warn!("Notice from {}: {}", relay, msg),
-        RelayMessage::OK(cr) => info!("OK {:?}", cr),
+        RelayMessage::OK(cr) => {
+            if !ctx
+                .outbox
+                .handle_ok(relay, cr.event_id(), cr.status(), cr.message())
+            {
+                info!("OK {:?}", cr);
+            }
+        }
+        RelayMessage::Closed(closed) => {
+            handle_closed(
+                &damus.subscriptions,
+                &mut damus.timeline_cache,
+                closed,
+                relay,
+            );
+        }
+        // the pool collects counts for RelayPool::count_result
+        RelayMessage::Count(_) => {}
+        // negentropy syncs are driven by the pool
+        RelayMessage::NegMsg(..) | RelayMessage::NegErr(_) => {}
+        RelayMessage::Auth(_challenge) => {
+            // the pool already recorded the challenge, answer it if we can sign
+            if let Some(keypair) = ctx.accounts.selected_filled() {
+                ctx.pool.authenticate(relay, keypair);
+            } else {
+                warn!("{relay} requires auth but the selected account can't sign");
+            }
+        }
         RelayMessage::Eose(sid) => {
+            // inbox relay lists keep coming in as we meet people
+            if *sid == dms::INBOX_RELAYS_SUBID {
+                return;
+            }
+
+            ctx.accounts.on_eose(sid);
+
+            if *sid == ctx.accounts.get_subs().channels.remote {
+                let account = ctx.accounts.selected_account_pubkey();
+                damus.channel_sync.on_eose(account);
+            }
+
             if let Err(err) = handle_eose(
                 &damus.subscriptions,
                 &mut damus.timeline_cache,
@@ -453,7 +659,7 @@ fn render_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ui: &mut egui::
         .note_options
         .set(NoteOptions::Wide, is_narrow(ui.ctx()));
 
-    let app_resp = if notedeck::ui::is_narrow(ui.ctx()) {
+    let mut app_resp = if notedeck::ui::is_narrow(ui.ctx()) {
         render_damus_mobile(damus, app_ctx, ui)
     } else {
         render_damus_desktop(damus, app_ctx, ui)
@@ -462,105 +668,14 @@ fn render_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ui: &mut egui::
     fullscreen_media_viewer_ui(ui, &mut damus.view_state.media_viewer, app_ctx.img_cache);
 
     // Show channel creation dialog
-    if let Some(dialog_action) = damus.channel_dialog.show(ui.ctx(), app_ctx.i18n) {
-        match dialog_action {
-            ui::ChannelDialogAction::Create { name, hashtags } => {
-                // Create new channel
-                let channel = crate::channels::Channel::new(name, hashtags);
-
-                // Add to active channels
-                damus
-                    .channels_cache
-                    .active_channels_mut(app_ctx.i18n, app_ctx.accounts)
-                    .add_channel(channel);
-
-                // Save channels cache
-                storage::save_channels_cache(app_ctx.path, &damus.channels_cache);
-
-                // Subscribe to the new channel
-                let txn = match nostrdb::Transaction::new(app_ctx.ndb) {
-                    Ok(txn) => txn,
-                    Err(e) => {
-                        error!("Failed to create transaction for channel subscription: {}", e);
-                        return app_resp;
-                    }
-                };
-                let channel_count = damus.channels_cache.active_channels(app_ctx.accounts).num_channels();
-                if let Some(channel) = damus.channels_cache.active_channels_mut(app_ctx.i18n, app_ctx.accounts).get_channel_mut(channel_count - 1) {
-                    // Check if timeline is already open (query TimelineCache directly)
-                    if damus.timeline_cache.get(&channel.timeline_kind).is_none() {
-                        if let Some(result) = damus.timeline_cache.open(
-                            &mut damus.subscriptions,
-                            app_ctx.ndb,
-                            app_ctx.note_cache,
-                            &txn,
-                            app_ctx.pool,
-                            &channel.timeline_kind,
-                        ) {
-                            result.process(
-                                app_ctx.ndb,
-                                app_ctx.note_cache,
-                                &txn,
-                                &mut damus.timeline_cache,
-                                app_ctx.unknown_ids,
-                            );
-                        }
-                    }
-                }
-            }
-            ui::ChannelDialogAction::Edit { index, name, hashtags } => {
-                // Edit existing channel
-                let edited = damus
-                    .channels_cache
-                    .active_channels_mut(app_ctx.i18n, app_ctx.accounts)
-                    .edit_channel(
-                        index,
-                        name,
-                        hashtags.clone(),
-                        &mut damus.timeline_cache,
-                        app_ctx.ndb,
-                        app_ctx.pool,
-                    );
-
-                if edited {
-                    // Save channels cache
-                    storage::save_channels_cache(app_ctx.path, &damus.channels_cache);
-
-                    // Subscribe to the new timeline if hashtags changed
-                    let txn = match nostrdb::Transaction::new(app_ctx.ndb) {
-                        Ok(txn) => txn,
-                        Err(e) => {
-                            error!("Failed to create transaction for channel subscription: {}", e);
-                            return app_resp;
-                        }
-                    };
+    if let Some(dialog_action) = damus.channel_dialog.show(ui.ctx(), app_ctx.i18n, app_ctx.ndb) {
+        process_channel_dialog_action(damus, app_ctx, dialog_action);
+    }
 
-                    if let Some(channel) = damus.channels_cache.active_channels_mut(app_ctx.i18n, app_ctx.accounts).get_channel_mut(index) {
-                        if damus.timeline_cache.get(&channel.timeline_kind).is_none() {
-                            if let Some(result) = damus.timeline_cache.open(
-                                &mut damus.subscriptions,
-                                app_ctx.ndb,
-                                app_ctx.note_cache,
-                                &txn,
-                                app_ctx.pool,
-                                &channel.timeline_kind,
-                            ) {
-                                result.process(
-                                    app_ctx.ndb,
-                                    app_ctx.note_cache,
-                                    &txn,
-                                    &mut damus.timeline_cache,
-                                    app_ctx.unknown_ids,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            ui::ChannelDialogAction::Cancel => {
-                // Dialog was canceled, nothing to do
-            }
-        }
+    // Stop fetching the public chat directory once the dialog is gone
+    if !damus.channel_dialog.is_open && damus.channel_dialog.browsing {
+        damus.channel_dialog.browsing = false;
+        app_ctx.pool.unsubscribe(PUBLIC_CHAT_BROWSE_SUBID.to_owned());
     }
 
     // Show relay dialog and handle relay addition
@@ -602,30 +717,58 @@ fn render_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ui: &mut egui::
             clipboard: app_ctx.clipboard,
             i18n: app_ctx.i18n,
             global_wallet: app_ctx.global_wallet,
+            outbox: app_ctx.outbox,
+            deletions: app_ctx.deletions,
         };
 
-        let (panel_action, note_action) = damus.thread_panel.show(
+        // replies go where the thread's channel posts to
+        let reply_relays = damus
+            .channels_cache
+            .active_channels(note_context.accounts)
+            .selected_channel()
+            .map_or(Some(vec![]), |channel| {
+                let can_reply = !channel.relays.read_only
+                    && !matches!(channel.kind, crate::channels::ChannelKind::Group(_));
+                can_reply.then(|| channel.relays.urls.iter().cloned().collect())
+            });
+
+        let panel_resp = damus.thread_panel.show(
             ui,
             &mut damus.threads,
+            &mut damus.drafts,
+            reply_relays,
             damus.note_options,
             &mut note_context,
             &mut damus.jobs,
             0, // col index for thread panel
         );
+        app_resp
+            .can_take_drag_from
+            .extend(panel_resp.can_take_drag_from);
 
-        if let Some(action) = panel_action {
-            match action {
-                ui::ThreadPanelAction::Close => {
-                    damus.thread_panel.close();
+        match panel_resp.action {
+            Some(ui::ThreadPanelAction::Close) => {
+                damus.thread_panel.close();
+            }
+            Some(ui::ThreadPanelAction::Post(new_post_action)) => {
+                let txn = Transaction::new(app_ctx.ndb).expect("txn");
+                match new_post_action.execute(
+                    app_ctx.ndb,
+                    &txn,
+                    app_ctx.accounts,
+                    app_ctx.outbox,
+                    &mut damus.drafts,
+                ) {
+                    Err(err) => error!("Error sending thread reply: {err}"),
+                    Ok(_) => debug!("Sent thread reply"),
                 }
             }
+            None => {}
         }
 
-        // Handle any note actions from the thread panel
-        // (e.g., opening another thread, replying, etc.)
-        if let Some(_note_action) = note_action {
-            // For now, we can ignore note actions within the thread panel
-            // In a full implementation, these would be handled by the action system
+        // Opening another thread, replying, reacting, etc.
+        if let Some(note_action) = panel_resp.note_action {
+            process_chat_action(note_action, damus, app_ctx, ui);
         }
     }
 
@@ -635,6 +778,352 @@ fn render_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ui: &mut egui::
     app_resp
 }
 
+/// Subscription id used while the channel dialog lists public chats
+const PUBLIC_CHAT_BROWSE_SUBID: &str = "public-chat-directory";
+
+fn process_channel_dialog_action(
+    damus: &mut Damus,
+    app_ctx: &mut AppContext<'_>,
+    action: ui::ChannelDialogAction,
+) {
+    match action {
+        ui::ChannelDialogAction::Create {
+            name,
+            hashtags,
+            filter,
+            relays,
+        } => {
+            // Create new channel
+            let channel = crate::channels::Channel::new(name, hashtags)
+                .with_filter(filter)
+                .with_relays(relays);
+            add_channel(damus, app_ctx, channel);
+        }
+        ui::ChannelDialogAction::Edit {
+            index,
+            name,
+            hashtags,
+            filter,
+            relays,
+        } => {
+            let edit = ChannelEdit {
+                name,
+                hashtags,
+                filter,
+                relays,
+            };
+            edit_channel(damus, app_ctx, index, edit);
+        }
+        ui::ChannelDialogAction::CreatePublicChat { metadata, relays } => {
+            let Some(creator) = app_ctx.accounts.selected_filled().map(|kp| kp.to_full()) else {
+                error!("Can't create a public chat without a private key");
+                return;
+            };
+
+            crate::channels::connect_relays(app_ctx.pool, relays.urls.iter().map(String::as_str));
+
+            let Some(channel_id) =
+                nip28::create_channel(app_ctx.ndb, app_ctx.pool, &creator, &metadata, &relays.urls)
+            else {
+                error!("Failed to build public chat creation event");
+                return;
+            };
+
+            let channel = crate::channels::Channel::public_chat(metadata.name, channel_id)
+                .with_relays(relays);
+            add_channel(damus, app_ctx, channel);
+        }
+        ui::ChannelDialogAction::EditPublicChat {
+            index,
+            metadata,
+            relays,
+        } => {
+            let channel_id = damus
+                .channels_cache
+                .active_channels(app_ctx.accounts)
+                .get_channel(index)
+                .and_then(|channel| channel.kind.public_chat_id().copied());
+            let Some(channel_id) = channel_id else {
+                return;
+            };
+
+            // kind 41 updates are only honored from the channel creator.
+            // Anyone can change which relays they follow the chat on.
+            let txn = Transaction::new(app_ctx.ndb).expect("txn");
+            let creator = nip28::channel_creator(app_ctx.ndb, &txn, &channel_id);
+            let current = nip28::channel_metadata(app_ctx.ndb, &txn, &channel_id);
+            let kp = app_ctx
+                .accounts
+                .selected_filled()
+                .filter(|kp| creator.as_ref() == Some(kp.pubkey));
+
+            let name = match kp {
+                Some(kp) if current.as_ref() != Some(&metadata) => {
+                    let Some(note) = nip28::channel_metadata_event(
+                        &channel_id,
+                        &metadata,
+                        &kp.secret_key.secret_bytes(),
+                    ) else {
+                        error!("Failed to build public chat metadata event");
+                        return;
+                    };
+                    nip28::send_event(app_ctx.ndb, app_ctx.pool, &note, &relays.urls);
+                    metadata.name
+                }
+                _ => current.map(|current| current.name).unwrap_or(metadata.name),
+            };
+
+            let edit = ChannelEdit {
+                name,
+                relays,
+                ..Default::default()
+            };
+            edit_channel(damus, app_ctx, index, edit);
+        }
+        ui::ChannelDialogAction::JoinPublicChat { channel_id, relays } => {
+            let existing = damus
+                .channels_cache
+                .active_channels(app_ctx.accounts)
+                .find_public_chat(&channel_id);
+            if let Some(index) = existing {
+                select_existing_channel(damus, app_ctx, index);
+                return;
+            }
+
+            debug!("Joining public chat {} (relay hints: {:?})", channel_id.hex(), relays);
+
+            let txn = Transaction::new(app_ctx.ndb).expect("txn");
+            let name = nip28::channel_metadata(app_ctx.ndb, &txn, &channel_id)
+                .map(|metadata| metadata.name)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| channel_id.hex()[..8].to_owned());
+
+            // follow the chat where the link says it lives
+            let relays = crate::channels::ChannelRelays::new(
+                relays
+                    .iter()
+                    .filter_map(|relay| canonical_relay_url(relay))
+                    .collect(),
+                false,
+            );
+            let channel =
+                crate::channels::Channel::public_chat(name, channel_id).with_relays(relays);
+            add_channel(damus, app_ctx, channel);
+        }
+        ui::ChannelDialogAction::JoinGroup { group } => {
+            let existing = damus
+                .channels_cache
+                .active_channels(app_ctx.accounts)
+                .find_group(&group);
+            if let Some(index) = existing {
+                select_existing_channel(damus, app_ctx, index);
+                return;
+            }
+
+            let channel = crate::channels::Channel::group(group.to_string(), group.clone());
+            add_channel(damus, app_ctx, channel);
+
+            // the relay decides whether we get in. Open groups usually let
+            // anyone in, closed ones wait for an admin.
+            match app_ctx.accounts.selected_filled() {
+                Some(kp) => {
+                    let seckey = kp.secret_key.secret_bytes();
+                    if !nip29::request_join(app_ctx.ndb, app_ctx.pool, &group, &seckey) {
+                        error!("Failed to build join request for group {group}");
+                    }
+                }
+                None => {
+                    warn!("Joined group {group} without a private key, can't request membership")
+                }
+            }
+        }
+        ui::ChannelDialogAction::BrowsePublicChats => {
+            damus.channel_dialog.browsing = true;
+            app_ctx.pool.subscribe(
+                PUBLIC_CHAT_BROWSE_SUBID.to_owned(),
+                vec![nip28::browse_filter()],
+            );
+        }
+        ui::ChannelDialogAction::Cancel => {
+            // Dialog was canceled, nothing to do
+        }
+    }
+}
+
+/// Update a channel from the channel dialog, save it and resubscribe if its
+/// timeline changed
+fn edit_channel(damus: &mut Damus, app_ctx: &mut AppContext<'_>, index: usize, edit: ChannelEdit) {
+    let edited = damus
+        .channels_cache
+        .active_channels_mut(app_ctx.i18n, app_ctx.accounts)
+        .edit_channel(
+            index,
+            edit,
+            &mut damus.timeline_cache,
+            app_ctx.ndb,
+            app_ctx.pool,
+        );
+
+    if !edited {
+        return;
+    }
+
+    // Save channels cache
+    storage::save_channels_cache(app_ctx.path, &damus.channels_cache);
+
+    // Subscribe to the new timeline if the channel's feed changed.
+    // Archived channels stay closed.
+    let timeline_kind = damus
+        .channels_cache
+        .active_channels(app_ctx.accounts)
+        .get_channel(index)
+        .filter(|channel| !channel.archived)
+        .map(|channel| channel.timeline_kind.clone());
+    if let Some(timeline_kind) = timeline_kind {
+        open_channel_timeline(damus, app_ctx, &timeline_kind);
+    }
+}
+
+/// Add a channel to the active account, save it and subscribe to it
+fn add_channel(damus: &mut Damus, app_ctx: &mut AppContext<'_>, channel: crate::channels::Channel) {
+    let timeline_kind = channel.timeline_kind.clone();
+
+    // Add to active channels
+    damus
+        .channels_cache
+        .active_channels_mut(app_ctx.i18n, app_ctx.accounts)
+        .add_channel(channel);
+
+    // Save channels cache
+    storage::save_channels_cache(app_ctx.path, &damus.channels_cache);
+
+    // Subscribe to the new channel
+    open_channel_timeline(damus, app_ctx, &timeline_kind);
+}
+
+/// Bring back an archived channel of the active account, save it and
+/// subscribe to it again
+fn unarchive_channel(damus: &mut Damus, app_ctx: &mut AppContext<'_>, index: usize) {
+    let channels = damus
+        .channels_cache
+        .active_channels_mut(app_ctx.i18n, app_ctx.accounts);
+    if !channels.unarchive_channel(index) {
+        return;
+    }
+
+    let timeline_kind = channels
+        .get_channel(index)
+        .map(|channel| channel.timeline_kind.clone());
+
+    storage::save_channels_cache(app_ctx.path, &damus.channels_cache);
+
+    if let Some(timeline_kind) = timeline_kind {
+        open_channel_timeline(damus, app_ctx, &timeline_kind);
+    }
+}
+
+/// Select a channel the user is joining again, unarchiving it if needed
+fn select_existing_channel(damus: &mut Damus, app_ctx: &mut AppContext<'_>, index: usize) {
+    unarchive_channel(damus, app_ctx, index);
+    damus
+        .channels_cache
+        .active_channels_mut(app_ctx.i18n, app_ctx.accounts)
+        .select_channel(index);
+}
+
+/// Open the timeline behind a channel, unless it is already open
+fn open_channel_timeline(
+    damus: &mut Damus,
+    app_ctx: &mut AppContext<'_>,
+    timeline_kind: &TimelineKind,
+) {
+    // Check if timeline is already open (query TimelineCache directly)
+    if damus.timeline_cache.get(timeline_kind).is_some() {
+        return;
+    }
+
+    crate::channels::connect_timeline_relays(app_ctx.pool, timeline_kind);
+
+    let txn = match nostrdb::Transaction::new(app_ctx.ndb) {
+        Ok(txn) => txn,
+        Err(e) => {
+            error!("Failed to create transaction for channel subscription: {}", e);
+            return;
+        }
+    };
+
+    if let Some(result) = damus.timeline_cache.open(
+        &mut damus.subscriptions,
+        app_ctx.ndb,
+        app_ctx.note_cache,
+        &txn,
+        app_ctx.pool,
+        timeline_kind,
+    ) {
+        result.process(
+            app_ctx.ndb,
+            app_ctx.note_cache,
+            &txn,
+            &mut damus.timeline_cache,
+            app_ctx.unknown_ids,
+        );
+    }
+}
+
+/// Publish a NIP-28 hide (kind 43) or mute (kind 44) event, or a NIP-29
+/// admin event to the group's relay. It is stored locally as well, so the
+/// chat view hides the message right away.
+fn process_chat_moderation(action: ui::ChatModerationAction, ctx: &mut AppContext<'_>) {
+    let Some(kp) = ctx.accounts.selected_filled() else {
+        warn!("Can't moderate chats without a private key");
+        return;
+    };
+    let seckey = kp.secret_key.secret_bytes();
+
+    let (note, group) = match &action {
+        ui::ChatModerationAction::HideMessage(note_id) => {
+            (nip28::hide_message_event(note_id, &seckey), None)
+        }
+        ui::ChatModerationAction::MuteUser(pubkey) => {
+            (nip28::mute_user_event(pubkey, &seckey), None)
+        }
+        ui::ChatModerationAction::DeleteGroupMessage(group, note_id) => {
+            (nip29::delete_message_event(group, note_id, &seckey), Some(group))
+        }
+        ui::ChatModerationAction::RemoveGroupUser(group, pubkey) => {
+            (nip29::remove_user_event(group, pubkey, &seckey), Some(group))
+        }
+    };
+
+    match (note, group) {
+        (Some(note), Some(group)) => nip29::send_event(ctx.ndb, ctx.pool, group, &note),
+        (Some(note), None) => nip28::send_event(ctx.ndb, ctx.pool, &note, &Default::default()),
+        (None, _) => error!("Failed to build chat moderation event"),
+    }
+}
+
+/// Messages fetched each time a chat is scrolled to the top
+const OLDER_MESSAGES_LIMIT: u64 = 100;
+
+fn load_older_messages(damus: &mut Damus, ctx: &mut AppContext<'_>, kind: &TimelineKind) {
+    let Some(timeline) = damus.timeline_cache.get_mut(kind) else {
+        return;
+    };
+
+    let txn = Transaction::new(ctx.ndb).expect("txn");
+    if let Err(err) = timeline.load_older(
+        &mut damus.subscriptions,
+        ctx.ndb,
+        &txn,
+        ctx.pool,
+        ctx.unknown_ids,
+        ctx.note_cache,
+        OLDER_MESSAGES_LIMIT,
+    ) {
+        error!("Failed to load older messages: {err}");
+    }
+}
+
 /// Present a fullscreen media viewer if the FullscreenMedia AppOptions flag is set. This is
 /// typically set by image carousels using a MediaAction's on_view_media callback when
 /// an image is clicked
@@ -694,7 +1183,17 @@ impl Damus {
 
         let account = app_context.accounts.selected_account_pubkey_bytes();
 
+        // the mock relay serves everything, gossip would only reach out
+        // to the network
+        let gossip_relays = if app_context.args.mock_relay.is_some() {
+            0
+        } else {
+            parsed_args.gossip_relays
+        };
         let mut timeline_cache = TimelineCache::default();
+        timeline_cache
+            .gossip_mut()
+            .set_max_connections(gossip_relays);
         let mut options = AppOptions::default();
         let tmp_columns = !parsed_args.columns.is_empty();
         options.set(AppOptions::TmpColumns, tmp_columns);
@@ -781,9 +1280,14 @@ impl Damus {
                 &mut timeline_cache,
                 app_context,
             );
+            active_channels.refresh_unread(&timeline_cache);
+            if let Ok(txn) = Transaction::new(app_context.ndb) {
+                active_channels.refresh_chat_names(app_context.ndb, &txn);
+            }
         }
 
         // Load startup config (if exists)
+        let mut forget_startup_nsec = false;
         if let Some(startup_config) = crate::storage::load_startup_config(app_context.path) {
             info!("StartupConfig: loaded from disk");
 
@@ -804,6 +1308,7 @@ impl Damus {
                                 &txn,
                             );
                         }
+                        forget_startup_nsec = true;
                     }
                     Err(e) => {
                         error!("StartupConfig: Failed to parse nsec: {}", e);
@@ -856,6 +1361,7 @@ impl Damus {
             state: DamusState::Initializing,
             need_account_redirect: false,
             need_relay_prompt,
+            forget_startup_nsec,
             note_options,
             options,
             //frame_history: FrameHistory::default(),
@@ -863,10 +1369,14 @@ impl Damus {
             support,
             decks_cache,
             channels_cache,
+            channels_cache_saver: storage::ChannelsCacheSaver::default(),
+            channel_sync: crate::channel_sync::ChannelSync::default(),
+            dms: DirectMessages::default(),
             relay_config,
             channel_dialog: ui::ChannelDialog::default(),
             relay_dialog: ui::RelayDialog::default(),
             thread_panel: ui::ThreadPanel::default(),
+            chat_views: HashMap::new(),
             unrecognized_args,
             jobs,
             threads,
@@ -916,6 +1426,7 @@ impl Damus {
             state: DamusState::Initializing,
             need_account_redirect: false,
             need_relay_prompt: relay_config.is_empty(),
+            forget_startup_nsec: false,
             note_options: NoteOptions::default(),
             //frame_history: FrameHistory::default(),
             view_state: ViewState::default(),
@@ -923,10 +1434,14 @@ impl Damus {
             options,
             decks_cache,
             channels_cache,
+            channels_cache_saver: storage::ChannelsCacheSaver::default(),
+            channel_sync: crate::channel_sync::ChannelSync::default(),
+            dms: DirectMessages::default(),
             relay_config,
             channel_dialog: ui::ChannelDialog::default(),
             relay_dialog: ui::RelayDialog::default(),
             thread_panel: ui::ThreadPanel::default(),
+            chat_views: HashMap::new(),
             unrecognized_args: BTreeSet::default(),
             jobs: JobsCache::default(),
             threads: Threads::default(),
@@ -1111,11 +1626,15 @@ fn should_show_compose_button(decks: &DecksCache, accounts: &Accounts) -> bool {
                 TimelineKind::Universe => true,
                 TimelineKind::Generic(_) => true,
                 TimelineKind::Hashtag(_) => true,
+                TimelineKind::Feed(_, _) => true,
                 TimelineKind::Relay(_, _) => true,
 
                 // no!
                 TimelineKind::Search(_) => false,
                 TimelineKind::Notifications(_) => false,
+                TimelineKind::PublicChat(_) => false,
+                TimelineKind::Group(_) => false,
+                TimelineKind::Scoped(_, _) => false,
             }
         }
 
@@ -1177,6 +1696,40 @@ fn render_damus_desktop(
 }
 
 /// Process chat view actions like Reply, React, Repost
+/// The conversations in the sidebar, named after the people in them
+fn dm_sidebar_rows(dms: &DirectMessages, ctx: &mut AppContext<'_>) -> Vec<DmRow> {
+    let Ok(txn) = Transaction::new(ctx.ndb) else {
+        return Vec::new();
+    };
+    let account = *ctx.accounts.selected_account_pubkey();
+
+    let mut rows: Vec<DmRow> = dms
+        .conversations()
+        .into_iter()
+        .map(|(participants, conversation)| DmRow {
+            participants: participants.clone(),
+            name: conversation_name(ctx.ndb, &txn, ctx.i18n, &account, participants),
+            unread: conversation.unread,
+        })
+        .collect();
+
+    // a conversation just started has no messages yet
+    if let Some(selected) = &dms.selected {
+        if dms.conversation(selected).is_none() {
+            rows.insert(
+                0,
+                DmRow {
+                    participants: selected.clone(),
+                    name: conversation_name(ctx.ndb, &txn, ctx.i18n, &account, selected),
+                    unread: false,
+                },
+            );
+        }
+    }
+
+    rows
+}
+
 fn process_chat_action(
     action: NoteAction,
     app: &mut Damus,
@@ -1189,12 +1742,12 @@ fn process_chat_action(
             app.thread_panel.open(*note_id.bytes());
         }
         NoteAction::Reply(note_id) => {
-            // Open thread panel for replying
-            app.thread_panel.open(*note_id.bytes());
+            // Reply from the thread panel's composer
+            app.thread_panel.reply(*note_id.bytes());
         }
         NoteAction::React(react_action) => {
             // Handle reaction (like) - send to relays
-            if let Some(filled) = ctx.accounts.selected_filled() {
+            if ctx.accounts.selected_poster().is_some() {
                 let txn = match Transaction::new(ctx.ndb) {
                     Ok(txn) => txn,
                     Err(e) => {
@@ -1207,8 +1760,8 @@ fn process_chat_action(
                 if let Err(err) = crate::actionbar::send_reaction_event(
                     ctx.ndb,
                     &txn,
-                    ctx.pool,
-                    filled,
+                    ctx.accounts,
+                    ctx.outbox,
                     &react_action,
                 ) {
                     error!("Failed to send reaction: {err}");
@@ -1221,6 +1774,18 @@ fn process_chat_action(
             // For now, open thread panel - could add repost dialog later
             app.thread_panel.open(*note_id.bytes());
         }
+        NoteAction::Context(ContextSelection {
+            note_key,
+            action: NoteContextSelection::Delete,
+        }) => {
+            let Ok(txn) = Transaction::new(ctx.ndb) else {
+                return;
+            };
+
+            if let Ok(note) = ctx.ndb.get_note_by_key(&txn, note_key) {
+                crate::actionbar::send_deletion_event(ctx.accounts, ctx.outbox, &note);
+            }
+        }
         _ => {
             // Other actions not yet supported in chat view
         }
@@ -1247,8 +1812,13 @@ fn timelines_view(
         matches!(route, Route::Accounts(_) | Route::Relays | Route::AddColumn(_) | Route::ComposeNote | Route::Search | Route::NewDeck | Route::EditDeck(_))
     });
 
+    // An open conversation takes the place of the selected channel
+    let open_dm = app.dms.selected.clone();
+
     // Only use channel layout if a channel is selected AND we're not showing a router view
-    let show_channel_layout = has_selected_channel && !has_router_view;
+    let show_channel_layout = (has_selected_channel || open_dm.is_some()) && !has_router_view;
+
+    let dm_rows = dm_sidebar_rows(&app.dms, ctx);
 
     if show_channel_layout {
         // When channel is selected: Side Panel | Channel Sidebar | Content Area (remainder)
@@ -1300,11 +1870,12 @@ fn timelines_view(
             strip.cell(|ui| {
                 let rect = ui.available_rect_before_wrap();
                 let mut channel_sidebar =
-                    ChannelSidebar::new(&app.channels_cache, ctx.accounts, ctx.i18n);
+                    ChannelSidebar::new(&app.channels_cache, ctx.accounts, ctx.i18n)
+                        .direct_messages(&dm_rows, open_dm.as_ref());
 
-                if let Some(response) = channel_sidebar.show(ui) {
-                    channel_sidebar_action = Some(response.action);
-                }
+                let response = channel_sidebar.show(ui);
+                can_take_drag_from.extend(response.can_take_drag_from());
+                channel_sidebar_action = response.action;
 
                 // vertical sidebar line
                 ui.painter().vline(
@@ -1315,12 +1886,73 @@ fn timelines_view(
             });
 
             // Render ChatView for the selected channel
-            let selected_timeline_kind = app.channels_cache
+            let selected_channel = app.channels_cache
                 .active_channels(ctx.accounts)
                 .selected_channel()
-                .map(|c| c.timeline_kind.clone());
+                .map(|c| {
+                    (
+                        c.id,
+                        c.name.clone(),
+                        c.hashtags.clone(),
+                        c.kind.clone(),
+                        c.relays.clone(),
+                        c.timeline_kind.clone(),
+                        c.unread_divider,
+                    )
+                });
+
+            if let Some(participants) = &open_dm {
+                strip.cell(|ui| {
+                    let rect = ui.available_rect_before_wrap();
+                    let v_line_stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+
+                    let action = {
+                        let mut note_context = notedeck::NoteContext {
+                            ndb: ctx.ndb,
+                            accounts: ctx.accounts,
+                            img_cache: ctx.img_cache,
+                            note_cache: ctx.note_cache,
+                            zaps: ctx.zaps,
+                            pool: ctx.pool,
+                            job_pool: ctx.job_pool,
+                            unknown_ids: ctx.unknown_ids,
+                            clipboard: ctx.clipboard,
+                            i18n: ctx.i18n,
+                            global_wallet: ctx.global_wallet,
+                            outbox: ctx.outbox,
+                            deletions: ctx.deletions,
+                        };
+
+                        ui::DmView::new(participants, &mut app.dms, &mut note_context).ui(ui)
+                    };
+
+                    match action {
+                        Some(ui::DmViewAction::Send(content)) => {
+                            app.dms.send(ctx, participants, &content);
+                        }
+                        Some(ui::DmViewAction::Open(participants)) => {
+                            app.dms.open(participants, ctx);
+                        }
+                        Some(ui::DmViewAction::SetInboxRelays(relays)) => {
+                            ctx.accounts.set_dm_relays(ui.ctx(), ctx.pool, ctx.outbox, relays);
+                        }
+                        None => {}
+                    }
 
-            if let Some(timeline_kind) = selected_timeline_kind {
+                    // vertical line
+                    ui.painter()
+                        .vline(rect.right(), rect.y_range(), v_line_stroke);
+                });
+            } else if let Some((
+                channel_id,
+                channel_name,
+                hashtags,
+                channel_kind,
+                channel_relays,
+                timeline_kind,
+                unread_divider,
+            )) = selected_channel
+            {
                 strip.cell(|ui| {
                     let rect = ui.available_rect_before_wrap();
                     let v_line_stroke = ui.visuals().widgets.noninteractive.bg_stroke;
@@ -1338,12 +1970,24 @@ fn timelines_view(
                         clipboard: ctx.clipboard,
                         i18n: ctx.i18n,
                         global_wallet: ctx.global_wallet,
+                        outbox: ctx.outbox,
+                        deletions: ctx.deletions,
                     };
 
                     // Create a ChatView for the selected channel
                     let mut chat_view = crate::ui::ChatView::new(
                         &timeline_kind,
                         &mut app.timeline_cache,
+                        crate::ui::ChatChannel {
+                            id: channel_id,
+                            name: &channel_name,
+                            hashtags: &hashtags,
+                            kind: &channel_kind,
+                            relays: &channel_relays,
+                            unread_divider,
+                        },
+                        app.chat_views.entry(timeline_kind.clone()).or_default(),
+                        app.drafts.channel_mut(&channel_id),
                         &mut note_context,
                         notedeck_ui::NoteOptions::default(),
                         &mut app.jobs,
@@ -1353,8 +1997,48 @@ fn timelines_view(
                     let chat_response = chat_view.ui(ui);
 
                     // Handle chat view actions
-                    if let Some(Some(action)) = chat_response.output {
-                        process_chat_action(action, app, ctx, ui);
+                    match chat_response.output {
+                        Some(ui::ChatViewAction::Note(action)) => {
+                            process_chat_action(action, app, ctx, ui);
+                        }
+                        Some(ui::ChatViewAction::Post(new_post_action)) => {
+                            let txn = Transaction::new(ctx.ndb).expect("txn");
+                            match new_post_action.execute(
+                                ctx.ndb,
+                                &txn,
+                                ctx.accounts,
+                                ctx.outbox,
+                                &mut app.drafts,
+                            ) {
+                                Err(err) => error!("Error sending channel message: {err}"),
+                                Ok(_) => debug!("Sent message to channel {}", channel_name),
+                            }
+                        }
+                        Some(ui::ChatViewAction::Moderate(moderation)) => {
+                            process_chat_moderation(moderation, ctx);
+                        }
+                        Some(ui::ChatViewAction::LoadOlder) => {
+                            load_older_messages(app, ctx, &timeline_kind);
+                        }
+                        Some(ui::ChatViewAction::RetryClosed) => {
+                            if let Some(timeline) = app.timeline_cache.get_mut(&timeline_kind) {
+                                timeline.filter.retry_all_now();
+                            }
+                        }
+                        None => {}
+                    }
+
+                    // The channel is on screen, so everything in it has been seen
+                    let latest = app
+                        .timeline_cache
+                        .get(&timeline_kind)
+                        .and_then(|tl| tl.current_view().units.latest().copied());
+                    let read_marker_moved = app
+                        .channels_cache
+                        .selected_channel_mut(ctx.i18n, ctx.accounts)
+                        .is_some_and(|channel| channel.mark_read(latest));
+                    if read_marker_moved {
+                        app.channels_cache_saver.mark_dirty();
                     }
 
                     // vertical line
@@ -1429,11 +2113,12 @@ fn timelines_view(
                 strip.cell(|ui| {
                     let rect = ui.available_rect_before_wrap();
                     let mut channel_sidebar =
-                        ChannelSidebar::new(&app.channels_cache, ctx.accounts, ctx.i18n);
+                        ChannelSidebar::new(&app.channels_cache, ctx.accounts, ctx.i18n)
+                            .direct_messages(&dm_rows, open_dm.as_ref());
 
-                    if let Some(response) = channel_sidebar.show(ui) {
-                        channel_sidebar_action = Some(response.action);
-                    }
+                    let response = channel_sidebar.show(ui);
+                    can_take_drag_from.extend(response.can_take_drag_from());
+                    channel_sidebar_action = response.action;
 
                     // vertical sidebar line
                     ui.painter().vline(
@@ -1488,11 +2173,20 @@ fn timelines_view(
     if let Some(action) = channel_sidebar_action {
         match action {
             ChannelSidebarAction::SelectChannel(idx) => {
-                app.channels_cache
-                    .active_channels_mut(ctx.i18n, ctx.accounts)
-                    .select_channel(idx);
+                app.dms.selected = None;
+                let channels = app.channels_cache.active_channels_mut(ctx.i18n, ctx.accounts);
+                channels.select_channel(idx);
                 // Note: Don't save on every selection to avoid excessive disk I/O
                 // Channel selection state will be saved on app close
+
+                // pick up follow sets that arrived after the feed was opened
+                if channels.reload_pending_feed(idx, &mut app.timeline_cache, ctx.ndb, ctx.pool) {
+                    if let Some(timeline_kind) =
+                        channels.get_channel(idx).map(|c| c.timeline_kind.clone())
+                    {
+                        open_channel_timeline(app, ctx, &timeline_kind);
+                    }
+                }
             }
             ChannelSidebarAction::AddChannel => {
                 app.channel_dialog.open();
@@ -1502,6 +2196,14 @@ fn timelines_view(
                     .active_channels_mut(ctx.i18n, ctx.accounts)
                     .remove_channel(idx, &mut app.timeline_cache, ctx.ndb, ctx.pool);
 
+                let left_group = removed.as_ref().and_then(|channel| channel.kind.group());
+                if let (Some(group), Some(kp)) = (left_group, ctx.accounts.selected_filled()) {
+                    let seckey = kp.secret_key.secret_bytes();
+                    if !nip29::request_leave(ctx.ndb, ctx.pool, group, &seckey) {
+                        error!("Failed to build leave request for group {group}");
+                    }
+                }
+
                 if removed.is_some() {
                     // Save channels cache after deletion
                     storage::save_channels_cache(ctx.path, &app.channels_cache);
@@ -1513,13 +2215,83 @@ fn timelines_view(
                     .active_channels(ctx.accounts)
                     .get_channel(idx)
                 {
-                    app.channel_dialog.open_for_edit(
-                        idx,
-                        channel.name.clone(),
-                        channel.hashtags.clone(),
-                    );
+                    if let Some(channel_id) = channel.kind.public_chat_id() {
+                        let txn = Transaction::new(ctx.ndb).expect("txn");
+                        let metadata = nip28::channel_metadata(ctx.ndb, &txn, channel_id)
+                            .unwrap_or_else(|| {
+                                nip28::ChannelMetadata::new(channel.name.clone(), String::new())
+                            });
+                        let is_creator = nip28::channel_creator(ctx.ndb, &txn, channel_id)
+                            .is_some_and(|creator| {
+                                ctx.accounts.selected_filled().is_some_and(|kp| *kp.pubkey == creator)
+                            });
+                        app.channel_dialog.open_for_public_chat_edit(
+                            idx,
+                            metadata,
+                            is_creator,
+                            &channel.relays,
+                        );
+                    } else {
+                        app.channel_dialog.open_for_edit(
+                            idx,
+                            channel.name.clone(),
+                            channel.hashtags.clone(),
+                            &channel.filter,
+                            &channel.relays,
+                        );
+                    }
+                }
+            }
+            ChannelSidebarAction::MoveChannel(idx, placement) => {
+                app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .move_channel(idx, placement);
+                storage::save_channels_cache(ctx.path, &app.channels_cache);
+            }
+            ChannelSidebarAction::SetPinned(idx, pinned) => {
+                app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .set_pinned(idx, pinned);
+                storage::save_channels_cache(ctx.path, &app.channels_cache);
+            }
+            ChannelSidebarAction::ArchiveChannel(idx) => {
+                let archived = app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .archive_channel(idx, &mut app.timeline_cache, ctx.ndb, ctx.pool);
+                if archived {
+                    storage::save_channels_cache(ctx.path, &app.channels_cache);
                 }
             }
+            ChannelSidebarAction::UnarchiveChannel(idx) => {
+                unarchive_channel(app, ctx, idx);
+            }
+            ChannelSidebarAction::AddSection(name) => {
+                app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .add_section(name);
+                storage::save_channels_cache(ctx.path, &app.channels_cache);
+            }
+            ChannelSidebarAction::RenameSection(id, name) => {
+                app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .rename_section(id, name);
+                storage::save_channels_cache(ctx.path, &app.channels_cache);
+            }
+            ChannelSidebarAction::DeleteSection(id) => {
+                app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .remove_section(id);
+                storage::save_channels_cache(ctx.path, &app.channels_cache);
+            }
+            ChannelSidebarAction::OpenDm(participants) => {
+                app.dms.open(participants, ctx);
+            }
+            ChannelSidebarAction::ToggleSection(id) => {
+                app.channels_cache
+                    .active_channels_mut(ctx.i18n, ctx.accounts)
+                    .toggle_section(id);
+                storage::save_channels_cache(ctx.path, &app.channels_cache);
+            }
         }
     }
 
@@ -1603,3 +2375,51 @@ fn columns_to_decks_cache(i18n: &mut Localization, cols: Columns, key: &[u8; 32]
     account_to_decks.insert(account, decks);
     DecksCache::new(account_to_decks, i18n)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::Timeline;
+    use enostr::{IncomingMessage, RelayLimitation, RelayLimits};
+    use nostrdb::Filter;
+
+    #[test]
+    fn test_split_subscription_closed_reaches_timeline() {
+        let relay_url = "wss://relay.example.com/";
+        let kind = TimelineKind::Hashtag(vec!["nostr".to_owned()]);
+        let mut timeline_cache = TimelineCache::default();
+        timeline_cache.insert(kind.clone(), Timeline::hashtag(vec!["nostr".to_owned()]));
+        let mut subscriptions = Subscriptions::default();
+        subscriptions
+            .subs
+            .insert("sub".to_owned(), SubKind::Timeline(kind.clone()));
+
+        // the relay takes one filter per REQ, so it sees `sub` and `sub:1`
+        let mut limits = RelayLimits::default();
+        limits.set_limitation(RelayLimitation {
+            max_filters: Some(1),
+            ..Default::default()
+        });
+        let filters = vec![
+            Filter::new().kinds([1]).build(),
+            Filter::new().kinds([7]).build(),
+        ];
+        limits.outgoing(&ClientMessage::req("sub".to_owned(), filters));
+
+        let IncomingMessage::Rewritten(txt) =
+            limits.incoming(r#"["CLOSED","sub:1","rate-limited: slow down"]"#)
+        else {
+            panic!("CLOSED of a split subscription part wasn't rewritten");
+        };
+        let Ok(RelayMessage::Closed(closed)) = RelayMessage::from_json(&txt) else {
+            panic!("bad CLOSED {txt}");
+        };
+        handle_closed(&subscriptions, &mut timeline_cache, &closed, relay_url);
+
+        let timeline = timeline_cache.get(&kind).unwrap();
+        let closed: Vec<_> = timeline.filter.closed().collect();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].0, relay_url);
+        assert!(closed[0].1.retry_at.is_some());
+    }
+}
@@ -15,6 +15,8 @@ pub enum ColumnsFlag {
 
 pub struct ColumnsArgs {
     pub columns: Vec<ArgColumn>,
+    /// Extra relay connections the gossip router may open, 0 disables it
+    pub gossip_relays: usize,
     flags: [u16; 2],
 }
 
@@ -35,6 +37,7 @@ impl ColumnsArgs {
         let mut unrecognized_args = BTreeSet::new();
         let mut res = Self {
             columns: vec![],
+            gossip_relays: crate::gossip::DEFAULT_MAX_CONNECTIONS,
             flags: [0; 2],
         };
 
@@ -54,6 +57,13 @@ impl ColumnsArgs {
                 res.set_flag(ColumnsFlag::Scramble);
             } else if arg == "--no-media" {
                 res.set_flag(ColumnsFlag::NoMedia);
+            } else if arg == "--gossip-relays" {
+                i += 1;
+                match args.get(i).map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) => res.gossip_relays = n,
+                    Some(Err(_)) => error!("invalid --gossip-relays count '{}'", args[i]),
+                    None => error!("gossip relay count missing?"),
+                }
             } else if arg == "--filter" {
                 i += 1;
                 let filter = if let Some(next_arg) = args.get(i) {
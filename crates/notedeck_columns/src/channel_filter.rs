@@ -0,0 +1,398 @@
+//! Channel feeds that go beyond hashtags
+//!
+//! A hashtag channel can narrow its feed down to an allow-list of authors
+//! or the members of a NIP-51 follow set, pick the note kinds it shows and
+//! hide notes that mention any of its mute words.
+
+use enostr::Pubkey;
+use nostrdb::{Filter, FilterBuilder, Ndb, Transaction};
+use notedeck::filter::{default_limit, HybridFilter, NdbQueryPackage, ValidKind};
+use notedeck::FilterState;
+use tokenator::{ParseError, TokenParser, TokenSerializable, TokenWriter};
+
+/// NIP-51 follow sets
+pub const KIND_FOLLOW_SET: u64 = 30000;
+
+/// Whose notes a channel shows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum AuthorFilter {
+    #[default]
+    Anyone,
+    Pubkeys(Vec<Pubkey>),
+    /// The members of a NIP-51 follow set, identified by the set's author
+    /// and `d` tag
+    FollowSet {
+        author: Pubkey,
+        identifier: String,
+    },
+}
+
+impl AuthorFilter {
+    /// Parse a comma or whitespace separated list of npubs or hex pubkeys.
+    /// Anything that isn't a pubkey is skipped.
+    pub fn parse_pubkeys(input: &str) -> Vec<Pubkey> {
+        let mut pubkeys: Vec<Pubkey> = input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|pk| Pubkey::parse(pk).ok())
+            .collect();
+        pubkeys.sort();
+        pubkeys.dedup();
+        pubkeys
+    }
+
+    /// Parse a follow set reference written as `<npub or hex>/<identifier>`
+    pub fn parse_follow_set(input: &str) -> Option<Self> {
+        let (author, identifier) = input.trim().split_once('/')?;
+        let author = Pubkey::parse(author.trim()).ok()?;
+        let identifier = identifier.trim();
+        if identifier.is_empty() {
+            return None;
+        }
+
+        Some(AuthorFilter::FollowSet {
+            author,
+            identifier: identifier.to_owned(),
+        })
+    }
+
+    /// The authors to allow, `None` for anyone. Follow sets that aren't in
+    /// nostrdb yet, or are empty, resolve to an empty list.
+    pub fn resolve(&self, ndb: &Ndb, txn: &Transaction) -> Option<Vec<Pubkey>> {
+        match self {
+            AuthorFilter::Anyone => None,
+            AuthorFilter::Pubkeys(pubkeys) => Some(pubkeys.clone()),
+            AuthorFilter::FollowSet { author, identifier } => {
+                Some(follow_set_members(ndb, txn, author, identifier).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// The extra conditions a hashtag channel puts on its feed. The default
+/// filter shows kind 1 notes from anyone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ChannelFilter {
+    pub authors: AuthorFilter,
+    /// Note kinds to show. Empty means kind 1 notes.
+    pub kinds: Vec<u64>,
+    /// Lowercase words that hide any note mentioning them
+    pub mute_words: Vec<String>,
+}
+
+impl ChannelFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Parse a comma or whitespace separated list of kind numbers
+    pub fn parse_kinds(input: &str) -> Vec<u64> {
+        let mut kinds: Vec<u64> = input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|kind| kind.parse().ok())
+            .collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        kinds
+    }
+
+    /// Parse a comma separated list of mute words. Phrases with spaces are
+    /// kept together.
+    pub fn parse_mute_words(input: &str) -> Vec<String> {
+        let mut words: Vec<String> = input
+            .split(',')
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        words.sort();
+        words.dedup();
+        words
+    }
+
+    pub fn kinds(&self) -> Vec<u64> {
+        if self.kinds.is_empty() {
+            vec![1]
+        } else {
+            self.kinds.clone()
+        }
+    }
+
+    /// Compile the feed into nostrdb filters, one per hashtag. Relays can't
+    /// filter on mute words, so those only apply to our local queries.
+    pub fn filter_state(&self, hashtags: &[String], ndb: &Ndb, txn: &Transaction) -> FilterState {
+        let authors = self.authors.resolve(ndb, txn);
+
+        if let (AuthorFilter::FollowSet { author, identifier }, Some(members)) =
+            (&self.authors, &authors)
+        {
+            if members.is_empty() {
+                // fetch the follow set first, the feed is rebuilt once we
+                // have it
+                return FilterState::ready(vec![follow_set_filter(author, identifier)]);
+            }
+        }
+
+        let remote = self.build_filters(hashtags, authors.as_deref(), false);
+        if self.mute_words.is_empty() {
+            return FilterState::ready(remote);
+        }
+
+        let local = NdbQueryPackage {
+            filters: self.build_filters(hashtags, authors.as_deref(), true),
+            // only used for logging
+            kind: ValidKind::One,
+        };
+
+        FilterState::ready_hybrid(HybridFilter::split(vec![local], remote))
+    }
+
+    /// Whether the feed is waiting on a follow set that has since shown up
+    /// in nostrdb
+    pub fn follow_set_ready(&self, ndb: &Ndb, txn: &Transaction) -> bool {
+        matches!(&self.authors, AuthorFilter::FollowSet { .. })
+            && self
+                .authors
+                .resolve(ndb, txn)
+                .is_some_and(|members| !members.is_empty())
+    }
+
+    fn build_filters(
+        &self,
+        hashtags: &[String],
+        authors: Option<&[Pubkey]>,
+        with_mute_words: bool,
+    ) -> Vec<Filter> {
+        let builder = || {
+            let mut builder = Filter::new().kinds(self.kinds()).limit(default_limit());
+            if let Some(authors) = authors {
+                builder = builder.authors(authors.iter().map(Pubkey::bytes));
+            }
+            if with_mute_words {
+                builder = self.mute_words_filter(builder);
+            }
+            builder
+        };
+
+        let mut filters: Vec<Filter> = hashtags
+            .iter()
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| builder().tags([tag.to_lowercase().as_str()], 't').build())
+            .collect();
+
+        if filters.is_empty() {
+            filters.push(builder().build());
+        }
+
+        filters
+    }
+
+    fn mute_words_filter(&self, builder: FilterBuilder) -> FilterBuilder {
+        let mute_words = self.mute_words.clone();
+        builder.custom(move |note| !mentions_any(note.content(), &mute_words))
+    }
+}
+
+/// Case-insensitive check for any of `words` in `content`. `words` must be
+/// lowercase.
+fn mentions_any(content: &str, words: &[String]) -> bool {
+    if words.is_empty() {
+        return false;
+    }
+
+    let content = content.to_lowercase();
+    words.iter().any(|word| content.contains(word.as_str()))
+}
+
+fn follow_set_filter(author: &Pubkey, identifier: &str) -> Filter {
+    Filter::new()
+        .kinds([KIND_FOLLOW_SET])
+        .authors([author.bytes()])
+        .tags([identifier], 'd')
+        .limit(1)
+        .build()
+}
+
+/// The public `p` tags of the newest version of a follow set
+fn follow_set_members(
+    ndb: &Ndb,
+    txn: &Transaction,
+    author: &Pubkey,
+    identifier: &str,
+) -> Option<Vec<Pubkey>> {
+    let results = ndb
+        .query(txn, &[follow_set_filter(author, identifier)], 1)
+        .ok()?;
+    let note = &results.first()?.note;
+
+    let mut members = Vec::new();
+    for tag in note.tags() {
+        if tag.count() < 2 || tag.get_str(0) != Some("p") {
+            continue;
+        }
+
+        if let Some(pk) = tag.get_id(1) {
+            members.push(Pubkey::new(*pk));
+        }
+    }
+
+    Some(members)
+}
+
+impl TokenSerializable for AuthorFilter {
+    fn serialize_tokens(&self, writer: &mut TokenWriter) {
+        match self {
+            AuthorFilter::Anyone => writer.write_token("anyone"),
+            AuthorFilter::Pubkeys(pubkeys) => {
+                writer.write_token("pubkeys");
+                let pubkeys: Vec<String> = pubkeys.iter().map(Pubkey::hex).collect();
+                writer.write_token(&pubkeys.join(" "));
+            }
+            AuthorFilter::FollowSet { author, identifier } => {
+                writer.write_token("follow_set");
+                writer.write_token(&author.hex());
+                writer.write_token(&urlencoding::encode(identifier));
+            }
+        }
+    }
+
+    fn parse_from_tokens<'a>(parser: &mut TokenParser<'a>) -> Result<Self, ParseError<'a>> {
+        TokenParser::alt(
+            parser,
+            &[
+                |p| {
+                    p.parse_token("anyone")?;
+                    Ok(AuthorFilter::Anyone)
+                },
+                |p| {
+                    p.parse_token("pubkeys")?;
+                    let pubkeys = p
+                        .pull_token()?
+                        .split_whitespace()
+                        .map(|pk| Pubkey::from_hex(pk).map_err(|_| ParseError::HexDecodeFailed))
+                        .collect::<Result<_, _>>()?;
+                    Ok(AuthorFilter::Pubkeys(pubkeys))
+                },
+                |p| {
+                    p.parse_token("follow_set")?;
+                    let author = Pubkey::from_hex(p.pull_token()?)
+                        .map_err(|_| ParseError::HexDecodeFailed)?;
+                    let identifier = urlencoding::decode(p.pull_token()?)
+                        .map_err(|_| ParseError::DecodeFailed)?
+                        .into_owned();
+                    Ok(AuthorFilter::FollowSet { author, identifier })
+                },
+            ],
+        )
+    }
+}
+
+impl TokenSerializable for ChannelFilter {
+    fn serialize_tokens(&self, writer: &mut TokenWriter) {
+        self.authors.serialize_tokens(writer);
+
+        let kinds: Vec<String> = self.kinds.iter().map(u64::to_string).collect();
+        writer.write_token(&kinds.join(" "));
+
+        let mute_words: Vec<_> = self
+            .mute_words
+            .iter()
+            .map(|word| urlencoding::encode(word))
+            .collect();
+        writer.write_token(&mute_words.join(" "));
+    }
+
+    fn parse_from_tokens<'a>(parser: &mut TokenParser<'a>) -> Result<Self, ParseError<'a>> {
+        let authors = AuthorFilter::parse_from_tokens(parser)?;
+
+        let kinds = parser
+            .pull_token()?
+            .split_whitespace()
+            .map(|kind| kind.parse().map_err(|_| ParseError::DecodeFailed))
+            .collect::<Result<_, _>>()?;
+
+        let mute_words = parser
+            .pull_token()?
+            .split_whitespace()
+            .map(|word| {
+                urlencoding::decode(word)
+                    .map(|word| word.into_owned())
+                    .map_err(|_| ParseError::DecodeFailed)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ChannelFilter {
+            authors,
+            kinds,
+            mute_words,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inputs() {
+        let pk = Pubkey::new([5; 32]);
+        let npub = pk.npub().unwrap();
+
+        assert_eq!(
+            AuthorFilter::parse_pubkeys(&format!("{npub}, {} nope", pk.hex())),
+            vec![pk]
+        );
+        assert_eq!(
+            AuthorFilter::parse_follow_set(&format!("{npub}/devs")),
+            Some(AuthorFilter::FollowSet {
+                author: pk,
+                identifier: "devs".to_owned()
+            })
+        );
+        assert_eq!(AuthorFilter::parse_follow_set(&format!("{npub}/")), None);
+
+        assert_eq!(ChannelFilter::parse_kinds("30023, 1 x 1"), vec![1, 30023]);
+        assert_eq!(
+            ChannelFilter::parse_mute_words("GM, price talk ,"),
+            vec!["gm".to_owned(), "price talk".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_tokens_roundtrip() {
+        let filters = [
+            ChannelFilter::default(),
+            ChannelFilter {
+                authors: AuthorFilter::Pubkeys(vec![Pubkey::new([1; 32]), Pubkey::new([2; 32])]),
+                kinds: vec![1, 30023],
+                mute_words: vec![],
+            },
+            ChannelFilter {
+                authors: AuthorFilter::FollowSet {
+                    author: Pubkey::new([3; 32]),
+                    identifier: "a:b c".to_owned(),
+                },
+                kinds: vec![],
+                mute_words: vec!["price talk".to_owned(), "gm".to_owned()],
+            },
+        ];
+
+        for filter in filters {
+            let mut writer = TokenWriter::default();
+            filter.serialize_tokens(&mut writer);
+            let data = writer.str().split(':').collect::<Vec<&str>>();
+            let mut parser = TokenParser::new(&data);
+
+            assert_eq!(
+                ChannelFilter::parse_from_tokens(&mut parser).unwrap(),
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_mentions_any() {
+        let words = vec!["price talk".to_owned()];
+        assert!(mentions_any("No Price Talk please", &words));
+        assert!(!mentions_any("talk about prices", &words));
+        assert!(!mentions_any("anything", &[]));
+    }
+}
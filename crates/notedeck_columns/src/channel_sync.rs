@@ -0,0 +1,308 @@
+//! Sync the channel list between the account's devices. The list is
+//! published as a NIP-78 application data event (kind 30078), encrypted to
+//! the account itself with NIP-44. The newest list wins.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use enostr::{ClientMessage, Pubkey, RelayPool, SecretKey};
+use nostrdb::{IngestMetadata, Ndb, NoteBuilder, Transaction};
+use notedeck::{AppContext, CHANNELS_APP_DATA_ID, KIND_APP_DATA};
+use tracing::{error, info};
+
+use crate::{
+    channels::{ChannelList, ChannelsCache},
+    storage,
+    subscriptions::Subscriptions,
+    timeline::{TimelineCache, TimelineKind},
+};
+
+/// Wait this long after a failed publish before trying again. The delay
+/// doubles with every failure in a row, up to [`PUBLISH_RETRY_MAX`].
+const PUBLISH_RETRY_BASE: Duration = Duration::from_secs(5);
+const PUBLISH_RETRY_MAX: Duration = Duration::from_secs(60 * 10);
+
+struct PublishRetry {
+    failures: u32,
+    at: Instant,
+}
+
+#[derive(Default)]
+pub struct ChannelSync {
+    /// The account whose channel list we're syncing
+    account: Option<Pubkey>,
+    /// created_at of the newest list we published or merged, per account
+    synced_at: HashMap<Pubkey, u64>,
+    /// Accounts whose relays finished sending us their synced list
+    fetched: HashSet<Pubkey>,
+    /// Accounts whose list from before syncing existed was published this
+    /// session. A list from another device that shows up late is merged
+    /// into it instead of losing to it.
+    published_unsynced: HashSet<Pubkey>,
+    /// When to try again after publishing failed, per account
+    retry: HashMap<Pubkey, PublishRetry>,
+}
+
+impl ChannelSync {
+    /// The relays finished sending the synced list of `account`
+    pub fn on_eose(&mut self, account: &Pubkey) {
+        self.fetched.insert(*account);
+    }
+
+    /// Merge lists published by the selected account's other devices, and
+    /// publish ours when it changed. Accounts without a secret key can't
+    /// do either.
+    pub fn update(
+        &mut self,
+        channels_cache: &mut ChannelsCache,
+        timeline_cache: &mut TimelineCache,
+        subs: &mut Subscriptions,
+        ctx: &mut AppContext<'_>,
+    ) {
+        let Some(kp) = ctx.accounts.selected_filled() else {
+            return;
+        };
+        let account = *kp.pubkey;
+        let seckey = kp.secret_key.clone();
+
+        let Some((created_at, content)) = self.poll_newest(&account, ctx) else {
+            return self.publish_changes(&account, &seckey, channels_cache, ctx);
+        };
+
+        let channel_list = channels_cache.get_channels_mut(ctx.i18n, &account);
+        if let Some(closed) = self.merge(&account, &seckey, created_at, &content, channel_list) {
+            for kind in &closed {
+                if let Err(err) = timeline_cache.pop(kind, ctx.ndb, ctx.pool) {
+                    error!("Failed to close channel timeline after sync: {err}");
+                }
+            }
+
+            channels_cache
+                .get_channels_mut(ctx.i18n, &account)
+                .subscribe_all(subs, timeline_cache, ctx);
+            storage::save_channels_cache(ctx.path, channels_cache);
+        }
+
+        self.publish_changes(&account, &seckey, channels_cache, ctx);
+    }
+
+    /// The newest synced list that arrived since the last frame. When the
+    /// account was just selected, the one we already have in nostrdb. The
+    /// relays' copy comes in through the subscription that
+    /// `Accounts::select_account` sets up.
+    fn poll_newest(&mut self, account: &Pubkey, ctx: &mut AppContext<'_>) -> Option<(u64, String)> {
+        let txn = Transaction::new(ctx.ndb).ok()?;
+
+        let note_keys = if self.account != Some(*account) {
+            self.account = Some(*account);
+            let filter = &ctx
+                .accounts
+                .get_selected_account()
+                .data
+                .app_data
+                .channels_filter;
+            ctx.ndb
+                .query(&txn, std::slice::from_ref(filter), 1)
+                .map(|results| results.into_iter().map(|r| r.note_key).collect())
+                .unwrap_or_default()
+        } else {
+            ctx.ndb
+                .poll_for_notes(ctx.accounts.get_subs().channels.local, 4)
+        };
+
+        note_keys
+            .into_iter()
+            .filter_map(|key| ctx.ndb.get_note_by_key(&txn, key).ok())
+            .max_by_key(|note| note.created_at())
+            .map(|note| (note.created_at(), note.content().to_owned()))
+    }
+
+    /// Merge the encrypted list another device published at `created_at`.
+    /// Returns the timelines to close if the list changed.
+    fn merge(
+        &mut self,
+        account: &Pubkey,
+        seckey: &SecretKey,
+        created_at: u64,
+        content: &str,
+        channel_list: &mut ChannelList,
+    ) -> Option<Vec<TimelineKind>> {
+        let synced_at = self.synced_at.entry(*account).or_default();
+        if created_at <= *synced_at {
+            // our own list coming back, or one we already merged
+            return None;
+        }
+        *synced_at = created_at;
+
+        // lists from before syncing existed keep their channels
+        let keep_local = channel_list.modified_at == 0 || self.published_unsynced.remove(account);
+        if created_at <= channel_list.modified_at && !keep_local {
+            info!("Local channel list is newer than the synced one");
+            return None;
+        }
+
+        let json = match enostr::nip44::decrypt(seckey, account, content) {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Could not decrypt synced channel list: {err}");
+                return None;
+            }
+        };
+        let remote = storage::parse_synced_channel_list(&json)?;
+
+        info!("Merging channel list synced at {created_at}");
+        Some(channel_list.merge_remote(remote, created_at, keep_local))
+    }
+
+    fn publish_changes(
+        &mut self,
+        account: &Pubkey,
+        seckey: &SecretKey,
+        channels_cache: &mut ChannelsCache,
+        ctx: &mut AppContext<'_>,
+    ) {
+        let channel_list = channels_cache.get_channels_mut(ctx.i18n, account);
+        let synced_at = self.synced_at.get(account).copied().unwrap_or(0);
+
+        // a list saved before syncing existed is published once the relays
+        // had their chance to send us a newer one
+        if channel_list.modified_at == 0
+            && synced_at == 0
+            && !channel_list.channels.is_empty()
+            && self.fetched.contains(account)
+        {
+            channel_list.touch();
+            self.published_unsynced.insert(*account);
+        }
+
+        if channel_list.modified_at <= synced_at {
+            return;
+        }
+
+        // nothing would get the list, it goes out once a relay reconnects
+        if !ctx.pool.has_healthy_relay() {
+            return;
+        }
+
+        let now = Instant::now();
+        if self.retry.get(account).is_some_and(|retry| now < retry.at) {
+            return;
+        }
+
+        let published_at = publish(ctx.ndb, ctx.pool, account, seckey, channel_list);
+        self.on_published(account, published_at, channel_list.modified_at, now);
+    }
+
+    /// Record the outcome of publishing the list modified at
+    /// `modified_at`. It only counts as synced when it went out; failures
+    /// are retried on a backoff.
+    fn on_published(
+        &mut self,
+        account: &Pubkey,
+        published_at: Option<u64>,
+        modified_at: u64,
+        now: Instant,
+    ) {
+        let Some(published_at) = published_at else {
+            let failures = self.retry.get(account).map_or(0, |retry| retry.failures) + 1;
+            let delay = retry_delay(failures);
+            error!("Publishing the channel list failed, retrying in {delay:?}");
+            self.retry.insert(
+                *account,
+                PublishRetry {
+                    failures,
+                    at: now + delay,
+                },
+            );
+            return;
+        };
+
+        self.retry.remove(account);
+        self.synced_at
+            .insert(*account, published_at.max(modified_at));
+    }
+}
+
+fn retry_delay(failures: u32) -> Duration {
+    PUBLISH_RETRY_BASE
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(PUBLISH_RETRY_MAX)
+}
+
+/// Publish `channel_list` encrypted to `account` itself. Returns the
+/// event's created_at.
+fn publish(
+    ndb: &Ndb,
+    pool: &mut RelayPool,
+    account: &Pubkey,
+    seckey: &SecretKey,
+    channel_list: &ChannelList,
+) -> Option<u64> {
+    let json = storage::synced_channel_list_json(channel_list)?;
+    let content = match enostr::nip44::encrypt(seckey, account, &json) {
+        Ok(content) => content,
+        Err(err) => {
+            error!("Could not encrypt channel list: {err}");
+            return None;
+        }
+    };
+
+    let Some(note) = NoteBuilder::new()
+        .kind(KIND_APP_DATA)
+        .content(&content)
+        .start_tag()
+        .tag_str("d")
+        .tag_str(CHANNELS_APP_DATA_ID)
+        .sign(&seckey.secret_bytes())
+        .build()
+    else {
+        error!("Could not build channel list event");
+        return None;
+    };
+
+    let Ok(event) = &ClientMessage::event(&note) else {
+        error!("Could not build channel list message");
+        return None;
+    };
+
+    let Ok(json) = event.to_json() else {
+        error!("Could not serialize channel list event");
+        return None;
+    };
+
+    let _ = ndb.process_event_with(&json, IngestMetadata::new().client(true));
+    pool.send(event);
+
+    info!("Published channel list");
+    Some(note.created_at())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_publish_is_retried() {
+        let account = Pubkey::new([1; 32]);
+        let mut sync = ChannelSync::default();
+        let now = Instant::now();
+
+        sync.on_published(&account, None, 100, now);
+        assert_eq!(sync.synced_at.get(&account), None);
+        assert_eq!(sync.retry[&account].at, now + PUBLISH_RETRY_BASE);
+
+        sync.on_published(&account, None, 100, now);
+        assert_eq!(sync.retry[&account].at, now + PUBLISH_RETRY_BASE * 2);
+
+        sync.on_published(&account, Some(120), 100, now);
+        assert_eq!(sync.synced_at[&account], 120);
+        assert!(!sync.retry.contains_key(&account));
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        assert_eq!(retry_delay(1), PUBLISH_RETRY_BASE);
+        assert_eq!(retry_delay(3), PUBLISH_RETRY_BASE * 4);
+        assert_eq!(retry_delay(u32::MAX), PUBLISH_RETRY_MAX);
+    }
+}
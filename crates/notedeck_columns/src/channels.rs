@@ -1,59 +1,244 @@
-use std::collections::HashMap;
-use enostr::Pubkey;
-use nostrdb::Transaction;
-use notedeck::{AppContext, Localization, FALLBACK_PUBKEY};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use enostr::{NoteId, Pubkey};
+use nostrdb::{Ndb, Transaction};
+use notedeck::{AppContext, Localization, NoteRef, FALLBACK_PUBKEY};
 use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::{
+    channel_filter::ChannelFilter,
+    nip28,
+    nip29::{self, GroupRef},
+    relay_config::canonical_relay_url,
     route::{Route, Router},
-    timeline::{TimelineCache, TimelineKind},
+    timeline::{Timeline, TimelineCache, TimelineKind},
     subscriptions::Subscriptions,
 };
 
+/// Where a channel's messages come from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChannelKind {
+    /// Notes tagged with any of the channel's hashtags, narrowed down by
+    /// its [`ChannelFilter`]
+    Hashtags,
+    /// A NIP-28 public chat, identified by its kind 40 creation event
+    PublicChat(NoteId),
+    /// A NIP-29 group hosted on a single relay
+    Group(GroupRef),
+}
+
+impl ChannelKind {
+    pub fn timeline_kind(
+        &self,
+        hashtags: &[String],
+        filter: &ChannelFilter,
+        relays: &ChannelRelays,
+    ) -> TimelineKind {
+        let timeline_kind = match self {
+            ChannelKind::Hashtags if filter.is_empty() => TimelineKind::Hashtag(hashtags.to_vec()),
+            ChannelKind::Hashtags => TimelineKind::Feed(hashtags.to_vec(), filter.clone()),
+            ChannelKind::PublicChat(channel_id) => TimelineKind::PublicChat(*channel_id),
+            // groups always live on their own relay
+            ChannelKind::Group(group) => return TimelineKind::Group(group.clone()),
+        };
+
+        if relays.urls.is_empty() {
+            timeline_kind
+        } else {
+            TimelineKind::Scoped(relays.urls.clone(), Box::new(timeline_kind))
+        }
+    }
+
+    pub fn public_chat_id(&self) -> Option<&NoteId> {
+        match self {
+            ChannelKind::PublicChat(channel_id) => Some(channel_id),
+            ChannelKind::Hashtags | ChannelKind::Group(_) => None,
+        }
+    }
+
+    pub fn group(&self) -> Option<&GroupRef> {
+        match self {
+            ChannelKind::Group(group) => Some(group),
+            ChannelKind::Hashtags | ChannelKind::PublicChat(_) => None,
+        }
+    }
+}
+
+/// Relays a channel uses instead of the global relay config
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelRelays {
+    /// Canonical relay urls. Empty means the global relays.
+    pub urls: BTreeSet<String>,
+    /// Only read from the channel, never post to it
+    pub read_only: bool,
+}
+
+impl ChannelRelays {
+    pub fn new(urls: BTreeSet<String>, read_only: bool) -> Self {
+        Self { urls, read_only }
+    }
+
+    /// Parse a comma or whitespace separated list of relay urls. Anything
+    /// that isn't a websocket url is skipped.
+    pub fn parse_urls(input: &str) -> BTreeSet<String> {
+        input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(canonical_relay_url)
+            .collect()
+    }
+}
+
+/// The user-editable parts of a channel, as submitted by the channel dialog
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelEdit {
+    pub name: String,
+    pub hashtags: Vec<String>,
+    pub filter: ChannelFilter,
+    pub relays: ChannelRelays,
+}
+
+/// A named, collapsible group of channels in the sidebar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelSection {
+    pub id: Uuid,
+    pub name: String,
+    pub collapsed: bool,
+}
+
+impl ChannelSection {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            collapsed: false,
+        }
+    }
+}
+
+/// Where a channel is moved to when it is dropped in the sidebar
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelPlacement {
+    /// Right before the channel at this index, in the same section
+    Before(usize),
+    /// At the end of a section. None is the default section.
+    Section(Option<Uuid>),
+    /// At the end of the starred channels
+    Starred,
+}
+
 /// Represents a single channel (like a Slack channel)
-/// Each channel filters notes by hashtag(s)
+/// Each channel filters notes by hashtag(s), or follows a NIP-28 public chat
 #[derive(Clone, Debug)]
 pub struct Channel {
     pub id: Uuid,
     pub name: String,
     pub hashtags: Vec<String>,
+    /// Only used by hashtag channels
+    pub filter: ChannelFilter,
+    pub kind: ChannelKind,
+    pub relays: ChannelRelays,
     pub timeline_kind: TimelineKind,
     pub router: Router<Route>,
     pub unread_count: usize,
+    /// created_at of the newest message seen in this channel. Persisted.
+    pub last_read: u64,
+    /// The newest note seen while the channel was visible
+    pub read_marker: Option<NoteRef>,
+    /// Where to draw the "new messages" divider. This is a snapshot of
+    /// `last_read` taken when the channel was selected, so the divider
+    /// stays put while the channel is being read.
+    pub unread_divider: Option<u64>,
+    /// The sidebar section the channel is listed under. None, or a section
+    /// that no longer exists, is the default section.
+    pub section: Option<Uuid>,
+    /// Starred channels are listed above every section
+    pub pinned: bool,
+    /// Archived channels keep their definition but aren't subscribed
+    pub archived: bool,
     // Note: subscribed state is tracked by TimelineCache, not here
 }
 
 impl Channel {
     pub fn new(name: String, hashtags: Vec<String>) -> Self {
-        let id = Uuid::new_v4();
-        let timeline_kind = TimelineKind::Hashtag(hashtags.clone());
-        let router = Router::new(vec![Route::timeline(timeline_kind.clone())]);
+        Self::with_id(Uuid::new_v4(), name, hashtags)
+    }
 
-        Self {
-            id,
+    pub fn with_id(id: Uuid, name: String, hashtags: Vec<String>) -> Self {
+        Self::with_kind(id, name, hashtags, ChannelKind::Hashtags)
+    }
+
+    pub fn public_chat(name: String, channel_id: NoteId) -> Self {
+        Self::with_kind(
+            Uuid::new_v4(),
             name,
-            hashtags,
-            timeline_kind,
-            router,
-            unread_count: 0,
-        }
+            vec![],
+            ChannelKind::PublicChat(channel_id),
+        )
     }
 
-    pub fn with_id(id: Uuid, name: String, hashtags: Vec<String>) -> Self {
-        let timeline_kind = TimelineKind::Hashtag(hashtags.clone());
+    pub fn group(name: String, group: GroupRef) -> Self {
+        Self::with_kind(Uuid::new_v4(), name, vec![], ChannelKind::Group(group))
+    }
+
+    pub fn with_kind(id: Uuid, name: String, hashtags: Vec<String>, kind: ChannelKind) -> Self {
+        let filter = ChannelFilter::default();
+        let relays = ChannelRelays::default();
+        let timeline_kind = kind.timeline_kind(&hashtags, &filter, &relays);
         let router = Router::new(vec![Route::timeline(timeline_kind.clone())]);
 
         Self {
             id,
             name,
             hashtags,
+            filter,
+            kind,
+            relays,
             timeline_kind,
             router,
             unread_count: 0,
+            last_read: unix_now(),
+            read_marker: None,
+            unread_divider: None,
+            section: None,
+            pinned: false,
+            archived: false,
+        }
+    }
+
+    /// Use `relays` instead of the global relays. This is for channels
+    /// that aren't subscribed yet, otherwise see [`ChannelList::edit_channel`].
+    pub fn with_relays(mut self, relays: ChannelRelays) -> Self {
+        self.relays = relays;
+        self.refresh_timeline_kind();
+        self
+    }
+
+    /// Narrow the channel's feed down with `filter`. Like
+    /// [`Self::with_relays`], only for channels that aren't subscribed yet.
+    pub fn with_filter(mut self, filter: ChannelFilter) -> Self {
+        self.filter = filter;
+        self.refresh_timeline_kind();
+        self
+    }
+
+    /// Recompute the channel's timeline after its hashtags, filter or
+    /// relays changed
+    fn refresh_timeline_kind(&mut self) {
+        let timeline_kind = self
+            .kind
+            .timeline_kind(&self.hashtags, &self.filter, &self.relays);
+        if timeline_kind != self.timeline_kind {
+            self.timeline_kind = timeline_kind;
+            self.router = Router::new(vec![Route::timeline(self.timeline_kind.clone())]);
         }
     }
 
+    /// Whether messages can be posted from this channel
+    pub fn can_post(&self) -> bool {
+        !self.relays.read_only
+    }
+
     pub fn router(&self) -> &Router<Route> {
         &self.router
     }
@@ -61,23 +246,113 @@ impl Channel {
     pub fn router_mut(&mut self) -> &mut Router<Route> {
         &mut self.router
     }
+
+    /// Number of units in the channel's timeline that are newer than the
+    /// read marker. Units are sorted newest first, so we can stop at the
+    /// first one we've already seen. Notes dated in the future don't count,
+    /// the read marker never gets past now.
+    pub fn count_unread(&self, timeline: &Timeline) -> usize {
+        let units = &timeline.current_view().units;
+        let now = unix_now();
+        (0..units.len())
+            .map_while(|i| units.get(i))
+            .map(|unit| unit.get_latest_ref().created_at)
+            .skip_while(|created_at| *created_at > now)
+            .take_while(|created_at| *created_at > self.last_read)
+            .count()
+    }
+
+    /// Record `latest` as the newest note seen in this channel. Returns true
+    /// if the persisted read marker moved forward.
+    pub fn mark_read(&mut self, latest: Option<NoteRef>) -> bool {
+        self.unread_count = 0;
+
+        let Some(latest) = latest else {
+            return false;
+        };
+
+        self.read_marker = Some(latest);
+        // a note dated in the future would leave everything posted until
+        // then marked as read
+        let created_at = latest.created_at.min(unix_now());
+        if created_at <= self.last_read {
+            return false;
+        }
+
+        self.last_read = created_at;
+        true
+    }
+}
+
+/// Relay-scoped timelines, like groups, may live on relays we don't
+/// otherwise use. Add them to the pool so we can subscribe to them.
+pub fn connect_timeline_relays(pool: &mut enostr::RelayPool, timeline_kind: &TimelineKind) {
+    if let Some(relays) = timeline_kind.relays() {
+        connect_relays(pool, relays);
+    }
+}
+
+/// Owner of the relays pinned by [`connect_relays`]
+const CHANNEL_RELAYS: &str = "channels";
+
+/// Add the relays we don't have yet to the pool. They are pinned, so
+/// updates to the account's relay list don't drop them.
+pub fn connect_relays<'a>(pool: &mut enostr::RelayPool, relays: impl IntoIterator<Item = &'a str>) {
+    if pool.is_mock() {
+        // the mock relay answers for every relay
+        return;
+    }
+
+    for relay_url in relays {
+        pool.pin_url(relay_url, CHANNEL_RELAYS);
+        if pool.has(relay_url) {
+            continue;
+        }
+
+        let wakeup = || {}; // Wakeup closure for relay events
+        if let Err(e) = pool.add_url(relay_url.to_owned(), wakeup) {
+            error!("Failed to add relay {}: {}", relay_url, e);
+        } else {
+            info!("Added channel relay: {}", relay_url);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 /// Contains all channels for a user
 #[derive(Clone, Debug)]
 pub struct ChannelList {
+    /// In sidebar order
     pub channels: Vec<Channel>,
+    pub sections: Vec<ChannelSection>,
     pub selected: usize,
+    /// When the parts of the list that are synced between devices last
+    /// changed. 0 if they never were synced.
+    pub modified_at: u64,
 }
 
 impl ChannelList {
     pub fn new() -> Self {
         Self {
             channels: Vec::new(),
+            sections: Vec::new(),
             selected: 0,
+            modified_at: 0,
         }
     }
 
+    /// Note that the synced parts of the list changed, so they get
+    /// published to the account's other devices
+    pub fn touch(&mut self) {
+        self.modified_at = unix_now().max(self.modified_at + 1);
+    }
+
     pub fn default_channels(_i18n: &mut Localization) -> Self {
         // Return empty channel list - user will create their own channels
         Self::new()
@@ -87,13 +362,13 @@ impl ChannelList {
         self.channels.push(channel);
         // Auto-select the newly added channel
         self.selected = self.channels.len() - 1;
+        self.touch();
     }
 
     pub fn edit_channel(
         &mut self,
         index: usize,
-        name: String,
-        hashtags: Vec<String>,
+        edit: ChannelEdit,
         timeline_cache: &mut TimelineCache,
         ndb: &mut nostrdb::Ndb,
         pool: &mut enostr::RelayPool,
@@ -103,24 +378,62 @@ impl ChannelList {
         }
 
         let channel = &mut self.channels[index];
-
-        // Unsubscribe from old timeline if hashtags changed
         let old_timeline_kind = channel.timeline_kind.clone();
-        let new_timeline_kind = TimelineKind::Hashtag(hashtags.clone());
 
-        if old_timeline_kind != new_timeline_kind {
+        // Update channel data
+        channel.name = edit.name;
+        channel.hashtags = edit.hashtags;
+        channel.filter = edit.filter;
+        channel.relays = edit.relays;
+        channel.refresh_timeline_kind();
+
+        // Unsubscribe from old timeline if the feed changed. Archived
+        // channels have nothing to unsubscribe from.
+        if old_timeline_kind != channel.timeline_kind && !channel.archived {
             if let Err(err) = timeline_cache.pop(&old_timeline_kind, ndb, pool) {
                 error!("Failed to unsubscribe from old channel timeline: {err}");
             }
         }
 
-        // Update channel data
-        channel.name = name;
-        channel.hashtags = hashtags.clone();
-        channel.timeline_kind = new_timeline_kind.clone();
-        channel.router = Router::new(vec![Route::timeline(new_timeline_kind)]);
-
         info!("Updated channel: {}", channel.name);
+        self.touch();
+        true
+    }
+
+    /// A feed opened before its follow set was in nostrdb has nothing to
+    /// show. Once the follow set arrives, drop the empty timeline so it can
+    /// be reopened with the set's members. Returns true if it was dropped.
+    pub fn reload_pending_feed(
+        &self,
+        index: usize,
+        timeline_cache: &mut TimelineCache,
+        ndb: &mut nostrdb::Ndb,
+        pool: &mut enostr::RelayPool,
+    ) -> bool {
+        let Some(channel) = self.channels.get(index) else {
+            return false;
+        };
+
+        let is_empty = timeline_cache
+            .get(&channel.timeline_kind)
+            .is_some_and(|timeline| timeline.all_or_any_entries().is_empty());
+        if !is_empty {
+            return false;
+        }
+
+        let ready = {
+            let txn = Transaction::new(ndb).expect("txn");
+            channel.filter.follow_set_ready(ndb, &txn)
+        };
+        if !ready {
+            return false;
+        }
+
+        if let Err(err) = timeline_cache.pop(&channel.timeline_kind, ndb, pool) {
+            error!("Failed to drop pending channel feed: {err}");
+            return false;
+        }
+
         true
     }
 
@@ -132,30 +445,346 @@ impl ChannelList {
         pool: &mut enostr::RelayPool,
     ) -> Option<Channel> {
         if index < self.channels.len() && self.channels.len() > 1 {
+            let is_last_active = !self.channels[index].archived && self.num_active() == 1;
+            if is_last_active {
+                return None;
+            }
+
             let removed = self.channels.remove(index);
 
             // Unsubscribe from the timeline
-            if let Err(err) = timeline_cache.pop(&removed.timeline_kind, ndb, pool) {
+            if removed.archived {
+                info!("Removed archived channel: {}", removed.name);
+            } else if let Err(err) = timeline_cache.pop(&removed.timeline_kind, ndb, pool) {
                 error!("Failed to unsubscribe from channel timeline: {err}");
             } else {
                 info!("Unsubscribed from removed channel: {}", removed.name);
             }
 
-            // Adjust selected index if needed
-            if self.selected >= self.channels.len() {
-                self.selected = self.channels.len() - 1;
+            // Keep the selection on an active channel
+            if self.selected > index {
+                self.selected -= 1;
+            } else if self.selected == index {
+                self.selected = self.first_active().unwrap_or(0);
             }
 
+            self.touch();
+
             Some(removed)
         } else {
             None
         }
     }
 
-    pub fn select_channel(&mut self, index: usize) {
-        if index < self.channels.len() {
+    /// Move the channel at `from` to `placement`. It takes on the section
+    /// and starred state of where it lands, and stays selected if it was.
+    pub fn move_channel(&mut self, from: usize, placement: ChannelPlacement) {
+        let Some(channel) = self.channels.get(from) else {
+            return;
+        };
+        if channel.archived {
+            return;
+        }
+
+        let (section, pinned, to) = match placement {
+            ChannelPlacement::Before(to) => match self.channels.get(to) {
+                Some(target) if to != from && !target.archived => {
+                    (self.section_of(target), target.pinned, to)
+                }
+                _ => return,
+            },
+            ChannelPlacement::Section(section) => (section, false, self.channels.len()),
+            ChannelPlacement::Starred => (channel.section, true, self.channels.len()),
+        };
+
+        let selected_id = self.selected_channel().map(|c| c.id);
+
+        let mut channel = self.channels.remove(from);
+        channel.section = section;
+        channel.pinned = pinned;
+        let to = if to > from { to - 1 } else { to };
+        self.channels.insert(to, channel);
+
+        if let Some(index) =
+            selected_id.and_then(|id| self.channels.iter().position(|c| c.id == id))
+        {
             self.selected = index;
         }
+
+        self.touch();
+    }
+
+    /// Star or unstar the channel at `index`
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) {
+        if let Some(channel) = self.channels.get_mut(index) {
+            channel.pinned = pinned;
+            self.touch();
+        }
+    }
+
+    /// Archive the channel at `index`, closing its subscription. The last
+    /// active channel can't be archived. Returns true if it was archived.
+    pub fn archive_channel(
+        &mut self,
+        index: usize,
+        timeline_cache: &mut TimelineCache,
+        ndb: &mut nostrdb::Ndb,
+        pool: &mut enostr::RelayPool,
+    ) -> bool {
+        let active = self.num_active();
+        let Some(channel) = self.channels.get_mut(index) else {
+            return false;
+        };
+        if channel.archived || active <= 1 {
+            return false;
+        }
+
+        if let Err(err) = timeline_cache.pop(&channel.timeline_kind, ndb, pool) {
+            error!("Failed to unsubscribe from archived channel timeline: {err}");
+        }
+
+        channel.archived = true;
+        channel.unread_count = 0;
+        channel.unread_divider = None;
+        channel.read_marker = None;
+        info!("Archived channel: {}", channel.name);
+        self.touch();
+
+        if index == self.selected {
+            if let Some(next) = self.first_active() {
+                self.select_channel(next);
+            }
+        }
+
+        true
+    }
+
+    /// Bring back an archived channel. The caller reopens its timeline.
+    /// Returns true if it was archived.
+    pub fn unarchive_channel(&mut self, index: usize) -> bool {
+        let Some(channel) = self.channels.get_mut(index) else {
+            return false;
+        };
+        if !channel.archived {
+            return false;
+        }
+
+        channel.archived = false;
+        info!("Unarchived channel: {}", channel.name);
+        self.touch();
+        true
+    }
+
+    fn first_active(&self) -> Option<usize> {
+        self.channels.iter().position(|c| !c.archived)
+    }
+
+    /// The section `channel` is listed under, None for the default section
+    pub fn section_of(&self, channel: &Channel) -> Option<Uuid> {
+        channel
+            .section
+            .filter(|id| self.sections.iter().any(|section| section.id == *id))
+    }
+
+    pub fn add_section(&mut self, name: String) -> Uuid {
+        let section = ChannelSection::new(name);
+        let id = section.id;
+        self.sections.push(section);
+        self.touch();
+        id
+    }
+
+    pub fn rename_section(&mut self, id: Uuid, name: String) {
+        if let Some(section) = self.sections.iter_mut().find(|s| s.id == id) {
+            section.name = name;
+            self.touch();
+        }
+    }
+
+    /// Remove a section. Its channels move to the default section.
+    pub fn remove_section(&mut self, id: Uuid) {
+        self.sections.retain(|section| section.id != id);
+        for channel in &mut self.channels {
+            if channel.section == Some(id) {
+                channel.section = None;
+            }
+        }
+        self.touch();
+    }
+
+    pub fn toggle_section(&mut self, id: Uuid) {
+        if let Some(section) = self.sections.iter_mut().find(|s| s.id == id) {
+            section.collapsed = !section.collapsed;
+        }
+    }
+
+    /// Take the synced parts of `remote`, a list another device published
+    /// at `created_at`. Channels we already have keep their read state.
+    /// With `keep_local`, channels and sections only we have are kept
+    /// after the remote ones, otherwise the remote list replaces ours.
+    ///
+    /// Returns the timelines that are no longer used by an active channel,
+    /// for the caller to close.
+    pub fn merge_remote(
+        &mut self,
+        mut remote: ChannelList,
+        created_at: u64,
+        keep_local: bool,
+    ) -> Vec<TimelineKind> {
+        let before: HashSet<TimelineKind> = self.active_timeline_kinds().cloned().collect();
+        let selected_id = self.selected_channel().map(|c| c.id);
+
+        for channel in &mut remote.channels {
+            let Some(local) = self.channels.iter().find(|c| c.id == channel.id) else {
+                continue;
+            };
+
+            channel.last_read = local.last_read;
+            channel.read_marker = local.read_marker;
+            channel.unread_count = local.unread_count;
+            channel.unread_divider = local.unread_divider;
+            if local.timeline_kind == channel.timeline_kind {
+                channel.router = local.router.clone();
+            }
+        }
+
+        let mut has_local_only = false;
+        if keep_local {
+            for channel in self.channels.drain(..) {
+                if !remote.channels.iter().any(|c| c.id == channel.id) {
+                    remote.channels.push(channel);
+                    has_local_only = true;
+                }
+            }
+
+            for section in self.sections.drain(..) {
+                if !remote.sections.iter().any(|s| s.id == section.id) {
+                    remote.sections.push(section);
+                    has_local_only = true;
+                }
+            }
+        }
+
+        self.channels = remote.channels;
+        self.sections = remote.sections;
+        self.modified_at = created_at;
+        if has_local_only {
+            // the other devices don't have these yet
+            self.touch();
+        }
+
+        self.selected = selected_id
+            .and_then(|id| self.channels.iter().position(|c| c.id == id && !c.archived))
+            .or_else(|| self.first_active())
+            .unwrap_or(0);
+
+        let after: HashSet<&TimelineKind> = self.active_timeline_kinds().collect();
+        before
+            .into_iter()
+            .filter(|kind| !after.contains(kind))
+            .collect()
+    }
+
+    fn active_timeline_kinds(&self) -> impl Iterator<Item = &TimelineKind> {
+        self.channels
+            .iter()
+            .filter(|c| !c.archived)
+            .map(|c| &c.timeline_kind)
+    }
+
+    pub fn select_channel(&mut self, index: usize) {
+        match self.channels.get(index) {
+            Some(channel) if !channel.archived => {}
+            _ => return,
+        }
+
+        if index != self.selected {
+            if let Some(previous) = self.channels.get_mut(self.selected) {
+                previous.unread_divider = None;
+            }
+        }
+
+        self.selected = index;
+
+        // remember where we left off so ChatView can show a divider there
+        let channel = &mut self.channels[index];
+        if channel.unread_count > 0 {
+            channel.unread_divider = Some(channel.last_read);
+        }
+    }
+
+    /// Recount unread messages for every channel except the selected one
+    pub fn refresh_unread(&mut self, timeline_cache: &TimelineCache) {
+        let selected = self.selected;
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            if index == selected {
+                continue;
+            }
+
+            if let Some(timeline) = timeline_cache.get(&channel.timeline_kind) {
+                channel.unread_count = channel.count_unread(timeline);
+            }
+        }
+    }
+
+    /// Recount unread messages for the non-selected channels whose
+    /// timelines just received new notes
+    pub fn on_timelines_updated(
+        &mut self,
+        updated: &[TimelineKind],
+        timeline_cache: &TimelineCache,
+    ) {
+        let selected = self.selected;
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            if index == selected || !updated.contains(&channel.timeline_kind) {
+                continue;
+            }
+
+            if let Some(timeline) = timeline_cache.get(&channel.timeline_kind) {
+                channel.unread_count = channel.count_unread(timeline);
+            }
+        }
+    }
+
+    /// Rename public chats and groups after their NIP-28 or NIP-29
+    /// metadata. Returns true if any name changed.
+    pub fn refresh_chat_names(&mut self, ndb: &Ndb, txn: &Transaction) -> bool {
+        let mut changed = false;
+        for channel in &mut self.channels {
+            let name = match &channel.kind {
+                ChannelKind::Hashtags => continue,
+                ChannelKind::PublicChat(channel_id) => {
+                    nip28::channel_metadata(ndb, txn, channel_id).map(|metadata| metadata.name)
+                }
+                ChannelKind::Group(group) => nip29::group_name(ndb, txn, group).map(str::to_owned),
+            };
+
+            let Some(name) = name else {
+                continue;
+            };
+
+            if !name.is_empty() && name != channel.name {
+                info!("Chat renamed: {} -> {}", channel.name, name);
+                channel.name = name;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Find the channel following the public chat `channel_id`
+    pub fn find_public_chat(&self, channel_id: &NoteId) -> Option<usize> {
+        self.channels
+            .iter()
+            .position(|c| c.kind.public_chat_id() == Some(channel_id))
+    }
+
+    /// Find the channel following `group`
+    pub fn find_group(&self, group: &GroupRef) -> Option<usize> {
+        self.channels
+            .iter()
+            .position(|c| c.kind.group() == Some(group))
     }
 
     pub fn selected_channel(&self) -> Option<&Channel> {
@@ -170,6 +799,11 @@ impl ChannelList {
         self.channels.len()
     }
 
+    /// Number of channels that aren't archived
+    pub fn num_active(&self) -> usize {
+        self.channels.iter().filter(|c| !c.archived).count()
+    }
+
     pub fn get_channel(&self, index: usize) -> Option<&Channel> {
         self.channels.get(index)
     }
@@ -194,11 +828,14 @@ impl ChannelList {
         };
 
         for channel in &mut self.channels {
-            // Skip if already subscribed (check TimelineCache directly)
-            if timeline_cache.get(&channel.timeline_kind).is_some() {
+            // Skip archived channels, and those already subscribed (check
+            // TimelineCache directly)
+            if channel.archived || timeline_cache.get(&channel.timeline_kind).is_some() {
                 continue;
             }
 
+            connect_timeline_relays(ctx.pool, &channel.timeline_kind);
+
             if let Some(result) = timeline_cache.open(
                 subs,
                 ctx.ndb,
@@ -228,7 +865,7 @@ impl ChannelList {
         ndb: &mut nostrdb::Ndb,
         pool: &mut enostr::RelayPool,
     ) {
-        for channel in &mut self.channels {
+        for channel in self.channels.iter_mut().filter(|c| !c.archived) {
             if let Err(err) = timeline_cache.pop(&channel.timeline_kind, ndb, pool) {
                 error!("Failed to unsubscribe from channel timeline: {err}");
             } else {
@@ -367,3 +1004,67 @@ impl ChannelsCache {
         &self.account_to_channels
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_group_relay_is_pinned() {
+        let group = GroupRef::new("ws://127.0.0.1:1", "notedeck").unwrap();
+        let relay = group.relay.clone();
+        let mut pool = enostr::RelayPool::new();
+        connect_timeline_relays(&mut pool, &TimelineKind::Group(group));
+        assert!(pool.has(&relay));
+
+        // an update of the account's relay list drops every relay it
+        // doesn't know about, except pinned ones
+        pool.remove_urls(&pool.urls());
+        assert!(pool.has(&relay));
+    }
+
+    // the NIP-28 channel in fixtures/mock_relay/channels.jsonl
+    const CHANNEL: &str = "ca1d0e59866027731fd599de8afa952202440680223295ab54bb82fc8133d2d8";
+
+    #[test]
+    fn test_public_chat_from_mock_relay() {
+        let (_tmp_dir, ndb) = test_utils::test_ndb();
+        let mut pool = test_utils::mock_pool();
+        let channel_id = NoteId::from_hex(CHANNEL).unwrap();
+
+        let filters = nip28::channel_filters(&channel_id);
+        pool.subscribe("chat".to_owned(), filters.clone());
+        // the creation, its metadata update and two messages
+        assert_eq!(test_utils::ingest(&mut pool, &ndb), 4);
+        test_utils::wait_for_query(&ndb, &filters, 4);
+
+        let txn = Transaction::new(&ndb).unwrap();
+        let metadata = nip28::channel_metadata(&ndb, &txn, &channel_id).unwrap();
+        assert_eq!(metadata.name, "notedeck dev");
+        let messages = ndb.query(&txn, &filters[..1], 10).unwrap();
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn test_group_from_mock_relay() {
+        let (_tmp_dir, ndb) = test_utils::test_ndb();
+        let mut pool = test_utils::mock_pool();
+        let group = GroupRef::new("wss://groups.example.com", "notedeck").unwrap();
+
+        // the mock relay answers for the group's relay
+        connect_relays(&mut pool, [group.relay.as_str()]);
+        assert_eq!(pool.urls(), BTreeSet::from([enostr::MockRelay::URL.to_owned()]));
+
+        let filters = nip29::group_filters(&group);
+        pool.subscribe_to(&group.relay, "group".to_owned(), filters.clone());
+        // a chat message and the group metadata
+        assert_eq!(test_utils::ingest(&mut pool, &ndb), 2);
+        test_utils::wait_for_query(&ndb, &filters, 2);
+
+        let txn = Transaction::new(&ndb).unwrap();
+        let messages = ndb.query(&txn, &filters[..1], 10).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].note.content(), "hello group");
+    }
+}
@@ -0,0 +1,269 @@
+//! The selected account's private direct messages. NIP-17 messages are
+//! rumors that are never signed, so nostrdb only has their gift wraps. The
+//! wraps are opened as they arrive and the messages are kept in memory,
+//! where they can be searched. Nothing decrypted is written to disk.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use enostr::nip59::{self, Rumor};
+use enostr::Pubkey;
+use nostrdb::{Filter, NoteKey, Transaction};
+use notedeck::{dm_inbox_relays, AppContext, DeliveryStatus, Outbox, SignedEvent, KIND_DM_RELAYS};
+use poll_promise::Promise;
+use tracing::{debug, error, warn};
+
+use crate::channels;
+use crate::nip17::{self, Participants};
+
+/// Subscription fetching the inbox relays of the people we talk to
+pub const INBOX_RELAYS_SUBID: &str = "dm-inbox-relays";
+
+/// How many gift wraps are opened when an account is selected
+const MAX_INITIAL_WRAPS: i32 = 5000;
+
+/// Messages of one conversation, oldest first
+#[derive(Default)]
+pub struct Conversation {
+    pub messages: Vec<Rumor>,
+    /// Whether messages arrived since the conversation was last open
+    pub unread: bool,
+}
+
+impl Conversation {
+    /// Returns false if we already have the message
+    fn insert(&mut self, rumor: Rumor) -> bool {
+        if self.messages.iter().any(|m| m.id == rumor.id) {
+            return false;
+        }
+
+        let pos = self
+            .messages
+            .partition_point(|m| m.created_at <= rumor.created_at);
+        self.messages.insert(pos, rumor);
+        true
+    }
+
+    pub fn last_activity(&self) -> u64 {
+        self.messages.last().map(|m| m.created_at).unwrap_or(0)
+    }
+}
+
+#[derive(Default)]
+pub struct DirectMessages {
+    /// The account whose messages are loaded
+    account: Option<Pubkey>,
+    /// Gift wraps we already tried to open
+    seen: HashSet<NoteKey>,
+    conversations: HashMap<Participants, Conversation>,
+    /// Messages we sent, and the wrap that went to the first of the other
+    /// participants. Its delivery stands in for the message's.
+    sent: HashMap<String, [u8; 32]>,
+    /// People whose inbox relays we asked for
+    inbox_requested: HashSet<Pubkey>,
+    /// Unsent messages, per conversation
+    pub drafts: HashMap<Participants, String>,
+    /// The conversation on screen, if any. Overrides the selected channel.
+    pub selected: Option<Participants>,
+    /// Searches the messages of every conversation when not empty
+    pub search: String,
+}
+
+impl DirectMessages {
+    /// Open the gift wraps that arrived since the last frame. When the
+    /// account was just selected, every wrap we already have for it.
+    /// Accounts without a secret key can't open them.
+    pub fn update(&mut self, ctx: &mut AppContext<'_>) {
+        let Some(kp) = ctx.accounts.selected_filled() else {
+            if self.account.is_some() {
+                *self = Self::default();
+            }
+            return;
+        };
+
+        let Ok(txn) = Transaction::new(ctx.ndb) else {
+            return;
+        };
+
+        // what we already had when the account was selected counts as read
+        let initial = self.account != Some(*kp.pubkey);
+        let note_keys = if initial {
+            *self = Self {
+                account: Some(*kp.pubkey),
+                ..Self::default()
+            };
+            let filter = &ctx.accounts.get_selected_account().data.dms.wraps_filter;
+            ctx.ndb
+                .query(&txn, std::slice::from_ref(filter), MAX_INITIAL_WRAPS)
+                .map(|results| results.into_iter().map(|r| r.note_key).collect())
+                .unwrap_or_default()
+        } else {
+            ctx.ndb
+                .poll_for_notes(ctx.accounts.get_subs().gift_wraps.local, 100)
+        };
+
+        for key in note_keys {
+            if !self.seen.insert(key) {
+                continue;
+            }
+
+            let Ok(wrap) = ctx.ndb.get_note_by_key(&txn, key) else {
+                continue;
+            };
+
+            let rumor = match nip59::unwrap(&wrap, kp) {
+                Ok(rumor) => rumor,
+                Err(err) => {
+                    debug!("could not open gift wrap {}: {err}", hex::encode(wrap.id()));
+                    continue;
+                }
+            };
+
+            // other rumor kinds, like file messages, aren't shown yet
+            if rumor.kind != nip17::KIND_PRIVATE_MESSAGE {
+                continue;
+            }
+
+            let participants = nip17::participants(&rumor, kp.pubkey);
+            for pk in &participants {
+                ctx.unknown_ids
+                    .add_pubkey_if_missing(ctx.ndb, &txn, pk.bytes());
+            }
+
+            let from_other = rumor.pubkey != *kp.pubkey;
+            let is_open = self.selected.as_ref() == Some(&participants);
+            let conversation = self.conversations.entry(participants).or_default();
+            if conversation.insert(rumor) && from_other && !is_open && !initial {
+                conversation.unread = true;
+            }
+        }
+    }
+
+    /// Conversations, the most recently active first
+    pub fn conversations(&self) -> Vec<(&Participants, &Conversation)> {
+        let mut conversations: Vec<_> = self.conversations.iter().collect();
+        conversations.sort_by_key(|(_, c)| std::cmp::Reverse(c.last_activity()));
+        conversations
+    }
+
+    pub fn conversation(&self, participants: &Participants) -> Option<&Conversation> {
+        self.conversations.get(participants)
+    }
+
+    /// Messages containing `query` in any conversation, newest first
+    pub fn search_messages(&self, query: &str) -> Vec<(&Participants, &Rumor)> {
+        let query = query.to_lowercase();
+        let mut found: Vec<_> = self
+            .conversations
+            .iter()
+            .flat_map(|(participants, c)| c.messages.iter().map(move |m| (participants, m)))
+            .filter(|(_, m)| m.content.to_lowercase().contains(&query))
+            .collect();
+        found.sort_by_key(|(_, m)| std::cmp::Reverse(m.created_at));
+        found
+    }
+
+    /// Delivery of a message we sent this session
+    pub fn delivery(&self, outbox: &Outbox, message_id: &str) -> Option<DeliveryStatus> {
+        self.sent
+            .get(message_id)
+            .and_then(|wrap_id| outbox.status(wrap_id))
+    }
+
+    /// Show the conversation with `participants`, and ask for their
+    /// profiles and the relays they receive messages on
+    pub fn open(&mut self, participants: Participants, ctx: &mut AppContext<'_>) {
+        if let Some(conversation) = self.conversations.get_mut(&participants) {
+            conversation.unread = false;
+        }
+
+        if let Ok(txn) = Transaction::new(ctx.ndb) {
+            for pk in &participants {
+                ctx.unknown_ids
+                    .add_pubkey_if_missing(ctx.ndb, &txn, pk.bytes());
+            }
+        }
+
+        let mut met_someone = false;
+        for pk in &participants {
+            met_someone |= self.inbox_requested.insert(*pk);
+        }
+
+        if met_someone {
+            // one subscription for all of them, replaced as we meet people
+            let requested: Vec<&[u8; 32]> =
+                self.inbox_requested.iter().map(|pk| pk.bytes()).collect();
+            let filter = Filter::new()
+                .authors(requested)
+                .kinds([KIND_DM_RELAYS as u64])
+                .build();
+            ctx.pool
+                .subscribe(INBOX_RELAYS_SUBID.to_owned(), vec![filter]);
+        }
+
+        self.selected = Some(participants);
+    }
+
+    /// Send `content` to `participants`. Each of them gets a copy on their
+    /// inbox relays, and we keep one on ours for our other devices.
+    pub fn send(&mut self, ctx: &mut AppContext<'_>, participants: &Participants, content: &str) {
+        let Some(kp) = ctx.accounts.selected_filled() else {
+            return;
+        };
+
+        let rumor = nip17::message_rumor(kp.pubkey, participants, content, None, unix_now());
+        let wraps = match nip17::wrap_message(&rumor, kp, participants) {
+            Ok(wraps) => wraps,
+            Err(err) => {
+                error!("Could not wrap direct message: {err}");
+                return;
+            }
+        };
+
+        let Ok(txn) = Transaction::new(ctx.ndb) else {
+            return;
+        };
+
+        let own_relays: Vec<String> = ctx
+            .accounts
+            .get_selected_account()
+            .data
+            .dms
+            .relays
+            .iter()
+            .map(|spec| spec.url.clone())
+            .collect();
+
+        for (pk, wrap) in wraps {
+            let relays = if pk == *kp.pubkey {
+                own_relays.clone()
+            } else {
+                dm_inbox_relays(ctx.ndb, &txn, pk.bytes())
+            };
+
+            if relays.is_empty() {
+                warn!("{} has no inbox relays, sending to all relays", pk.hex());
+            }
+            channels::connect_relays(ctx.pool, relays.iter().map(String::as_str));
+
+            if pk != *kp.pubkey || participants.contains(&pk) {
+                self.sent.entry(rumor.id.clone()).or_insert(*wrap.id());
+            }
+
+            let signed = Promise::from_ready(SignedEvent::from_note(&wrap));
+            ctx.outbox.sign_and_send(signed, relays);
+        }
+
+        self.conversations
+            .entry(participants.clone())
+            .or_default()
+            .insert(rumor);
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1,8 +1,10 @@
 use egui::text::LayoutJob;
+use notedeck::Outbox;
 use poll_promise::Promise;
 
 use crate::{media_upload::Nip94Event, post::PostBuffer, ui::note::PostType, Error};
 use std::collections::HashMap;
+use uuid::Uuid;
 
 #[derive(Default)]
 pub struct Draft {
@@ -12,6 +14,8 @@ pub struct Draft {
     pub uploaded_media: Vec<Nip94Event>, // media uploads to include
     pub uploading_media: Vec<Promise<Result<Nip94Event, Error>>>, // promises that aren't ready yet
     pub upload_errors: Vec<String>,      // media upload errors to show the user
+    pub signing: Option<u64>,            // outbox id of the post waiting for its signer
+    pub sign_error: Option<String>,      // why the signer didn't sign the last post
 }
 
 pub struct MentionHint {
@@ -24,6 +28,7 @@ pub struct MentionHint {
 pub struct Drafts {
     replies: HashMap<[u8; 32], Draft>,
     quotes: HashMap<[u8; 32], Draft>,
+    channels: HashMap<Uuid, Draft>,
     compose: Draft,
 }
 
@@ -37,6 +42,9 @@ impl Drafts {
             PostType::New => self.compose_mut(),
             PostType::Quote(note_id) => self.quote_mut(note_id.bytes()),
             PostType::Reply(note_id) => self.reply_mut(note_id.bytes()),
+            PostType::Channel(channel_id)
+            | PostType::PublicChat(channel_id, _)
+            | PostType::Group(channel_id, _) => self.channel_mut(channel_id),
         }
     }
 
@@ -47,6 +55,26 @@ impl Drafts {
     pub fn quote_mut(&mut self, id: &[u8; 32]) -> &mut Draft {
         self.quotes.entry(*id).or_default()
     }
+
+    /// The chat composer draft for a channel
+    pub fn channel_mut(&mut self, id: &Uuid) -> &mut Draft {
+        self.channels.entry(*id).or_default()
+    }
+
+    /// Clear the drafts that got signed and published. The ones the signer
+    /// refused keep their text along with the error.
+    pub fn update(&mut self, outbox: &mut Outbox) {
+        let drafts = self
+            .replies
+            .values_mut()
+            .chain(self.quotes.values_mut())
+            .chain(self.channels.values_mut())
+            .chain(std::iter::once(&mut self.compose));
+
+        for draft in drafts {
+            draft.update_signing(outbox);
+        }
+    }
 }
 
 impl Draft {
@@ -59,5 +87,27 @@ impl Draft {
         self.upload_errors = Vec::new();
         self.uploaded_media = Vec::new();
         self.uploading_media = Vec::new();
+        self.signing = None;
+        self.sign_error = None;
+    }
+
+    /// Whether the last post is still waiting for the signer
+    pub fn is_signing(&self) -> bool {
+        self.signing.is_some()
+    }
+
+    fn update_signing(&mut self, outbox: &mut Outbox) {
+        let Some(id) = self.signing else {
+            return;
+        };
+
+        match outbox.take_sign_result(id) {
+            None => {}
+            Some(Ok(())) => self.clear(),
+            Some(Err(err)) => {
+                self.signing = None;
+                self.sign_error = Some(err.to_string());
+            }
+        }
     }
 }
@@ -0,0 +1,312 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use enostr::{Filter, PoolRelay, Pubkey, RelayPool, RelayStatus};
+use nostrdb::{Ndb, Transaction};
+use notedeck::contacts::contacts_filter;
+use tracing::{debug, error, info};
+
+use crate::timeline::{kind::ListKind, TimelineKind};
+
+/// Extra relay connections opened for gossip unless `--gossip-relays` says
+/// otherwise
+pub const DEFAULT_MAX_CONNECTIONS: usize = 4;
+
+/// Owner of the gossip relays pinned in the pool
+const GOSSIP_RELAYS: &str = "gossip";
+
+/// Routes profile and contact list timelines to the relays their authors
+/// publish to, according to the authors' NIP-65 relay lists (the "outbox
+/// model"). Authors our configured relays don't cover get a minimal set of
+/// temporary connections, which are dropped again once no timeline needs
+/// them.
+pub struct GossipRouter {
+    max_connections: usize,
+
+    /// Relays we connected to for gossip, and the timelines using them
+    connections: HashMap<String, HashSet<TimelineKind>>,
+
+    /// The gossip relays each timeline was routed to
+    routes: HashMap<TimelineKind, Vec<String>>,
+}
+
+impl Default for GossipRouter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONNECTIONS)
+    }
+}
+
+impl GossipRouter {
+    pub fn new(max_connections: usize) -> Self {
+        Self {
+            max_connections,
+            connections: HashMap::new(),
+            routes: HashMap::new(),
+        }
+    }
+
+    pub fn set_max_connections(&mut self, max_connections: usize) {
+        self.max_connections = max_connections;
+    }
+
+    /// Whether we opened the connection to `relay_url` ourselves
+    pub fn is_gossip_relay(&self, relay_url: &str) -> bool {
+        self.connections.contains_key(relay_url)
+    }
+
+    /// The timelines to subscribe on `relay_url`, when it's one of our
+    /// gossip relays
+    pub fn routed_to(&self, relay_url: &str) -> Option<&HashSet<TimelineKind>> {
+        self.connections.get(relay_url)
+    }
+
+    /// Find the relays the authors of `kind` publish to and connect to
+    /// the ones our pool is missing. `sub_id` and `filters` are the
+    /// timeline's remote subscription, sent right away to gossip relays
+    /// that are already connected. The others get it when they open.
+    pub fn route(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        kind: &TimelineKind,
+        sub_id: Option<&str>,
+        filters: &[Filter],
+    ) {
+        if self.max_connections == 0 || kind.relays().is_some() || self.routes.contains_key(kind) {
+            return;
+        }
+
+        let author_relays: HashMap<Pubkey, Vec<String>> = authors(ndb, txn, kind)
+            .into_iter()
+            .map(|pk| {
+                let relays = notedeck::nip65_write_relays(ndb, txn, pk.bytes());
+                (pk, relays)
+            })
+            .filter(|(_, relays)| !relays.is_empty())
+            .collect();
+
+        if author_relays.is_empty() {
+            return;
+        }
+
+        let configured: HashSet<String> = pool
+            .relays
+            .iter()
+            .filter(|relay| matches!(relay, PoolRelay::Websocket(_)))
+            .map(|relay| relay.url().to_owned())
+            .filter(|url| !self.is_gossip_relay(url))
+            .collect();
+
+        let reusable: HashSet<String> = self.connections.keys().cloned().collect();
+        let budget = self.max_connections.saturating_sub(self.connections.len());
+
+        let relays = cover(&author_relays, &configured, &reusable, budget);
+        if relays.is_empty() {
+            return;
+        }
+
+        info!("gossip: routing {:?} to {:?}", kind, relays);
+
+        for url in &relays {
+            if self.connect(pool, kind, url) {
+                continue;
+            }
+
+            if let Some(sub_id) = sub_id {
+                if is_connected(pool, url) {
+                    pool.subscribe_to(url, sub_id.to_owned(), filters.to_vec());
+                }
+            }
+        }
+
+        self.routes.insert(kind.clone(), relays);
+    }
+
+    /// Note that `kind` uses `url`, connecting to it unless we already
+    /// were. Returns true if the connection is new.
+    fn connect(&mut self, pool: &mut RelayPool, kind: &TimelineKind, url: &str) -> bool {
+        let is_new = !self.connections.contains_key(url);
+        self.connections
+            .entry(url.to_owned())
+            .or_default()
+            .insert(kind.clone());
+
+        if is_new {
+            // pinned, or the next update of the account's relays drops it
+            pool.pin_url(url, GOSSIP_RELAYS);
+            if let Err(err) = pool.add_url(url.to_owned(), || {}) {
+                error!("gossip: could not connect to {url}: {err}");
+            }
+        }
+
+        is_new
+    }
+
+    /// Forget the routes of a timeline that was removed, and disconnect
+    /// from gossip relays no other timeline needs
+    pub fn unroute(&mut self, pool: &mut RelayPool, kind: &TimelineKind) {
+        let Some(relays) = self.routes.remove(kind) else {
+            return;
+        };
+
+        let mut unused = BTreeSet::new();
+        for url in relays {
+            let Some(kinds) = self.connections.get_mut(&url) else {
+                continue;
+            };
+
+            kinds.remove(kind);
+            if kinds.is_empty() {
+                self.connections.remove(&url);
+                unused.insert(url);
+            }
+        }
+
+        if !unused.is_empty() {
+            debug!("gossip: disconnecting from {:?}", unused);
+            for url in &unused {
+                pool.unpin_url(url, GOSSIP_RELAYS);
+            }
+            pool.remove_urls(&unused);
+        }
+    }
+}
+
+/// The authors whose notes `kind` shows, if we know where to find them
+fn authors(ndb: &Ndb, txn: &Transaction, kind: &TimelineKind) -> Vec<Pubkey> {
+    match kind {
+        TimelineKind::Profile(pk) => vec![*pk],
+
+        TimelineKind::List(ListKind::Contact(pk)) => {
+            let Ok(results) = ndb.query(txn, &[contacts_filter(pk.bytes())], 1) else {
+                return vec![];
+            };
+
+            let Some(contacts) = results.first() else {
+                return vec![];
+            };
+
+            contacts
+                .note
+                .tags()
+                .into_iter()
+                .filter(|tag| tag.count() >= 2 && tag.get_str(0) == Some("p"))
+                .filter_map(|tag| tag.get_id(1))
+                .map(|pk| Pubkey::new(*pk))
+                .collect()
+        }
+
+        _ => vec![],
+    }
+}
+
+/// Greedily pick the fewest relays that cover every author our configured
+/// relays don't. Relays in `reusable` are already connected and preferred,
+/// at most `budget` new ones are picked. Authors that can't be covered
+/// within the budget are left to the configured relays.
+fn cover(
+    author_relays: &HashMap<Pubkey, Vec<String>>,
+    configured: &HashSet<String>,
+    reusable: &HashSet<String>,
+    mut budget: usize,
+) -> Vec<String> {
+    let mut uncovered: Vec<&Vec<String>> = author_relays
+        .values()
+        .filter(|relays| !relays.iter().any(|url| configured.contains(url)))
+        .collect();
+
+    let mut picked = vec![];
+    loop {
+        let mut candidates: HashMap<&str, usize> = HashMap::new();
+        for relays in &uncovered {
+            for url in relays.iter() {
+                if reusable.contains(url) || budget > 0 {
+                    *candidates.entry(url.as_str()).or_default() += 1;
+                }
+            }
+        }
+
+        // most authors first, then already connected, then by url so
+        // ties don't depend on hashmap order
+        let Some((best, _)) = candidates
+            .into_iter()
+            .max_by_key(|(url, n)| (*n, reusable.contains(*url), std::cmp::Reverse(*url)))
+        else {
+            break;
+        };
+
+        let best = best.to_owned();
+        uncovered.retain(|relays| !relays.contains(&best));
+        if !reusable.contains(&best) {
+            budget -= 1;
+        }
+        picked.push(best);
+    }
+
+    picked
+}
+
+fn is_connected(pool: &RelayPool, url: &str) -> bool {
+    pool.relays
+        .iter()
+        .any(|relay| relay.url() == url && matches!(relay.status(), RelayStatus::Connected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relays(urls: &[&str]) -> Vec<String> {
+        urls.iter().map(|url| url.to_string()).collect()
+    }
+
+    fn set(urls: &[&str]) -> HashSet<String> {
+        urls.iter().map(|url| url.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cover() {
+        let author_relays = HashMap::from([
+            (Pubkey::new([1; 32]), relays(&["wss://a/", "wss://b/"])),
+            (Pubkey::new([2; 32]), relays(&["wss://b/", "wss://c/"])),
+            (Pubkey::new([3; 32]), relays(&["wss://c/", "wss://d/"])),
+            (Pubkey::new([4; 32]), relays(&["wss://damus/", "wss://e/"])),
+            (Pubkey::new([5; 32]), relays(&["wss://f/"])),
+        ]);
+        let configured = set(&["wss://damus/"]);
+
+        // b covers 1 and 2, then c/d and f are tied on one author each
+        let picked = cover(&author_relays, &configured, &HashSet::new(), 10);
+        assert_eq!(picked, relays(&["wss://b/", "wss://c/", "wss://f/"]));
+
+        // an existing connection wins ties
+        let picked = cover(&author_relays, &configured, &set(&["wss://d/"]), 10);
+        assert_eq!(picked, relays(&["wss://b/", "wss://d/", "wss://f/"]));
+
+        // the cap only counts new connections
+        let picked = cover(&author_relays, &configured, &set(&["wss://d/"]), 1);
+        assert_eq!(picked, relays(&["wss://b/", "wss://d/"]));
+
+        assert!(cover(&author_relays, &configured, &HashSet::new(), 0).is_empty());
+    }
+
+    #[test]
+    fn test_gossip_relays_are_pinned() {
+        let url = "ws://127.0.0.1:1/";
+        let kind = TimelineKind::Profile(Pubkey::new([1; 32]));
+        let mut router = GossipRouter::default();
+        let mut pool = RelayPool::new();
+
+        assert!(router.connect(&mut pool, &kind, url));
+        router.routes.insert(kind.clone(), relays(&[url]));
+        assert_eq!(pool.urls().len(), 1);
+
+        // an update of the account's relays leaves it alone
+        pool.remove_urls(&pool.urls());
+        assert_eq!(pool.urls().len(), 1);
+
+        // until no timeline needs it anymore
+        router.unroute(&mut pool, &kind);
+        assert!(pool.urls().is_empty());
+    }
+}
@@ -4,6 +4,7 @@ use std::str::FromStr;
 use crate::Error;
 use ehttp::{Request, Response};
 use enostr::{Keypair, Pubkey, SecretKey};
+use notedeck::{BunkerUri, RemoteLogin, SignerError};
 use poll_promise::Promise;
 use serde::{Deserialize, Serialize};
 use tracing::error;
@@ -12,6 +13,7 @@ use tracing::error;
 pub enum AcquireKeyError {
     InvalidKey,
     Nip05Failed(String),
+    RemoteSigner(SignerError),
 }
 
 impl std::fmt::Display for AcquireKeyError {
@@ -21,12 +23,29 @@ impl std::fmt::Display for AcquireKeyError {
             AcquireKeyError::Nip05Failed(e) => {
                 write!(f, "Failed to get pubkey from Nip05 address: {e}")
             }
+            AcquireKeyError::RemoteSigner(e) => write!(f, "Remote signer login failed: {e}"),
         }
     }
 }
 
 impl std::error::Error for AcquireKeyError {}
 
+/// What the user logged in with: a key, or a remote signer that holds it
+#[derive(Debug, PartialEq, Clone)]
+pub enum AcquiredKey {
+    Keypair(Keypair),
+    RemoteSigner(RemoteLogin),
+}
+
+impl AcquiredKey {
+    pub fn pubkey(&self) -> &Pubkey {
+        match self {
+            AcquiredKey::Keypair(keypair) => &keypair.pubkey,
+            AcquiredKey::RemoteSigner(login) => &login.pubkey,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Nip05Result {
     pub names: HashMap<String, String>,
@@ -97,14 +116,14 @@ fn retrieving_nip05_pubkey(key: &str) -> bool {
     key.contains('@')
 }
 
-fn nip05_promise_wrapper(id: &str) -> Promise<Result<Keypair, AcquireKeyError>> {
+fn nip05_promise_wrapper(id: &str) -> Promise<Result<AcquiredKey, AcquireKeyError>> {
     let (sender, promise) = Promise::new();
     let original_promise = get_nip05_pubkey(id);
 
     std::thread::spawn(move || {
         let result = original_promise.block_and_take();
         let transformed_result = match result {
-            Ok(public_key) => Ok(Keypair::only_pubkey(public_key)),
+            Ok(public_key) => Ok(AcquiredKey::Keypair(Keypair::only_pubkey(public_key))),
             Err(e) => {
                 error!("Nip05 Failed: {e}");
                 Err(AcquireKeyError::Nip05Failed(e.to_string()))
@@ -116,6 +135,27 @@ fn nip05_promise_wrapper(id: &str) -> Promise<Result<Keypair, AcquireKeyError>>
     promise
 }
 
+fn bunker_promise_wrapper(uri: &str) -> Promise<Result<AcquiredKey, AcquireKeyError>> {
+    let uri = match BunkerUri::parse(uri) {
+        Ok(uri) => uri,
+        Err(e) => return Promise::from_ready(Err(AcquireKeyError::RemoteSigner(e))),
+    };
+
+    // the login view spins while we wait, so no need for a wakeup
+    let original_promise = RemoteLogin::bunker(uri, || {});
+
+    let (sender, promise) = Promise::new();
+    std::thread::spawn(move || {
+        let result = original_promise.block_and_take().map_err(|e| {
+            error!("Remote signer login failed: {e}");
+            AcquireKeyError::RemoteSigner(e)
+        });
+        sender.send(result.map(AcquiredKey::RemoteSigner));
+    });
+
+    promise
+}
+
 /// Attempts to turn a string slice key from the user into a Nostr-Sdk Keypair object.
 /// The `key` can be in any of the following formats:
 /// - Public Bech32 key (prefix "npub"): "npub1xyz..."
@@ -123,15 +163,18 @@ fn nip05_promise_wrapper(id: &str) -> Promise<Result<Keypair, AcquireKeyError>>
 /// - Public hex key: "02a1..."
 /// - Private hex key: "5dab..."
 /// - NIP-05 address: "example@nostr.com"
+/// - NIP-46 remote signer: "bunker://02a1...?relay=wss://..."
 ///
-pub fn perform_key_retrieval(key: &str) -> Promise<Result<Keypair, AcquireKeyError>> {
+pub fn perform_key_retrieval(key: &str) -> Promise<Result<AcquiredKey, AcquireKeyError>> {
     let tmp_key: &str = if let Some(stripped) = key.strip_prefix('@') {
         stripped
     } else {
         key
     };
 
-    if retrieving_nip05_pubkey(tmp_key) {
+    if tmp_key.starts_with("bunker://") {
+        bunker_promise_wrapper(tmp_key)
+    } else if retrieving_nip05_pubkey(tmp_key) {
         nip05_promise_wrapper(tmp_key)
     } else {
         let res = if let Ok(pubkey) = Pubkey::try_from_bech32_string(tmp_key, true) {
@@ -144,7 +187,7 @@ pub fn perform_key_retrieval(key: &str) -> Promise<Result<Keypair, AcquireKeyErr
             Err(AcquireKeyError::InvalidKey)
         };
 
-        Promise::from_ready(res)
+        Promise::from_ready(res.map(AcquiredKey::Keypair))
     }
 }
 
@@ -162,7 +205,7 @@ mod tests {
 
         promise_assert!(
             assert_eq,
-            Ok(Keypair::only_pubkey(expected_pubkey)),
+            Ok(AcquiredKey::Keypair(Keypair::only_pubkey(expected_pubkey))),
             &login_key_result
         );
     }
@@ -175,7 +218,7 @@ mod tests {
 
         promise_assert!(
             assert_eq,
-            Ok(Keypair::only_pubkey(expected_pubkey)),
+            Ok(AcquiredKey::Keypair(Keypair::only_pubkey(expected_pubkey))),
             &login_key_result
         );
     }
@@ -188,7 +231,7 @@ mod tests {
 
         promise_assert!(
             assert_eq,
-            Ok(Keypair::from_secret(expected_privkey)),
+            Ok(AcquiredKey::Keypair(Keypair::from_secret(expected_privkey))),
             &login_key_result
         );
     }
@@ -201,7 +244,7 @@ mod tests {
 
         promise_assert!(
             assert_eq,
-            Ok(Keypair::from_secret(expected_privkey)),
+            Ok(AcquiredKey::Keypair(Keypair::from_secret(expected_privkey))),
             &login_key_result
         );
     }
@@ -218,7 +261,7 @@ mod tests {
 
         promise_assert!(
             assert_eq,
-            Ok(Keypair::only_pubkey(expected_pubkey)),
+            Ok(AcquiredKey::Keypair(Keypair::only_pubkey(expected_pubkey))),
             &login_key_result
         );
     }
@@ -236,4 +279,17 @@ mod tests {
         let res = login_key_result.block_and_take().expect("Should not error");
         assert_eq!(expected_pubkey, res);
     }
+
+    #[test]
+    fn test_invalid_bunker() {
+        let login_key_result = perform_key_retrieval("bunker://nope?relay=wss://relay.damus.io");
+
+        promise_assert!(
+            assert_eq,
+            Err(AcquireKeyError::RemoteSigner(SignerError::InvalidUri(
+                "bad remote signer pubkey".to_owned()
+            ))),
+            &login_key_result
+        );
+    }
 }
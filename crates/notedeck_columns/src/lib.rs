@@ -8,11 +8,15 @@ pub mod actionbar;
 pub mod app_creation;
 mod app_style;
 mod args;
+pub mod channel_filter;
+mod channel_sync;
 pub mod channels;
 pub mod column;
 mod deck_state;
 mod decks;
+mod dms;
 mod draft;
+mod gossip;
 pub mod relay_config;
 pub mod startup_config;
 mod key_parsing;
@@ -20,6 +24,9 @@ pub mod login_manager;
 mod media_upload;
 mod multi_subscriber;
 mod nav;
+pub mod nip17;
+pub mod nip28;
+pub mod nip29;
 mod onboarding;
 pub mod options;
 mod post;
@@ -1,7 +1,6 @@
 use crate::key_parsing::perform_key_retrieval;
-use crate::key_parsing::AcquireKeyError;
+use crate::key_parsing::{AcquireKeyError, AcquiredKey};
 use egui::{TextBuffer, TextEdit};
-use enostr::Keypair;
 use notedeck::{tr, Localization};
 use poll_promise::Promise;
 
@@ -9,7 +8,7 @@ use poll_promise::Promise;
 #[derive(Default)]
 pub struct AcquireKeyState {
     desired_key: String,
-    promise_query: Option<(String, Promise<Result<Keypair, AcquireKeyError>>)>,
+    promise_query: Option<(String, Promise<Result<AcquiredKey, AcquireKeyError>>)>,
     error: Option<AcquireKeyError>,
     key_on_error: Option<String>,
     should_create_new: bool,
@@ -72,7 +71,7 @@ impl<'a> AcquireKeyState {
     }
 
     /// Whether to indicate to the user that a successful login occured
-    pub fn get_login_keypair(&mut self) -> Option<&Keypair> {
+    pub fn get_login_key(&mut self) -> Option<&AcquiredKey> {
         if let Some((_, promise)) = &self.promise_query {
             match promise.poll() {
                 std::task::Poll::Ready(inner) => match inner {
@@ -145,6 +144,15 @@ fn show_error(ui: &mut egui::Ui, i18n: &mut Localization, err: &AcquireKeyError)
             AcquireKeyError::Nip05Failed(e) => {
                 egui::Label::new(egui::RichText::new(e).color(ui.visuals().error_fg_color))
             }
+            AcquireKeyError::RemoteSigner(e) => egui::Label::new(
+                egui::RichText::new(tr!(
+                    i18n,
+                    "Remote signer login failed: {error}",
+                    "Error message when logging in with a bunker:// remote signer fails",
+                    error = e.to_string()
+                ))
+                .color(ui.visuals().error_fg_color),
+            ),
         };
         ui.add(error_label.truncate());
     });
@@ -152,7 +160,7 @@ fn show_error(ui: &mut egui::Ui, i18n: &mut Localization, err: &AcquireKeyError)
 
 #[cfg(test)]
 mod tests {
-    use enostr::Pubkey;
+    use enostr::{Keypair, Pubkey};
 
     use super::*;
     use std::time::{Duration, Instant};
@@ -161,7 +169,9 @@ mod tests {
     fn test_retrieve_key() {
         let mut manager = AcquireKeyState::new();
         let expected_str = "3efdaebb1d8923ebd99c9e7ace3b4194ab45512e2be79c1b7d68d9243e0d2681";
-        let expected_key = Keypair::only_pubkey(Pubkey::from_hex(expected_str).unwrap());
+        let expected_key = AcquiredKey::Keypair(Keypair::only_pubkey(
+            Pubkey::from_hex(expected_str).unwrap(),
+        ));
 
         let start_time = Instant::now();
 
@@ -194,7 +204,7 @@ mod tests {
                 manager.apply_acquire();
             }
 
-            if let Some(key) = manager.get_login_keypair() {
+            if let Some(key) = manager.get_login_key() {
                 assert_eq!(expected_key, key.clone());
                 return;
             }
@@ -384,26 +384,24 @@ impl TimelineSub {
     }
 
     pub fn try_add_remote(&mut self, subs: &mut Subscriptions, pool: &mut RelayPool, filter: &HybridFilter, timeline_kind: &TimelineKind) {
-        self.try_add_remote_with_relay(subs, pool, filter, None, timeline_kind);
+        self.try_add_remote_with_relays(subs, pool, filter, None, timeline_kind);
     }
 
-    pub fn try_add_remote_with_relay(
+    /// Like [`Self::try_add_remote`], but only subscribes on `relay_urls`
+    /// when given
+    pub fn try_add_remote_with_relays(
         &mut self,
         subs: &mut Subscriptions,
         pool: &mut RelayPool,
         filter: &HybridFilter,
-        relay_url: Option<&str>,
+        relay_urls: Option<&[&str]>,
         timeline_kind: &TimelineKind,
     ) {
         let before = self.state.clone();
         match &mut self.state {
             SubState::NoSub { dependers } => {
                 let subid = subscriptions::new_sub_id();
-                if let Some(relay) = relay_url {
-                    pool.subscribe_to(relay, subid.clone(), filter.remote().to_vec());
-                } else {
-                    pool.subscribe(subid.clone(), filter.remote().to_vec());
-                }
+                remote_subscribe(pool, subid.clone(), filter, relay_urls);
                 subs.subs.insert(subid.clone(), SubKind::Timeline(timeline_kind.clone()));
                 self.filter = Some(filter.to_owned());
                 self.state = SubState::RemoteOnly {
@@ -413,11 +411,7 @@ impl TimelineSub {
             }
             SubState::LocalOnly { local, dependers } => {
                 let subid = subscriptions::new_sub_id();
-                if let Some(relay) = relay_url {
-                    pool.subscribe_to(relay, subid.clone(), filter.remote().to_vec());
-                } else {
-                    pool.subscribe(subid.clone(), filter.remote().to_vec());
-                }
+                remote_subscribe(pool, subid.clone(), filter, relay_urls);
                 subs.subs.insert(subid.clone(), SubKind::Timeline(timeline_kind.clone()));
                 self.filter = Some(filter.to_owned());
                 self.state = SubState::Unified {
@@ -546,6 +540,25 @@ impl TimelineSub {
         );
     }
 
+    /// The id of our relay subscription, if we have one
+    pub fn remote_id(&self) -> Option<&str> {
+        match &self.state {
+            SubState::NoSub { dependers: _ } => None,
+            SubState::LocalOnly {
+                local: _,
+                dependers: _,
+            } => None,
+            SubState::RemoteOnly {
+                remote,
+                dependers: _,
+            } => Some(remote),
+            SubState::Unified {
+                unified,
+                dependers: _,
+            } => Some(&unified.remote),
+        }
+    }
+
     pub fn get_filter(&self) -> Option<&HybridFilter> {
         self.filter.as_ref()
     }
@@ -554,3 +567,20 @@ impl TimelineSub {
         matches!(self.state, SubState::NoSub { dependers: _ })
     }
 }
+
+/// Subscribe on `relay_urls`, or on every relay in the pool if None
+fn remote_subscribe(
+    pool: &mut RelayPool,
+    subid: String,
+    filter: &HybridFilter,
+    relay_urls: Option<&[&str]>,
+) {
+    match relay_urls {
+        Some(relay_urls) => {
+            for relay_url in relay_urls {
+                pool.subscribe_to(relay_url, subid.clone(), filter.remote().to_vec());
+            }
+        }
+        None => pool.subscribe(subid, filter.remote().to_vec()),
+    }
+}
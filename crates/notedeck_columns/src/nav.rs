@@ -474,7 +474,8 @@ fn process_render_nav_action(
         }
         RenderNavAction::PostAction(new_post_action) => {
             let txn = Transaction::new(ctx.ndb).expect("txn");
-            match new_post_action.execute(ctx.ndb, &txn, ctx.pool, &mut app.drafts) {
+            match new_post_action.execute(ctx.ndb, &txn, ctx.accounts, ctx.outbox, &mut app.drafts)
+            {
                 Err(err) => tracing::error!("Error executing post action: {err}"),
                 Ok(_) => tracing::debug!("Post action executed"),
             }
@@ -494,6 +495,7 @@ fn process_render_nav_action(
                 &mut app.threads,
                 ctx.note_cache,
                 ctx.pool,
+                ctx.outbox,
                 &txn,
                 ctx.unknown_ids,
                 ctx.accounts,
@@ -525,14 +527,20 @@ fn process_render_nav_action(
         }
         RenderNavAction::RelayAction(action) => {
             ctx.accounts
-                .process_relay_action(ui.ctx(), ctx.pool, action);
+                .process_relay_action(ui.ctx(), ctx.pool, ctx.outbox, action);
             None
         }
-        RenderNavAction::SettingsAction(action) => {
-            action.process_settings_action(app, ctx.settings, ctx.i18n, ctx.img_cache, ui.ctx())
-        }
+        RenderNavAction::SettingsAction(action) => action.process_settings_action(
+            app,
+            ctx.settings,
+            ctx.i18n,
+            ctx.img_cache,
+            ctx.accounts,
+            ctx.outbox,
+            ui.ctx(),
+        ),
         RenderNavAction::RepostAction(action) => {
-            action.process(ctx.ndb, &ctx.accounts.get_selected_account().key, ctx.pool)
+            action.process(ctx.ndb, ctx.accounts, ctx.pool, ctx.outbox)
         }
     };
 
@@ -569,6 +577,8 @@ fn render_nav_body(
         clipboard: ctx.clipboard,
         i18n: ctx.i18n,
         global_wallet: ctx.global_wallet,
+        outbox: ctx.outbox,
+        deletions: ctx.deletions,
     };
     match top {
         Route::Timeline(kind) => {
@@ -671,7 +681,7 @@ fn render_nav_body(
                 return BodyResponse::none();
             };
 
-            let Some(poster) = ctx.accounts.selected_filled() else {
+            let Some(poster) = ctx.accounts.selected_poster() else {
                 return BodyResponse::none();
             };
 
@@ -710,7 +720,7 @@ fn render_nav_body(
                 return BodyResponse::none();
             };
 
-            let Some(poster) = ctx.accounts.selected_filled() else {
+            let Some(poster) = ctx.accounts.selected_poster() else {
                 return BodyResponse::none();
             };
 
@@ -731,7 +741,7 @@ fn render_nav_body(
             response.map_output_maybe(|o| Some(o.action?.into()))
         }
         Route::ComposeNote => {
-            let Some(kp) = ctx.accounts.get_selected_account().key.to_full() else {
+            let Some(kp) = ctx.accounts.selected_poster() else {
                 return BodyResponse::none();
             };
             let draft = app.drafts.compose_mut();
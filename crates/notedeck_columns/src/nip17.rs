@@ -0,0 +1,124 @@
+//! NIP-17 private direct messages
+//!
+//! A message is a kind 14 rumor, an event that is never signed, with a `p`
+//! tag for every recipient. It is sealed and gift wrapped (NIP-59) once for
+//! each recipient and once for the author, whose other devices read their
+//! sent messages from that copy. Wraps are published to the relays each
+//! recipient lists in their kind 10050.
+
+use std::collections::BTreeSet;
+
+use enostr::nip59::{self, Rumor};
+use enostr::{FilledKeypair, Pubkey};
+use nostrdb::Note;
+
+pub const KIND_PRIVATE_MESSAGE: u32 = 14;
+
+/// Everyone in a conversation except the account looking at it. A
+/// conversation with oneself has just the account.
+pub type Participants = BTreeSet<Pubkey>;
+
+/// Build the kind 14 rumor `author` sends to `recipients`, optionally
+/// replying to the message with id `reply_to`
+pub fn message_rumor(
+    author: &Pubkey,
+    recipients: &Participants,
+    content: &str,
+    reply_to: Option<&str>,
+    created_at: u64,
+) -> Rumor {
+    let mut tags: Vec<Vec<String>> = recipients
+        .iter()
+        .map(|pk| vec!["p".to_owned(), pk.hex()])
+        .collect();
+
+    if let Some(reply_to) = reply_to {
+        tags.push(vec!["e".to_owned(), reply_to.to_owned()]);
+    }
+
+    Rumor::new(
+        *author,
+        created_at,
+        KIND_PRIVATE_MESSAGE,
+        tags,
+        content.to_owned(),
+    )
+}
+
+/// Who `account` talks to in the conversation `rumor` belongs to: its
+/// author and recipients, without the account itself
+pub fn participants(rumor: &Rumor, account: &Pubkey) -> Participants {
+    let mut participants: Participants = rumor
+        .tag_values("p")
+        .filter_map(|hex| Pubkey::from_hex(hex).ok())
+        .chain(std::iter::once(rumor.pubkey))
+        .filter(|pk| pk != account)
+        .collect();
+
+    if participants.is_empty() {
+        participants.insert(*account);
+    }
+
+    participants
+}
+
+/// Gift wrap `rumor` for each of its recipients and for its author. The
+/// wraps come paired with who they're for.
+pub fn wrap_message(
+    rumor: &Rumor,
+    author: FilledKeypair<'_>,
+    recipients: &Participants,
+) -> Result<Vec<(Pubkey, Note<'static>)>, enostr::Error> {
+    let mut receivers = recipients.clone();
+    receivers.insert(*author.pubkey);
+
+    receivers
+        .into_iter()
+        .map(|pk| Ok((pk, nip59::gift_wrap(rumor, author, &pk)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+
+    #[test]
+    fn test_message_wraps() {
+        let alice = FullKeypair::generate();
+        let bob = FullKeypair::generate();
+        let recipients = Participants::from([bob.pubkey]);
+
+        let rumor = message_rumor(&alice.pubkey, &recipients, "hi bob", None, 1_700_000_000);
+        let wraps = wrap_message(&rumor, alice.to_filled(), &recipients).unwrap();
+        assert_eq!(wraps.len(), 2);
+
+        // both sides see the conversation as being with the other
+        for (pk, wrap) in &wraps {
+            let kp = if *pk == alice.pubkey { &alice } else { &bob };
+            let unwrapped = nip59::unwrap(wrap, kp.to_filled()).unwrap();
+            assert_eq!(unwrapped.kind, KIND_PRIVATE_MESSAGE);
+            assert_eq!(unwrapped.content, "hi bob");
+
+            let other = if *pk == alice.pubkey {
+                bob.pubkey
+            } else {
+                alice.pubkey
+            };
+            assert_eq!(participants(&unwrapped, pk), Participants::from([other]));
+        }
+    }
+
+    #[test]
+    fn test_note_to_self() {
+        let alice = FullKeypair::generate();
+        let recipients = Participants::from([alice.pubkey]);
+
+        let rumor = message_rumor(&alice.pubkey, &recipients, "todo", None, 1_700_000_000);
+        assert_eq!(participants(&rumor, &alice.pubkey), recipients);
+
+        // one wrap is enough
+        let wraps = wrap_message(&rumor, alice.to_filled(), &recipients).unwrap();
+        assert_eq!(wraps.len(), 1);
+    }
+}
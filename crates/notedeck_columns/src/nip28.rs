@@ -0,0 +1,303 @@
+//! NIP-28 public chat channels
+//!
+//! A channel is created with a kind 40 event whose id identifies the
+//! channel from then on. The creator can update the channel metadata with
+//! kind 41 events, and messages are kind 42 events that reference the
+//! creation event with a `root` marked `e` tag. Kinds 43 and 44 let a user
+//! hide a message or mute a user for themselves.
+
+use std::collections::{BTreeSet, HashSet};
+
+use enostr::{FullKeypair, NoteId, Pubkey, RelayPool};
+use nostrdb::{Filter, IngestMetadata, Ndb, Note, NoteBuilder, Transaction};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+pub const KIND_CHANNEL_CREATE: u32 = 40;
+pub const KIND_CHANNEL_METADATA: u32 = 41;
+pub const KIND_CHANNEL_MESSAGE: u32 = 42;
+pub const KIND_HIDE_MESSAGE: u32 = 43;
+pub const KIND_MUTE_USER: u32 = 44;
+
+/// The content of kind 40 and kind 41 events
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChannelMetadata {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub about: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub picture: String,
+}
+
+impl ChannelMetadata {
+    pub fn new(name: String, about: String) -> Self {
+        Self {
+            name,
+            about,
+            picture: String::new(),
+        }
+    }
+
+    pub fn from_note(note: &Note) -> Option<Self> {
+        serde_json::from_str(note.content()).ok()
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("channel metadata should serialize")
+    }
+}
+
+/// Build a kind 40 event creating a new channel
+pub fn create_channel_event<'a>(metadata: &ChannelMetadata, seckey: &[u8; 32]) -> Option<Note<'a>> {
+    NoteBuilder::new()
+        .kind(KIND_CHANNEL_CREATE)
+        .content(&metadata.to_json())
+        .sign(seckey)
+        .build()
+}
+
+/// Build a kind 41 event updating the metadata of `channel_id`. Clients
+/// only honor these when they come from the channel creator.
+pub fn channel_metadata_event<'a>(
+    channel_id: &NoteId,
+    metadata: &ChannelMetadata,
+    seckey: &[u8; 32],
+) -> Option<Note<'a>> {
+    NoteBuilder::new()
+        .kind(KIND_CHANNEL_METADATA)
+        .content(&metadata.to_json())
+        .start_tag()
+        .tag_str("e")
+        .tag_id(channel_id.bytes())
+        .tag_str("")
+        .tag_str("root")
+        .sign(seckey)
+        .build()
+}
+
+/// Build a kind 43 event hiding `message_id` for the signer
+pub fn hide_message_event<'a>(message_id: &NoteId, seckey: &[u8; 32]) -> Option<Note<'a>> {
+    NoteBuilder::new()
+        .kind(KIND_HIDE_MESSAGE)
+        .content("")
+        .start_tag()
+        .tag_str("e")
+        .tag_id(message_id.bytes())
+        .sign(seckey)
+        .build()
+}
+
+/// Build a kind 44 event muting `pubkey` in public chats for the signer
+pub fn mute_user_event<'a>(pubkey: &Pubkey, seckey: &[u8; 32]) -> Option<Note<'a>> {
+    NoteBuilder::new()
+        .kind(KIND_MUTE_USER)
+        .content("")
+        .start_tag()
+        .tag_str("p")
+        .tag_id(pubkey.bytes())
+        .sign(seckey)
+        .build()
+}
+
+/// Store `note` locally and send it to `relays`, or to all of our relays
+/// when empty
+pub fn send_event(ndb: &Ndb, pool: &mut RelayPool, note: &Note, relays: &BTreeSet<String>) {
+    let Ok(event) = &enostr::ClientMessage::event(note) else {
+        error!("nip28: failed to build client message");
+        return;
+    };
+
+    let Ok(json) = event.to_json() else {
+        error!("nip28: failed to serialize event");
+        return;
+    };
+
+    let _ = ndb.process_event_with(&json, IngestMetadata::new().client(true));
+
+    if relays.is_empty() {
+        pool.send(event);
+    } else {
+        for relay in relays {
+            pool.send_to(event, relay);
+        }
+    }
+}
+
+/// The filters used to follow a channel: its messages, its creation
+/// event and the metadata updates that reference it
+pub fn channel_filters(channel_id: &NoteId) -> Vec<Filter> {
+    vec![
+        Filter::new()
+            .kinds([KIND_CHANNEL_MESSAGE as u64])
+            .event(channel_id.bytes())
+            .limit(notedeck::filter::default_limit())
+            .build(),
+        Filter::new().ids([channel_id.bytes()]).build(),
+        Filter::new()
+            .kinds([KIND_CHANNEL_METADATA as u64])
+            .event(channel_id.bytes())
+            .limit(10)
+            .build(),
+    ]
+}
+
+/// Public channels we know about, newest first. Used to browse channels
+/// to join.
+pub fn browse_filter() -> Filter {
+    Filter::new()
+        .kinds([KIND_CHANNEL_CREATE as u64])
+        .limit(100)
+        .build()
+}
+
+/// The current metadata of a channel: the newest kind 41 from the channel
+/// creator, falling back to the kind 40 it was created with
+pub fn channel_metadata(
+    ndb: &Ndb,
+    txn: &Transaction,
+    channel_id: &NoteId,
+) -> Option<ChannelMetadata> {
+    let create = ndb.get_note_by_id(txn, channel_id.bytes()).ok()?;
+    if create.kind() != KIND_CHANNEL_CREATE {
+        return None;
+    }
+
+    let updates = Filter::new()
+        .kinds([KIND_CHANNEL_METADATA as u64])
+        .authors([create.pubkey()])
+        .event(channel_id.bytes())
+        .limit(1)
+        .build();
+
+    let latest = ndb
+        .query(txn, &[updates], 1)
+        .ok()
+        .and_then(|results| results.into_iter().next())
+        .and_then(|result| ChannelMetadata::from_note(&result.note));
+
+    latest.or_else(|| ChannelMetadata::from_note(&create))
+}
+
+/// The pubkey that created the channel, if we have its kind 40
+pub fn channel_creator(ndb: &Ndb, txn: &Transaction, channel_id: &NoteId) -> Option<Pubkey> {
+    let create = ndb.get_note_by_id(txn, channel_id.bytes()).ok()?;
+    (create.kind() == KIND_CHANNEL_CREATE).then(|| Pubkey::new(*create.pubkey()))
+}
+
+/// Messages and users the current account has hidden in public chats
+#[derive(Default, Debug)]
+pub struct Moderation {
+    pub hidden_messages: HashSet<NoteId>,
+    pub muted_users: HashSet<Pubkey>,
+}
+
+impl Moderation {
+    pub fn load(ndb: &Ndb, txn: &Transaction, account: &Pubkey) -> Self {
+        let mut moderation = Moderation::default();
+
+        let filter = Filter::new()
+            .kinds([KIND_HIDE_MESSAGE as u64, KIND_MUTE_USER as u64])
+            .authors([account.bytes()])
+            .limit(notedeck::filter::default_limit())
+            .build();
+
+        let Ok(results) = ndb.query(txn, &[filter], notedeck::filter::default_limit() as i32)
+        else {
+            return moderation;
+        };
+
+        for result in results {
+            let (tag_name, kind) = match result.note.kind() {
+                KIND_HIDE_MESSAGE => ("e", KIND_HIDE_MESSAGE),
+                KIND_MUTE_USER => ("p", KIND_MUTE_USER),
+                _ => continue,
+            };
+
+            for tag in result.note.tags() {
+                if tag.count() < 2 || tag.get_str(0) != Some(tag_name) {
+                    continue;
+                }
+
+                let Some(id) = tag.get_id(1) else {
+                    continue;
+                };
+
+                if kind == KIND_HIDE_MESSAGE {
+                    moderation.hidden_messages.insert(NoteId::new(*id));
+                } else {
+                    moderation.muted_users.insert(Pubkey::new(*id));
+                }
+            }
+        }
+
+        moderation
+    }
+
+    pub fn is_hidden(&self, note: &Note) -> bool {
+        self.hidden_messages.contains(&NoteId::new(*note.id()))
+            || self.muted_users.contains(&Pubkey::new(*note.pubkey()))
+    }
+}
+
+/// Parse a channel reference as pasted by a user: a `nevent`, a `note` or
+/// a hex event id, with or without the `nostr:` prefix. Returns the channel
+/// id and any relay hints.
+pub fn parse_channel_ref(input: &str) -> Option<(NoteId, Vec<String>)> {
+    let input = input.trim();
+    let input = input.strip_prefix("nostr:").unwrap_or(input);
+
+    if let Some(parsed) = NoteId::from_nevent(input) {
+        return Some(parsed);
+    }
+
+    NoteId::from_bech(input)
+        .or_else(|| NoteId::from_hex(input).ok())
+        .map(|id| (id, vec![]))
+}
+
+/// Create a channel on `relays` (all of our relays when empty), returning
+/// its id
+pub fn create_channel(
+    ndb: &Ndb,
+    pool: &mut RelayPool,
+    creator: &FullKeypair,
+    metadata: &ChannelMetadata,
+    relays: &BTreeSet<String>,
+) -> Option<NoteId> {
+    let note = create_channel_event(metadata, &creator.secret_key.secret_bytes())?;
+    send_event(ndb, pool, &note, relays);
+    Some(NoteId::new(*note.id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_channel_ref() {
+        let id = NoteId::new([7; 32]);
+
+        assert_eq!(parse_channel_ref(&id.hex()), Some((id, vec![])));
+
+        let bech = id.to_bech().unwrap();
+        assert_eq!(parse_channel_ref(&bech), Some((id, vec![])));
+        assert_eq!(
+            parse_channel_ref(&format!("  nostr:{bech} ")),
+            Some((id, vec![]))
+        );
+
+        assert_eq!(parse_channel_ref("not a channel"), None);
+    }
+
+    #[test]
+    fn test_metadata_json() {
+        let metadata = ChannelMetadata::new("nostr".to_owned(), String::new());
+        assert_eq!(metadata.to_json(), r#"{"name":"nostr"}"#);
+
+        let parsed: ChannelMetadata =
+            serde_json::from_str(r#"{"name":"dev","about":"builders","relays":[]}"#).unwrap();
+        assert_eq!(parsed.name, "dev");
+        assert_eq!(parsed.about, "builders");
+    }
+}
@@ -0,0 +1,395 @@
+//! NIP-29 relay-based groups
+//!
+//! A group lives on a single relay, which enforces who can read and write
+//! to it. Its id is only unique on that relay, so a group is referenced as
+//! `<relay host>'<group id>`. Messages are kind 9 events tagged with
+//! `["h", <group id>]` and must only ever be sent to the group's relay.
+//! The relay publishes the group metadata, admins and members as
+//! addressable events (kinds 39000-39002) whose `d` tag is the group id.
+//! Users ask to join or leave with kinds 9021 and 9022, and admins moderate
+//! the group with the 9000-9020 range.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use enostr::{NoteId, Pubkey, RelayPool};
+use nostrdb::{Filter, IngestMetadata, Ndb, Note, NoteBuilder, Transaction};
+use tracing::error;
+
+use crate::relay_config::canonical_relay_url;
+
+pub const KIND_CHAT_MESSAGE: u32 = 9;
+pub const KIND_PUT_USER: u32 = 9000;
+pub const KIND_REMOVE_USER: u32 = 9001;
+pub const KIND_DELETE_EVENT: u32 = 9005;
+pub const KIND_JOIN_REQUEST: u32 = 9021;
+pub const KIND_LEAVE_REQUEST: u32 = 9022;
+pub const KIND_GROUP_METADATA: u32 = 39000;
+pub const KIND_GROUP_ADMINS: u32 = 39001;
+pub const KIND_GROUP_MEMBERS: u32 = 39002;
+
+/// A group on a specific relay
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupRef {
+    /// Canonical websocket url of the relay hosting the group, as used by
+    /// the [`RelayPool`]
+    pub relay: String,
+    /// Used as the `h` tag of group events and the `d` tag of the group
+    /// state events
+    pub id: String,
+}
+
+impl GroupRef {
+    /// Returns None if `relay` isn't a websocket url or `id` isn't a valid
+    /// group id
+    pub fn new(relay: &str, id: &str) -> Option<Self> {
+        if !is_valid_group_id(id) {
+            return None;
+        }
+
+        Some(Self {
+            relay: canonical_relay_url(relay)?,
+            id: id.to_owned(),
+        })
+    }
+
+    /// Parse a group reference as shared between users: `host'group-id`,
+    /// optionally with a `ws://` or `wss://` scheme. Relays default to
+    /// `wss://`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let (relay, id) = input.trim().rsplit_once('\'')?;
+
+        if relay.contains("://") {
+            Self::new(relay, id)
+        } else {
+            Self::new(&format!("wss://{relay}"), id)
+        }
+    }
+}
+
+impl fmt::Display for GroupRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let host = url::Url::parse(&self.relay)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_owned()));
+
+        match host {
+            Some(host) => write!(f, "{host}'{}", self.id),
+            None => write!(f, "{}'{}", self.relay, self.id),
+        }
+    }
+}
+
+/// Group ids are restricted to `a-z0-9-_`
+fn is_valid_group_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// The group metadata published by the relay in kind 39000 events
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GroupMetadata {
+    pub name: String,
+    pub about: String,
+    pub picture: String,
+    /// Only members can read the group
+    pub private: bool,
+    /// Join requests are not honored automatically
+    pub closed: bool,
+}
+
+impl GroupMetadata {
+    pub fn from_note(note: &Note) -> Self {
+        let mut metadata = GroupMetadata::default();
+
+        for tag in note.tags() {
+            let Some(name) = tag.get_str(0) else {
+                continue;
+            };
+
+            let value = || tag.get_str(1).unwrap_or_default().to_owned();
+            match name {
+                "name" => metadata.name = value(),
+                "about" => metadata.about = value(),
+                "picture" => metadata.picture = value(),
+                "private" => metadata.private = true,
+                "public" => metadata.private = false,
+                "closed" => metadata.closed = true,
+                "open" => metadata.closed = false,
+                _ => {}
+            }
+        }
+
+        metadata
+    }
+}
+
+fn group_event<'a>(kind: u32, group: &GroupRef) -> NoteBuilder<'a> {
+    NoteBuilder::new()
+        .kind(kind)
+        .content("")
+        .start_tag()
+        .tag_str("h")
+        .tag_str(group.id.as_str())
+}
+
+/// Build a kind 9021 event asking to join `group`
+pub fn join_request_event<'a>(group: &GroupRef, seckey: &[u8; 32]) -> Option<Note<'a>> {
+    group_event(KIND_JOIN_REQUEST, group).sign(seckey).build()
+}
+
+/// Build a kind 9022 event asking to be removed from `group`
+pub fn leave_request_event<'a>(group: &GroupRef, seckey: &[u8; 32]) -> Option<Note<'a>> {
+    group_event(KIND_LEAVE_REQUEST, group).sign(seckey).build()
+}
+
+/// Build a kind 9001 event removing `pubkey` from `group`. Only honored by
+/// the relay when signed by an admin.
+pub fn remove_user_event<'a>(
+    group: &GroupRef,
+    pubkey: &Pubkey,
+    seckey: &[u8; 32],
+) -> Option<Note<'a>> {
+    group_event(KIND_REMOVE_USER, group)
+        .start_tag()
+        .tag_str("p")
+        .tag_id(pubkey.bytes())
+        .sign(seckey)
+        .build()
+}
+
+/// Build a kind 9005 event deleting `message_id` from `group`. Only
+/// honored by the relay when signed by an admin.
+pub fn delete_message_event<'a>(
+    group: &GroupRef,
+    message_id: &NoteId,
+    seckey: &[u8; 32],
+) -> Option<Note<'a>> {
+    group_event(KIND_DELETE_EVENT, group)
+        .start_tag()
+        .tag_str("e")
+        .tag_id(message_id.bytes())
+        .sign(seckey)
+        .build()
+}
+
+/// Store `note` locally and send it to the group's relay only
+pub fn send_event(ndb: &Ndb, pool: &mut RelayPool, group: &GroupRef, note: &Note) {
+    let Ok(event) = &enostr::ClientMessage::event(note) else {
+        error!("nip29: failed to build client message");
+        return;
+    };
+
+    let Ok(json) = event.to_json() else {
+        error!("nip29: failed to serialize event");
+        return;
+    };
+
+    let _ = ndb.process_event_with(&json, IngestMetadata::new().client(true));
+
+    pool.send_to(event, &group.relay);
+}
+
+/// The filters used to follow a group: its messages, the deletions done
+/// by its admins and the relay-published group state
+pub fn group_filters(group: &GroupRef) -> Vec<Filter> {
+    vec![
+        Filter::new()
+            .kinds([KIND_CHAT_MESSAGE as u64])
+            .tags([group.id.as_str()], 'h')
+            .limit(notedeck::filter::default_limit())
+            .build(),
+        Filter::new()
+            .kinds([KIND_DELETE_EVENT as u64])
+            .tags([group.id.as_str()], 'h')
+            .limit(notedeck::filter::default_limit())
+            .build(),
+        Filter::new()
+            .kinds([
+                KIND_GROUP_METADATA as u64,
+                KIND_GROUP_ADMINS as u64,
+                KIND_GROUP_MEMBERS as u64,
+            ])
+            .tags([group.id.as_str()], 'd')
+            .build(),
+    ]
+}
+
+/// The newest relay-published state event of `kind` for `group`
+fn latest_state_note<'a>(
+    ndb: &Ndb,
+    txn: &'a Transaction,
+    group: &GroupRef,
+    kind: u32,
+) -> Option<Note<'a>> {
+    let filter = Filter::new()
+        .kinds([kind as u64])
+        .tags([group.id.as_str()], 'd')
+        .limit(1)
+        .build();
+
+    ndb.query(txn, &[filter], 1)
+        .ok()
+        .and_then(|results| results.into_iter().next())
+        .map(|result| result.note)
+}
+
+fn tagged_pubkeys(note: &Note) -> HashSet<Pubkey> {
+    note.tags()
+        .iter()
+        .filter(|tag| tag.count() >= 2 && tag.get_str(0) == Some("p"))
+        .filter_map(|tag| tag.get_id(1).map(|id| Pubkey::new(*id)))
+        .collect()
+}
+
+pub fn group_metadata(ndb: &Ndb, txn: &Transaction, group: &GroupRef) -> Option<GroupMetadata> {
+    latest_state_note(ndb, txn, group, KIND_GROUP_METADATA)
+        .map(|note| GroupMetadata::from_note(&note))
+}
+
+/// The group name from its kind 39000, borrowed from the transaction
+pub fn group_name<'a>(ndb: &Ndb, txn: &'a Transaction, group: &GroupRef) -> Option<&'a str> {
+    let note = latest_state_note(ndb, txn, group, KIND_GROUP_METADATA)?;
+    note.tags()
+        .iter()
+        .find(|tag| tag.count() >= 2 && tag.get_str(0) == Some("name"))
+        .and_then(|tag| tag.get_str(1))
+        .filter(|name| !name.is_empty())
+}
+
+/// What we know about a group from its relay: admins, members and the
+/// messages its admins deleted
+#[derive(Default, Debug)]
+pub struct GroupState {
+    pub admins: HashSet<Pubkey>,
+    pub members: HashSet<Pubkey>,
+    pub deleted_messages: HashSet<NoteId>,
+}
+
+impl GroupState {
+    pub fn load(ndb: &Ndb, txn: &Transaction, group: &GroupRef) -> Self {
+        let mut state = GroupState::default();
+
+        if let Some(note) = latest_state_note(ndb, txn, group, KIND_GROUP_ADMINS) {
+            state.admins = tagged_pubkeys(&note);
+        }
+
+        if let Some(note) = latest_state_note(ndb, txn, group, KIND_GROUP_MEMBERS) {
+            state.members = tagged_pubkeys(&note);
+        }
+
+        if state.admins.is_empty() {
+            return state;
+        }
+
+        // the relay drops deleted messages itself, but we may already have
+        // them locally
+        let admins: Vec<&[u8; 32]> = state.admins.iter().map(|pk| pk.bytes()).collect();
+        let deletions = Filter::new()
+            .kinds([KIND_DELETE_EVENT as u64])
+            .authors(admins)
+            .tags([group.id.as_str()], 'h')
+            .limit(notedeck::filter::default_limit())
+            .build();
+
+        let Ok(results) = ndb.query(txn, &[deletions], notedeck::filter::default_limit() as i32)
+        else {
+            return state;
+        };
+
+        for result in results {
+            for tag in result.note.tags() {
+                if tag.count() < 2 || tag.get_str(0) != Some("e") {
+                    continue;
+                }
+
+                if let Some(id) = tag.get_id(1) {
+                    state.deleted_messages.insert(NoteId::new(*id));
+                }
+            }
+        }
+
+        state
+    }
+
+    pub fn is_admin(&self, pubkey: &Pubkey) -> bool {
+        self.admins.contains(pubkey)
+    }
+
+    pub fn is_member(&self, pubkey: &Pubkey) -> bool {
+        self.members.contains(pubkey) || self.admins.contains(pubkey)
+    }
+
+    pub fn is_hidden(&self, note: &Note) -> bool {
+        self.deleted_messages.contains(&NoteId::new(*note.id()))
+    }
+}
+
+/// Ask the group's relay to let us join, returning false if the event
+/// couldn't be built
+pub fn request_join(ndb: &Ndb, pool: &mut RelayPool, group: &GroupRef, seckey: &[u8; 32]) -> bool {
+    let Some(note) = join_request_event(group, seckey) else {
+        return false;
+    };
+    send_event(ndb, pool, group, &note);
+    true
+}
+
+/// Ask the group's relay to remove us from the group, returning false if
+/// the event couldn't be built
+pub fn request_leave(ndb: &Ndb, pool: &mut RelayPool, group: &GroupRef, seckey: &[u8; 32]) -> bool {
+    let Some(note) = leave_request_event(group, seckey) else {
+        return false;
+    };
+    send_event(ndb, pool, group, &note);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_group_ref() {
+        let group = GroupRef::parse("groups.example.com'dev-team").unwrap();
+        assert_eq!(group.relay, "wss://groups.example.com/");
+        assert_eq!(group.id, "dev-team");
+        assert_eq!(group.to_string(), "groups.example.com'dev-team");
+
+        let local = GroupRef::parse(" ws://localhost:7777'_ ").unwrap();
+        assert_eq!(local.relay, "ws://localhost:7777/");
+        assert_eq!(local.id, "_");
+
+        assert_eq!(GroupRef::parse("groups.example.com"), None);
+        assert_eq!(GroupRef::parse("groups.example.com'Dev Team"), None);
+        assert_eq!(GroupRef::parse("https://example.com'dev"), None);
+    }
+
+    #[test]
+    fn test_group_events_are_h_tagged() {
+        let group = GroupRef::parse("groups.example.com'dev").unwrap();
+        let seckey = [1; 32];
+
+        let join = join_request_event(&group, &seckey).unwrap();
+        assert_eq!(join.kind(), KIND_JOIN_REQUEST);
+
+        let remove = remove_user_event(&group, &Pubkey::new([2; 32]), &seckey).unwrap();
+        assert_eq!(remove.kind(), KIND_REMOVE_USER);
+
+        for note in [&join, &remove] {
+            let h = note
+                .tags()
+                .iter()
+                .find(|tag| tag.get_str(0) == Some("h"))
+                .and_then(|tag| tag.get_str(1));
+            assert_eq!(h, Some("dev"));
+        }
+
+        assert_eq!(
+            tagged_pubkeys(&remove),
+            HashSet::from([Pubkey::new([2; 32])])
+        );
+    }
+}
@@ -3,8 +3,9 @@ use egui::{
     text_edit::TextEditOutput,
     TextBuffer, TextEdit, TextFormat,
 };
-use enostr::{FullKeypair, Pubkey};
+use enostr::{NoteId, Pubkey};
 use nostrdb::{Note, NoteBuilder, NoteReply};
+use notedeck::UnsignedEvent;
 use std::{
     any::TypeId,
     collections::{BTreeMap, HashMap, HashSet},
@@ -14,10 +15,12 @@ use std::{
 use tracing::error;
 
 use crate::media_upload::Nip94Event;
+use crate::nip28;
+use crate::nip29::{self, GroupRef};
 
 pub struct NewPost {
     pub content: String,
-    pub account: FullKeypair,
+    pub account: Pubkey,
     pub media: Vec<Nip94Event>,
     pub mentions: Vec<Pubkey>,
 }
@@ -44,7 +47,7 @@ fn add_client_tag(builder: NoteBuilder<'_>) -> NoteBuilder<'_> {
 impl NewPost {
     pub fn new(
         content: String,
-        account: enostr::FullKeypair,
+        account: Pubkey,
         media: Vec<Nip94Event>,
         mentions: Vec<Pubkey>,
     ) -> Self {
@@ -57,10 +60,10 @@ impl NewPost {
     }
 
     /// creates a NoteBuilder with all the shared data between note, reply & quote reply
-    fn builder_with_shared_tags<'a>(&self, mut content: String) -> NoteBuilder<'a> {
+    fn builder_with_shared_tags<'a>(&self, kind: u32, mut content: String) -> NoteBuilder<'a> {
         append_urls(&mut content, &self.media);
 
-        let mut builder = NoteBuilder::new().kind(1).content(&content);
+        let mut builder = NoteBuilder::new().kind(kind).content(&content);
         builder = add_client_tag(builder);
 
         for hashtag in Self::extract_hashtags(&self.content) {
@@ -78,14 +81,51 @@ impl NewPost {
         builder
     }
 
-    pub fn to_note(&self, seckey: &[u8; 32]) -> Note<'_> {
-        let builder = self.builder_with_shared_tags(self.content.clone());
+    /// The note for the account's signer to sign
+    fn unsigned(&self, builder: NoteBuilder<'_>) -> UnsignedEvent {
+        UnsignedEvent::new(&self.account, builder).expect("expected build to work")
+    }
+
+    pub fn to_note(&self) -> UnsignedEvent {
+        let builder = self.builder_with_shared_tags(1, self.content.clone());
+
+        self.unsigned(builder)
+    }
+
+    /// A NIP-28 kind 42 message posted at the top level of a public chat
+    pub fn to_channel_message(&self, channel_id: &NoteId) -> UnsignedEvent {
+        let builder = self
+            .builder_with_shared_tags(nip28::KIND_CHANNEL_MESSAGE, self.content.clone())
+            .start_tag()
+            .tag_str("e")
+            .tag_str(&channel_id.hex())
+            .tag_str("")
+            .tag_str("root");
+
+        self.unsigned(builder)
+    }
+
+    /// A NIP-29 kind 9 message for `group`. It must only be sent to the
+    /// group's relay.
+    pub fn to_group_message(&self, group: &GroupRef) -> UnsignedEvent {
+        let builder = self
+            .builder_with_shared_tags(nip29::KIND_CHAT_MESSAGE, self.content.clone())
+            .start_tag()
+            .tag_str("h")
+            .tag_str(&group.id);
 
-        builder.sign(seckey).build().expect("note should be ok")
+        self.unsigned(builder)
     }
 
-    pub fn to_reply(&self, seckey: &[u8; 32], replying_to: &Note) -> Note<'_> {
-        let mut builder = self.builder_with_shared_tags(self.content.clone());
+    pub fn to_reply(&self, replying_to: &Note) -> UnsignedEvent {
+        // replies to public chat messages stay in the chat. Their root is
+        // the channel, so the NIP-10 tags below work out the same.
+        let kind = if replying_to.kind() == nip28::KIND_CHANNEL_MESSAGE {
+            nip28::KIND_CHANNEL_MESSAGE
+        } else {
+            1
+        };
+        let mut builder = self.builder_with_shared_tags(kind, self.content.clone());
 
         let nip10 = NoteReply::new(replying_to.tags());
 
@@ -101,7 +141,6 @@ impl NewPost {
                 .tag_str(&hex::encode(replying_to.id()))
                 .tag_str("")
                 .tag_str("reply")
-                .sign(seckey)
         } else {
             // we're replying to a post that isn't in a thread,
             // just add a single reply-to-root tag
@@ -111,7 +150,6 @@ impl NewPost {
                 .tag_str(&hex::encode(replying_to.id()))
                 .tag_str("")
                 .tag_str("root")
-                .sign(seckey)
         };
 
         let mut seen_p: HashSet<&[u8; 32]> = HashSet::new();
@@ -147,31 +185,50 @@ impl NewPost {
             builder = builder.start_tag().tag_str("p").tag_str(&hex::encode(id));
         }
 
-        builder
-            .sign(seckey)
-            .build()
-            .expect("expected build to work")
+        self.unsigned(builder)
     }
 
-    pub fn to_quote(&self, seckey: &[u8; 32], quoting: &Note) -> Note<'_> {
+    pub fn to_quote(&self, quoting: &Note) -> UnsignedEvent {
         let new_content = format!(
             "{}\nnostr:{}",
             self.content,
             enostr::NoteId::new(*quoting.id()).to_bech().unwrap()
         );
 
-        let builder = self.builder_with_shared_tags(new_content);
+        let builder = self.builder_with_shared_tags(1, new_content);
 
-        builder
+        let builder = builder
             .start_tag()
             .tag_str("q")
             .tag_str(&hex::encode(quoting.id()))
             .start_tag()
             .tag_str("p")
-            .tag_str(&hex::encode(quoting.pubkey()))
-            .sign(seckey)
-            .build()
-            .expect("expected build to work")
+            .tag_str(&hex::encode(quoting.pubkey()));
+
+        self.unsigned(builder)
+    }
+
+    /// Appends a `#tag` for every channel hashtag missing from `content`.
+    /// The `t` tags are derived from the content, so this makes sure a
+    /// message posted into a channel is tagged with all of its hashtags.
+    pub fn with_channel_hashtags(content: &str, hashtags: &[String]) -> String {
+        let mut present = Self::extract_hashtags(content);
+        let mut content = content.to_owned();
+
+        for hashtag in hashtags {
+            let hashtag = hashtag.trim_start_matches('#');
+            if hashtag.is_empty() || !present.insert(hashtag.to_lowercase()) {
+                continue;
+            }
+
+            if !content.is_empty() && !content.ends_with(char::is_whitespace) {
+                content.push(' ');
+            }
+            content.push('#');
+            content.push_str(hashtag);
+        }
+
+        content
     }
 
     fn extract_hashtags(content: &str) -> HashSet<String> {
@@ -756,6 +813,7 @@ pub struct MentionInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use enostr::FullKeypair;
     use pretty_assertions::assert_eq;
 
     impl MentionInfo {
@@ -884,6 +942,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_with_channel_hashtags() {
+        let hashtags = vec!["nostr".to_owned(), "#Dev".to_owned()];
+
+        let test_cases = vec![
+            ("hello", "hello #nostr #Dev"),
+            ("hello #nostr", "hello #nostr #Dev"),
+            ("#DEV and #NOSTR", "#DEV and #NOSTR"),
+            ("trailing space ", "trailing space #nostr #Dev"),
+            ("", "#nostr #Dev"),
+        ];
+
+        for (input, expected) in test_cases {
+            let result = NewPost::with_channel_hashtags(input, &hashtags);
+            assert_eq!(result, expected, "Failed for input: {}", input);
+
+            let tags = NewPost::extract_hashtags(&result);
+            assert!(tags.contains("nostr") && tags.contains("dev"));
+        }
+    }
+
     #[test]
     fn test_insert_single_mention() {
         let mut buf = PostBuffer::default();
@@ -1273,18 +1352,18 @@ mod tests {
 
         let out = buf.output();
         let kp = FullKeypair::generate();
-        let post = NewPost::new(out.text, kp.clone(), Vec::new(), out.mentions);
-        let note = post.to_note(&kp.pubkey);
+        let post = NewPost::new(out.text, kp.pubkey, Vec::new(), out.mentions);
+        let note = post.to_note();
 
-        let mut tags_iter = note.tags().iter();
+        let mut tags_iter = note.tags.iter();
         tags_iter.next(); //ignore the first one, the client tag
         let tag = tags_iter.next().unwrap();
-        assert_eq!(tag.count(), 2);
-        assert_eq!(tag.get(0).unwrap().str().unwrap(), "p");
-        assert_eq!(tag.get(1).unwrap().id().unwrap(), JB55().bytes());
+        assert_eq!(tag.len(), 2);
+        assert_eq!(tag[0], "p");
+        assert_eq!(tag[1], JB55().hex());
         assert!(tags_iter.next().is_none());
         assert_eq!(
-            note.content(),
+            note.content,
             "nostr:npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s"
         );
     }
@@ -1303,24 +1382,24 @@ mod tests {
 
         let out = buf.output();
         let kp = FullKeypair::generate();
-        let post = NewPost::new(out.text, kp.clone(), Vec::new(), out.mentions);
-        let note = post.to_note(&kp.pubkey);
+        let post = NewPost::new(out.text, kp.pubkey, Vec::new(), out.mentions);
+        let note = post.to_note();
 
-        let mut tags_iter = note.tags().iter();
+        let mut tags_iter = note.tags.iter();
         tags_iter.next(); //ignore the first one, the client tag
         let jb_tag = tags_iter.next().unwrap();
-        assert_eq!(jb_tag.count(), 2);
-        assert_eq!(jb_tag.get(0).unwrap().str().unwrap(), "p");
-        assert_eq!(jb_tag.get(1).unwrap().id().unwrap(), JB55().bytes());
+        assert_eq!(jb_tag.len(), 2);
+        assert_eq!(jb_tag[0], "p");
+        assert_eq!(jb_tag[1], JB55().hex());
 
         let kk_tag = tags_iter.next().unwrap();
-        assert_eq!(kk_tag.count(), 2);
-        assert_eq!(kk_tag.get(0).unwrap().str().unwrap(), "p");
-        assert_eq!(kk_tag.get(1).unwrap().id().unwrap(), KK().bytes());
+        assert_eq!(kk_tag.len(), 2);
+        assert_eq!(kk_tag[0], "p");
+        assert_eq!(kk_tag[1], KK().hex());
 
         assert!(tags_iter.next().is_none());
 
-        assert_eq!(note.content(), "nostr:npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s test nostr:npub1fgz3pungsr2quse0fpjuk4c5m8fuyqx2d6a3ddqc4ek92h6hf9ns0mjeck test");
+        assert_eq!(note.content, "nostr:npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s test nostr:npub1fgz3pungsr2quse0fpjuk4c5m8fuyqx2d6a3ddqc4ek92h6hf9ns0mjeck test");
     }
 
     #[test]
@@ -1332,12 +1411,12 @@ mod tests {
 
         let out = buf.output();
         let kp = FullKeypair::generate();
-        let post = NewPost::new(out.text, kp.clone(), Vec::new(), out.mentions);
-        let note = post.to_note(&kp.pubkey);
+        let post = NewPost::new(out.text, kp.pubkey, Vec::new(), out.mentions);
+        let note = post.to_note();
 
-        let mut tags_iter = note.tags().iter();
+        let mut tags_iter = note.tags.iter();
         tags_iter.next(); //ignore the first one, the client tag
         assert!(tags_iter.next().is_none());
-        assert_eq!(note.content(), "test @jb55 test");
+        assert_eq!(note.content, "test @jb55 test");
     }
 }
@@ -62,6 +62,19 @@ impl Default for RelayConfig {
     }
 }
 
+/// Normalize a websocket relay url the way the RelayPool does (ie,
+/// trailing slashes), so it can be used with `RelayPool::send_to` and
+/// `RelayPool::subscribe_to`. Returns None for anything that isn't a
+/// `ws://` or `wss://` url.
+pub fn canonical_relay_url(url: &str) -> Option<String> {
+    let url = url::Url::parse(url.trim()).ok()?;
+    if !matches!(url.scheme(), "ws" | "wss") || url.host_str().is_none() {
+        return None;
+    }
+
+    Some(url.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,6 +92,16 @@ mod tests {
         assert!(config.is_empty());
     }
 
+    #[test]
+    fn test_canonical_relay_url() {
+        assert_eq!(
+            canonical_relay_url(" wss://relay.example.com "),
+            Some("wss://relay.example.com/".to_string())
+        );
+        assert_eq!(canonical_relay_url("https://relay.example.com"), None);
+        assert_eq!(canonical_relay_url("relay.example.com"), None);
+    }
+
     #[test]
     fn test_default_relays() {
         let config = RelayConfig::default_relays();
@@ -1,14 +1,15 @@
-use enostr::{Keypair, NoteId, RelayPool};
-use nostrdb::{Ndb, Note, NoteBuilder, Transaction};
+use enostr::{NoteId, Pubkey, RelayPool};
+use nostrdb::{Ndb, NoteBuilder, Transaction};
+use notedeck::{Accounts, Outbox, UnsignedEvent};
 
 use crate::{nav::RouterAction, Route};
 
-pub fn generate_repost_event<'a>(
-    ndb: &'a Ndb,
+pub fn generate_repost_event(
+    ndb: &Ndb,
     noteid_to_repost: &NoteId,
-    signer_nsec: &[u8; 32],
+    reposter: &Pubkey,
     pool: &RelayPool,
-) -> Result<Note<'a>, String> {
+) -> Result<UnsignedEvent, String> {
     let txn = Transaction::new(ndb).expect("txn");
     let note_to_repost = ndb
         .get_note_by_id(&txn, noteid_to_repost.bytes())
@@ -33,7 +34,7 @@ pub fn generate_repost_event<'a>(
         .json()
         .map_err(|e| format!("could not convert note {note_to_repost:?} to json: {e}"))?;
 
-    NoteBuilder::new()
+    let builder = NoteBuilder::new()
         .content(&note_to_repost_content)
         .kind(6)
         .start_tag()
@@ -42,10 +43,9 @@ pub fn generate_repost_event<'a>(
         .tag_str(relay)
         .start_tag()
         .tag_str("p")
-        .tag_id(note_to_repost.pubkey())
-        .sign(signer_nsec)
-        .build()
-        .ok_or("Failure in NoteBuilder::build".to_owned())
+        .tag_id(note_to_repost.pubkey());
+
+    UnsignedEvent::new(reposter, builder).map_err(|e| format!("Failure in NoteBuilder::build: {e}"))
 }
 
 pub enum RepostAction {
@@ -58,41 +58,26 @@ impl RepostAction {
     pub fn process(
         self,
         ndb: &nostrdb::Ndb,
-        current_user: &Keypair,
+        accounts: &mut Accounts,
         pool: &mut RelayPool,
+        outbox: &mut Outbox,
     ) -> Option<RouterAction> {
         match self {
             RepostAction::Quote(note_id) => {
                 Some(RouterAction::CloseSheetThenRoute(Route::quote(note_id)))
             }
             RepostAction::Kind06Repost(note_id) => {
-                let Some(full_user) = current_user.to_full() else {
-                    tracing::error!("Attempting to make a kind 6 repost, but we don't have nsec");
-                    return None;
-                };
-
-                let repost_ev = generate_repost_event(
-                    ndb,
-                    &note_id,
-                    &full_user.secret_key.secret_bytes(),
-                    pool,
-                )
-                .inspect_err(|e| tracing::error!("failure to generate repost event: {e}"))
-                .ok()?;
-
-                let Ok(event) = &enostr::ClientMessage::event(&repost_ev) else {
-                    tracing::error!("send_note_builder: failed to build json");
-                    return None;
-                };
-
-                let Ok(json) = event.to_json() else {
-                    tracing::error!("send_note_builder: failed to build json");
+                let Some(current_user) = accounts.selected_poster() else {
+                    tracing::error!("Attempting to make a kind 6 repost, but we can't sign");
                     return None;
                 };
 
-                let _ = ndb.process_event_with(&json, nostrdb::IngestMetadata::new().client(true));
+                let repost_ev = generate_repost_event(ndb, &note_id, current_user.pubkey, pool)
+                    .inspect_err(|e| tracing::error!("failure to generate repost event: {e}"))
+                    .ok()?;
 
-                pool.send(event);
+                let signed = accounts.sign(repost_ev);
+                outbox.sign_and_send(signed, vec![]);
 
                 Some(RouterAction::GoBack)
             }
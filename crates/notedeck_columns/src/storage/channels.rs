@@ -1,16 +1,29 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
-use enostr::Pubkey;
+use enostr::{NoteId, Pubkey};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 use uuid::Uuid;
 
-use crate::channels::{Channel, ChannelList, ChannelsCache};
+use crate::channel_filter::{AuthorFilter, ChannelFilter};
+use crate::channels::{
+    Channel, ChannelKind, ChannelList, ChannelRelays, ChannelSection, ChannelsCache,
+};
+use crate::nip29::GroupRef;
 
+use notedeck::debouncer::Debouncer;
 use notedeck::{storage, DataPath, DataPathType, Directory, Localization};
 
 pub static CHANNELS_CACHE_FILE: &str = "channels_cache.json";
 
+/// Files without a version were written before channels could be
+/// reordered, and remember the selected channel by its index
+const CHANNELS_CACHE_VERSION: u32 = 1;
+
+/// Version of the channel list synced between devices
+const SYNCED_CHANNELS_VERSION: u32 = 1;
+
 pub fn load_channels_cache(path: &DataPath, i18n: &mut Localization) -> Option<ChannelsCache> {
     let data_path = path.path(DataPathType::Setting);
 
@@ -25,8 +38,9 @@ pub fn load_channels_cache(path: &DataPath, i18n: &mut Localization) -> Option<C
         }
     };
 
-    let serializable_channels_cache =
+    let mut serializable_channels_cache =
         serde_json::from_str::<SerializableChannelsCache>(&channels_cache_str).ok()?;
+    serializable_channels_cache.migrate();
 
     Some(serializable_channels_cache.channels_cache(i18n))
 }
@@ -57,8 +71,93 @@ pub fn save_channels_cache(path: &DataPath, channels_cache: &ChannelsCache) {
     }
 }
 
+/// Writes the channels cache at most every couple of seconds, for changes
+/// that can happen every frame like read markers. The last ones can be
+/// lost on exit, which only means rereading a few messages.
+pub struct ChannelsCacheSaver {
+    debouncer: Debouncer,
+    dirty: bool,
+}
+
+impl Default for ChannelsCacheSaver {
+    fn default() -> Self {
+        Self {
+            debouncer: Debouncer::new(Duration::from_secs(2)),
+            dirty: false,
+        }
+    }
+}
+
+impl ChannelsCacheSaver {
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Save the cache if it changed and the last save was long enough ago
+    pub fn update(&mut self, path: &DataPath, channels_cache: &ChannelsCache) {
+        if self.dirty && self.debouncer.should_act() {
+            self.debouncer.bounce();
+            self.dirty = false;
+            save_channels_cache(path, channels_cache);
+        }
+    }
+}
+
+/// The parts of a channel list that are synced between devices, as JSON.
+/// Which channel is selected and how far it was read stay on each device.
+pub fn synced_channel_list_json(channel_list: &ChannelList) -> Option<String> {
+    let mut channel_list = SerializableChannelList::from_channel_list(channel_list);
+    channel_list.selected_id = None;
+    channel_list.modified_at = 0;
+    for channel in &mut channel_list.channels {
+        channel.last_read = None;
+    }
+
+    let synced = SyncedChannelList {
+        version: SYNCED_CHANNELS_VERSION,
+        channel_list,
+    };
+
+    match serde_json::to_string(&synced) {
+        Ok(json) => Some(json),
+        Err(e) => {
+            error!("Could not serialize synced channel list: {}", e);
+            None
+        }
+    }
+}
+
+/// Parse a channel list synced by [`synced_channel_list_json`]
+pub fn parse_synced_channel_list(json: &str) -> Option<ChannelList> {
+    let synced = match serde_json::from_str::<SyncedChannelList>(json) {
+        Ok(synced) => synced,
+        Err(e) => {
+            error!("Could not parse synced channel list: {}", e);
+            return None;
+        }
+    };
+
+    if synced.version > SYNCED_CHANNELS_VERSION {
+        warn!(
+            "Synced channel list is version {}, we only know {}",
+            synced.version, SYNCED_CHANNELS_VERSION
+        );
+    }
+
+    Some(synced.channel_list.channel_list())
+}
+
+#[derive(Serialize, Deserialize)]
+struct SyncedChannelList {
+    version: u32,
+    #[serde(flatten)]
+    channel_list: SerializableChannelList,
+}
+
 #[derive(Serialize, Deserialize)]
 struct SerializableChannelsCache {
+    #[serde(default)]
+    version: u32,
     #[serde(serialize_with = "serialize_map", deserialize_with = "deserialize_map")]
     channels_cache: HashMap<Pubkey, SerializableChannelList>,
 }
@@ -66,6 +165,7 @@ struct SerializableChannelsCache {
 impl SerializableChannelsCache {
     fn to_serializable(channels_cache: &ChannelsCache) -> Self {
         SerializableChannelsCache {
+            version: CHANNELS_CACHE_VERSION,
             channels_cache: channels_cache
                 .get_mapping()
                 .iter()
@@ -74,6 +174,17 @@ impl SerializableChannelsCache {
         }
     }
 
+    /// Bring files written by older versions up to date
+    fn migrate(&mut self) {
+        if self.version < 1 {
+            for channel_list in self.channels_cache.values_mut() {
+                channel_list.migrate_selected_index();
+            }
+        }
+
+        self.version = CHANNELS_CACHE_VERSION;
+    }
+
     pub fn channels_cache(self, i18n: &mut Localization) -> ChannelsCache {
         let account_to_channels = self
             .channels_cache
@@ -120,7 +231,21 @@ where
 #[derive(Serialize, Deserialize)]
 struct SerializableChannelList {
     channels: Vec<SerializableChannel>,
-    selected: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sections: Vec<SerializableSection>,
+    /// Id of the selected channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selected_id: Option<String>,
+    /// Index of the selected channel, only in version 0 files
+    #[serde(default, skip_serializing)]
+    selected: Option<usize>,
+    /// When the synced parts of the list last changed
+    #[serde(default, skip_serializing_if = "is_zero")]
+    modified_at: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
 }
 
 impl SerializableChannelList {
@@ -131,7 +256,24 @@ impl SerializableChannelList {
                 .iter()
                 .map(SerializableChannel::from_channel)
                 .collect(),
-            selected: channel_list.selected,
+            sections: channel_list
+                .sections
+                .iter()
+                .map(SerializableSection::from_section)
+                .collect(),
+            selected_id: channel_list
+                .selected_channel()
+                .map(|channel| channel.id.to_string()),
+            selected: None,
+            modified_at: channel_list.modified_at,
+        }
+    }
+
+    /// The selected index stops pointing at the same channel once channels
+    /// are reordered, so remember the channel's id instead
+    fn migrate_selected_index(&mut self) {
+        if let Some(channel) = self.selected.take().and_then(|i| self.channels.get(i)) {
+            self.selected_id = Some(channel.id.clone());
         }
     }
 
@@ -142,27 +284,175 @@ impl SerializableChannelList {
             .map(|c| c.channel())
             .collect();
 
-        // Ensure selected index is within bounds
-        let selected = if channels.is_empty() {
-            0
-        } else if self.selected >= channels.len() {
-            channels.len() - 1
-        } else {
-            self.selected
-        };
+        let sections = self
+            .sections
+            .into_iter()
+            .filter_map(SerializableSection::section)
+            .collect();
+
+        // Archived channels can't be selected, fall back to the first
+        // active one
+        let selected_id = self.selected_id.and_then(|id| Uuid::parse_str(&id).ok());
+        let selected = channels
+            .iter()
+            .position(|c| Some(c.id) == selected_id && !c.archived)
+            .or_else(|| channels.iter().position(|c| !c.archived))
+            .unwrap_or(0);
 
         ChannelList {
             channels,
+            sections,
             selected,
+            modified_at: self.modified_at,
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct SerializableSection {
+    id: String,
+    name: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    collapsed: bool,
+}
+
+impl SerializableSection {
+    fn from_section(section: &ChannelSection) -> Self {
+        Self {
+            id: section.id.to_string(),
+            name: section.name.clone(),
+            collapsed: section.collapsed,
+        }
+    }
+
+    fn section(self) -> Option<ChannelSection> {
+        let Ok(id) = Uuid::parse_str(&self.id) else {
+            error!("Invalid id for channel section {}: {}", self.name, self.id);
+            return None;
+        };
+
+        Some(ChannelSection {
+            id,
+            name: self.name,
+            collapsed: self.collapsed,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct SerializableChannel {
     id: String,
     name: String,
     hashtags: Vec<String>,
+    /// Missing in files written before read markers existed. Those channels
+    /// start out fully read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_read: Option<u64>,
+    /// Hex id of the kind 40 event, for NIP-28 public chats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_chat: Option<String>,
+    /// For NIP-29 groups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<SerializableGroup>,
+    /// Relays used instead of the global relays
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    relays: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    read_only: bool,
+    /// Authors, kinds and mute words of hashtag channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filter: Option<SerializableChannelFilter>,
+    /// Id of the sidebar section the channel is listed under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    section: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pinned: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    archived: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializableGroup {
+    relay: String,
+    id: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SerializableChannelFilter {
+    /// Hex pubkeys
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    follow_set: Option<SerializableFollowSet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    kinds: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mute_words: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializableFollowSet {
+    author: String,
+    identifier: String,
+}
+
+impl SerializableChannelFilter {
+    fn from_filter(filter: &ChannelFilter) -> Option<Self> {
+        if filter.is_empty() {
+            return None;
+        }
+
+        let mut serializable = Self {
+            kinds: filter.kinds.clone(),
+            mute_words: filter.mute_words.clone(),
+            ..Default::default()
+        };
+
+        match &filter.authors {
+            AuthorFilter::Anyone => {}
+            AuthorFilter::Pubkeys(pubkeys) => {
+                serializable.authors = pubkeys.iter().map(Pubkey::hex).collect();
+            }
+            AuthorFilter::FollowSet { author, identifier } => {
+                serializable.follow_set = Some(SerializableFollowSet {
+                    author: author.hex(),
+                    identifier: identifier.clone(),
+                });
+            }
+        }
+
+        Some(serializable)
+    }
+
+    fn filter(self) -> ChannelFilter {
+        let authors = if let Some(follow_set) = self.follow_set {
+            match Pubkey::from_hex(&follow_set.author) {
+                Ok(author) => AuthorFilter::FollowSet {
+                    author,
+                    identifier: follow_set.identifier,
+                },
+                Err(err) => {
+                    error!("Invalid follow set author {}: {err}", follow_set.author);
+                    AuthorFilter::Anyone
+                }
+            }
+        } else if self.authors.is_empty() {
+            AuthorFilter::Anyone
+        } else {
+            AuthorFilter::Pubkeys(
+                self.authors
+                    .iter()
+                    .filter_map(|pk| Pubkey::from_hex(pk).ok())
+                    .collect(),
+            )
+        };
+
+        ChannelFilter {
+            authors,
+            kinds: self.kinds,
+            mute_words: self.mute_words,
+        }
+    }
 }
 
 impl SerializableChannel {
@@ -171,11 +461,371 @@ impl SerializableChannel {
             id: channel.id.to_string(),
             name: channel.name.clone(),
             hashtags: channel.hashtags.clone(),
+            last_read: Some(channel.last_read),
+            public_chat: channel.kind.public_chat_id().map(|id| id.hex()),
+            group: channel.kind.group().map(|group| SerializableGroup {
+                relay: group.relay.clone(),
+                id: group.id.clone(),
+            }),
+            relays: channel.relays.urls.iter().cloned().collect(),
+            read_only: channel.relays.read_only,
+            filter: SerializableChannelFilter::from_filter(&channel.filter),
+            section: channel.section.map(|id| id.to_string()),
+            pinned: channel.pinned,
+            archived: channel.archived,
         }
     }
 
     pub fn channel(self) -> Channel {
         let id = Uuid::parse_str(&self.id).unwrap_or_else(|_| Uuid::new_v4());
-        Channel::with_id(id, self.name, self.hashtags)
+        let kind = match (
+            self.public_chat.as_deref().map(NoteId::from_hex),
+            self.group,
+        ) {
+            (Some(Ok(channel_id)), _) => ChannelKind::PublicChat(channel_id),
+            (Some(Err(err)), _) => {
+                error!("Invalid public chat id for channel {}: {err}", self.name);
+                ChannelKind::Hashtags
+            }
+            (None, Some(group)) => match GroupRef::new(&group.relay, &group.id) {
+                Some(group) => ChannelKind::Group(group),
+                None => {
+                    error!(
+                        "Invalid group {}'{} for channel {}",
+                        group.relay, group.id, self.name
+                    );
+                    ChannelKind::Hashtags
+                }
+            },
+            (None, None) => ChannelKind::Hashtags,
+        };
+        let relays = ChannelRelays::new(self.relays.into_iter().collect(), self.read_only);
+        let filter = self.filter.map(|f| f.filter()).unwrap_or_default();
+        let mut channel = Channel::with_kind(id, self.name, self.hashtags, kind)
+            .with_filter(filter)
+            .with_relays(relays);
+        if let Some(last_read) = self.last_read {
+            channel.last_read = last_read;
+        }
+        channel.section = self.section.and_then(|id| Uuid::parse_str(&id).ok());
+        channel.pinned = self.pinned;
+        channel.archived = self.archived;
+        channel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::ChannelPlacement;
+    use crate::timeline::{Timeline, TimelineCache, TimelineKind};
+    use nostrdb::NoteKey;
+    use notedeck::NoteRef;
+
+    /// Around 2096
+    const FUTURE: u64 = 4_000_000_000;
+
+    fn hashtag_timeline(hashtag: &str, created_at: &[u64]) -> Timeline {
+        let mut timeline = Timeline::hashtag(vec![hashtag.to_owned()]);
+        for (i, created_at) in created_at.iter().enumerate() {
+            let note_ref = NoteRef {
+                key: NoteKey::new(i as u64 + 1),
+                created_at: *created_at,
+            };
+            timeline.current_view_mut().units.merge_single_note(note_ref);
+        }
+        timeline
+    }
+
+    #[test]
+    fn test_last_read_roundtrip() {
+        let mut channel = Channel::new("nostr".to_owned(), vec!["nostr".to_owned()]);
+        channel.last_read = 1_700_000_000;
+
+        let json = serde_json::to_string(&SerializableChannel::from_channel(&channel)).unwrap();
+        let restored = serde_json::from_str::<SerializableChannel>(&json)
+            .unwrap()
+            .channel();
+
+        assert_eq!(restored.id, channel.id);
+        assert_eq!(restored.last_read, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_legacy_channel_starts_read() {
+        let json = r#"{"id":"not-a-uuid","name":"dev","hashtags":["dev"]}"#;
+        let channel = serde_json::from_str::<SerializableChannel>(json)
+            .unwrap()
+            .channel();
+
+        assert_eq!(channel.name, "dev");
+        assert!(channel.last_read > 0);
+        assert_eq!(channel.unread_count, 0);
+        assert_eq!(channel.kind, ChannelKind::Hashtags);
+        assert_eq!(channel.relays, ChannelRelays::default());
+    }
+
+    #[test]
+    fn test_public_chat_roundtrip() {
+        let channel_id = NoteId::new([3; 32]);
+        let channel = Channel::public_chat("lounge".to_owned(), channel_id);
+
+        let json = serde_json::to_string(&SerializableChannel::from_channel(&channel)).unwrap();
+        let restored = serde_json::from_str::<SerializableChannel>(&json)
+            .unwrap()
+            .channel();
+
+        assert_eq!(restored.kind, ChannelKind::PublicChat(channel_id));
+        assert_eq!(restored.timeline_kind, channel.timeline_kind);
+        assert!(restored.hashtags.is_empty());
+    }
+
+    #[test]
+    fn test_group_roundtrip() {
+        let group = GroupRef::parse("groups.example.com'dev").unwrap();
+        let channel = Channel::group("dev".to_owned(), group.clone());
+
+        let json = serde_json::to_string(&SerializableChannel::from_channel(&channel)).unwrap();
+        let restored = serde_json::from_str::<SerializableChannel>(&json)
+            .unwrap()
+            .channel();
+
+        assert_eq!(restored.kind, ChannelKind::Group(group));
+        assert_eq!(restored.timeline_kind, channel.timeline_kind);
+    }
+
+    #[test]
+    fn test_channel_relays_roundtrip() {
+        let relays = ChannelRelays::new(
+            ChannelRelays::parse_urls("wss://relay.example.com, ws://localhost:7777"),
+            true,
+        );
+        let channel =
+            Channel::new("local".to_owned(), vec!["local".to_owned()]).with_relays(relays.clone());
+        assert!(matches!(channel.timeline_kind, TimelineKind::Scoped(_, _)));
+        assert!(!channel.can_post());
+
+        let json = serde_json::to_string(&SerializableChannel::from_channel(&channel)).unwrap();
+        let restored = serde_json::from_str::<SerializableChannel>(&json)
+            .unwrap()
+            .channel();
+
+        assert_eq!(restored.relays, relays);
+        assert_eq!(restored.timeline_kind, channel.timeline_kind);
+    }
+
+    #[test]
+    fn test_migrate_selected_index() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let json = format!(
+            r#"{{"channels_cache":{{"{}":{{"channels":[
+                {{"id":"{first}","name":"nostr","hashtags":["nostr"]}},
+                {{"id":"{second}","name":"dev","hashtags":["dev"]}}
+            ],"selected":1}}}}}}"#,
+            Pubkey::new([1; 32]).hex()
+        );
+
+        let mut cache = serde_json::from_str::<SerializableChannelsCache>(&json).unwrap();
+        assert_eq!(cache.version, 0);
+        cache.migrate();
+        assert_eq!(cache.version, CHANNELS_CACHE_VERSION);
+
+        let mut channel_list = cache
+            .channels_cache
+            .remove(&Pubkey::new([1; 32]))
+            .unwrap()
+            .channel_list();
+        assert_eq!(channel_list.selected_channel().unwrap().id, second);
+
+        // the selection follows the channel once it moves
+        channel_list.move_channel(1, ChannelPlacement::Before(0));
+        let json =
+            serde_json::to_string(&SerializableChannelList::from_channel_list(&channel_list))
+                .unwrap();
+        assert!(!json.contains(r#""selected":"#));
+
+        let restored = serde_json::from_str::<SerializableChannelList>(&json)
+            .unwrap()
+            .channel_list();
+        assert_eq!(restored.selected, 0);
+        assert_eq!(restored.selected_channel().unwrap().id, second);
+    }
+
+    #[test]
+    fn test_sections_roundtrip() {
+        let mut channel_list = ChannelList::new();
+        channel_list.add_channel(Channel::new("nostr".to_owned(), vec!["nostr".to_owned()]));
+        channel_list.add_channel(Channel::new("dev".to_owned(), vec!["dev".to_owned()]));
+        channel_list.add_channel(Channel::new("art".to_owned(), vec!["art".to_owned()]));
+
+        let work = channel_list.add_section("work".to_owned());
+        channel_list.toggle_section(work);
+        channel_list.move_channel(0, ChannelPlacement::Section(Some(work)));
+        channel_list.move_channel(0, ChannelPlacement::Starred);
+        // archived through the channel directly, there's no subscription
+        // to close here
+        channel_list.channels[0].archived = true;
+        channel_list.select_channel(1);
+
+        let json =
+            serde_json::to_string(&SerializableChannelList::from_channel_list(&channel_list))
+                .unwrap();
+        let restored = serde_json::from_str::<SerializableChannelList>(&json)
+            .unwrap()
+            .channel_list();
+
+        assert_eq!(restored.sections, channel_list.sections);
+        assert!(restored.sections[0].collapsed);
+
+        let names: Vec<_> = restored.channels.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["art", "nostr", "dev"]);
+
+        let nostr = &restored.channels[1];
+        assert_eq!(nostr.section, Some(work));
+        assert!(!nostr.pinned);
+
+        let dev = &restored.channels[2];
+        assert_eq!(restored.section_of(dev), None);
+        assert!(dev.pinned);
+
+        assert!(restored.channels[0].archived);
+        assert_eq!(restored.selected, 1);
+
+        // archived channels can't be selected
+        let mut restored = restored;
+        restored.select_channel(0);
+        assert_eq!(restored.selected, 1);
+    }
+
+    #[test]
+    fn test_channel_filter_roundtrip() {
+        let filter = ChannelFilter {
+            authors: AuthorFilter::FollowSet {
+                author: Pubkey::new([9; 32]),
+                identifier: "core devs".to_owned(),
+            },
+            kinds: vec![1, 30023],
+            mute_words: vec!["price talk".to_owned()],
+        };
+        let channel =
+            Channel::new("devs".to_owned(), vec!["dev".to_owned()]).with_filter(filter.clone());
+        assert!(matches!(channel.timeline_kind, TimelineKind::Feed(_, _)));
+
+        let json = serde_json::to_string(&SerializableChannel::from_channel(&channel)).unwrap();
+        let restored = serde_json::from_str::<SerializableChannel>(&json)
+            .unwrap()
+            .channel();
+
+        assert_eq!(restored.filter, filter);
+        assert_eq!(restored.timeline_kind, channel.timeline_kind);
+    }
+
+    #[test]
+    fn test_synced_list_roundtrip() {
+        let mut channel_list = ChannelList::new();
+        channel_list.add_channel(Channel::new("nostr".to_owned(), vec!["nostr".to_owned()]));
+        channel_list.add_channel(Channel::new("dev".to_owned(), vec!["dev".to_owned()]));
+        let work = channel_list.add_section("work".to_owned());
+        channel_list.move_channel(1, ChannelPlacement::Section(Some(work)));
+        channel_list.channels[0].last_read = 1_700_000_000;
+        channel_list.select_channel(1);
+
+        let json = synced_channel_list_json(&channel_list).unwrap();
+        assert!(!json.contains("last_read"));
+        assert!(!json.contains("selected"));
+        assert!(!json.contains("modified_at"));
+
+        let synced = parse_synced_channel_list(&json).unwrap();
+        assert_eq!(synced.sections, channel_list.sections);
+        let names: Vec<_> = synced.channels.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["nostr", "dev"]);
+        assert_eq!(synced.channels[1].section, Some(work));
+
+        assert!(parse_synced_channel_list("not json").is_none());
+    }
+
+    #[test]
+    fn test_merge_remote() {
+        let mut local = ChannelList::new();
+        local.add_channel(Channel::new("nostr".to_owned(), vec!["nostr".to_owned()]));
+        local.add_channel(Channel::new("local".to_owned(), vec!["local".to_owned()]));
+        local.channels[0].last_read = 1_700_000_000;
+        local.select_channel(1);
+
+        let mut remote =
+            parse_synced_channel_list(&synced_channel_list_json(&local).unwrap()).unwrap();
+        remote.channels.remove(1);
+        remote.add_channel(Channel::new("remote".to_owned(), vec!["remote".to_owned()]));
+
+        // replacing: the local only channel goes away and its timeline
+        // gets closed
+        let mut replaced = local.clone();
+        let closed = replaced.merge_remote(remote.clone(), 42, false);
+        let names: Vec<_> = replaced.channels.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["nostr", "remote"]);
+        assert_eq!(closed, vec![local.channels[1].timeline_kind.clone()]);
+        assert_eq!(replaced.channels[0].last_read, 1_700_000_000);
+        assert_eq!(replaced.modified_at, 42);
+        assert_eq!(replaced.selected, 0);
+
+        // keeping local channels: they go after the remote ones, and the
+        // merged list needs publishing
+        let mut kept = local.clone();
+        let closed = kept.merge_remote(remote, 42, true);
+        let names: Vec<_> = kept.channels.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["nostr", "remote", "local"]);
+        assert!(closed.is_empty());
+        assert!(kept.modified_at > 42);
+        assert_eq!(kept.selected, 2);
+    }
+
+    #[test]
+    fn test_count_unread() {
+        let mut channel = Channel::new("nostr".to_owned(), vec!["nostr".to_owned()]);
+        channel.last_read = 1_700_000_000;
+        let timeline = hashtag_timeline(
+            "nostr",
+            &[1_600_000_000, 1_700_000_100, 1_700_000_200, FUTURE],
+        );
+
+        // notes dated in the future don't count
+        assert_eq!(channel.count_unread(&timeline), 2);
+
+        // and don't push the read marker past now
+        let latest = timeline.current_view().units.latest().copied();
+        assert!(channel.mark_read(latest));
+        assert!(channel.last_read < FUTURE);
+        assert_eq!(channel.count_unread(&timeline), 0);
+    }
+
+    #[test]
+    fn test_on_timelines_updated() {
+        let mut channel_list = ChannelList::new();
+        let mut timeline_cache = TimelineCache::default();
+        for hashtag in ["nostr", "dev", "art"] {
+            let mut channel = Channel::new(hashtag.to_owned(), vec![hashtag.to_owned()]);
+            channel.last_read = 1_700_000_000;
+            channel_list.add_channel(channel);
+
+            let timeline = hashtag_timeline(hashtag, &[1_700_000_100, 1_700_000_200]);
+            timeline_cache.insert(timeline.kind.clone(), timeline);
+        }
+        channel_list.select_channel(0);
+
+        let updated: Vec<TimelineKind> = channel_list.channels[..2]
+            .iter()
+            .map(|channel| channel.timeline_kind.clone())
+            .collect();
+        channel_list.on_timelines_updated(&updated, &timeline_cache);
+
+        // the selected channel is being read, and art's timeline didn't
+        // change
+        let unread: Vec<_> = channel_list
+            .channels
+            .iter()
+            .map(|channel| channel.unread_count)
+            .collect();
+        assert_eq!(unread, [0, 2, 0]);
     }
 }
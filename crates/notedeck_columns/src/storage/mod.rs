@@ -3,7 +3,12 @@ mod decks;
 mod relay_config;
 mod startup_config;
 
-pub use channels::{load_channels_cache, save_channels_cache, CHANNELS_CACHE_FILE};
+pub use channels::{
+    load_channels_cache, parse_synced_channel_list, save_channels_cache, synced_channel_list_json,
+    ChannelsCacheSaver, CHANNELS_CACHE_FILE,
+};
 pub use decks::{load_decks_cache, save_decks_cache, DECKS_CACHE_FILE};
 pub use relay_config::{load_relay_config, save_relay_config, RELAY_CONFIG_FILE};
-pub use startup_config::{load_startup_config, save_startup_config, STARTUP_CONFIG_FILE};
+pub use startup_config::{
+    forget_startup_nsec, load_startup_config, save_startup_config, STARTUP_CONFIG_FILE,
+};
@@ -33,6 +33,19 @@ pub fn load_startup_config(path: &DataPath) -> Option<StartupConfig> {
     }
 }
 
+/// Drop the nsec from the startup config once its key is in the keystore,
+/// so it doesn't stay on disk in plain text
+pub fn forget_startup_nsec(path: &DataPath) {
+    let Some(mut config) = load_startup_config(path) else {
+        return;
+    };
+
+    if config.nsec.take().is_some() {
+        info!("StartupConfig: nsec is in the keystore now, removing it from the config");
+        save_startup_config(path, &config);
+    }
+}
+
 /// Save startup configuration to disk (optional - mainly for reference)
 pub fn save_startup_config(path: &DataPath, config: &StartupConfig) {
     let serialized_config = match serde_json::to_string_pretty(config) {
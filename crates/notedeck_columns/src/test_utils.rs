@@ -1,3 +1,6 @@
+use enostr::ewebsock::{WsEvent, WsMessage};
+use enostr::RelayPool;
+use nostrdb::{Config, Filter, IngestMetadata, Ndb, Transaction};
 use poll_promise::Promise;
 use std::thread;
 use std::time::Duration;
@@ -34,3 +37,47 @@ macro_rules! promise_assert {
         $assertion_closure!(*result, $expected);
     };
 }
+
+/// A fresh database in a temporary directory, which must outlive it
+pub fn test_ndb() -> (tempfile::TempDir, Ndb) {
+    let tmp_dir = tempfile::TempDir::new().unwrap();
+    let ndb = Ndb::new(tmp_dir.path().to_str().unwrap(), &Config::new()).unwrap();
+    (tmp_dir, ndb)
+}
+
+/// A pool answering from the fixtures in `fixtures/mock_relay`, like
+/// `--mock-relay` does
+pub fn mock_pool() -> RelayPool {
+    let fixtures = format!("{}/../../fixtures/mock_relay", env!("CARGO_MANIFEST_DIR"));
+    let mut pool = RelayPool::new();
+    pool.add_mock_relay(fixtures, || {}).unwrap();
+    pool
+}
+
+/// Store the events the pool received in `ndb`, returns how many there were
+pub fn ingest(pool: &mut RelayPool, ndb: &Ndb) -> usize {
+    let mut events = 0;
+    while let Some(ev) = pool.try_recv() {
+        let WsEvent::Message(WsMessage::Text(txt)) = ev.event else {
+            continue;
+        };
+        if txt.starts_with("[\"EVENT\"") {
+            ndb.process_event_with(&txt, IngestMetadata::new()).unwrap();
+            events += 1;
+        }
+    }
+    events
+}
+
+/// Wait until `ndb` stored `expected` notes matching `filters`, ingestion
+/// happens in the background
+pub fn wait_for_query(ndb: &Ndb, filters: &[Filter], expected: usize) {
+    for _ in 0..500 {
+        let txn = Transaction::new(ndb).unwrap();
+        if ndb.query(&txn, filters, 100).unwrap().len() >= expected {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("ndb never got {expected} notes");
+}
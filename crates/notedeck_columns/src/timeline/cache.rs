@@ -1,6 +1,7 @@
 use crate::{
     actionbar::TimelineOpenResult,
     error::Error,
+    gossip::GossipRouter,
     timeline::{Timeline, TimelineKind, UnknownPksOwned},
 };
 
@@ -14,6 +15,7 @@ use tracing::{debug, error, info, warn};
 #[derive(Default)]
 pub struct TimelineCache {
     timelines: HashMap<TimelineKind, Timeline>,
+    gossip: GossipRouter,
 }
 
 pub enum Vitality<'a, M> {
@@ -70,6 +72,7 @@ impl TimelineCache {
                 id
             );
             self.timelines.remove(id);
+            self.gossip.unroute(pool, id);
         }
 
         Ok(())
@@ -182,8 +185,11 @@ impl TimelineCache {
         pool: &mut RelayPool,
         id: &TimelineKind,
     ) -> Option<TimelineOpenResult> {
-        let notes_resp = self.notes(ndb, note_cache, txn, id);
-        let (mut open_result, timeline) = match notes_resp.vitality {
+        let GetNotesResponse {
+            vitality,
+            unknown_pks,
+        } = self.notes(ndb, note_cache, txn, id);
+        let (mut open_result, timeline) = match vitality {
             Vitality::Stale(timeline) => {
                 // The timeline cache is stale, let's update it
                 let notes = {
@@ -218,17 +224,22 @@ impl TimelineCache {
             Vitality::Fresh(timeline) => (None, timeline),
         };
 
+        let mut gossip_sub = None;
         if let Some(filter) = timeline.filter.get_any_ready() {
             debug!("got open with *new* subscription for {:?}", &timeline.kind);
             timeline.subscription.try_add_local(ndb, filter);
 
-            // Check if this is a relay-specific timeline
-            match &timeline.kind {
-                TimelineKind::Relay(relay_url, _) => {
-                    timeline.subscription.try_add_remote_with_relay(subs, pool, filter, Some(relay_url), &timeline.kind);
+            // Relay-specific timelines, groups and channels with their own
+            // relays only subscribe to those relays
+            match timeline.kind.relays() {
+                Some(relays) => {
+                    timeline.subscription.try_add_remote_with_relays(subs, pool, filter, Some(&relays), &timeline.kind);
                 }
-                _ => {
+                None => {
                     timeline.subscription.try_add_remote(subs, pool, filter, &timeline.kind);
+
+                    let sub_id = timeline.subscription.remote_id().map(str::to_owned);
+                    gossip_sub = Some((sub_id, filter.remote().to_vec()));
                 }
             }
         } else {
@@ -241,7 +252,12 @@ impl TimelineCache {
 
         timeline.subscription.increment();
 
-        if let Some(unknowns) = notes_resp.unknown_pks {
+        if let Some((sub_id, filters)) = gossip_sub {
+            self.gossip
+                .route(ndb, txn, pool, id, sub_id.as_deref(), &filters);
+        }
+
+        if let Some(unknowns) = unknown_pks {
             match &mut open_result {
                 Some(o) => o.insert_pks(unknowns.pks),
                 None => open_result = Some(TimelineOpenResult::new_pks(unknowns.pks)),
@@ -259,6 +275,15 @@ impl TimelineCache {
         self.timelines.get_mut(id)
     }
 
+    /// Routes timelines to the relays their authors publish to
+    pub fn gossip(&self) -> &GossipRouter {
+        &self.gossip
+    }
+
+    pub fn gossip_mut(&mut self) -> &mut GossipRouter {
+        &mut self.gossip
+    }
+
     pub fn num_timelines(&self) -> usize {
         self.timelines.len()
     }
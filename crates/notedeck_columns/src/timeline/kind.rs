@@ -1,6 +1,9 @@
+use crate::channel_filter::ChannelFilter;
 use crate::error::Error;
+use crate::nip28;
+use crate::nip29::{self, GroupRef};
 use crate::search::SearchQuery;
-use crate::timeline::{Timeline, TimelineTab};
+use crate::timeline::{Timeline, TimelineTab, ViewFilter};
 use enostr::{Filter, NoteId, Pubkey};
 use nostrdb::{Ndb, Transaction};
 use notedeck::filter::{NdbQueryPackage, ValidKind};
@@ -11,6 +14,7 @@ use notedeck::{
 };
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
 use tokenator::{ParseError, TokenParser, TokenSerializable, TokenWriter};
 use tracing::{error, warn};
@@ -216,9 +220,23 @@ pub enum TimelineKind {
 
     Hashtag(Vec<String>),
 
+    /// Hashtag feed narrowed down by authors, kinds and mute words. Used
+    /// by channels with a [`ChannelFilter`].
+    Feed(Vec<String>, ChannelFilter),
+
     /// Relay-specific timeline with optional hashtag filtering
     /// Format: Relay(relay_url, optional_hashtags)
     Relay(String, Option<Vec<String>>),
+
+    /// NIP-28 public chat channel, identified by its kind 40 creation event
+    PublicChat(NoteId),
+
+    /// NIP-29 group, only ever subscribed to on the group's relay
+    Group(GroupRef),
+
+    /// Another timeline, subscribed to on the given relays only instead of
+    /// every relay in the pool. Used by channels with their own relays.
+    Scoped(BTreeSet<String>, Box<TimelineKind>),
 }
 
 const NOTIFS_TOKEN_DEPRECATED: &str = "notifs";
@@ -319,7 +337,11 @@ impl TimelineKind {
             TimelineKind::Universe => None,
             TimelineKind::Generic(_) => None,
             TimelineKind::Hashtag(_ht) => None,
+            TimelineKind::Feed(_, _) => None,
             TimelineKind::Relay(_, _) => None,
+            TimelineKind::PublicChat(_) => None,
+            TimelineKind::Group(_) => None,
+            TimelineKind::Scoped(_, inner) => inner.pubkey(),
             TimelineKind::Search(query) => query.author(),
         }
     }
@@ -335,11 +357,34 @@ impl TimelineKind {
             TimelineKind::Universe => true,
             TimelineKind::Generic(_) => true,
             TimelineKind::Hashtag(_ht) => true,
+            TimelineKind::Feed(_, _) => true,
             TimelineKind::Relay(_, _) => true,
+            TimelineKind::PublicChat(_) => true,
+            TimelineKind::Group(_) => true,
+            TimelineKind::Scoped(_, inner) => inner.should_subscribe_locally(),
             TimelineKind::Search(_q) => true,
         }
     }
 
+    /// The only relays this timeline should be subscribed to, for
+    /// timelines that are scoped to specific relays
+    pub fn relays(&self) -> Option<Vec<&str>> {
+        match self {
+            TimelineKind::Relay(relay_url, _) => Some(vec![relay_url]),
+            TimelineKind::Group(group) => Some(vec![&group.relay]),
+            TimelineKind::Scoped(relays, _) => Some(relays.iter().map(String::as_str).collect()),
+            _ => None,
+        }
+    }
+
+    /// The timeline without any relay scoping
+    pub fn unscoped(&self) -> &TimelineKind {
+        match self {
+            TimelineKind::Scoped(_, inner) => inner.unscoped(),
+            _ => self,
+        }
+    }
+
     // NOTE!!: if you just added a TimelineKind enum, make sure to update
     //         the parser below as well
     pub fn serialize_tokens(&self, writer: &mut TokenWriter) {
@@ -369,6 +414,11 @@ impl TimelineKind {
                 writer.write_token("hashtag");
                 writer.write_token(&ht.join(" "));
             }
+            TimelineKind::Feed(ht, filter) => {
+                writer.write_token("feed");
+                writer.write_token(&ht.join(" "));
+                filter.serialize_tokens(writer);
+            }
             TimelineKind::Relay(relay_url, hashtags) => {
                 writer.write_token("relay");
                 // URL-encode the relay URL to avoid issues with the ":" delimiter
@@ -379,6 +429,21 @@ impl TimelineKind {
                     writer.write_token("");
                 }
             }
+            TimelineKind::PublicChat(channel_id) => {
+                writer.write_token("public_chat");
+                writer.write_token(&channel_id.hex());
+            }
+            TimelineKind::Group(group) => {
+                writer.write_token("group");
+                writer.write_token(&urlencoding::encode(&group.relay));
+                writer.write_token(&group.id);
+            }
+            TimelineKind::Scoped(relays, inner) => {
+                writer.write_token("scoped");
+                let relays: Vec<_> = relays.iter().map(|r| urlencoding::encode(r)).collect();
+                writer.write_token(&relays.join(" "));
+                inner.serialize_tokens(writer);
+            }
         }
     }
 
@@ -417,6 +482,24 @@ impl TimelineKind {
             return algo_tl;
         }
 
+        let scoped = parser.try_parse(|p| {
+            p.parse_token("scoped")?;
+            let relays = p
+                .pull_token()?
+                .split_whitespace()
+                .map(|r| {
+                    urlencoding::decode(r)
+                        .map(|r| r.into_owned())
+                        .map_err(|_| ParseError::DecodeFailed)
+                })
+                .collect::<Result<BTreeSet<_>, _>>()?;
+            let inner = TimelineKind::parse(p, deck_author)?;
+            Ok(TimelineKind::Scoped(relays, Box::new(inner)))
+        });
+        if scoped.is_ok() {
+            return scoped;
+        }
+
         TokenParser::alt(
             parser,
             &[
@@ -439,6 +522,16 @@ impl TimelineKind {
                             .collect(),
                     ))
                 },
+                |p| {
+                    p.parse_token("feed")?;
+                    let hashtags = p
+                        .pull_token()?
+                        .split_whitespace()
+                        .map(|s| s.to_lowercase())
+                        .collect();
+                    let filter = ChannelFilter::parse_from_tokens(p)?;
+                    Ok(TimelineKind::Feed(hashtags, filter))
+                },
                 |p| {
                     p.parse_token("search")?;
                     let search_query = SearchQuery::parse_from_tokens(p)?;
@@ -466,6 +559,21 @@ impl TimelineKind {
                     };
                     Ok(TimelineKind::Relay(relay_url, hashtags))
                 },
+                |p| {
+                    p.parse_token("public_chat")?;
+                    let channel_id = NoteId::from_hex(p.pull_token()?)
+                        .map_err(|_| ParseError::HexDecodeFailed)?;
+                    Ok(TimelineKind::PublicChat(channel_id))
+                },
+                |p| {
+                    p.parse_token("group")?;
+                    let encoded_relay_url = p.pull_token()?;
+                    let relay_url = urlencoding::decode(encoded_relay_url)
+                        .map_err(|_| ParseError::DecodeFailed)?;
+                    let group = GroupRef::new(&relay_url, p.pull_token()?)
+                        .ok_or(ParseError::DecodeFailed)?;
+                    Ok(TimelineKind::Group(group))
+                },
             ],
         )
     }
@@ -490,6 +598,17 @@ impl TimelineKind {
         TimelineKind::Profile(pk)
     }
 
+    /// Timelines backing a channel
+    pub fn is_channel(&self) -> bool {
+        matches!(
+            self.unscoped(),
+            TimelineKind::Hashtag(_)
+                | TimelineKind::Feed(..)
+                | TimelineKind::PublicChat(_)
+                | TimelineKind::Group(_)
+        )
+    }
+
     pub fn is_notifications(&self) -> bool {
         matches!(self, TimelineKind::Notifications(_))
     }
@@ -540,6 +659,8 @@ impl TimelineKind {
                 FilterState::ready(filters)
             }
 
+            TimelineKind::Feed(hashtags, filter) => filter.filter_state(hashtags, ndb, txn),
+
             TimelineKind::Relay(_relay_url, hashtags) => {
                 let filters = if let Some(hashtags) = hashtags {
                     // Filter by hashtags if provided
@@ -576,6 +697,14 @@ impl TimelineKind {
             }
 
             TimelineKind::Profile(pk) => FilterState::ready_hybrid(profile_filter(pk.bytes())),
+
+            TimelineKind::PublicChat(channel_id) => {
+                FilterState::ready(nip28::channel_filters(channel_id))
+            }
+
+            TimelineKind::Group(group) => FilterState::ready(nip29::group_filters(group)),
+
+            TimelineKind::Scoped(_, inner) => inner.filters(txn, ndb),
         }
     }
 
@@ -656,6 +785,15 @@ impl TimelineKind {
 
             TimelineKind::Hashtag(hashtag) => Some(Timeline::hashtag(hashtag)),
 
+            TimelineKind::Feed(hashtags, filter) => {
+                let filter_state = filter.filter_state(&hashtags, ndb, txn);
+                Some(Timeline::new(
+                    TimelineKind::Feed(hashtags, filter),
+                    filter_state,
+                    TimelineTab::only_notes_and_replies(),
+                ))
+            }
+
             TimelineKind::Relay(relay_url, hashtags) => {
                 let filters = if let Some(ref hashtags) = hashtags {
                     hashtags
@@ -688,6 +826,30 @@ impl TimelineKind {
                 contact_filter_state(txn, ndb, &pk),
                 TimelineTab::full_tabs(),
             )),
+
+            // the filter also pulls in the channel's kind 40/41 metadata,
+            // those never become timeline units
+            TimelineKind::PublicChat(channel_id) => Some(Timeline::new(
+                TimelineKind::PublicChat(channel_id),
+                FilterState::ready(nip28::channel_filters(&channel_id)),
+                vec![TimelineTab::new(ViewFilter::All)],
+            )),
+
+            // likewise for the relay-published group state and deletions
+            TimelineKind::Group(group) => {
+                let filters = nip29::group_filters(&group);
+                Some(Timeline::new(
+                    TimelineKind::Group(group),
+                    FilterState::ready(filters),
+                    vec![TimelineTab::new(ViewFilter::All)],
+                ))
+            }
+
+            TimelineKind::Scoped(relays, inner) => {
+                let mut timeline = inner.clone().into_timeline(txn, ndb)?;
+                timeline.kind = TimelineKind::Scoped(relays, inner);
+                Some(timeline)
+            }
         }
     }
 
@@ -719,6 +881,13 @@ impl TimelineKind {
                 ColumnTitle::formatted(tr!(i18n, "Custom", "Column title for custom timelines"))
             }
             TimelineKind::Hashtag(hashtag) => ColumnTitle::formatted(hashtag.join(" ").to_string()),
+            TimelineKind::Feed(hashtags, _filter) => {
+                if hashtags.is_empty() {
+                    ColumnTitle::formatted(tr!(i18n, "Feed", "Column title for filtered feeds"))
+                } else {
+                    ColumnTitle::formatted(hashtags.join(" "))
+                }
+            }
             TimelineKind::Relay(relay_url, hashtags) => {
                 if let Some(hashtags) = hashtags {
                     ColumnTitle::formatted(format!("{} ({})", relay_url, hashtags.join(" ")))
@@ -726,10 +895,27 @@ impl TimelineKind {
                     ColumnTitle::formatted(relay_url.to_string())
                 }
             }
+            TimelineKind::PublicChat(_channel_id) => ColumnTitle::needs_db(self),
+            TimelineKind::Group(_group) => ColumnTitle::needs_db(self),
+            TimelineKind::Scoped(_, inner) => inner.to_title(i18n),
         }
     }
 }
 
+/// Borrow the channel name out of a kind 40 content without allocating,
+/// so it can live as long as the transaction
+fn public_chat_name(content: &str) -> Option<&str> {
+    #[derive(Deserialize)]
+    struct Name<'a> {
+        name: &'a str,
+    }
+
+    serde_json::from_str::<Name>(content)
+        .ok()
+        .map(|n| n.name)
+        .filter(|name| !name.is_empty())
+}
+
 pub fn notifications_filter(pk: &Pubkey) -> Filter {
     Filter::new()
         .pubkeys([pk.bytes()])
@@ -761,6 +947,13 @@ impl<'a> TitleNeedsDb<'a> {
                 .map(|p| notedeck::name::get_display_name(Some(p)).name());
 
             m_name.unwrap_or("Profile")
+        } else if let TimelineKind::PublicChat(channel_id) = self.kind {
+            ndb.get_note_by_id(txn, channel_id.bytes())
+                .ok()
+                .and_then(|note| public_chat_name(note.content()))
+                .unwrap_or("Public chat")
+        } else if let TimelineKind::Group(group) = self.kind {
+            nip29::group_name(ndb, txn, group).unwrap_or("Group")
         } else {
             "Unknown"
         }
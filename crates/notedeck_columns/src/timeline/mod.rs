@@ -9,14 +9,15 @@ use crate::{
 use notedeck::{
     contacts::hybrid_contacts_filter,
     filter::{self, HybridFilter},
-    tr, Accounts, CachedNote, ContactState, FilterError, FilterState, FilterStates, Localization,
-    NoteCache, NoteRef, UnknownIds,
+    tr, Accounts, CachedNote, ContactState, Deletions, FilterError, FilterState, FilterStates,
+    Localization, NoteCache, NoteRef, UnknownIds,
 };
 
 use egui_virtual_list::VirtualList;
-use enostr::{PoolRelay, Pubkey, RelayPool};
+use enostr::{ClientMessage, PoolRelay, Pubkey, RelayPool, SyncRequest, SyncSupport};
 use nostrdb::{Filter, Ndb, Note, NoteKey, Transaction};
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{cell::RefCell, collections::HashSet};
 
 use tracing::{debug, error, info, warn};
@@ -240,6 +241,10 @@ pub struct Timeline {
 
     pub subscription: TimelineSub,
     pub enable_front_insert: bool,
+
+    /// The deletions generation and number of units at the last
+    /// [`Timeline::prune_deleted`]
+    pruned: Option<(u64, usize)>,
 }
 
 impl Timeline {
@@ -312,6 +317,7 @@ impl Timeline {
             selected_view,
             enable_front_insert,
             seen_latest_notes: false,
+            pruned: None,
         }
     }
 
@@ -461,6 +467,37 @@ impl Timeline {
         Ok(())
     }
 
+    fn num_units(&self) -> usize {
+        self.views.iter().map(|view| view.units.len()).sum()
+    }
+
+    /// Drop the notes their authors deleted, and anything built on them
+    /// like reactions and reposts. Only does the work when notes came in
+    /// or more deletions arrived since the last time.
+    pub fn prune_deleted(&mut self, ndb: &Ndb, txn: &Transaction, deletions: &Deletions) {
+        let current = (deletions.generation(), self.num_units());
+        if self.pruned == Some(current) {
+            return;
+        }
+
+        let mut removed = 0;
+        for view in &mut self.views {
+            removed += view.units.units.retain(|unit| {
+                let key = unit.get_underlying_noteref().key;
+                let Ok(note) = ndb.get_note_by_key(txn, key) else {
+                    return true;
+                };
+                !deletions.is_deleted(&note)
+            });
+        }
+
+        if removed > 0 {
+            debug!("dropped {removed} deleted notes from {:?}", self.kind);
+        }
+
+        self.pruned = Some((deletions.generation(), self.num_units()));
+    }
+
     #[profiling::function]
     pub fn poll_notes_into_view(
         &mut self,
@@ -489,6 +526,60 @@ impl Timeline {
 
         self.insert(&new_note_ids, ndb, txn, unknown_ids, note_cache, reversed)
     }
+
+    /// Page back through history from the oldest note in the current
+    /// view: what nostrdb already has is inserted right away, and relays
+    /// get a one-shot request for the rest. Their notes come in through
+    /// the timeline's local subscription.
+    #[allow(clippy::too_many_arguments)]
+    pub fn load_older(
+        &mut self,
+        subs: &mut Subscriptions,
+        ndb: &Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        unknown_ids: &mut UnknownIds,
+        note_cache: &mut NoteCache,
+        limit: u64,
+    ) -> Result<()> {
+        let units = &self.current_view().units;
+        let Some(oldest) = units
+            .len()
+            .checked_sub(1)
+            .and_then(|i| units.get(i))
+            .map(|unit| unit.get_underlying_noteref().created_at)
+        else {
+            return Ok(());
+        };
+
+        let Some(filter) = self.filter.get_any_ready() else {
+            return Ok(());
+        };
+
+        let local = filter::make_filters_until(&filter.local().combined(), oldest, limit);
+        let remote = filter::make_filters_until(filter.remote(), oldest, limit);
+
+        let note_keys: Vec<NoteKey> = ndb
+            .query(txn, &local, limit as i32)?
+            .into_iter()
+            .map(|result| result.note_key)
+            .collect();
+        debug!("loading {} older local notes", note_keys.len());
+
+        let sub_id = subscriptions::new_sub_id();
+        subs.subs.insert(sub_id.clone(), SubKind::OneShot);
+        let msg = ClientMessage::req(sub_id, remote);
+        match self.kind.relays() {
+            Some(relays) => {
+                for relay in relays {
+                    pool.send_to(&msg, relay);
+                }
+            }
+            None => pool.send(&msg),
+        }
+
+        self.insert(&note_keys, ndb, txn, unknown_ids, note_cache, false)
+    }
 }
 
 pub struct UnknownPksOwned {
@@ -569,7 +660,7 @@ pub fn setup_new_timeline(
     }
 
     for relay in &mut pool.relays {
-        send_initial_timeline_filter(since_optimize, subs, relay, timeline, accounts);
+        send_initial_timeline_filter(since_optimize, ndb, subs, relay, timeline, accounts);
     }
     timeline.subscription.increment();
 }
@@ -580,6 +671,7 @@ pub fn setup_new_timeline(
 /// setup_new_timeline.
 pub fn send_initial_timeline_filters(
     since_optimize: bool,
+    ndb: &Ndb,
     timeline_cache: &mut TimelineCache,
     subs: &mut Subscriptions,
     pool: &mut RelayPool,
@@ -589,8 +681,21 @@ pub fn send_initial_timeline_filters(
     info!("Sending initial filters to {}", relay_id);
     let relay = &mut pool.relays.iter_mut().find(|r| r.url() == relay_id)?;
 
-    for (_kind, timeline) in timeline_cache {
-        send_initial_timeline_filter(since_optimize, subs, relay, timeline, accounts);
+    // temporary gossip connections only get the timelines routed to them
+    let routed = timeline_cache.gossip().routed_to(relay_id).cloned();
+
+    for (kind, timeline) in timeline_cache {
+        // don't leak relay-scoped timelines, like private groups, to other
+        // relays
+        if kind.relays().is_some_and(|relays| !relays.contains(&relay_id)) {
+            continue;
+        }
+
+        if routed.as_ref().is_some_and(|kinds| !kinds.contains(kind)) {
+            continue;
+        }
+
+        send_initial_timeline_filter(since_optimize, ndb, subs, relay, timeline, accounts);
     }
 
     Some(())
@@ -598,6 +703,7 @@ pub fn send_initial_timeline_filters(
 
 pub fn send_initial_timeline_filter(
     can_since_optimize: bool,
+    ndb: &Ndb,
     subs: &mut Subscriptions,
     relay: &mut PoolRelay,
     timeline: &mut Timeline,
@@ -620,6 +726,14 @@ pub fn send_initial_timeline_filter(
             error!("GotRemote state when sending initial timeline filter?");
         }
 
+        FilterState::Closed(closed) => {
+            debug!(
+                "not sending initial filter to {}, it closed our subscription: {}",
+                relay.url(),
+                closed.reason
+            );
+        }
+
         FilterState::Ready(filter) => {
             let filter = filter.to_owned();
             let new_filters: Vec<Filter> = filter.remote().to_owned().into_iter().map(|f| {
@@ -651,9 +765,15 @@ pub fn send_initial_timeline_filter(
 
             //let sub_id = damus.gen_subid(&SubKind::Initial);
             let sub_id = subscriptions::new_sub_id();
-            subs.subs.insert(sub_id.clone(), SubKind::Initial);
+            subs.subs
+                .insert(sub_id.clone(), SubKind::Initial(timeline.kind.clone()));
+
+            let sent = match channel_sync_request(ndb, timeline, relay, &filter, &new_filters) {
+                Some(request) => relay.sync(sub_id.clone(), request),
+                None => relay.subscribe(sub_id.clone(), new_filters.clone()),
+            };
 
-            if let Err(err) = relay.subscribe(sub_id.clone(), new_filters.clone()) {
+            if let Err(err) = sent {
                 error!("error subscribing: {err}");
             } else {
                 timeline.subscription.force_add_remote(sub_id);
@@ -665,6 +785,66 @@ pub fn send_initial_timeline_filter(
     }
 }
 
+/// Channels backfill with a NIP-77 negentropy sync on relays that support
+/// it, so we only download the events nostrdb doesn't have yet. `fallback`
+/// is the REQ we'd send otherwise.
+fn channel_sync_request(
+    ndb: &Ndb,
+    timeline: &Timeline,
+    relay: &PoolRelay,
+    filter: &HybridFilter,
+    fallback: &[Filter],
+) -> Option<SyncRequest> {
+    if !timeline.kind.is_channel() || relay.sync_support() == SyncSupport::Unsupported {
+        return None;
+    }
+
+    let [remote] = filter.remote() else {
+        return None;
+    };
+
+    let limit = remote
+        .limit()
+        .unwrap_or(filter::default_remote_limit())
+        .min(filter::default_remote_limit());
+
+    let txn = Transaction::new(ndb).ok()?;
+    let have: Vec<(u64, [u8; 32])> = ndb
+        .query(&txn, std::slice::from_ref(remote), limit as i32)
+        .ok()?
+        .iter()
+        .map(|qr| (qr.note.created_at(), *qr.note.id()))
+        .collect();
+
+    // a channel we have nothing of yet is a plain backfill
+    let oldest = have.iter().map(|(created_at, _)| *created_at).min()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // reconcile the window we have locally, then stay subscribed to new
+    // notes and page back for whatever is missing before it
+    let mut followup = filter::make_filters_since(std::slice::from_ref(remote), now);
+    if (have.len() as u64) < limit {
+        followup.extend(filter::make_filters_until(
+            std::slice::from_ref(remote),
+            oldest,
+            limit - have.len() as u64,
+        ));
+    }
+
+    Some(SyncRequest {
+        filter: Filter::copy_from(remote)
+            .since(oldest)
+            .build()
+            .limit_mut(limit),
+        have,
+        followup,
+        fallback: fallback.to_vec(),
+    })
+}
+
 pub fn fetch_contact_list(subs: &mut Subscriptions, timeline: &mut Timeline, accounts: &Accounts) {
     if timeline.filter.get_any_ready().is_some() {
         return;
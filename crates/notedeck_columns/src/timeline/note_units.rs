@@ -237,6 +237,29 @@ impl NoteUnits {
         }
     }
 
+    /// Drop the units `keep` returns false for, keeping the order of the rest
+    pub fn retain(&mut self, mut keep: impl FnMut(&NoteUnit) -> bool) -> usize {
+        let before = self.storage.len();
+        let mut new_index = vec![None; before];
+        let mut storage = Vec::with_capacity(before);
+        for (i, unit) in std::mem::take(&mut self.storage).into_iter().enumerate() {
+            if keep(&unit) {
+                new_index[i] = Some(storage.len());
+                storage.push(unit);
+            }
+        }
+
+        self.order = self.order.iter().filter_map(|&i| new_index[i]).collect();
+        self.lookup = storage
+            .iter()
+            .enumerate()
+            .map(|(i, unit)| (unit.key(), i))
+            .collect();
+        self.storage = storage;
+
+        before - self.storage.len()
+    }
+
     pub fn latest_ref(&self) -> Option<&NoteRef> {
         if self.reversed {
             self.order.last().map(|&i| &self.storage[i])
@@ -640,6 +663,35 @@ mod tests {
         builder.aeq(2, Expect::Single(&single1));
     }
 
+    #[test]
+    fn test_retain() {
+        let mut builder = UnitBuilder::default();
+        let reaction_note = builder.new_noteref();
+
+        let single0 = builder.insert_note();
+        let reac1 = builder.insert_reac_frag(reaction_note);
+        let single1 = builder.insert_note();
+        let single2 = builder.insert_note();
+
+        let Some(NoteUnitFragment::Single(dropped)) = builder.frags.get(&single1).cloned() else {
+            panic!("fail");
+        };
+
+        let removed = builder
+            .units
+            .retain(|unit| unit.get_underlying_noteref().key != dropped.key);
+        assert_eq!(removed, 1);
+        assert_eq!(builder.units.len(), 3);
+        builder.aeq(0, Expect::Single(&single2));
+        builder.aeq(1, Expect::Reaction(vec![&reac1]));
+        builder.aeq(2, Expect::Single(&single0));
+
+        // and new notes still merge in order
+        let single3 = builder.insert_note();
+        builder.aeq(0, Expect::Single(&single3));
+        builder.aeq(1, Expect::Single(&single2));
+    }
+
     #[test]
     fn test_repost() {
         let mut builder = UnitBuilder::default();
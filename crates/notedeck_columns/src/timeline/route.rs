@@ -28,7 +28,11 @@ pub fn render_timeline_route(
         | TimelineKind::Notifications(_)
         | TimelineKind::Universe
         | TimelineKind::Hashtag(_)
+        | TimelineKind::Feed(_, _)
         | TimelineKind::Relay(_, _)
+        | TimelineKind::PublicChat(_)
+        | TimelineKind::Group(_)
+        | TimelineKind::Scoped(_, _)
         | TimelineKind::Generic(_) => {
             let resp =
                 ui::TimelineView::new(kind, timeline_cache, note_context, note_options, jobs, col)
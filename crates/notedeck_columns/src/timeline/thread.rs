@@ -424,3 +424,55 @@ impl SingleNoteUnits {
         self.units.contains_key(&UnitKey::Single(*k))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use notedeck::RootNoteIdBuf;
+
+    // the thread in fixtures/mock_relay/threads.jsonl
+    const ROOT: &str = "af61cebbe7a246c3057becf231b94d494d5ba3a2a39259c47a54fb1413e2c4ce";
+    const REPLY: &str = "5170639d31f4a2ad9d173ef26470998b5029d59d322dd3b483b93e5e390224f0";
+
+    #[test]
+    fn test_thread_from_mock_relay() {
+        let (_tmp_dir, mut ndb) = test_utils::test_ndb();
+        let mut pool = test_utils::mock_pool();
+        let root_id = NoteId::from_hex(ROOT).unwrap();
+        let selection = ThreadSelection::from_root_id(RootNoteIdBuf::new_unsafe(*root_id.bytes()));
+
+        let mut threads = Threads::default();
+        {
+            let txn = Transaction::new(&ndb).unwrap();
+            threads.open(&mut ndb, &txn, &mut pool, &selection, true, 0, 0.0);
+        }
+        // the root, its reply and a reply to that
+        assert_eq!(test_utils::ingest(&mut pool, &ndb), 3);
+        test_utils::wait_for_query(&ndb, &replies_filter_remote(&selection), 3);
+
+        let mut note_cache = NoteCache::default();
+        let mut unknown_ids = UnknownIds::default();
+        for _ in 0..500 {
+            let txn = Transaction::new(&ndb).unwrap();
+            let root = ndb.get_note_by_id(&txn, root_id.bytes()).unwrap();
+            threads.update(&root, &mut note_cache, &ndb, &txn, &mut unknown_ids, 0);
+            if threads.threads[&root_id].replies.values().next().is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // only direct replies belong to the root's node
+        let txn = Transaction::new(&ndb).unwrap();
+        let reply = ndb
+            .get_note_by_id(&txn, NoteId::from_hex(REPLY).unwrap().bytes())
+            .unwrap();
+        let replies: Vec<NoteKey> = threads.threads[&root_id]
+            .replies
+            .values()
+            .map(|note_ref| note_ref.key)
+            .collect();
+        assert_eq!(replies, vec![reply.key().unwrap()]);
+    }
+}
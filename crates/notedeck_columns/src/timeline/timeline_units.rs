@@ -117,7 +117,9 @@ fn to_fragment<'a>(
     txn: &Transaction,
 ) -> Option<NoteUnitFragmentResponse<'a>> {
     match payload.note.kind() {
-        1 => Some(NoteUnitFragmentResponse {
+        // kind 42 is a NIP-28 public chat message, kind 9 a NIP-29 group
+        // message
+        1 | 9 | 42 => Some(NoteUnitFragmentResponse {
             fragment: NoteUnitFragment::Single(NoteRef {
                 key: payload.key,
                 created_at: payload.note.created_at(),
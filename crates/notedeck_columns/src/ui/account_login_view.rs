@@ -1,3 +1,4 @@
+use crate::key_parsing::AcquiredKey;
 use crate::login_manager::AcquireKeyState;
 use crate::ui::onboarding::FollowPacksResponse;
 use crate::ui::{Preview, PreviewConfig};
@@ -6,7 +7,7 @@ use egui::{
 };
 use egui_winit::clipboard::Clipboard;
 use enostr::Keypair;
-use notedeck::{fonts::get_font_size, tr, Localization, NotedeckTextStyle};
+use notedeck::{fonts::get_font_size, tr, Localization, NotedeckTextStyle, RemoteLogin};
 use notedeck_ui::{
     app_images,
     context_menu::{input_context, PasteBehavior},
@@ -22,6 +23,7 @@ pub enum AccountLoginResponse {
     CreatingNew,
     Onboarding(FollowPacksResponse),
     LoginWith(Keypair),
+    LoginWithSigner(RemoteLogin),
 }
 
 impl<'a> AccountLoginView<'a> {
@@ -101,8 +103,14 @@ impl<'a> AccountLoginView<'a> {
             return Some(AccountLoginResponse::CreatingNew);
         }
 
-        if let Some(keypair) = self.manager.get_login_keypair() {
-            return Some(AccountLoginResponse::LoginWith(keypair.clone()));
+        match self.manager.get_login_key() {
+            Some(AcquiredKey::Keypair(keypair)) => {
+                return Some(AccountLoginResponse::LoginWith(keypair.clone()))
+            }
+            Some(AcquiredKey::RemoteSigner(login)) => {
+                return Some(AccountLoginResponse::LoginWithSigner(login.clone()))
+            }
+            None => {}
         }
         None
     }
@@ -335,17 +335,15 @@ impl<'a> AddColumnView<'a> {
             key_state.handle_input_change_after_acquire();
             key_state.loading_and_error_ui(ui, self.i18n);
 
-            if key_state.get_login_keypair().is_none()
-                && ui.add(find_user_button(self.i18n)).clicked()
+            if key_state.get_login_key().is_none() && ui.add(find_user_button(self.i18n)).clicked()
             {
                 key_state.apply_acquire();
             }
 
-            let resp = if let Some(keypair) = key_state.get_login_keypair() {
+            let resp = if let Some(key) = key_state.get_login_key() {
                 {
                     let txn = Transaction::new(self.ndb).expect("txn");
-                    if let Ok(profile) =
-                        self.ndb.get_profile_by_pubkey(&txn, keypair.pubkey.bytes())
+                    if let Ok(profile) = self.ndb.get_profile_by_pubkey(&txn, key.pubkey().bytes())
                     {
                         egui::Frame::window(ui.style())
                             .outer_margin(Margin {
@@ -362,7 +360,7 @@ impl<'a> AddColumnView<'a> {
 
                 ui.add(add_column_button(self.i18n))
                     .clicked()
-                    .then(|| to_option(keypair.pubkey).take_as_response(self.cur_account))
+                    .then(|| to_option(*key.pubkey()).take_as_response(self.cur_account))
             } else {
                 None
             };
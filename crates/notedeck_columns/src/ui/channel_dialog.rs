@@ -1,18 +1,93 @@
-use egui::{RichText, TextEdit, Vec2};
+use egui::{RichText, ScrollArea, TextEdit, Vec2};
 
+use enostr::{NoteId, Pubkey};
+use nostrdb::{Ndb, Transaction};
 use notedeck::{tr, Localization};
 
+use crate::channel_filter::{AuthorFilter, ChannelFilter};
+use crate::channels::ChannelRelays;
+use crate::nip28::{self, ChannelMetadata};
+use crate::nip29::GroupRef;
+
+/// What kind of channel the dialog creates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelDialogMode {
+    /// A channel following one or more hashtags
+    Hashtags,
+    /// A new NIP-28 public chat
+    PublicChat,
+    /// Join an existing NIP-28 public chat
+    Join,
+    /// Join a NIP-29 group on its relay
+    Group,
+}
+
+/// Whose notes a hashtag channel shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorMode {
+    Anyone,
+    Pubkeys,
+    FollowSet,
+}
+
 pub struct ChannelDialog {
     pub name: String,
     pub hashtags: String,
+    pub author_mode: AuthorMode,
+    /// npubs or hex pubkeys, for [`AuthorMode::Pubkeys`]
+    pub authors: String,
+    /// `<npub>/<identifier>`, for [`AuthorMode::FollowSet`]
+    pub follow_set: String,
+    pub kinds: String,
+    pub mute_words: String,
+    pub about: String,
+    pub join_ref: String,
+    pub group_ref: String,
+    /// Relays the channel uses instead of the global relays
+    pub relays: String,
+    pub read_only: bool,
+    pub mode: ChannelDialogMode,
     pub is_open: bool,
     pub focus_requested: bool,
     pub editing_index: Option<usize>,
+    /// Only the creator of a public chat can change its metadata
+    pub can_edit_metadata: bool,
+    /// Whether the app is subscribed to public chats for the browse list
+    pub browsing: bool,
 }
 
 pub enum ChannelDialogAction {
-    Create { name: String, hashtags: Vec<String> },
-    Edit { index: usize, name: String, hashtags: Vec<String> },
+    Create {
+        name: String,
+        hashtags: Vec<String>,
+        filter: ChannelFilter,
+        relays: ChannelRelays,
+    },
+    Edit {
+        index: usize,
+        name: String,
+        hashtags: Vec<String>,
+        filter: ChannelFilter,
+        relays: ChannelRelays,
+    },
+    CreatePublicChat {
+        metadata: ChannelMetadata,
+        relays: ChannelRelays,
+    },
+    EditPublicChat {
+        index: usize,
+        metadata: ChannelMetadata,
+        relays: ChannelRelays,
+    },
+    JoinPublicChat {
+        channel_id: NoteId,
+        relays: Vec<String>,
+    },
+    JoinGroup {
+        group: GroupRef,
+    },
+    /// The join tab was opened, fetch public chats to browse
+    BrowsePublicChats,
     Cancel,
 }
 
@@ -21,9 +96,22 @@ impl ChannelDialog {
         Self {
             name: String::new(),
             hashtags: String::new(),
+            author_mode: AuthorMode::Anyone,
+            authors: String::new(),
+            follow_set: String::new(),
+            kinds: String::new(),
+            mute_words: String::new(),
+            about: String::new(),
+            join_ref: String::new(),
+            group_ref: String::new(),
+            relays: String::new(),
+            read_only: false,
+            mode: ChannelDialogMode::Hashtags,
             is_open: false,
             focus_requested: false,
             editing_index: None,
+            can_edit_metadata: true,
+            browsing: false,
         }
     }
 
@@ -31,26 +119,177 @@ impl ChannelDialog {
         self.is_open = true;
         self.name.clear();
         self.hashtags.clear();
+        self.author_mode = AuthorMode::Anyone;
+        self.authors.clear();
+        self.follow_set.clear();
+        self.kinds.clear();
+        self.mute_words.clear();
+        self.about.clear();
+        self.join_ref.clear();
+        self.group_ref.clear();
+        self.relays.clear();
+        self.read_only = false;
+        self.mode = ChannelDialogMode::Hashtags;
         self.focus_requested = false;
         self.editing_index = None;
+        self.can_edit_metadata = true;
     }
 
-    pub fn open_for_edit(&mut self, index: usize, name: String, hashtags: Vec<String>) {
-        self.is_open = true;
+    pub fn open_for_edit(
+        &mut self,
+        index: usize,
+        name: String,
+        hashtags: Vec<String>,
+        filter: &ChannelFilter,
+        relays: &ChannelRelays,
+    ) {
+        self.open();
         self.name = name;
         self.hashtags = hashtags.join(", ");
-        self.focus_requested = false;
+        self.set_filter(filter);
+        self.set_relays(relays);
         self.editing_index = Some(index);
     }
 
+    pub fn open_for_public_chat_edit(
+        &mut self,
+        index: usize,
+        metadata: ChannelMetadata,
+        can_edit_metadata: bool,
+        relays: &ChannelRelays,
+    ) {
+        self.open();
+        self.mode = ChannelDialogMode::PublicChat;
+        self.name = metadata.name;
+        self.about = metadata.about;
+        self.set_relays(relays);
+        self.editing_index = Some(index);
+        self.can_edit_metadata = can_edit_metadata;
+    }
+
+    fn set_filter(&mut self, filter: &ChannelFilter) {
+        fn npub(pk: &Pubkey) -> String {
+            pk.npub().unwrap_or_else(|| pk.hex())
+        }
+
+        match &filter.authors {
+            AuthorFilter::Anyone => self.author_mode = AuthorMode::Anyone,
+            AuthorFilter::Pubkeys(pubkeys) => {
+                self.author_mode = AuthorMode::Pubkeys;
+                self.authors = pubkeys.iter().map(npub).collect::<Vec<_>>().join(", ");
+            }
+            AuthorFilter::FollowSet { author, identifier } => {
+                self.author_mode = AuthorMode::FollowSet;
+                self.follow_set = format!("{}/{}", npub(author), identifier);
+            }
+        }
+
+        let kinds: Vec<String> = filter.kinds.iter().map(u64::to_string).collect();
+        self.kinds = kinds.join(", ");
+        self.mute_words = filter.mute_words.join(", ");
+    }
+
+    fn set_relays(&mut self, relays: &ChannelRelays) {
+        self.relays = relays.urls.iter().cloned().collect::<Vec<_>>().join(", ");
+        self.read_only = relays.read_only;
+    }
+
     pub fn close(&mut self) {
         self.is_open = false;
     }
 
+    fn parsed_hashtags(&self) -> Vec<String> {
+        self.hashtags
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn parsed_authors(&self) -> Option<AuthorFilter> {
+        match self.author_mode {
+            AuthorMode::Anyone => Some(AuthorFilter::Anyone),
+            AuthorMode::Pubkeys => {
+                let pubkeys = AuthorFilter::parse_pubkeys(&self.authors);
+                Some(if pubkeys.is_empty() {
+                    AuthorFilter::Anyone
+                } else {
+                    AuthorFilter::Pubkeys(pubkeys)
+                })
+            }
+            AuthorMode::FollowSet => AuthorFilter::parse_follow_set(&self.follow_set),
+        }
+    }
+
+    fn parsed_filter(&self) -> Option<ChannelFilter> {
+        Some(ChannelFilter {
+            authors: self.parsed_authors()?,
+            kinds: ChannelFilter::parse_kinds(&self.kinds),
+            mute_words: ChannelFilter::parse_mute_words(&self.mute_words),
+        })
+    }
+
+    fn parsed_relays(&self) -> ChannelRelays {
+        ChannelRelays::new(ChannelRelays::parse_urls(&self.relays), self.read_only)
+    }
+
+    fn can_submit(&self) -> bool {
+        match self.mode {
+            ChannelDialogMode::Hashtags => {
+                !self.name.trim().is_empty() && self.parsed_authors().is_some()
+            }
+            // the channel's relays can be changed even when its metadata can't
+            ChannelDialogMode::PublicChat => !self.name.trim().is_empty(),
+            ChannelDialogMode::Join => nip28::parse_channel_ref(&self.join_ref).is_some(),
+            ChannelDialogMode::Group => GroupRef::parse(&self.group_ref).is_some(),
+        }
+    }
+
+    fn submit_action(&self) -> Option<ChannelDialogAction> {
+        if !self.can_submit() {
+            return None;
+        }
+
+        let name = self.name.trim().to_string();
+
+        Some(match (self.mode, self.editing_index) {
+            (ChannelDialogMode::Hashtags, Some(index)) => ChannelDialogAction::Edit {
+                index,
+                name,
+                hashtags: self.parsed_hashtags(),
+                filter: self.parsed_filter()?,
+                relays: self.parsed_relays(),
+            },
+            (ChannelDialogMode::Hashtags, None) => ChannelDialogAction::Create {
+                name,
+                hashtags: self.parsed_hashtags(),
+                filter: self.parsed_filter()?,
+                relays: self.parsed_relays(),
+            },
+            (ChannelDialogMode::PublicChat, Some(index)) => ChannelDialogAction::EditPublicChat {
+                index,
+                metadata: ChannelMetadata::new(name, self.about.trim().to_string()),
+                relays: self.parsed_relays(),
+            },
+            (ChannelDialogMode::PublicChat, None) => ChannelDialogAction::CreatePublicChat {
+                metadata: ChannelMetadata::new(name, self.about.trim().to_string()),
+                relays: self.parsed_relays(),
+            },
+            (ChannelDialogMode::Join, _) => {
+                let (channel_id, relays) = nip28::parse_channel_ref(&self.join_ref)?;
+                ChannelDialogAction::JoinPublicChat { channel_id, relays }
+            }
+            (ChannelDialogMode::Group, _) => ChannelDialogAction::JoinGroup {
+                group: GroupRef::parse(&self.group_ref)?,
+            },
+        })
+    }
+
     pub fn show(
         &mut self,
         ctx: &egui::Context,
         i18n: &mut Localization,
+        ndb: &Ndb,
     ) -> Option<ChannelDialogAction> {
         if !self.is_open {
             return None;
@@ -61,7 +300,11 @@ impl ChannelDialog {
         let title = if self.editing_index.is_some() {
             tr!(i18n, "Edit Channel", "Dialog title for editing a channel")
         } else {
-            tr!(i18n, "Create Channel", "Dialog title for creating a new channel")
+            tr!(
+                i18n,
+                "Create Channel",
+                "Dialog title for creating a new channel"
+            )
         };
 
         egui::Window::new(title)
@@ -73,60 +316,69 @@ impl ChannelDialog {
                 ui.vertical(|ui| {
                     ui.add_space(16.0);
 
-                    // Channel name input
-                    ui.label(
-                        RichText::new(tr!(i18n, "Channel Name", "Label for channel name input"))
-                            .size(14.0)
-                            .strong(),
-                    );
-                    ui.add_space(8.0);
+                    // Channel type selector, only when creating
+                    if self.editing_index.is_none() {
+                        let prev_mode = self.mode;
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(
+                                &mut self.mode,
+                                ChannelDialogMode::Hashtags,
+                                tr!(i18n, "Hashtags", "Channel type for hashtag channels"),
+                            );
+                            ui.selectable_value(
+                                &mut self.mode,
+                                ChannelDialogMode::PublicChat,
+                                tr!(i18n, "Public chat", "Channel type for NIP-28 public chats"),
+                            );
+                            ui.selectable_value(
+                                &mut self.mode,
+                                ChannelDialogMode::Join,
+                                tr!(
+                                    i18n,
+                                    "Join",
+                                    "Channel type for joining an existing public chat"
+                                ),
+                            );
+                            ui.selectable_value(
+                                &mut self.mode,
+                                ChannelDialogMode::Group,
+                                tr!(i18n, "Group", "Channel type for NIP-29 relay groups"),
+                            );
+                        });
 
-                    let name_response = ui.add(
-                        TextEdit::singleline(&mut self.name)
-                            .hint_text(tr!(
-                                i18n,
-                                "e.g., General, Bitcoin, News...",
-                                "Placeholder for channel name"
-                            ))
-                            .desired_width(f32::INFINITY),
-                    );
+                        if self.mode != prev_mode {
+                            self.focus_requested = false;
+                            if self.mode == ChannelDialogMode::Join && !self.browsing {
+                                action = Some(ChannelDialogAction::BrowsePublicChats);
+                            }
+                        }
 
-                    // Auto-focus on name field when first opened
-                    if !self.focus_requested {
-                        name_response.request_focus();
-                        self.focus_requested = true;
+                        ui.add_space(16.0);
                     }
 
-                    ui.add_space(16.0);
-
-                    // Hashtags input
-                    ui.label(
-                        RichText::new(tr!(i18n, "Hashtags", "Label for hashtags input"))
-                            .size(14.0)
-                            .strong(),
-                    );
-                    ui.add_space(4.0);
-                    ui.label(
-                        RichText::new(tr!(
-                            i18n,
-                            "Comma-separated hashtags to track",
-                            "Help text for hashtags input"
-                        ))
-                        .size(12.0)
-                        .color(ui.visuals().weak_text_color()),
-                    );
-                    ui.add_space(8.0);
-
-                    let hashtags_response = ui.add(
-                        TextEdit::multiline(&mut self.hashtags)
-                            .hint_text(tr!(
-                                i18n,
-                                "e.g., bitcoin, nostr, news",
-                                "Placeholder for hashtags"
-                            ))
-                            .desired_width(f32::INFINITY)
-                            .desired_rows(3),
-                    );
+                    let multiline_focused = match self.mode {
+                        ChannelDialogMode::Hashtags => {
+                            let focused = self.hashtags_ui(ui, i18n);
+                            self.filter_ui(ui, i18n);
+                            self.relays_ui(ui, i18n);
+                            focused
+                        }
+                        ChannelDialogMode::PublicChat => {
+                            let focused = self.public_chat_ui(ui, i18n);
+                            self.relays_ui(ui, i18n);
+                            focused
+                        }
+                        ChannelDialogMode::Join => {
+                            if let Some(join) = self.join_ui(ui, i18n, ndb) {
+                                action = Some(join);
+                            }
+                            false
+                        }
+                        ChannelDialogMode::Group => {
+                            self.group_ui(ui, i18n);
+                            false
+                        }
+                    };
 
                     // Handle Escape key to close dialog
                     let escape_pressed = ui.input(|i| i.key_pressed(egui::Key::Escape));
@@ -136,28 +388,10 @@ impl ChannelDialog {
                         action = Some(ChannelDialogAction::Cancel);
                     }
 
-                    // Handle Enter key when name is focused (not hashtags multiline field)
-                    if enter_pressed && !hashtags_response.has_focus() {
-                        if !self.name.trim().is_empty() {
-                            let hashtags: Vec<String> = self
-                                .hashtags
-                                .split(',')
-                                .map(|s| s.trim().to_string())
-                                .filter(|s| !s.is_empty())
-                                .collect();
-
-                            action = if let Some(index) = self.editing_index {
-                                Some(ChannelDialogAction::Edit {
-                                    index,
-                                    name: self.name.trim().to_string(),
-                                    hashtags,
-                                })
-                            } else {
-                                Some(ChannelDialogAction::Create {
-                                    name: self.name.trim().to_string(),
-                                    hashtags,
-                                })
-                            };
+                    // Handle Enter key when a single line field is focused
+                    if enter_pressed && !multiline_focused {
+                        if let Some(submit) = self.submit_action() {
+                            action = Some(submit);
                         }
                     }
 
@@ -166,43 +400,26 @@ impl ChannelDialog {
                     // Buttons
                     ui.horizontal(|ui| {
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            // Create/Save button (hashtags are optional)
-                            let button_enabled = !self.name.trim().is_empty();
+                            let button_enabled = self.can_submit();
 
                             let button_text = if self.editing_index.is_some() {
                                 tr!(i18n, "Save", "Button to save channel edits")
+                            } else if matches!(
+                                self.mode,
+                                ChannelDialogMode::Join | ChannelDialogMode::Group
+                            ) {
+                                tr!(i18n, "Join", "Button to join a public chat")
                             } else {
                                 tr!(i18n, "Create", "Button to create channel")
                             };
 
-                            let button = egui::Button::new(
-                                RichText::new(button_text)
-                                    .size(14.0),
-                            )
-                            .min_size(Vec2::new(80.0, 32.0));
+                            let button = egui::Button::new(RichText::new(button_text).size(14.0))
+                                .min_size(Vec2::new(80.0, 32.0));
 
                             let button_response = ui.add_enabled(button_enabled, button);
 
                             if button_response.clicked() {
-                                let hashtags: Vec<String> = self
-                                    .hashtags
-                                    .split(',')
-                                    .map(|s| s.trim().to_string())
-                                    .filter(|s| !s.is_empty())
-                                    .collect();
-
-                                action = if let Some(index) = self.editing_index {
-                                    Some(ChannelDialogAction::Edit {
-                                        index,
-                                        name: self.name.trim().to_string(),
-                                        hashtags,
-                                    })
-                                } else {
-                                    Some(ChannelDialogAction::Create {
-                                        name: self.name.trim().to_string(),
-                                        hashtags,
-                                    })
-                                };
+                                action = self.submit_action();
                             }
 
                             ui.add_space(8.0);
@@ -225,12 +442,396 @@ impl ChannelDialog {
             });
 
         // Close dialog if action was taken
-        if action.is_some() {
+        if action
+            .as_ref()
+            .is_some_and(|a| !matches!(a, ChannelDialogAction::BrowsePublicChats))
+        {
             self.close();
         }
 
         action
     }
+
+    fn name_ui(&mut self, ui: &mut egui::Ui, i18n: &mut Localization, enabled: bool) {
+        ui.label(
+            RichText::new(tr!(i18n, "Channel Name", "Label for channel name input"))
+                .size(14.0)
+                .strong(),
+        );
+        ui.add_space(8.0);
+
+        let name_response = ui.add_enabled(
+            enabled,
+            TextEdit::singleline(&mut self.name)
+                .hint_text(tr!(
+                    i18n,
+                    "e.g., General, Bitcoin, News...",
+                    "Placeholder for channel name"
+                ))
+                .desired_width(f32::INFINITY),
+        );
+
+        // Auto-focus on name field when first opened
+        if !self.focus_requested {
+            name_response.request_focus();
+            self.focus_requested = true;
+        }
+    }
+
+    /// Returns true if the multiline hashtags field has focus
+    fn hashtags_ui(&mut self, ui: &mut egui::Ui, i18n: &mut Localization) -> bool {
+        self.name_ui(ui, i18n, true);
+
+        ui.add_space(16.0);
+
+        // Hashtags input
+        ui.label(
+            RichText::new(tr!(i18n, "Hashtags", "Label for hashtags input"))
+                .size(14.0)
+                .strong(),
+        );
+        ui.add_space(4.0);
+        ui.label(
+            RichText::new(tr!(
+                i18n,
+                "Comma-separated hashtags to track",
+                "Help text for hashtags input"
+            ))
+            .size(12.0)
+            .color(ui.visuals().weak_text_color()),
+        );
+        ui.add_space(8.0);
+
+        ui.add(
+            TextEdit::multiline(&mut self.hashtags)
+                .hint_text(tr!(
+                    i18n,
+                    "e.g., bitcoin, nostr, news",
+                    "Placeholder for hashtags"
+                ))
+                .desired_width(f32::INFINITY)
+                .desired_rows(3),
+        )
+        .has_focus()
+    }
+
+    /// Returns true if the multiline about field has focus
+    fn public_chat_ui(&mut self, ui: &mut egui::Ui, i18n: &mut Localization) -> bool {
+        self.name_ui(ui, i18n, self.can_edit_metadata);
+
+        ui.add_space(16.0);
+
+        ui.label(
+            RichText::new(tr!(
+                i18n,
+                "About",
+                "Label for public chat description input"
+            ))
+            .size(14.0)
+            .strong(),
+        );
+        ui.add_space(8.0);
+
+        let about_focused = ui
+            .add_enabled(
+                self.can_edit_metadata,
+                TextEdit::multiline(&mut self.about)
+                    .hint_text(tr!(
+                        i18n,
+                        "What is this channel about?",
+                        "Placeholder for public chat description"
+                    ))
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(3),
+            )
+            .has_focus();
+
+        ui.add_space(4.0);
+        let help = if self.can_edit_metadata {
+            tr!(
+                i18n,
+                "Anyone can find and join public chats",
+                "Help text when creating a public chat"
+            )
+        } else {
+            tr!(
+                i18n,
+                "Only the creator of this chat can edit it",
+                "Help text when editing a public chat created by someone else"
+            )
+        };
+        ui.label(
+            RichText::new(help)
+                .size(12.0)
+                .color(ui.visuals().weak_text_color()),
+        );
+
+        about_focused
+    }
+
+    /// Authors, kinds and mute words, collapsed unless the channel has any
+    fn filter_ui(&mut self, ui: &mut egui::Ui, i18n: &mut Localization) {
+        ui.add_space(16.0);
+
+        let has_filter = self.author_mode != AuthorMode::Anyone
+            || !self.kinds.is_empty()
+            || !self.mute_words.is_empty();
+
+        egui::CollapsingHeader::new(
+            RichText::new(tr!(i18n, "Filters", "Label for channel filter options"))
+                .size(14.0)
+                .strong(),
+        )
+        .id_salt("channel_dialog_filters")
+        .default_open(has_filter)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr!(i18n, "From", "Label for channel author options"));
+                ui.selectable_value(
+                    &mut self.author_mode,
+                    AuthorMode::Anyone,
+                    tr!(i18n, "Anyone", "Channel shows notes from anyone"),
+                );
+                ui.selectable_value(
+                    &mut self.author_mode,
+                    AuthorMode::Pubkeys,
+                    tr!(i18n, "People", "Channel shows notes from a list of people"),
+                );
+                ui.selectable_value(
+                    &mut self.author_mode,
+                    AuthorMode::FollowSet,
+                    tr!(
+                        i18n,
+                        "Follow set",
+                        "Channel shows notes from a NIP-51 follow set"
+                    ),
+                );
+            });
+            ui.add_space(4.0);
+
+            match self.author_mode {
+                AuthorMode::Anyone => {}
+                AuthorMode::Pubkeys => {
+                    ui.add(
+                        TextEdit::singleline(&mut self.authors)
+                            .hint_text(tr!(
+                                i18n,
+                                "npub1..., npub1...",
+                                "Placeholder for channel authors"
+                            ))
+                            .desired_width(f32::INFINITY),
+                    );
+                }
+                AuthorMode::FollowSet => {
+                    ui.add(
+                        TextEdit::singleline(&mut self.follow_set)
+                            .hint_text(tr!(
+                                i18n,
+                                "npub1.../follow-set-name",
+                                "Placeholder for a NIP-51 follow set"
+                            ))
+                            .desired_width(f32::INFINITY),
+                    );
+                }
+            }
+
+            ui.add_space(8.0);
+            ui.label(tr!(i18n, "Kinds", "Label for channel note kinds input"));
+            ui.add(
+                TextEdit::singleline(&mut self.kinds)
+                    .hint_text(tr!(
+                        i18n,
+                        "Text notes. e.g., 1, 30023",
+                        "Placeholder for channel note kinds"
+                    ))
+                    .desired_width(f32::INFINITY),
+            );
+
+            ui.add_space(8.0);
+            ui.label(tr!(
+                i18n,
+                "Mute words",
+                "Label for channel mute words input"
+            ));
+            ui.add(
+                TextEdit::singleline(&mut self.mute_words)
+                    .hint_text(tr!(
+                        i18n,
+                        "Hide notes mentioning any of these, e.g., gm, airdrop",
+                        "Placeholder for channel mute words"
+                    ))
+                    .desired_width(f32::INFINITY),
+            );
+        });
+    }
+
+    fn relays_ui(&mut self, ui: &mut egui::Ui, i18n: &mut Localization) {
+        ui.add_space(16.0);
+
+        ui.label(
+            RichText::new(tr!(i18n, "Relays", "Label for channel relays input"))
+                .size(14.0)
+                .strong(),
+        );
+        ui.add_space(4.0);
+        ui.label(
+            RichText::new(tr!(
+                i18n,
+                "Optional. This channel only uses these relays instead of yours",
+                "Help text for channel relays input"
+            ))
+            .size(12.0)
+            .color(ui.visuals().weak_text_color()),
+        );
+        ui.add_space(8.0);
+
+        ui.add(
+            TextEdit::singleline(&mut self.relays)
+                .hint_text(tr!(
+                    i18n,
+                    "e.g., wss://relay.example.com",
+                    "Placeholder for channel relays"
+                ))
+                .desired_width(f32::INFINITY),
+        );
+
+        ui.add_space(8.0);
+        ui.checkbox(
+            &mut self.read_only,
+            tr!(i18n, "Read-only", "Checkbox to make a channel read-only"),
+        );
+    }
+
+    fn group_ui(&mut self, ui: &mut egui::Ui, i18n: &mut Localization) {
+        ui.label(
+            RichText::new(tr!(
+                i18n,
+                "Group address",
+                "Label for NIP-29 group address input"
+            ))
+            .size(14.0)
+            .strong(),
+        );
+        ui.add_space(8.0);
+
+        let group_response = ui.add(
+            TextEdit::singleline(&mut self.group_ref)
+                .hint_text(tr!(
+                    i18n,
+                    "groups.example.com'group-id",
+                    "Placeholder for NIP-29 group address"
+                ))
+                .desired_width(f32::INFINITY),
+        );
+
+        if !self.focus_requested {
+            group_response.request_focus();
+            self.focus_requested = true;
+        }
+
+        ui.add_space(4.0);
+        ui.label(
+            RichText::new(tr!(
+                i18n,
+                "Groups live on a single relay, which decides who can join",
+                "Help text when joining a NIP-29 group"
+            ))
+            .size(12.0)
+            .color(ui.visuals().weak_text_color()),
+        );
+    }
+
+    fn join_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &mut Localization,
+        ndb: &Ndb,
+    ) -> Option<ChannelDialogAction> {
+        ui.label(
+            RichText::new(tr!(
+                i18n,
+                "Channel link",
+                "Label for public chat link input"
+            ))
+            .size(14.0)
+            .strong(),
+        );
+        ui.add_space(8.0);
+
+        let join_response = ui.add(
+            TextEdit::singleline(&mut self.join_ref)
+                .hint_text(tr!(
+                    i18n,
+                    "nevent1... or note1...",
+                    "Placeholder for public chat link"
+                ))
+                .desired_width(f32::INFINITY),
+        );
+
+        if !self.focus_requested {
+            join_response.request_focus();
+            self.focus_requested = true;
+        }
+
+        ui.add_space(16.0);
+
+        ui.label(
+            RichText::new(tr!(i18n, "Browse", "Label for the public chat directory"))
+                .size(14.0)
+                .strong(),
+        );
+        ui.add_space(8.0);
+
+        let Ok(txn) = Transaction::new(ndb) else {
+            return None;
+        };
+
+        let results = ndb
+            .query(&txn, &[nip28::browse_filter()], 100)
+            .unwrap_or_default();
+
+        if results.is_empty() {
+            ui.label(
+                RichText::new(tr!(
+                    i18n,
+                    "Looking for public chats...",
+                    "Shown while the public chat directory is empty"
+                ))
+                .size(12.0)
+                .color(ui.visuals().weak_text_color()),
+            );
+            return None;
+        }
+
+        let mut action = None;
+
+        ScrollArea::vertical()
+            .id_salt("public_chat_directory")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for result in &results {
+                    let Some(metadata) = ChannelMetadata::from_note(&result.note) else {
+                        continue;
+                    };
+                    if metadata.name.is_empty() {
+                        continue;
+                    }
+
+                    let resp = ui
+                        .selectable_label(false, RichText::new(&metadata.name).strong())
+                        .on_hover_text(&metadata.about);
+
+                    if resp.clicked() {
+                        action = Some(ChannelDialogAction::JoinPublicChat {
+                            channel_id: NoteId::new(*result.note.id()),
+                            relays: vec![],
+                        });
+                    }
+                }
+            });
+
+        action
+    }
 }
 
 impl Default for ChannelDialog {
@@ -1,10 +1,13 @@
 use egui::{
-    vec2, Color32, CursorIcon, Margin, Rect, RichText, ScrollArea,
-    Separator, Stroke, TextStyle, Widget,
+    vec2, Color32, CursorIcon, Margin, Rect, RichText, ScrollArea, Separator, Stroke, TextStyle,
+    Widget,
 };
+use uuid::Uuid;
 
-use crate::channels::ChannelsCache;
+use crate::channels::{Channel, ChannelKind, ChannelList, ChannelPlacement, ChannelsCache};
+use crate::nip17::Participants;
 
+use enostr::Pubkey;
 use notedeck::{tr, Accounts, Localization};
 use notedeck_ui::colors;
 
@@ -14,27 +17,54 @@ pub struct ChannelSidebar<'a> {
     channels_cache: &'a ChannelsCache,
     accounts: &'a Accounts,
     i18n: &'a mut Localization,
+    dms: &'a [DmRow],
+    /// The open conversation, shown instead of the selected channel
+    selected_dm: Option<&'a Participants>,
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+/// A conversation in the sidebar's direct messages group
+pub struct DmRow {
+    pub participants: Participants,
+    pub name: String,
+    pub unread: bool,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum ChannelSidebarAction {
     SelectChannel(usize),
     AddChannel,
     DeleteChannel(usize),
     EditChannel(usize),
+    MoveChannel(usize, ChannelPlacement),
+    SetPinned(usize, bool),
+    ArchiveChannel(usize),
+    UnarchiveChannel(usize),
+    AddSection(String),
+    RenameSection(Uuid, String),
+    DeleteSection(Uuid),
+    ToggleSection(Uuid),
+    OpenDm(Participants),
 }
 
+#[derive(Default)]
 pub struct ChannelSidebarResponse {
-    pub response: egui::Response,
-    pub action: ChannelSidebarAction,
+    pub action: Option<ChannelSidebarAction>,
+    /// Ids of the channel rows that can be dragged
+    drag_ids: Vec<egui::Id>,
 }
 
 impl ChannelSidebarResponse {
-    fn new(action: ChannelSidebarAction, response: egui::Response) -> Self {
-        ChannelSidebarResponse { action, response }
+    /// Drags starting on these ids reorder channels, they shouldn't be
+    /// taken over by navigation
+    pub fn can_take_drag_from(&self) -> Vec<egui::Id> {
+        self.drag_ids.clone()
     }
 }
 
+/// The drag and drop payload of a channel row: the channel's index
+#[derive(Clone, Copy)]
+struct DraggedChannel(usize);
+
 impl<'a> ChannelSidebar<'a> {
     pub fn new(
         channels_cache: &'a ChannelsCache,
@@ -45,12 +75,19 @@ impl<'a> ChannelSidebar<'a> {
             channels_cache,
             accounts,
             i18n,
+            dms: &[],
+            selected_dm: None,
         }
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<ChannelSidebarResponse> {
-        let frame =
-            egui::Frame::new().inner_margin(Margin::symmetric(12, 16));
+    pub fn direct_messages(mut self, dms: &'a [DmRow], selected: Option<&'a Participants>) -> Self {
+        self.dms = dms;
+        self.selected_dm = selected;
+        self
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> ChannelSidebarResponse {
+        let frame = egui::Frame::new().inner_margin(Margin::symmetric(12, 16));
 
         // Background color
         if !ui.visuals().dark_mode {
@@ -67,56 +104,32 @@ impl<'a> ChannelSidebar<'a> {
         frame.show(ui, |ui| self.show_inner(ui)).inner
     }
 
-    fn show_inner(&mut self, ui: &mut egui::Ui) -> Option<ChannelSidebarResponse> {
+    fn show_inner(&mut self, ui: &mut egui::Ui) -> ChannelSidebarResponse {
         let channel_list = self.channels_cache.active_channels(self.accounts);
-        let selected_index = channel_list.selected;
+        let mut response = ChannelSidebarResponse::default();
 
         ui.vertical(|ui| {
             // Header
             ui.add_space(8.0);
-            ui.heading(RichText::new(tr!(
-                self.i18n,
-                "Channels",
-                "Header for channels sidebar"
-            ))
-            .size(18.0)
-            .strong());
+            ui.heading(
+                RichText::new(tr!(self.i18n, "Channels", "Header for channels sidebar"))
+                    .size(18.0)
+                    .strong(),
+            );
 
             ui.add_space(8.0);
             ui.add(Separator::default().horizontal().spacing(0.0));
             ui.add_space(8.0);
 
             // Scrollable channel list
-            let scroll_response = ScrollArea::vertical()
+            ScrollArea::vertical()
                 .id_salt("channel_list")
                 .show(ui, |ui| {
-                    let mut selected_action = None;
-
-                    for (index, channel) in channel_list.channels.iter().enumerate() {
-                        let is_selected = index == selected_index;
-                        let resp = channel_item(ui, &channel.name, is_selected, channel.unread_count, channel_list.num_channels(), index, self.i18n);
-
-                        match resp {
-                            ChannelItemResponse::Select => {
-                                selected_action = Some(ChannelSidebarAction::SelectChannel(index));
-                            }
-                            ChannelItemResponse::Delete => {
-                                selected_action = Some(ChannelSidebarAction::DeleteChannel(index));
-                            }
-                            ChannelItemResponse::Edit => {
-                                selected_action = Some(ChannelSidebarAction::EditChannel(index));
-                            }
-                            ChannelItemResponse::None => {}
-                        }
-                    }
-
-                    selected_action
-                })
-                .inner;
-
-            if let Some(action) = scroll_response {
-                return Some(action);
-            }
+                    // no channel is highlighted while a conversation is open
+                    let selected = self.selected_dm.is_none().then_some(channel_list.selected);
+                    channel_groups(ui, channel_list, selected, self.i18n, &mut response);
+                    dm_group(ui, self.dms, self.selected_dm, self.i18n, &mut response);
+                });
 
             ui.add_space(8.0);
 
@@ -124,74 +137,351 @@ impl<'a> ChannelSidebar<'a> {
             let add_channel_resp = ui.add(add_channel_button(self.i18n));
 
             if add_channel_resp.clicked() {
-                Some(ChannelSidebarAction::AddChannel)
-            } else {
-                None
+                response.action = Some(ChannelSidebarAction::AddChannel);
             }
-        })
-        .inner
-        .map(|action| {
-            // We need to create a dummy response for ChannelSidebarResponse
-            // Use the UI's interact_rect to create a valid response
-            let dummy_rect = ui.available_rect_before_wrap();
-            let dummy_response = ui.interact(dummy_rect, ui.id().with("channel_sidebar"), egui::Sense::hover());
-            ChannelSidebarResponse::new(action, dummy_response)
-        })
-    }
-}
+        });
 
-enum ChannelItemResponse {
-    Select,
-    Delete,
-    Edit,
-    None,
+        response
+    }
 }
 
-fn channel_item(
+/// Starred channels, the default section, the user's sections and the
+/// archived channels, in that order
+fn channel_groups(
     ui: &mut egui::Ui,
-    name: &str,
-    is_selected: bool,
-    unread_count: usize,
-    total_channels: usize,
-    _channel_index: usize,
+    channel_list: &ChannelList,
+    selected: Option<usize>,
     i18n: &mut Localization,
-) -> ChannelItemResponse {
-    let desired_size = vec2(ui.available_width(), 36.0);
-
-    let (rect, response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
+    response: &mut ChannelSidebarResponse,
+) {
+    let is_dragging = egui::DragAndDrop::has_payload_of_type::<DraggedChannel>(ui.ctx());
+
+    // show the starred group while dragging so channels can be dropped on it
+    let starred = channel_indices(channel_list, |c| c.pinned && !c.archived);
+    if !starred.is_empty() || is_dragging {
+        let title = tr!(i18n, "Starred", "Sidebar group of starred channels");
+        let placement = Some(ChannelPlacement::Starred);
+        if builtin_group_header(ui, "starred", &title, false, placement, response) {
+            channel_rows(ui, channel_list, selected, &starred, i18n, response);
+        }
+    }
 
-    let mut action = ChannelItemResponse::None;
+    // the default section only needs a header when there are others
+    let unsectioned = channel_indices(channel_list, |c| {
+        !c.pinned && !c.archived && channel_list.section_of(c).is_none()
+    });
+    if channel_list.sections.is_empty() {
+        channel_rows(ui, channel_list, selected, &unsectioned, i18n, response);
+    } else {
+        let title = tr!(
+            i18n,
+            "Channels",
+            "Sidebar section of channels without a section"
+        );
+        let placement = Some(ChannelPlacement::Section(None));
+        if builtin_group_header(ui, "channels", &title, false, placement, response) {
+            channel_rows(ui, channel_list, selected, &unsectioned, i18n, response);
+        }
+    }
 
-    if ui.is_rect_visible(rect) {
-        let visuals = ui.style().interact(&response);
-        let bg_color = if is_selected {
-            if ui.visuals().dark_mode {
-                Color32::from_rgb(45, 55, 72) // Darker blue for dark mode
-            } else {
-                Color32::from_rgb(219, 234, 254) // Light blue for light mode
-            }
-        } else if response.hovered() {
-            if ui.visuals().dark_mode {
-                Color32::from_rgb(30, 41, 59)
-            } else {
-                Color32::from_rgb(243, 244, 246)
+    for section in &channel_list.sections {
+        let rename_id = egui::Id::new(("channel_section_rename", section.id));
+        if is_editing_name(ui, rename_id) {
+            let hint = tr!(i18n, "Section name", "Hint for the section name field");
+            if let Some(name) = section_name_edit(ui, rename_id, &hint) {
+                response.action = Some(ChannelSidebarAction::RenameSection(section.id, name));
             }
         } else {
-            Color32::TRANSPARENT
-        };
+            let placement = Some(ChannelPlacement::Section(Some(section.id)));
+            let header = group_header(ui, &section.name, section.collapsed, placement, response);
+            if header.clicked() {
+                response.action = Some(ChannelSidebarAction::ToggleSection(section.id));
+            }
+
+            header.context_menu(|ui| {
+                if ui
+                    .button(tr!(
+                        i18n,
+                        "Rename Section",
+                        "Context menu option to rename a section"
+                    ))
+                    .clicked()
+                {
+                    start_name_edit(ui, rename_id, section.name.clone());
+                    ui.close_menu();
+                }
+
+                if ui
+                    .button(tr!(
+                        i18n,
+                        "Delete Section",
+                        "Context menu option to delete a section"
+                    ))
+                    .clicked()
+                {
+                    response.action = Some(ChannelSidebarAction::DeleteSection(section.id));
+                    ui.close_menu();
+                }
+            });
+        }
+
+        if !section.collapsed {
+            let channels = channel_indices(channel_list, |c| {
+                !c.pinned && !c.archived && c.section == Some(section.id)
+            });
+            channel_rows(ui, channel_list, selected, &channels, i18n, response);
+        }
+    }
+
+    let archived = channel_indices(channel_list, |c| c.archived);
+    if !archived.is_empty() {
+        let title = tr!(i18n, "Archived", "Sidebar group of archived channels");
+        if builtin_group_header(ui, "archived", &title, true, None, response) {
+            channel_rows(ui, channel_list, selected, &archived, i18n, response);
+        }
+    }
+
+    ui.add_space(4.0);
+
+    let new_section_id = egui::Id::new("channel_sidebar_new_section");
+    if is_editing_name(ui, new_section_id) {
+        let hint = tr!(i18n, "Section name", "Hint for the section name field");
+        if let Some(name) = section_name_edit(ui, new_section_id, &hint) {
+            response.action = Some(ChannelSidebarAction::AddSection(name));
+        }
+    } else {
+        let label = tr!(
+            i18n,
+            "+ New Section",
+            "Button to add a section to the channel sidebar"
+        );
+        let button = egui::Button::new(RichText::new(label).small().weak()).frame(false);
+        if ui.add(button).clicked() {
+            start_name_edit(ui, new_section_id, String::new());
+        }
+    }
+}
 
-        // Draw background
-        if bg_color != Color32::TRANSPARENT {
+/// Indices of the channels matching `include`, in sidebar order
+fn channel_indices(channel_list: &ChannelList, include: impl Fn(&Channel) -> bool) -> Vec<usize> {
+    (0..channel_list.channels.len())
+        .filter(|&i| include(&channel_list.channels[i]))
+        .collect()
+}
+
+/// Header of a group whose collapsed state only lives in egui's memory.
+/// Returns true if the group is expanded.
+fn builtin_group_header(
+    ui: &mut egui::Ui,
+    key: &str,
+    title: &str,
+    default_collapsed: bool,
+    placement: Option<ChannelPlacement>,
+    response: &mut ChannelSidebarResponse,
+) -> bool {
+    let id = egui::Id::new(("channel_sidebar_group", key));
+    let collapsed = ui
+        .data(|d| d.get_temp::<bool>(id))
+        .unwrap_or(default_collapsed);
+
+    if group_header(ui, title, collapsed, placement, response).clicked() {
+        ui.data_mut(|d| d.insert_temp(id, !collapsed));
+    }
+
+    !collapsed
+}
+
+/// A collapsible group header. Channels dropped on it move to
+/// `placement`, if any.
+fn group_header(
+    ui: &mut egui::Ui,
+    title: &str,
+    collapsed: bool,
+    placement: Option<ChannelPlacement>,
+    response: &mut ChannelSidebarResponse,
+) -> egui::Response {
+    ui.add_space(4.0);
+
+    let desired_size = vec2(ui.available_width(), 24.0);
+    let (rect, header) = ui.allocate_exact_size(desired_size, egui::Sense::click());
+
+    let is_drop_target =
+        placement.is_some() && header.dnd_hover_payload::<DraggedChannel>().is_some();
+
+    if ui.is_rect_visible(rect) {
+        if is_drop_target {
             ui.painter().rect(
                 rect,
-                4.0, // rounded corners
-                bg_color,
+                4.0,
+                ui.visuals().selection.bg_fill.gamma_multiply(0.3),
                 Stroke::NONE,
                 egui::StrokeKind::Inside,
             );
         }
 
-        // Draw hashtag icon
+        let color = if header.hovered() {
+            ui.visuals().strong_text_color()
+        } else {
+            ui.visuals().weak_text_color()
+        };
+
+        // collapse arrow
+        let icon_rect = Rect::from_center_size(rect.left_center() + vec2(8.0, 0.0), vec2(7.0, 7.0));
+        let points = if collapsed {
+            vec![
+                icon_rect.left_top(),
+                icon_rect.right_center(),
+                icon_rect.left_bottom(),
+            ]
+        } else {
+            vec![
+                icon_rect.left_top(),
+                icon_rect.right_top(),
+                icon_rect.center_bottom(),
+            ]
+        };
+        ui.painter()
+            .add(egui::Shape::convex_polygon(points, color, Stroke::NONE));
+
+        ui.painter().text(
+            rect.left_center() + vec2(20.0, 0.0),
+            egui::Align2::LEFT_CENTER,
+            title.to_uppercase(),
+            TextStyle::Small.resolve(ui.style()),
+            color,
+        );
+    }
+
+    if let (Some(placement), Some(dragged)) =
+        (placement, header.dnd_release_payload::<DraggedChannel>())
+    {
+        response.action = Some(ChannelSidebarAction::MoveChannel(dragged.0, placement));
+    }
+
+    header.on_hover_cursor(CursorIcon::PointingHand)
+}
+
+/// The channels at `indices`. Active channels can be dragged onto each
+/// other to reorder them.
+fn channel_rows(
+    ui: &mut egui::Ui,
+    channel_list: &ChannelList,
+    selected: Option<usize>,
+    indices: &[usize],
+    i18n: &mut Localization,
+    response: &mut ChannelSidebarResponse,
+) {
+    for &index in indices {
+        let channel = &channel_list.channels[index];
+
+        let action = if channel.archived {
+            channel_item(ui, channel_list, selected, index, i18n)
+        } else {
+            let drag_id = egui::Id::new(("channel_drag", channel.id));
+            response.drag_ids.push(drag_id);
+
+            let row = ui.dnd_drag_source(drag_id, DraggedChannel(index), |ui| {
+                channel_item(ui, channel_list, selected, index, i18n)
+            });
+
+            // dropping a channel here puts it right above this one
+            if row.response.dnd_hover_payload::<DraggedChannel>().is_some() {
+                let rect = row.response.rect;
+                ui.painter().hline(
+                    rect.x_range(),
+                    rect.top(),
+                    Stroke::new(2.0, ui.visuals().selection.bg_fill),
+                );
+            }
+
+            if let Some(dragged) = row.response.dnd_release_payload::<DraggedChannel>() {
+                response.action = Some(ChannelSidebarAction::MoveChannel(
+                    dragged.0,
+                    ChannelPlacement::Before(index),
+                ));
+            }
+
+            row.inner
+        };
+
+        if action.is_some() {
+            response.action = action;
+        }
+    }
+}
+
+/// Whether the name field `id` is being edited
+fn is_editing_name(ui: &egui::Ui, id: egui::Id) -> bool {
+    ui.data(|d| d.get_temp::<SectionNameEdit>(id)).is_some()
+}
+
+/// The text of a section name field while it is being edited
+#[derive(Clone)]
+struct SectionNameEdit(String);
+
+fn start_name_edit(ui: &egui::Ui, id: egui::Id, name: String) {
+    ui.data_mut(|d| d.insert_temp(id, SectionNameEdit(name)));
+    ui.memory_mut(|m| m.request_focus(id));
+}
+
+/// An inline field for naming a section. Returns the name once it is
+/// submitted with enter. Leaving the field any other way cancels it.
+fn section_name_edit(ui: &mut egui::Ui, id: egui::Id, hint: &str) -> Option<String> {
+    let mut name = ui
+        .data(|d| d.get_temp::<SectionNameEdit>(id))
+        .map(|edit| edit.0)
+        .unwrap_or_default();
+
+    ui.add_space(4.0);
+    let resp = ui.add(
+        egui::TextEdit::singleline(&mut name)
+            .id(id)
+            .hint_text(hint)
+            .desired_width(f32::INFINITY),
+    );
+
+    if !resp.lost_focus() {
+        ui.data_mut(|d| d.insert_temp(id, SectionNameEdit(name)));
+        return None;
+    }
+
+    ui.data_mut(|d| d.remove::<SectionNameEdit>(id));
+
+    let name = name.trim();
+    let submitted = ui.input(|i| i.key_pressed(egui::Key::Enter));
+    (submitted && !name.is_empty()).then(|| name.to_owned())
+}
+
+fn channel_item(
+    ui: &mut egui::Ui,
+    channel_list: &ChannelList,
+    selected: Option<usize>,
+    index: usize,
+    i18n: &mut Localization,
+) -> Option<ChannelSidebarAction> {
+    let channel = &channel_list.channels[index];
+    let kind = &channel.kind;
+    let name = channel.name.as_str();
+    let is_selected = selected == Some(index);
+    let unread_count = channel.unread_count;
+
+    let desired_size = vec2(ui.available_width(), 36.0);
+
+    let (rect, response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
+
+    let mut action = None;
+    let is_group = matches!(kind, ChannelKind::Group(_));
+
+    if ui.is_rect_visible(rect) {
+        let visuals = ui.style().interact(&response);
+        row_background(ui, rect, is_selected, response.hovered());
+
+        // Draw channel type icon
+        let icon = match kind {
+            ChannelKind::Hashtags => "#",
+            ChannelKind::PublicChat(_) => "💬",
+            ChannelKind::Group(_) => "🔒",
+        };
         let icon_rect = egui::Rect::from_min_size(
             rect.min + vec2(8.0, rect.height() / 2.0 - 8.0),
             vec2(16.0, 16.0),
@@ -199,7 +489,7 @@ fn channel_item(
         ui.painter().text(
             icon_rect.center(),
             egui::Align2::CENTER_CENTER,
-            "#",
+            icon,
             egui::TextStyle::Body.resolve(ui.style()),
             visuals.text_color(),
         );
@@ -210,11 +500,9 @@ fn channel_item(
             vec2(rect.width() - 64.0, rect.height()),
         );
         let text_color = if is_selected {
-            if ui.visuals().dark_mode {
-                Color32::WHITE
-            } else {
-                Color32::from_rgb(30, 58, 138)
-            }
+            selected_text_color(ui)
+        } else if channel.archived {
+            ui.visuals().weak_text_color()
         } else {
             visuals.text_color()
         };
@@ -259,22 +547,119 @@ fn channel_item(
         }
     }
 
-    // Handle clicks
-    if response.clicked() {
-        action = ChannelItemResponse::Select;
+    // Handle clicks. Archived channels have to be unarchived first.
+    if response.clicked() && !channel.archived {
+        action = Some(ChannelSidebarAction::SelectChannel(index));
     }
 
+    // the last active channel can't be archived or removed
+    let num_active = channel_list.num_active();
+
     // Show context menu on right-click
     response.context_menu(|ui| {
-        if ui.button(tr!(i18n, "Edit Channel", "Context menu option to edit channel")).clicked() {
-            action = ChannelItemResponse::Edit;
-            ui.close_menu();
+        if channel.archived {
+            if ui
+                .button(tr!(
+                    i18n,
+                    "Unarchive Channel",
+                    "Context menu option to unarchive channel"
+                ))
+                .clicked()
+            {
+                action = Some(ChannelSidebarAction::UnarchiveChannel(index));
+                ui.close_menu();
+            }
+        } else {
+            // group metadata is managed by the group's relay
+            if !is_group
+                && ui
+                    .button(tr!(
+                        i18n,
+                        "Edit Channel",
+                        "Context menu option to edit channel"
+                    ))
+                    .clicked()
+            {
+                action = Some(ChannelSidebarAction::EditChannel(index));
+                ui.close_menu();
+            }
+
+            let star_label = if channel.pinned {
+                tr!(
+                    i18n,
+                    "Unstar Channel",
+                    "Context menu option to unstar channel"
+                )
+            } else {
+                tr!(i18n, "Star Channel", "Context menu option to star channel")
+            };
+            if ui.button(star_label).clicked() {
+                action = Some(ChannelSidebarAction::SetPinned(index, !channel.pinned));
+                ui.close_menu();
+            }
+
+            if !channel_list.sections.is_empty() {
+                let label = tr!(
+                    i18n,
+                    "Move to Section",
+                    "Context menu to move channel to a section"
+                );
+                let default_section = tr!(
+                    i18n,
+                    "Channels",
+                    "Sidebar section of channels without a section"
+                );
+                ui.menu_button(label, |ui| {
+                    let current = channel_list.section_of(channel);
+                    let sections = std::iter::once((None, default_section.as_str())).chain(
+                        channel_list
+                            .sections
+                            .iter()
+                            .map(|section| (Some(section.id), section.name.as_str())),
+                    );
+
+                    for (section, name) in sections {
+                        if section != current && ui.button(name).clicked() {
+                            action = Some(ChannelSidebarAction::MoveChannel(
+                                index,
+                                ChannelPlacement::Section(section),
+                            ));
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+
+            if num_active > 1
+                && ui
+                    .button(tr!(
+                        i18n,
+                        "Archive Channel",
+                        "Context menu option to archive channel"
+                    ))
+                    .clicked()
+            {
+                action = Some(ChannelSidebarAction::ArchiveChannel(index));
+                ui.close_menu();
+            }
         }
 
-        // Only allow delete if not the last channel
-        if total_channels > 1 {
-            if ui.button(tr!(i18n, "Delete Channel", "Context menu option to delete channel")).clicked() {
-                action = ChannelItemResponse::Delete;
+        if channel.archived || num_active > 1 {
+            let label = if is_group {
+                tr!(
+                    i18n,
+                    "Leave Group",
+                    "Context menu option to leave a group channel"
+                )
+            } else {
+                tr!(
+                    i18n,
+                    "Delete Channel",
+                    "Context menu option to delete channel"
+                )
+            };
+            if ui.button(label).clicked() {
+                action = Some(ChannelSidebarAction::DeleteChannel(index));
                 ui.close_menu();
             }
         }
@@ -283,6 +668,140 @@ fn channel_item(
     action
 }
 
+/// Highlight a sidebar row when it's selected or hovered
+fn row_background(ui: &egui::Ui, rect: Rect, is_selected: bool, hovered: bool) {
+    let bg_color = if is_selected {
+        if ui.visuals().dark_mode {
+            Color32::from_rgb(45, 55, 72) // Darker blue for dark mode
+        } else {
+            Color32::from_rgb(219, 234, 254) // Light blue for light mode
+        }
+    } else if hovered {
+        if ui.visuals().dark_mode {
+            Color32::from_rgb(30, 41, 59)
+        } else {
+            Color32::from_rgb(243, 244, 246)
+        }
+    } else {
+        Color32::TRANSPARENT
+    };
+
+    // Draw background
+    if bg_color != Color32::TRANSPARENT {
+        ui.painter().rect(
+            rect,
+            4.0, // rounded corners
+            bg_color,
+            Stroke::NONE,
+            egui::StrokeKind::Inside,
+        );
+    }
+}
+
+fn selected_text_color(ui: &egui::Ui) -> Color32 {
+    if ui.visuals().dark_mode {
+        Color32::WHITE
+    } else {
+        Color32::from_rgb(30, 58, 138)
+    }
+}
+
+/// The account's private conversations, and a field to start one with
+/// someone's public key
+fn dm_group(
+    ui: &mut egui::Ui,
+    rows: &[DmRow],
+    selected: Option<&Participants>,
+    i18n: &mut Localization,
+    response: &mut ChannelSidebarResponse,
+) {
+    let title = tr!(
+        i18n,
+        "Direct Messages",
+        "Sidebar group of private conversations"
+    );
+    if !builtin_group_header(ui, "direct_messages", &title, false, None, response) {
+        return;
+    }
+
+    for row in rows {
+        if dm_item(ui, row, selected == Some(&row.participants)).clicked() {
+            response.action = Some(ChannelSidebarAction::OpenDm(row.participants.clone()));
+        }
+    }
+
+    ui.add_space(4.0);
+
+    let new_dm_id = egui::Id::new("channel_sidebar_new_dm");
+    if is_editing_name(ui, new_dm_id) {
+        let hint = tr!(
+            i18n,
+            "npub or hex public key",
+            "Hint for the field starting a direct message conversation"
+        );
+        let pubkey =
+            section_name_edit(ui, new_dm_id, &hint).and_then(|key| Pubkey::parse(&key).ok());
+        if let Some(pubkey) = pubkey {
+            response.action = Some(ChannelSidebarAction::OpenDm(Participants::from([pubkey])));
+        }
+    } else {
+        let label = tr!(
+            i18n,
+            "+ New Message",
+            "Button to start a direct message conversation"
+        );
+        let button = egui::Button::new(RichText::new(label).small().weak()).frame(false);
+        if ui.add(button).clicked() {
+            start_name_edit(ui, new_dm_id, String::new());
+        }
+    }
+}
+
+fn dm_item(ui: &mut egui::Ui, row: &DmRow, is_selected: bool) -> egui::Response {
+    let desired_size = vec2(ui.available_width(), 36.0);
+    let (rect, response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
+
+    if ui.is_rect_visible(rect) {
+        let visuals = ui.style().interact(&response);
+        row_background(ui, rect, is_selected, response.hovered());
+
+        let icon_center = rect.min + vec2(16.0, rect.height() / 2.0);
+        ui.painter().text(
+            icon_center,
+            egui::Align2::CENTER_CENTER,
+            "✉",
+            TextStyle::Body.resolve(ui.style()),
+            visuals.text_color(),
+        );
+
+        let text_color = if is_selected {
+            selected_text_color(ui)
+        } else if row.unread {
+            ui.visuals().strong_text_color()
+        } else {
+            visuals.text_color()
+        };
+        ui.painter().text(
+            rect.left_center() + vec2(32.0, 0.0),
+            egui::Align2::LEFT_CENTER,
+            &row.name,
+            TextStyle::Body.resolve(ui.style()),
+            text_color,
+        );
+
+        // a dot for conversations with messages we haven't seen
+        if row.unread {
+            ui.painter().circle_filled(
+                rect.right_center() - vec2(16.0, 0.0),
+                4.0,
+                Color32::from_rgb(239, 68, 68),
+            );
+        }
+    }
+
+    response.on_hover_cursor(CursorIcon::PointingHand)
+}
+
 fn add_channel_button(i18n: &mut Localization) -> impl Widget + '_ {
     move |ui: &mut egui::Ui| {
         let desired_size = vec2(ui.available_width(), 36.0);
@@ -298,13 +817,8 @@ fn add_channel_button(i18n: &mut Localization) -> impl Widget + '_ {
                 } else {
                     Color32::from_rgb(243, 244, 246)
                 };
-                ui.painter().rect(
-                    rect,
-                    4.0,
-                    bg_color,
-                    Stroke::NONE,
-                    egui::StrokeKind::Inside,
-                );
+                ui.painter()
+                    .rect(rect, 4.0, bg_color, Stroke::NONE, egui::StrokeKind::Inside);
             }
 
             // Draw + icon
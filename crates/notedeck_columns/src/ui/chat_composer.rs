@@ -0,0 +1,204 @@
+use egui::{Key, Margin, Modifiers, RichText, TextBuffer, TextEdit};
+use enostr::KeypairUnowned;
+use nostrdb::Transaction;
+use notedeck::{tr, NoteContext};
+use tracing::error;
+
+use crate::draft::Draft;
+use crate::post::{downcast_post_buffer, NewPost};
+use crate::ui::note::post::{
+    get_cursor_index, show_mention_hints, show_sign_error, text_edit_default_layout, NewPostAction,
+    PostType,
+};
+
+/// Height reserved above the composer for the mention picker
+const MENTION_PICKER_HEIGHT: f32 = 240.0;
+
+/// Slack-style single box message input. Enter sends the message,
+/// Shift+Enter inserts a newline.
+pub struct ChatComposer<'a, 'd> {
+    note_context: &'a mut NoteContext<'d>,
+    draft: &'a mut Draft,
+    post_type: PostType,
+    poster: KeypairUnowned<'a>,
+    hashtags: &'a [String],
+    relays: Vec<String>,
+    hint_text: Option<String>,
+    request_focus: bool,
+}
+
+#[derive(Default)]
+pub struct ChatComposerResponse {
+    pub action: Option<NewPostAction>,
+    pub mention_hints_drag_id: Option<egui::Id>,
+}
+
+impl<'a, 'd> ChatComposer<'a, 'd> {
+    pub fn new(
+        note_context: &'a mut NoteContext<'d>,
+        draft: &'a mut Draft,
+        post_type: PostType,
+        poster: KeypairUnowned<'a>,
+    ) -> Self {
+        Self {
+            note_context,
+            draft,
+            post_type,
+            poster,
+            hashtags: &[],
+            relays: vec![],
+            hint_text: None,
+            request_focus: false,
+        }
+    }
+
+    /// Hashtags that are added to every message sent from this composer
+    pub fn hashtags(mut self, hashtags: &'a [String]) -> Self {
+        self.hashtags = hashtags;
+        self
+    }
+
+    /// Relays messages are published to. Empty means every relay in the
+    /// pool.
+    pub fn relays(mut self, relays: Vec<String>) -> Self {
+        self.relays = relays;
+        self
+    }
+
+    pub fn hint_text(mut self, hint_text: String) -> Self {
+        self.hint_text = Some(hint_text);
+        self
+    }
+
+    /// Move keyboard focus to the composer this frame
+    pub fn request_focus(mut self, request_focus: bool) -> Self {
+        self.request_focus = request_focus;
+        self
+    }
+
+    fn id(&self, ui: &egui::Ui) -> egui::Id {
+        match &self.post_type {
+            PostType::Channel(channel_id)
+            | PostType::PublicChat(channel_id, _)
+            | PostType::Group(channel_id, _) => ui.id().with(("chat_composer", channel_id)),
+            PostType::Reply(note_id) | PostType::Quote(note_id) => {
+                ui.id().with(("chat_composer", note_id.bytes()))
+            }
+            PostType::New => ui.id().with("chat_composer"),
+        }
+    }
+
+    pub fn ui(&mut self, txn: &Transaction, ui: &mut egui::Ui) -> ChatComposerResponse {
+        let composer_rect = ui.max_rect();
+        let edit_id = self.id(ui);
+        if self.request_focus {
+            ui.memory_mut(|m| m.request_focus(edit_id));
+        }
+
+        // Grab the Enter key before the TextEdit sees it, otherwise it would
+        // insert a newline. Shift+Enter is left alone for multiline messages.
+        let has_focus = ui.memory(|m| m.has_focus(edit_id));
+        let send_pressed = has_focus
+            && !self.draft.buffer.is_empty()
+            && !self.draft.is_signing()
+            && ui.input_mut(|i| !i.modifiers.shift && i.consume_key(Modifiers::NONE, Key::Enter));
+
+        let stroke = if has_focus {
+            ui.visuals().selection.stroke
+        } else {
+            ui.visuals().noninteractive().bg_stroke
+        };
+
+        let mut mention_hints_drag_id = None;
+
+        show_sign_error(ui, self.draft);
+
+        egui::Frame::new()
+            .inner_margin(Margin::symmetric(12, 8))
+            .fill(ui.visuals().extreme_bg_color)
+            .stroke(stroke)
+            .corner_radius(8.0)
+            .show(ui, |ui| {
+                let mut updated_layout = false;
+                let cur_layout = &mut self.draft.cur_layout;
+                let mut layouter = |ui: &egui::Ui, buf: &dyn TextBuffer, wrap_width: f32| {
+                    if let Some(post_buffer) = downcast_post_buffer(buf) {
+                        if post_buffer.need_new_layout(cur_layout.as_ref()) {
+                            let job = post_buffer.to_layout_job(ui);
+                            *cur_layout = Some((post_buffer.text_buffer.clone(), job));
+                            updated_layout = true;
+                        }
+                    };
+
+                    let mut layout_job = if let Some((_, job)) = cur_layout.as_ref() {
+                        job.clone()
+                    } else {
+                        error!("Failed to get custom mentions layouter");
+                        text_edit_default_layout(ui, buf.as_str().to_owned(), wrap_width)
+                    };
+
+                    layout_job.wrap.max_width = wrap_width;
+                    ui.fonts(|f| f.layout_job(layout_job))
+                };
+
+                let hint_text = self.hint_text.clone().unwrap_or_else(|| {
+                    tr!(
+                        self.note_context.i18n,
+                        "Send a message",
+                        "Placeholder for the chat message input field"
+                    )
+                });
+
+                let out = TextEdit::multiline(&mut self.draft.buffer)
+                    .id(edit_id)
+                    .hint_text(RichText::new(hint_text).weak())
+                    .frame(false)
+                    .desired_rows(1)
+                    .desired_width(ui.available_width())
+                    .layouter(&mut layouter)
+                    .show(ui);
+
+                if updated_layout {
+                    self.draft.buffer.selected_mention = false;
+                }
+
+                if let Some(cursor_index) = get_cursor_index(&out.state.cursor.char_range()) {
+                    mention_hints_drag_id = show_mention_hints(
+                        self.note_context,
+                        self.draft,
+                        txn,
+                        ui,
+                        cursor_index,
+                        &out,
+                        |_hint| {
+                            // the composer sits at the bottom of the view, so
+                            // show the picker above it
+                            let mut hint_rect = composer_rect;
+                            hint_rect.set_bottom(composer_rect.top());
+                            hint_rect.set_top(composer_rect.top() - MENTION_PICKER_HEIGHT);
+                            hint_rect
+                        },
+                    );
+                }
+            });
+
+        let action = if send_pressed {
+            let output = self.draft.buffer.output();
+            let content = NewPost::with_channel_hashtags(&output.text, self.hashtags);
+            let new_post = NewPost::new(
+                content,
+                *self.poster.pubkey,
+                self.draft.uploaded_media.clone(),
+                output.mentions,
+            );
+            Some(NewPostAction::new(self.post_type.clone(), new_post).relays(self.relays.clone()))
+        } else {
+            None
+        };
+
+        ChatComposerResponse {
+            action,
+            mention_hints_drag_id,
+        }
+    }
+}
@@ -1,43 +1,222 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use egui::{
     vec2, Align, Color32, CursorIcon, Layout, Margin, RichText, ScrollArea, Sense,
     Stroke,
 };
+use egui_virtual_list::VirtualList;
+use enostr::{NoteId, Pubkey};
 use nostrdb::{Filter, Note, NoteKey, Transaction};
 use notedeck::fonts::get_font_size;
 use notedeck::name::get_display_name;
 use notedeck::note::ReactAction;
-use notedeck::{tr, JobsCache, NoteAction, NoteContext, NotedeckTextStyle};
+use notedeck::{
+    tr, tr_plural, ContextSelection, DeliveryStatus, JobsCache, NoteAction, NoteContext,
+    NoteContextSelection, NotedeckTextStyle,
+};
+use notedeck_ui::note::delivery_indicator;
 use notedeck_ui::{app_images, ProfilePic};
 use tracing::warn;
+use uuid::Uuid;
 
+use crate::channels::{ChannelKind, ChannelRelays};
+use crate::draft::Draft;
 use crate::nav::BodyResponse;
-use crate::timeline::{TimelineCache, TimelineKind};
+use crate::nip28;
+use crate::nip29::{self, GroupRef};
+use crate::timeline::{TimelineCache, TimelineKind, TimelineUnits};
+use crate::ui::chat_composer::{ChatComposer, ChatComposerResponse};
+use crate::ui::note::{NewPostAction, PostType};
+use crate::ui::thread_panel::{summarize_replies, ReplySummary};
 use notedeck_ui::NoteOptions;
 
-const MESSAGE_BUBBLE_PADDING: i8 = 12;
-const MESSAGE_SPACING: f32 = 8.0;
-const GROUP_SPACING: f32 = 16.0;
-const AVATAR_SIZE: f32 = 36.0;
-const MAX_BUBBLE_WIDTH_RATIO: f32 = 0.75; // 75% of available width
+pub(crate) const MESSAGE_BUBBLE_PADDING: i8 = 12;
+pub(crate) const MESSAGE_SPACING: f32 = 8.0;
+pub(crate) const GROUP_SPACING: f32 = 16.0;
+pub(crate) const AVATAR_SIZE: f32 = 36.0;
+pub(crate) const MAX_BUBBLE_WIDTH_RATIO: f32 = 0.75; // 75% of available width
+/// Reply counts under messages stop at this
+const MAX_REPLY_COUNT: u64 = 100;
+/// How close to the top of the messages older history starts loading
+const LOAD_OLDER_THRESHOLD: f32 = 50.0;
+/// How far above the newest message "jump to latest" shows up
+const JUMP_TO_LATEST_THRESHOLD: f32 = 200.0;
 
 struct MessageBubbleResponse {
     action: Option<NoteAction>,
     hovered: bool,
 }
 
+pub enum ChatViewAction {
+    Note(NoteAction),
+    Post(NewPostAction),
+    Moderate(ChatModerationAction),
+    /// Scrolled to the top, fetch messages older than the oldest shown
+    LoadOlder,
+    /// Subscribe again on relays that closed the channel's subscription
+    RetryClosed,
+}
+
+/// How the messages changed since the last frame
+#[derive(Debug, PartialEq, Eq)]
+enum ListChange {
+    Unchanged,
+    /// Older history came in before the first row, new messages after
+    /// the last one, or both
+    Grew {
+        older: usize,
+    },
+    /// Messages went away or showed up in between
+    Reset,
+}
+
+/// Scroll state of a channel's chat, kept across frames
+pub struct ChatViewState {
+    /// Rows are messages, oldest first
+    list: Rc<RefCell<VirtualList>>,
+    /// Oldest and newest messages and how many there were last frame,
+    /// to tell newly arrived messages from older history
+    oldest: Option<NoteKey>,
+    newest: Option<NoteKey>,
+    len: usize,
+    /// The oldest message when we last asked for older history
+    older_requested: Option<NoteKey>,
+    /// Scroll down to the newest message on the next frame
+    jump_to_latest: bool,
+}
+
+fn unit_key(units: &TimelineUnits, index: usize) -> Option<NoteKey> {
+    units.get(index).map(|u| u.get_underlying_noteref().key)
+}
+
+impl Default for ChatViewState {
+    fn default() -> Self {
+        let mut list = VirtualList::new();
+        list.hide_on_resize(None);
+        list.over_scan(50.0);
+
+        Self {
+            list: Rc::new(RefCell::new(list)),
+            oldest: None,
+            newest: None,
+            len: 0,
+            older_requested: None,
+            jump_to_latest: false,
+        }
+    }
+}
+
+impl ChatViewState {
+    /// Keep the virtual list in step with the timeline. Older history is
+    /// inserted at the start of the list, new messages are appended.
+    /// Anything else makes the list forget its row heights.
+    fn sync(&mut self, units: &TimelineUnits) {
+        let mut list = self.list.borrow_mut();
+        match self.change(units) {
+            ListChange::Unchanged => return,
+            ListChange::Grew { older } => {
+                if older > 0 {
+                    list.items_inserted_at_start(older);
+                }
+            }
+            ListChange::Reset => list.reset(),
+        }
+        drop(list);
+
+        let len = units.len();
+        self.len = len;
+        self.oldest = len.checked_sub(1).and_then(|i| unit_key(units, i));
+        self.newest = unit_key(units, 0);
+    }
+
+    fn change(&self, units: &TimelineUnits) -> ListChange {
+        let len = units.len();
+        let key = |index: usize| unit_key(units, index);
+        let oldest = len.checked_sub(1).and_then(key);
+        let newest = key(0);
+
+        if (len, oldest, newest) == (self.len, self.oldest, self.newest) {
+            return ListChange::Unchanged;
+        }
+
+        // units are newest first: older history shows up after our old
+        // oldest message, new messages before our old newest one
+        let inserted_at_start = self
+            .oldest
+            .and_then(|old| (0..len).rev().find(|i| key(*i) == Some(old)))
+            .map(|i| len - 1 - i);
+        let appended = self
+            .newest
+            .and_then(|old| (0..len).find(|i| key(*i) == Some(old)));
+
+        match (inserted_at_start, appended) {
+            (Some(start), Some(end)) if self.len + start + end == len => {
+                ListChange::Grew { older: start }
+            }
+            _ => ListChange::Reset,
+        }
+    }
+
+    /// Whether to ask for history older than our oldest message. Only
+    /// once per oldest message, so a channel without older history
+    /// isn't asked again every frame.
+    fn request_older(&mut self) -> bool {
+        if self.oldest.is_none() || self.older_requested == self.oldest {
+            return false;
+        }
+
+        self.older_requested = self.oldest;
+        true
+    }
+}
+
+/// Moderation picked from a message's hover menu
+pub enum ChatModerationAction {
+    /// Hide a public chat message for the current user (NIP-28 kind 43)
+    HideMessage(NoteId),
+    /// Hide all public chat messages from a user (NIP-28 kind 44)
+    MuteUser(Pubkey),
+    /// Delete a message from a group for everyone (NIP-29 kind 9005)
+    DeleteGroupMessage(GroupRef, NoteId),
+    /// Remove a user from a group (NIP-29 kind 9001)
+    RemoveGroupUser(GroupRef, Pubkey),
+}
+
+/// The channel a ChatView is showing, used by its message composer
+pub struct ChatChannel<'a> {
+    pub id: Uuid,
+    pub name: &'a str,
+    pub hashtags: &'a [String],
+    pub kind: &'a ChannelKind,
+    pub relays: &'a ChannelRelays,
+    /// Show a "new messages" divider below messages newer than this
+    pub unread_divider: Option<u64>,
+}
+
 pub struct ChatView<'a, 'd> {
     timeline_id: &'a TimelineKind,
     timeline_cache: &'a mut TimelineCache,
+    channel: ChatChannel<'a>,
+    state: &'a mut ChatViewState,
+    draft: &'a mut Draft,
     _note_options: NoteOptions,
     note_context: &'a mut NoteContext<'d>,
     _jobs: &'a mut JobsCache,
     col: usize,
+    /// Whether the current account administers the group being shown,
+    /// refreshed every frame from the relay-published admin list
+    is_group_admin: bool,
 }
 
 impl<'a, 'd> ChatView<'a, 'd> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         timeline_id: &'a TimelineKind,
         timeline_cache: &'a mut TimelineCache,
+        channel: ChatChannel<'a>,
+        state: &'a mut ChatViewState,
+        draft: &'a mut Draft,
         note_context: &'a mut NoteContext<'d>,
         note_options: NoteOptions,
         jobs: &'a mut JobsCache,
@@ -46,141 +225,284 @@ impl<'a, 'd> ChatView<'a, 'd> {
         Self {
             timeline_id,
             timeline_cache,
+            channel,
+            state,
+            draft,
             _note_options: note_options,
             note_context,
             _jobs: jobs,
             col,
+            is_group_admin: false,
         }
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) -> BodyResponse<Option<NoteAction>> {
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> BodyResponse<ChatViewAction> {
         // Check that timeline exists
         if self.timeline_cache.get(self.timeline_id).is_none() {
             return BodyResponse::none();
         }
 
+        let mut resp = BodyResponse::none();
+
+        // Message composer pinned to the bottom of the view
+        egui::TopBottomPanel::bottom(egui::Id::new(("chat_composer_panel", self.channel.id)))
+            .frame(egui::Frame::new().inner_margin(Margin::symmetric(16, 12)))
+            .show_separator_line(false)
+            .show_inside(ui, |ui| {
+                let composer_resp = self.composer_ui(ui);
+                if let Some(drag_id) = composer_resp.mention_hints_drag_id {
+                    resp.set_drag_id_raw(drag_id);
+                }
+                if let Some(action) = composer_resp.action {
+                    resp.set_output(ChatViewAction::Post(action));
+                }
+            });
+
+        if let Some(action) = self.messages_ui(ui) {
+            if resp.output.is_none() {
+                resp.set_output(action);
+            }
+        }
+
+        resp
+    }
+
+    fn composer_ui(&mut self, ui: &mut egui::Ui) -> ChatComposerResponse {
+        if self.channel.relays.read_only {
+            ui.label(
+                RichText::new(tr!(
+                    self.note_context.i18n,
+                    "This channel is read-only",
+                    "Shown in place of the chat composer for read-only channels"
+                ))
+                .color(ui.visuals().weak_text_color()),
+            );
+            return ChatComposerResponse::default();
+        }
+
+        let accounts = self.note_context.accounts;
+        let Some(poster) = accounts.selected_poster() else {
+            ui.label(
+                RichText::new(tr!(
+                    self.note_context.i18n,
+                    "Add an account with a private key to send messages",
+                    "Shown in place of the chat composer for read-only accounts"
+                ))
+                .color(ui.visuals().weak_text_color()),
+            );
+            return ChatComposerResponse::default();
+        };
+
+        let Ok(txn) = Transaction::new(self.note_context.ndb) else {
+            warn!("Failed to create transaction for chat composer");
+            return ChatComposerResponse::default();
+        };
+
+        let hint_text = tr!(
+            self.note_context.i18n,
+            "Message #{channel}",
+            "Placeholder for the chat message input field",
+            channel = self.channel.name
+        );
+
+        let post_type = match self.channel.kind {
+            ChannelKind::Hashtags => PostType::Channel(self.channel.id),
+            ChannelKind::PublicChat(channel_id) => {
+                PostType::PublicChat(self.channel.id, *channel_id)
+            }
+            ChannelKind::Group(group) => PostType::Group(self.channel.id, group.clone()),
+        };
+
+        ChatComposer::new(self.note_context, self.draft, post_type, poster)
+            .hashtags(self.channel.hashtags)
+            .relays(self.channel.relays.urls.iter().cloned().collect())
+            .hint_text(hint_text)
+            .ui(&txn, ui)
+    }
+
+    fn messages_ui(&mut self, ui: &mut egui::Ui) -> Option<ChatViewAction> {
         let scroll_id = egui::Id::new(("chat_scroll", self.timeline_id, self.col));
 
-        let mut note_action: Option<NoteAction> = None;
+        let (len, closed) = {
+            let Some(timeline) = self.timeline_cache.get(self.timeline_id) else {
+                warn!("Timeline missing in chat view");
+                return None;
+            };
+            let units = &timeline.current_view().units;
+            self.state.sync(units);
+
+            let closed: Vec<(String, String)> = timeline
+                .filter
+                .closed()
+                .map(|(relay, closed)| (relay.to_owned(), closed.reason.clone()))
+                .collect();
+            (units.len(), closed)
+        };
+
+        let Ok(txn) = Transaction::new(self.note_context.ndb) else {
+            warn!("Failed to create transaction for chat view");
+            return None;
+        };
+
+        if len == 0 {
+            let retry = empty_chat_ui(ui, self.note_context.i18n, &closed);
+            return retry.then_some(ChatViewAction::RetryClosed);
+        }
+
+        // messages and users hidden with NIP-28 kinds 43/44
+        let moderation = matches!(self.channel.kind, ChannelKind::PublicChat(_)).then(|| {
+            let account = self.note_context.accounts.selected_account_pubkey();
+            nip28::Moderation::load(self.note_context.ndb, &txn, account)
+        });
+
+        // messages deleted by NIP-29 group admins
+        let group_state = self
+            .channel
+            .kind
+            .group()
+            .map(|group| nip29::GroupState::load(self.note_context.ndb, &txn, group));
+        self.is_group_admin = group_state.as_ref().is_some_and(|state| {
+            state.is_admin(self.note_context.accounts.selected_account_pubkey())
+        });
+
+        // the account's mute list. Chat messages aren't threads, so only
+        // a muted message id matches as one.
+        let mute = self.note_context.accounts.mute();
 
-        // Main scroll area for messages
-        let _scroll_response = ScrollArea::vertical()
+        let mut scroll_area = ScrollArea::vertical()
             .id_salt(scroll_id)
             .stick_to_bottom(true)
-            .auto_shrink([false, false])
-            .show(ui, |ui| {
-                ui.with_layout(Layout::top_down(Align::Min), |ui| {
-                    let units_len = {
-                        let timeline = if let Some(tl) = self.timeline_cache.get(self.timeline_id) {
-                            tl
-                        } else {
-                            warn!("Timeline missing in chat view");
-                            return;
-                        };
-                        timeline.current_view().units.len()
-                    };
+            .auto_shrink([false, false]);
+        if std::mem::take(&mut self.state.jump_to_latest) {
+            // clamped to the bottom of the content by the scroll area
+            scroll_area = scroll_area.vertical_scroll_offset(f32::MAX);
+        }
 
-                    let txn = if let Ok(txn) = Transaction::new(self.note_context.ndb) {
-                        txn
-                    } else {
-                        warn!("Failed to create transaction for chat view");
-                        return;
+        let mut note_action: Option<ChatViewAction> = None;
+        let list = self.state.list.clone();
+        let output = scroll_area.show(ui, |ui| {
+            ui.with_layout(Layout::top_down(Align::Min), |ui| {
+                list.borrow_mut().ui_custom_layout(ui, len, |ui, index| {
+                    // the list is oldest first, units are newest first
+                    let Some((note, note_key, prev)) = self.chat_row(&txn, len - 1 - index) else {
+                        return 1;
                     };
 
-                    if units_len == 0 {
-                        // Empty state
-                        ui.add_space(50.0);
-                        ui.vertical_centered(|ui| {
-                            ui.label(
-                                RichText::new(tr!(
-                                    self.note_context.i18n,
-                                    "No messages yet",
-                                    "Empty state message for chat"
-                                ))
-                                .size(16.0)
-                                .color(ui.visuals().weak_text_color()),
-                            );
-                            ui.label(
-                                RichText::new(tr!(
-                                    self.note_context.i18n,
-                                    "Messages will appear here when they arrive",
-                                    "Empty state subtext for chat"
-                                ))
-                                .size(14.0)
-                                .color(ui.visuals().weak_text_color()),
-                            );
-                        });
-                        return;
+                    if moderation.as_ref().is_some_and(|m| m.is_hidden(&note))
+                        || group_state.as_ref().is_some_and(|g| g.is_hidden(&note))
+                        || mute.is_muted(&note, note.id())
+                    {
+                        return 1;
                     }
 
-                    let mut last_author: Option<Vec<u8>> = None;
-                    let mut last_timestamp: u64 = 0;
-
-                    for i in 0..units_len {
-                        let note_key = {
-                            let timeline = if let Some(tl) = self.timeline_cache.get(self.timeline_id) {
-                                tl
-                            } else {
-                                continue;
-                            };
-
-                            let unit = if let Some(u) = timeline.current_view().units.get(i) {
-                                u
-                            } else {
-                                continue;
-                            };
-
-                            // Extract the note key from the unit
-                            match unit {
-                                crate::timeline::NoteUnit::Single(note_ref) => note_ref.key,
-                                crate::timeline::NoteUnit::Composite(composite) => {
-                                    match composite {
-                                        crate::timeline::CompositeUnit::Reaction(r) => r.note_reacted_to.key,
-                                        crate::timeline::CompositeUnit::Repost(r) => r.note_reposted.key,
-                                    }
-                                }
-                            }
-                        };
-
-                        let note = if let Ok(note) = self.note_context.ndb.get_note_by_key(&txn, note_key) {
-                            note
-                        } else {
-                            continue;
-                        };
-
-                        // Check if this is from the same author within a short time window
-                        let same_group = if let Some(ref last_auth) = last_author {
-                            let author_bytes = note.pubkey();
-                            let time_diff = note.created_at().abs_diff(last_timestamp);
-                            author_bytes == last_auth.as_slice() && time_diff < 300 // 5 minutes
-                        } else {
-                            false
-                        };
-
-                        if !same_group {
-                            ui.add_space(GROUP_SPACING);
-                        }
+                    // the divider goes above the first message we haven't read
+                    let show_divider = self.channel.unread_divider.is_some_and(|last_read| {
+                        note.created_at() > last_read
+                            && prev.as_ref().is_some_and(|p| p.created_at() <= last_read)
+                    });
 
-                        let action = self.render_message(ui, &note, &txn, note_key, !same_group);
-                        if action.is_some() && note_action.is_none() {
-                            note_action = action;
-                        }
+                    if show_divider {
+                        ui.add_space(GROUP_SPACING);
+                        new_messages_divider(ui, self.note_context.i18n);
+                    }
 
-                        last_author = Some(note.pubkey().to_vec());
-                        last_timestamp = note.created_at();
+                    // Messages from the same author within 5 minutes are
+                    // grouped under one header
+                    let same_group = !show_divider
+                        && prev.as_ref().is_some_and(|prev| {
+                            prev.pubkey() == note.pubkey()
+                                && note.created_at().abs_diff(prev.created_at()) < 300
+                        });
 
-                        if !same_group {
-                            ui.add_space(MESSAGE_SPACING);
-                        } else {
-                            ui.add_space(MESSAGE_SPACING / 2.0);
-                        }
+                    if !same_group {
+                        ui.add_space(GROUP_SPACING);
+                    }
+
+                    let action = self.render_message(ui, &note, &txn, note_key, !same_group);
+                    if action.is_some() && note_action.is_none() {
+                        note_action = action;
+                    }
+
+                    if !same_group {
+                        ui.add_space(MESSAGE_SPACING);
+                    } else {
+                        ui.add_space(MESSAGE_SPACING / 2.0);
                     }
 
-                    ui.add_space(16.0); // Bottom padding
+                    if index + 1 == len {
+                        ui.add_space(16.0); // Bottom padding
+                    }
+
+                    1
                 });
             });
+        });
+
+        let offset = output.state.offset.y;
+        let max_offset = (output.content_size.y - output.inner_rect.height()).max(0.0);
+
+        // reached the top: ask for messages older than the oldest we have
+        if note_action.is_none() && offset <= LOAD_OLDER_THRESHOLD && self.state.request_older() {
+            note_action = Some(ChatViewAction::LoadOlder);
+        }
+
+        if max_offset - offset > JUMP_TO_LATEST_THRESHOLD && self.jump_to_latest_button(ui, &output)
+        {
+            self.state.jump_to_latest = true;
+        }
+
+        note_action
+    }
+
+    /// The message at `unit_index` and the one before it, which decides
+    /// whether they are grouped together
+    fn chat_row<'t>(
+        &self,
+        txn: &'t Transaction,
+        unit_index: usize,
+    ) -> Option<(Note<'t>, NoteKey, Option<Note<'t>>)> {
+        let units = &self
+            .timeline_cache
+            .get(self.timeline_id)?
+            .current_view()
+            .units;
+        let note_key = units.get(unit_index)?.get_underlying_noteref().key;
+        let note = self.note_context.ndb.get_note_by_key(txn, note_key).ok()?;
+        let prev = units.get(unit_index + 1).and_then(|unit| {
+            let prev_key = unit.get_underlying_noteref().key;
+            self.note_context.ndb.get_note_by_key(txn, prev_key).ok()
+        });
+
+        Some((note, note_key, prev))
+    }
 
-        BodyResponse::output(Some(note_action))
+    /// A button over the bottom of the messages to scroll back down.
+    /// Returns whether it was clicked.
+    fn jump_to_latest_button(
+        &mut self,
+        ui: &mut egui::Ui,
+        output: &egui::scroll_area::ScrollAreaOutput<()>,
+    ) -> bool {
+        let text = tr!(
+            self.note_context.i18n,
+            "Jump to latest",
+            "Button shown when scrolled up in a chat, scrolls to the newest message"
+        );
+
+        let size = vec2(140.0, 32.0);
+        let rect = egui::Rect::from_min_size(
+            output.inner_rect.center_bottom() - vec2(size.x / 2.0, size.y + 12.0),
+            size,
+        );
+
+        ui.put(
+            rect,
+            egui::Button::new(RichText::new(format!("↓ {text}")).size(13.0))
+                .fill(ui.visuals().extreme_bg_color)
+                .corner_radius(16.0),
+        )
+        .on_hover_cursor(CursorIcon::PointingHand)
+        .clicked()
     }
 
     fn render_message(
@@ -190,8 +512,8 @@ impl<'a, 'd> ChatView<'a, 'd> {
         txn: &Transaction,
         note_key: NoteKey,
         show_header: bool,
-    ) -> Option<NoteAction> {
-        let mut note_action: Option<NoteAction> = None;
+    ) -> Option<ChatViewAction> {
+        let mut note_action: Option<ChatViewAction> = None;
         let available_width = ui.available_width();
         let max_bubble_width = available_width * MAX_BUBBLE_WIDTH_RATIO;
 
@@ -216,7 +538,9 @@ impl<'a, 'd> ChatView<'a, 'd> {
                         );
 
                         if resp.clicked() {
-                            note_action = Some(NoteAction::Profile(enostr::Pubkey::new(*note.pubkey())));
+                            note_action = Some(ChatViewAction::Note(NoteAction::Profile(
+                                Pubkey::new(*note.pubkey()),
+                            )));
                         }
                     } else {
                         // Just spacing for grouped messages
@@ -235,16 +559,42 @@ impl<'a, 'd> ChatView<'a, 'd> {
                     self.render_message_header(ui, note, txn);
                 }
 
+                // keep the spot of deleted messages so the conversation
+                // still reads right
+                if self.note_context.deletions.is_deleted(note) {
+                    deleted_message(ui, self.note_context.i18n);
+                    return;
+                }
+
                 // Message bubble
                 let bubble_response = self.render_message_bubble(ui, note, txn);
                 if bubble_response.action.is_some() && note_action.is_none() {
-                    note_action = bubble_response.action;
+                    note_action = bubble_response.action.map(ChatViewAction::Note);
+                }
+
+                // "N replies, last reply X ago" under messages with a thread
+                if let Some(summary) = reply_summary(self.note_context.ndb, txn, note.id()) {
+                    if self.reply_summary_ui(ui, txn, &summary).clicked() && note_action.is_none() {
+                        note_action = Some(ChatViewAction::Note(NoteAction::note(NoteId::new(
+                            *note.id(),
+                        ))));
+                    }
                 }
 
-                // Interaction bar (show on hover)
-                if bubble_response.hovered {
-                    ui.add_space(4.0);
-                    let action_bar_resp = self.render_action_bar(ui, note, txn, note_key);
+                // Interaction bar (show on hover). Keep it up while the
+                // pointer is on the bar itself or its menu is open, so it
+                // doesn't vanish when moving off the bubble to use it.
+                let bar_id = ui.id().with(("chat_action_bar", note.id()));
+                let bar_active = ui.ctx().data(|d| d.get_temp::<bool>(bar_id).unwrap_or(false));
+                if self.has_action_bar() && (bubble_response.hovered || bar_active) {
+                    let bar = ui.scope(|ui| {
+                        ui.add_space(4.0);
+                        self.render_action_bar(ui, note, txn, note_key)
+                    });
+                    let (action_bar_resp, menu_open) = bar.inner;
+                    let keep_bar = menu_open || bar.response.contains_pointer();
+                    ui.ctx().data_mut(|d| d.insert_temp(bar_id, keep_bar));
+
                     if action_bar_resp.is_some() && note_action.is_none() {
                         note_action = action_bar_resp;
                     }
@@ -256,40 +606,24 @@ impl<'a, 'd> ChatView<'a, 'd> {
     }
 
     fn render_message_header(&mut self, ui: &mut egui::Ui, note: &Note, txn: &Transaction) {
-        ui.horizontal(|ui| {
-            ui.spacing_mut().item_spacing.x = 8.0;
-
-            // Author name
-            let profile = self.note_context.ndb
-                .get_profile_by_pubkey(txn, note.pubkey())
-                .ok();
-
-            let display_name = get_display_name(profile.as_ref());
-
-            let name_response = ui.add(
-                egui::Label::new(
-                    RichText::new(display_name.name())
-                        .size(15.0)
-                        .strong()
-                        .color(ui.visuals().strong_text_color()),
-                )
-                .sense(Sense::click()),
-            );
-
-            if name_response.clicked() {
-                // TODO: Show profile preview
-            }
-
-            // Timestamp
-            let timestamp = format_timestamp(note.created_at(), self.note_context.i18n);
-            ui.label(
-                RichText::new(timestamp)
-                    .size(12.0)
-                    .color(ui.visuals().weak_text_color()),
-            );
-        });
-
-        ui.add_space(4.0);
+        // Author name
+        let profile = self
+            .note_context
+            .ndb
+            .get_profile_by_pubkey(txn, note.pubkey())
+            .ok();
+
+        let display_name = get_display_name(profile.as_ref());
+
+        // Only our own messages are in the outbox
+        let status = self.note_context.outbox.status(note.id());
+        message_header(
+            ui,
+            self.note_context.i18n,
+            display_name.name(),
+            note.created_at(),
+            status.as_ref(),
+        );
     }
 
     fn render_message_bubble(
@@ -300,36 +634,10 @@ impl<'a, 'd> ChatView<'a, 'd> {
     ) -> MessageBubbleResponse {
         let mut note_action: Option<NoteAction> = None;
 
-        let frame = egui::Frame::new()
-            .inner_margin(Margin::same(MESSAGE_BUBBLE_PADDING))
-            .fill(self.get_bubble_color(ui))
-            .corner_radius(8.0)
-            .stroke(Stroke::new(
-                1.0,
-                if ui.visuals().dark_mode {
-                    Color32::from_rgb(55, 65, 81)
-                } else {
-                    Color32::from_rgb(229, 231, 235)
-                },
-            ));
-
-        let response = frame
-            .show(ui, |ui| {
-                ui.set_max_width(ui.available_width());
-
-                // Message content
-                let content = note.content();
-
-                let text = RichText::new(content)
-                    .size(get_font_size(ui.ctx(), &NotedeckTextStyle::Body));
-
-                ui.add(egui::Label::new(text).wrap());
-            })
-            .response;
+        let response = message_bubble(ui, note.content());
 
         // Make bubble clickable to open thread
         if response.clicked() {
-            use enostr::NoteId;
             note_action = Some(NoteAction::note(NoteId::new(*note.id())));
         }
 
@@ -346,14 +654,79 @@ impl<'a, 'd> ChatView<'a, 'd> {
         }
     }
 
-    fn render_action_bar(&mut self, ui: &mut egui::Ui, note: &Note, txn: &Transaction, note_key: NoteKey) -> Option<NoteAction> {
+    fn reply_summary_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        summary: &ReplySummary,
+    ) -> egui::Response {
+        let i18n = &mut *self.note_context.i18n;
+        let last_reply = format_timestamp(summary.last_reply, i18n);
+        let text = tr_plural!(
+            i18n,
+            "{count} reply, last reply {last_reply}",
+            "{count} replies, last reply {last_reply}",
+            "Summary of the thread under a chat message",
+            summary.count,
+            last_reply = last_reply
+        );
+
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for pubkey in &summary.participants {
+                let profile = self
+                    .note_context
+                    .ndb
+                    .get_profile_by_pubkey(txn, pubkey.bytes())
+                    .ok();
+                ui.add(
+                    &mut ProfilePic::from_profile_or_default(
+                        self.note_context.img_cache,
+                        profile.as_ref(),
+                    )
+                    .size(16.0),
+                );
+            }
+            ui.add_space(4.0);
+
+            ui.add(
+                egui::Label::new(
+                    RichText::new(text)
+                        .size(12.0)
+                        .color(ui.visuals().hyperlink_color),
+                )
+                .sense(Sense::click()),
+            )
+            .on_hover_cursor(CursorIcon::PointingHand)
+        })
+        .inner
+    }
+
+    /// Group members that aren't admins have nothing to do with a message:
+    /// replies, reactions and reposts would be sent outside the group
+    fn has_action_bar(&self) -> bool {
+        !matches!(self.channel.kind, ChannelKind::Group(_)) || self.is_group_admin
+    }
+
+    /// Returns the picked action, and whether the moderation menu is open
+    fn render_action_bar(&mut self, ui: &mut egui::Ui, note: &Note, txn: &Transaction, note_key: NoteKey) -> (Option<ChatViewAction>, bool) {
         let mut action: Option<NoteAction> = None;
+        let mut moderation_action: Option<ChatModerationAction> = None;
+        let mut menu_action: Option<ChatViewAction> = None;
+        let mut menu_open = false;
         let spacing = 16.0;
 
         ui.horizontal(|ui| {
             ui.spacing_mut().item_spacing.x = 2.0;
             ui.set_min_height(24.0);
 
+            let kind = self.channel.kind;
+            if let ChannelKind::Group(group) = kind {
+                (moderation_action, menu_open) = self.group_moderation_menu(ui, note, group);
+                return;
+            }
+
             // Reply button
             let reply_resp =
                 self.reply_button(ui, note_key).on_hover_cursor(egui::CursorIcon::PointingHand);
@@ -388,9 +761,116 @@ impl<'a, 'd> ChatView<'a, 'd> {
             if repost_resp.clicked() {
                 action = Some(NoteAction::Repost(enostr::NoteId::new(*note.id())));
             }
+
+            let is_own_message =
+                self.note_context.accounts.selected_account_pubkey().bytes() == note.pubkey();
+            if matches!(self.channel.kind, ChannelKind::PublicChat(_)) || is_own_message {
+                ui.add_space(spacing);
+                (menu_action, menu_open) = self.message_menu(ui, note, note_key);
+            }
+        });
+
+        let action = menu_action
+            .or(moderation_action.map(ChatViewAction::Moderate))
+            .or(action.map(ChatViewAction::Note));
+
+        (action, menu_open)
+    }
+
+    /// Deleting our own messages, and hiding messages in public chats
+    fn message_menu(
+        &mut self,
+        ui: &mut egui::Ui,
+        note: &Note,
+        note_key: NoteKey,
+    ) -> (Option<ChatViewAction>, bool) {
+        let mut action = None;
+        let is_own_message =
+            self.note_context.accounts.selected_account_pubkey().bytes() == note.pubkey();
+        let is_public_chat = matches!(self.channel.kind, ChannelKind::PublicChat(_));
+
+        let i18n = &mut *self.note_context.i18n;
+        let menu = ui.menu_button(RichText::new("⋯").size(16.0), |ui| {
+            if is_own_message
+                && ui
+                    .button(tr!(i18n, "Delete message", "Menu option to delete your own message"))
+                    .clicked()
+            {
+                action = Some(ChatViewAction::Note(NoteAction::Context(ContextSelection {
+                    note_key,
+                    action: NoteContextSelection::Delete,
+                })));
+                ui.close_menu();
+            }
+
+            if is_public_chat
+                && ui
+                    .button(tr!(i18n, "Hide message", "Menu option to hide a public chat message"))
+                    .clicked()
+            {
+                let hide = ChatModerationAction::HideMessage(NoteId::new(*note.id()));
+                action = Some(ChatViewAction::Moderate(hide));
+                ui.close_menu();
+            }
+
+            if is_public_chat
+                && !is_own_message
+                && ui
+                    .button(tr!(i18n, "Mute user", "Menu option to mute a user in public chats"))
+                    .clicked()
+            {
+                let mute = ChatModerationAction::MuteUser(Pubkey::new(*note.pubkey()));
+                action = Some(ChatViewAction::Moderate(mute));
+                ui.close_menu();
+            }
+        });
+
+        (action, menu.inner.is_some())
+    }
+
+    /// Admin actions for a group message. These are only honored by the
+    /// group's relay when they come from an admin.
+    fn group_moderation_menu(
+        &mut self,
+        ui: &mut egui::Ui,
+        note: &Note,
+        group: &GroupRef,
+    ) -> (Option<ChatModerationAction>, bool) {
+        let mut action = None;
+        let is_own_message =
+            self.note_context.accounts.selected_account_pubkey().bytes() == note.pubkey();
+
+        let i18n = &mut *self.note_context.i18n;
+        let menu = ui.menu_button(RichText::new("⋯").size(16.0), |ui| {
+            if ui
+                .button(tr!(i18n, "Delete message", "Menu option to delete a group message"))
+                .clicked()
+            {
+                action = Some(ChatModerationAction::DeleteGroupMessage(
+                    group.clone(),
+                    NoteId::new(*note.id()),
+                ));
+                ui.close_menu();
+            }
+
+            if !is_own_message
+                && ui
+                    .button(tr!(
+                        i18n,
+                        "Remove from group",
+                        "Menu option to remove a user from a group"
+                    ))
+                    .clicked()
+            {
+                action = Some(ChatModerationAction::RemoveGroupUser(
+                    group.clone(),
+                    Pubkey::new(*note.pubkey()),
+                ));
+                ui.close_menu();
+            }
         });
 
-        action
+        (action, menu.inner.is_some())
     }
 
     fn reply_button(&mut self, ui: &mut egui::Ui, _note_key: NoteKey) -> egui::Response {
@@ -450,16 +930,190 @@ impl<'a, 'd> ChatView<'a, 'd> {
                 "Hover text for repost button"
             ))
     }
+}
 
-    fn get_bubble_color(&self, ui: &egui::Ui) -> Color32 {
-        if ui.visuals().dark_mode {
-            Color32::from_rgb(31, 41, 55) // Dark gray for dark mode
-        } else {
-            Color32::from_rgb(249, 250, 251) // Light gray for light mode
+/// Author name and time above a group of chat messages, with the delivery
+/// status of our own
+pub(crate) fn message_header(
+    ui: &mut egui::Ui,
+    i18n: &mut notedeck::Localization,
+    name: &str,
+    created_at: u64,
+    status: Option<&DeliveryStatus>,
+) {
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 8.0;
+
+        let name_response = ui.add(
+            egui::Label::new(
+                RichText::new(name)
+                    .size(15.0)
+                    .strong()
+                    .color(ui.visuals().strong_text_color()),
+            )
+            .sense(Sense::click()),
+        );
+
+        if name_response.clicked() {
+            // TODO: Show profile preview
+        }
+
+        // Timestamp
+        let timestamp = format_timestamp(created_at, i18n);
+        ui.label(
+            RichText::new(timestamp)
+                .size(12.0)
+                .color(ui.visuals().weak_text_color()),
+        );
+
+        if let Some(status) = status {
+            delivery_indicator(ui, i18n, status);
         }
+    });
+
+    ui.add_space(4.0);
+}
+
+/// The bubble around a chat message's text
+pub(crate) fn message_bubble(ui: &mut egui::Ui, content: &str) -> egui::Response {
+    let frame = egui::Frame::new()
+        .inner_margin(Margin::same(MESSAGE_BUBBLE_PADDING))
+        .fill(bubble_color(ui))
+        .corner_radius(8.0)
+        .stroke(Stroke::new(
+            1.0,
+            if ui.visuals().dark_mode {
+                Color32::from_rgb(55, 65, 81)
+            } else {
+                Color32::from_rgb(229, 231, 235)
+            },
+        ));
+
+    frame
+        .show(ui, |ui| {
+            ui.set_max_width(ui.available_width());
+
+            let text =
+                RichText::new(content).size(get_font_size(ui.ctx(), &NotedeckTextStyle::Body));
+
+            ui.add(egui::Label::new(text).wrap());
+        })
+        .response
+}
+
+fn bubble_color(ui: &egui::Ui) -> Color32 {
+    if ui.visuals().dark_mode {
+        Color32::from_rgb(31, 41, 55) // Dark gray for dark mode
+    } else {
+        Color32::from_rgb(249, 250, 251) // Light gray for light mode
     }
 }
 
+/// `closed` lists relays that closed the channel's subscription and their
+/// reasons, since that's usually why a channel stays empty. Returns true
+/// when retry was clicked.
+fn empty_chat_ui(
+    ui: &mut egui::Ui,
+    i18n: &mut notedeck::Localization,
+    closed: &[(String, String)],
+) -> bool {
+    ui.add_space(50.0);
+    ui.vertical_centered(|ui| {
+        ui.label(
+            RichText::new(tr!(i18n, "No messages yet", "Empty state message for chat"))
+                .size(16.0)
+                .color(ui.visuals().weak_text_color()),
+        );
+
+        if closed.is_empty() {
+            ui.label(
+                RichText::new(tr!(
+                    i18n,
+                    "Messages will appear here when they arrive",
+                    "Empty state subtext for chat"
+                ))
+                .size(14.0)
+                .color(ui.visuals().weak_text_color()),
+            );
+            return false;
+        }
+
+        ui.add_space(8.0);
+        for (relay, reason) in closed {
+            let reason = if reason.is_empty() {
+                tr!(
+                    i18n,
+                    "no reason given",
+                    "Shown when a relay closed a subscription without saying why"
+                )
+            } else {
+                reason.clone()
+            };
+            ui.label(
+                RichText::new(tr!(
+                    i18n,
+                    "{relay} stopped sending messages: {reason}",
+                    "Shown in an empty chat for each relay that closed its subscription",
+                    relay = relay,
+                    reason = reason
+                ))
+                .size(14.0)
+                .color(ui.visuals().warn_fg_color),
+            );
+        }
+
+        ui.add_space(8.0);
+        ui.button(tr!(
+            i18n,
+            "Retry",
+            "Button to subscribe again on relays that closed a chat's subscription"
+        ))
+        .clicked()
+    })
+    .inner
+}
+
+/// Stands in for a message its author deleted
+fn deleted_message(ui: &mut egui::Ui, i18n: &mut notedeck::Localization) {
+    let text = tr!(
+        i18n,
+        "Message deleted",
+        "Shown in place of a chat message its author deleted"
+    );
+    ui.label(RichText::new(text).italics().weak());
+}
+
+/// A red "New messages" line marking where the user stopped reading
+fn new_messages_divider(ui: &mut egui::Ui, i18n: &mut notedeck::Localization) {
+    let color = Color32::from_rgb(239, 68, 68);
+    let label = tr!(
+        i18n,
+        "New messages",
+        "Divider shown above messages that arrived since the channel was last read"
+    );
+
+    ui.horizontal(|ui| {
+        let galley = ui.painter().layout_no_wrap(
+            label,
+            egui::TextStyle::Small.resolve(ui.style()),
+            color,
+        );
+        let (rect, _) = ui.allocate_exact_size(
+            vec2(ui.available_width(), galley.size().y),
+            Sense::hover(),
+        );
+
+        let text_pos = egui::pos2(rect.right() - galley.size().x, rect.top());
+        let line_y = rect.center().y;
+        ui.painter().hline(
+            rect.left()..=(text_pos.x - 8.0),
+            line_y,
+            Stroke::new(1.0, color),
+        );
+        ui.painter().galley(text_pos, galley, color);
+    });
+}
+
 /// Check if the current user has already reacted to a note
 fn has_user_reacted(
     ndb: &nostrdb::Ndb,
@@ -483,6 +1137,26 @@ fn has_user_reacted(
         .unwrap_or(false)
 }
 
+/// Replies anywhere in the thread under `note_id`, up to a limit
+fn reply_summary(
+    ndb: &nostrdb::Ndb,
+    txn: &Transaction,
+    note_id: &[u8; 32],
+) -> Option<ReplySummary> {
+    let filter = Filter::new()
+        .kinds([1, nip28::KIND_CHANNEL_MESSAGE as u64])
+        .event(note_id)
+        .limit(MAX_REPLY_COUNT)
+        .build();
+
+    let results = ndb.query(txn, &[filter], MAX_REPLY_COUNT as i32).ok()?;
+    summarize_replies(
+        results
+            .iter()
+            .map(|r| (*r.note.pubkey(), r.note.created_at())),
+    )
+}
+
 /// Format timestamp as relative time (e.g., "5m ago", "2h ago", "Yesterday")
 fn format_timestamp(created_at: u64, i18n: &mut notedeck::Localization) -> String {
     let now = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
@@ -514,3 +1188,69 @@ fn format_timestamp(created_at: u64, i18n: &mut notedeck::Localization) -> Strin
         format!("{} {} {}", days, tr!(i18n, "days", "Plural days"), tr!(i18n, "ago", "Time suffix"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notedeck::NoteRef;
+
+    /// Messages sent at `times`, keyed by their time
+    fn units(times: impl Iterator<Item = u64>) -> TimelineUnits {
+        TimelineUnits::from_refs_single(
+            times
+                .map(|t| NoteRef {
+                    key: NoteKey::new(t),
+                    created_at: t,
+                })
+                .collect(),
+        )
+    }
+
+    fn synced(times: impl Iterator<Item = u64>) -> ChatViewState {
+        let mut state = ChatViewState::default();
+        state.sync(&units(times));
+        state
+    }
+
+    #[test]
+    fn test_sync_unchanged() {
+        let state = synced(10..20);
+        assert_eq!(state.change(&units(10..20)), ListChange::Unchanged);
+    }
+
+    #[test]
+    fn test_sync_prepend_older() {
+        let mut state = synced(10..20);
+        assert_eq!(state.change(&units(5..20)), ListChange::Grew { older: 5 });
+
+        state.sync(&units(5..20));
+        assert_eq!(state.oldest, Some(NoteKey::new(5)));
+        assert_eq!(state.newest, Some(NoteKey::new(19)));
+        assert_eq!(state.len, 15);
+    }
+
+    #[test]
+    fn test_sync_append_newer() {
+        let state = synced(10..20);
+        assert_eq!(state.change(&units(10..25)), ListChange::Grew { older: 0 });
+
+        // both at once keeps the rows in between too
+        assert_eq!(state.change(&units(7..25)), ListChange::Grew { older: 3 });
+    }
+
+    #[test]
+    fn test_sync_reset() {
+        // the first messages
+        let state = ChatViewState::default();
+        assert_eq!(state.change(&units(10..20)), ListChange::Reset);
+
+        // a message in between went away
+        let state = synced(10..20);
+        let without_15 = units((10..20).filter(|t| *t != 15));
+        assert_eq!(state.change(&without_15), ListChange::Reset);
+
+        // or showed up late
+        let state = synced((10..20).filter(|t| *t != 15));
+        assert_eq!(state.change(&units(10..20)), ListChange::Reset);
+    }
+}
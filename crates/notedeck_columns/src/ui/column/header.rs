@@ -456,7 +456,7 @@ impl<'a> NavTitle<'a> {
     fn title_pfp(&mut self, ui: &mut egui::Ui, top: &Route, pfp_size: f32) -> Option<Response> {
         match top {
             Route::Timeline(kind) => match kind {
-                TimelineKind::Hashtag(_ht) => Some(ui.add(
+                TimelineKind::Hashtag(_) | TimelineKind::Feed(_, _) => Some(ui.add(
                     app_images::hashtag_image().fit_to_exact_size(egui::vec2(pfp_size, pfp_size)),
                 )),
 
@@ -464,6 +464,10 @@ impl<'a> NavTitle<'a> {
                     app_images::add_relay_image().fit_to_exact_size(egui::vec2(pfp_size, pfp_size)),
                 )),
 
+                TimelineKind::PublicChat(_) | TimelineKind::Group(_) => Some(ui.add(
+                    app_images::hashtag_image().fit_to_exact_size(egui::vec2(pfp_size, pfp_size)),
+                )),
+
                 TimelineKind::Profile(pubkey) => Some(self.show_profile(ui, pubkey, pfp_size)),
 
                 TimelineKind::Search(_sq) => {
@@ -476,6 +480,7 @@ impl<'a> NavTitle<'a> {
                 | TimelineKind::Algo(_)
                 | TimelineKind::Notifications(_)
                 | TimelineKind::Generic(_)
+                | TimelineKind::Scoped(_, _)
                 | TimelineKind::List(_) => Some(self.timeline_pfp(ui, kind, pfp_size)),
             },
             Route::Reply(_) => None,
@@ -0,0 +1,453 @@
+use egui::{
+    vec2, Align, CursorIcon, Key, Layout, Margin, Modifiers, RichText, ScrollArea, TextEdit,
+};
+use enostr::nip59::Rumor;
+use enostr::Pubkey;
+use nostrdb::{Ndb, Transaction};
+use notedeck::name::get_display_name;
+use notedeck::{tr, Localization, NoteContext};
+use notedeck_ui::ProfilePic;
+
+use crate::dms::DirectMessages;
+use crate::nip17::Participants;
+use crate::ui::chat_view::{
+    message_bubble, message_header, AVATAR_SIZE, GROUP_SPACING, MAX_BUBBLE_WIDTH_RATIO,
+    MESSAGE_BUBBLE_PADDING, MESSAGE_SPACING,
+};
+
+pub enum DmViewAction {
+    Send(String),
+    /// Show another conversation, picked from the search results
+    Open(Participants),
+    /// Publish the relays the account receives direct messages on
+    SetInboxRelays(Vec<String>),
+}
+
+/// A private conversation, laid out like a channel's chat
+pub struct DmView<'a, 'd> {
+    participants: &'a Participants,
+    dms: &'a mut DirectMessages,
+    note_context: &'a mut NoteContext<'d>,
+}
+
+impl<'a, 'd> DmView<'a, 'd> {
+    pub fn new(
+        participants: &'a Participants,
+        dms: &'a mut DirectMessages,
+        note_context: &'a mut NoteContext<'d>,
+    ) -> Self {
+        Self {
+            participants,
+            dms,
+            note_context,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<DmViewAction> {
+        let Ok(txn) = Transaction::new(self.note_context.ndb) else {
+            return None;
+        };
+
+        let mut action = None;
+
+        egui::TopBottomPanel::top(egui::Id::new(("dm_header", self.participants)))
+            .frame(egui::Frame::new().inner_margin(Margin::symmetric(16, 12)))
+            .show_inside(ui, |ui| {
+                action = self.header_ui(ui, &txn);
+            });
+
+        egui::TopBottomPanel::bottom(egui::Id::new(("dm_composer", self.participants)))
+            .frame(egui::Frame::new().inner_margin(Margin::symmetric(16, 12)))
+            .show_separator_line(false)
+            .show_inside(ui, |ui| {
+                if let Some(composed) = self.composer_ui(ui) {
+                    action = Some(composed);
+                }
+            });
+
+        if self.dms.search.is_empty() {
+            self.messages_ui(ui, &txn);
+        } else if let Some(picked) = self.search_results_ui(ui, &txn) {
+            action = Some(picked);
+        }
+
+        action
+    }
+
+    fn header_ui(&mut self, ui: &mut egui::Ui, txn: &Transaction) -> Option<DmViewAction> {
+        let mut action = None;
+        let account = *self.note_context.accounts.selected_account_pubkey();
+        let name = conversation_name(
+            self.note_context.ndb,
+            txn,
+            self.note_context.i18n,
+            &account,
+            self.participants,
+        );
+
+        ui.horizontal(|ui| {
+            ui.heading(RichText::new(name).size(18.0).strong());
+
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                action = self.inbox_relays_menu(ui);
+
+                let hint = tr!(
+                    self.note_context.i18n,
+                    "Search messages",
+                    "Hint for the field searching all direct messages"
+                );
+                ui.add(
+                    TextEdit::singleline(&mut self.dms.search)
+                        .hint_text(hint)
+                        .desired_width(180.0),
+                );
+            });
+        });
+
+        let has_inbox = !self
+            .note_context
+            .accounts
+            .get_selected_account()
+            .data
+            .dms
+            .relays
+            .is_empty();
+        if !has_inbox {
+            ui.label(
+                RichText::new(tr!(
+                    self.note_context.i18n,
+                    "Set your inbox relays so people can send you messages",
+                    "Shown in direct messages when the account has no inbox relays"
+                ))
+                .size(12.0)
+                .color(ui.visuals().warn_fg_color),
+            );
+        }
+
+        action
+    }
+
+    /// A menu to edit the account's inbox relays, one url per line
+    fn inbox_relays_menu(&mut self, ui: &mut egui::Ui) -> Option<DmViewAction> {
+        let mut action = None;
+        let i18n = &mut *self.note_context.i18n;
+        let relays = &self
+            .note_context
+            .accounts
+            .get_selected_account()
+            .data
+            .dms
+            .relays;
+        let edit_id = ui.id().with("dm_inbox_relays_edit");
+
+        let label = tr!(
+            i18n,
+            "Inbox relays",
+            "Menu to edit the relays the account receives direct messages on"
+        );
+        let menu = ui.menu_button(label, |ui| {
+            ui.set_min_width(280.0);
+            ui.label(
+                RichText::new(tr!(
+                    i18n,
+                    "People send you messages on these relays, one per line",
+                    "Explanation in the inbox relays menu"
+                ))
+                .size(12.0)
+                .color(ui.visuals().weak_text_color()),
+            );
+
+            let mut text = ui
+                .data(|d| d.get_temp::<String>(edit_id))
+                .unwrap_or_else(|| {
+                    relays
+                        .iter()
+                        .map(|spec| spec.url.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                });
+            ui.add(
+                TextEdit::multiline(&mut text)
+                    .hint_text("wss://")
+                    .desired_rows(3)
+                    .desired_width(f32::INFINITY),
+            );
+
+            if ui
+                .button(tr!(i18n, "Save", "Button to save the inbox relays"))
+                .clicked()
+            {
+                let relays = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+                action = Some(DmViewAction::SetInboxRelays(relays));
+                ui.data_mut(|d| d.remove::<String>(edit_id));
+                ui.close_menu();
+            } else {
+                ui.data_mut(|d| d.insert_temp(edit_id, text));
+            }
+        });
+
+        // edits that weren't saved are dropped with the menu
+        if menu.inner.is_none() {
+            ui.data_mut(|d| d.remove::<String>(edit_id));
+        }
+
+        action
+    }
+
+    fn composer_ui(&mut self, ui: &mut egui::Ui) -> Option<DmViewAction> {
+        if self.note_context.accounts.selected_filled().is_none() {
+            ui.label(
+                RichText::new(tr!(
+                    self.note_context.i18n,
+                    "Add an account with a private key to send direct messages",
+                    "Replaces the direct message composer for accounts without a secret key"
+                ))
+                .color(ui.visuals().weak_text_color()),
+            );
+            return None;
+        }
+
+        let edit_id = egui::Id::new(("dm_composer_edit", self.participants));
+        let draft = self
+            .dms
+            .drafts
+            .entry(self.participants.clone())
+            .or_default();
+
+        // Enter sends, Shift+Enter inserts a newline, like the chat composer
+        let has_focus = ui.memory(|m| m.has_focus(edit_id));
+        let send_pressed = has_focus
+            && !draft.trim().is_empty()
+            && ui.input_mut(|i| !i.modifiers.shift && i.consume_key(Modifiers::NONE, Key::Enter));
+
+        let stroke = if has_focus {
+            ui.visuals().selection.stroke
+        } else {
+            ui.visuals().noninteractive().bg_stroke
+        };
+
+        let hint = tr!(
+            self.note_context.i18n,
+            "Send a private message",
+            "Placeholder for the direct message input field"
+        );
+
+        egui::Frame::new()
+            .inner_margin(Margin::symmetric(12, 8))
+            .fill(ui.visuals().extreme_bg_color)
+            .stroke(stroke)
+            .corner_radius(8.0)
+            .show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(draft)
+                        .id(edit_id)
+                        .hint_text(RichText::new(hint).weak())
+                        .frame(false)
+                        .desired_rows(1)
+                        .desired_width(ui.available_width()),
+                );
+            });
+
+        send_pressed.then(|| DmViewAction::Send(std::mem::take(draft).trim().to_owned()))
+    }
+
+    fn messages_ui(&mut self, ui: &mut egui::Ui, txn: &Transaction) {
+        let Some(conversation) = self.dms.conversation(self.participants) else {
+            ui.add_space(50.0);
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    RichText::new(tr!(
+                        self.note_context.i18n,
+                        "No messages yet",
+                        "Empty state message for chat"
+                    ))
+                    .size(16.0)
+                    .color(ui.visuals().weak_text_color()),
+                );
+            });
+            return;
+        };
+
+        ScrollArea::vertical()
+            .id_salt(("dm_scroll", self.participants))
+            .stick_to_bottom(true)
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                let mut prev: Option<&Rumor> = None;
+                for message in &conversation.messages {
+                    // Messages from the same author within 5 minutes are
+                    // grouped under one header, like in channels
+                    let same_group = prev.is_some_and(|prev| {
+                        prev.pubkey == message.pubkey
+                            && message.created_at.abs_diff(prev.created_at) < 300
+                    });
+
+                    if !same_group {
+                        ui.add_space(GROUP_SPACING);
+                    }
+
+                    let status = self.dms.delivery(self.note_context.outbox, &message.id);
+                    let header = (!same_group).then_some(status.as_ref());
+                    message_row(ui, self.note_context, txn, message, header);
+
+                    if !same_group {
+                        ui.add_space(MESSAGE_SPACING);
+                    } else {
+                        ui.add_space(MESSAGE_SPACING / 2.0);
+                    }
+
+                    prev = Some(message);
+                }
+                ui.add_space(16.0);
+            });
+    }
+
+    /// Messages matching the search in every conversation. Clicking one
+    /// opens its conversation.
+    fn search_results_ui(&mut self, ui: &mut egui::Ui, txn: &Transaction) -> Option<DmViewAction> {
+        let account = *self.note_context.accounts.selected_account_pubkey();
+        let results = self.dms.search_messages(&self.dms.search);
+
+        if results.is_empty() {
+            ui.add_space(50.0);
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    RichText::new(tr!(
+                        self.note_context.i18n,
+                        "No messages found",
+                        "Shown when a direct message search has no results"
+                    ))
+                    .size(16.0)
+                    .color(ui.visuals().weak_text_color()),
+                );
+            });
+            return None;
+        }
+
+        let mut action = None;
+        ScrollArea::vertical()
+            .id_salt("dm_search_results")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (participants, message) in results {
+                    ui.add_space(GROUP_SPACING);
+
+                    let name = conversation_name(
+                        self.note_context.ndb,
+                        txn,
+                        self.note_context.i18n,
+                        &account,
+                        participants,
+                    );
+                    ui.label(
+                        RichText::new(name)
+                            .size(12.0)
+                            .color(ui.visuals().weak_text_color()),
+                    );
+
+                    let bubble = message_row(ui, self.note_context, txn, message, Some(None));
+                    if bubble.on_hover_cursor(CursorIcon::PointingHand).clicked() {
+                        action = Some(DmViewAction::Open(participants.clone()));
+                    }
+                }
+            });
+
+        if action.is_some() {
+            self.dms.search.clear();
+        }
+
+        action
+    }
+}
+
+/// A message with the author's avatar. `header` holds the delivery status
+/// when the author's name and the time are shown above the message.
+/// Returns the bubble's response.
+fn message_row(
+    ui: &mut egui::Ui,
+    note_context: &mut NoteContext<'_>,
+    txn: &Transaction,
+    message: &Rumor,
+    header: Option<Option<&notedeck::DeliveryStatus>>,
+) -> egui::Response {
+    let max_bubble_width = ui.available_width() * MAX_BUBBLE_WIDTH_RATIO;
+    let profile = note_context
+        .ndb
+        .get_profile_by_pubkey(txn, message.pubkey.bytes())
+        .ok();
+
+    ui.horizontal(|ui| {
+        ui.allocate_ui_with_layout(
+            vec2(
+                AVATAR_SIZE + MESSAGE_BUBBLE_PADDING as f32,
+                ui.available_height(),
+            ),
+            Layout::top_down(Align::Min),
+            |ui| {
+                if header.is_some() {
+                    let pic = ProfilePic::from_profile_or_default(
+                        note_context.img_cache,
+                        profile.as_ref(),
+                    );
+                    ui.add(&mut pic.size(AVATAR_SIZE));
+                } else {
+                    ui.add_space(AVATAR_SIZE);
+                }
+            },
+        );
+
+        ui.with_layout(Layout::top_down(Align::Min), |ui| {
+            ui.set_max_width(max_bubble_width);
+
+            if let Some(status) = header {
+                let name = participant_name(profile.as_ref(), &message.pubkey);
+                message_header(ui, note_context.i18n, &name, message.created_at, status);
+            }
+
+            message_bubble(ui, &message.content).interact(egui::Sense::click())
+        })
+        .inner
+    })
+    .inner
+}
+
+/// What a conversation is called: the names of the people in it
+pub fn conversation_name(
+    ndb: &Ndb,
+    txn: &Transaction,
+    i18n: &mut Localization,
+    account: &Pubkey,
+    participants: &Participants,
+) -> String {
+    if participants.len() == 1 && participants.contains(account) {
+        return tr!(
+            i18n,
+            "Note to self",
+            "Name of the direct message conversation with oneself"
+        );
+    }
+
+    participants
+        .iter()
+        .map(|pk| {
+            let profile = ndb.get_profile_by_pubkey(txn, pk.bytes()).ok();
+            participant_name(profile.as_ref(), pk)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The profile's name, or the start of the npub while we don't have it
+fn participant_name(profile: Option<&nostrdb::ProfileRecord<'_>>, pubkey: &Pubkey) -> String {
+    if profile.is_some() {
+        return get_display_name(profile).name().to_owned();
+    }
+
+    let npub = pubkey.npub().unwrap_or_else(|| pubkey.hex());
+    format!("{}…", &npub[..npub.len().min(16)])
+}
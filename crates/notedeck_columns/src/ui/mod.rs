@@ -3,9 +3,11 @@ pub mod accounts;
 pub mod add_column;
 pub mod channel_dialog;
 pub mod channel_sidebar;
+pub mod chat_composer;
 pub mod chat_view;
 pub mod column;
 pub mod configure_deck;
+pub mod dm_view;
 pub mod edit_deck;
 pub mod images;
 pub mod mentions_picker;
@@ -31,7 +33,9 @@ pub mod widgets;
 pub use accounts::AccountsView;
 pub use channel_dialog::{ChannelDialog, ChannelDialogAction};
 pub use channel_sidebar::{ChannelSidebar, ChannelSidebarAction};
-pub use chat_view::ChatView;
+pub use chat_composer::ChatComposer;
+pub use chat_view::{ChatChannel, ChatModerationAction, ChatView, ChatViewAction, ChatViewState};
+pub use dm_view::{DmView, DmViewAction};
 pub use note::{PostReplyView, PostView};
 pub use preview::{Preview, PreviewApp, PreviewConfig};
 pub use profile::ProfileView;
@@ -40,5 +44,5 @@ pub use relay_dialog::{RelayDialog, RelayDialogAction};
 pub use settings::SettingsView;
 pub use side_panel::{DesktopSidePanel, SidePanelAction};
 pub use thread::ThreadView;
-pub use thread_panel::{ThreadPanel, ThreadPanelAction};
+pub use thread_panel::{ThreadPanel, ThreadPanelAction, ThreadPanelResponse};
 pub use timeline::TimelineView;
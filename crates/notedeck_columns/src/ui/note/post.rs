@@ -1,6 +1,7 @@
 use crate::draft::{Draft, Drafts, MentionHint};
 use crate::media_upload::nostrbuild_nip96_upload;
 use crate::nav::BodyResponse;
+use crate::nip29::GroupRef;
 use crate::post::{downcast_post_buffer, MentionType, NewPost};
 use crate::ui::mentions_picker::MentionPickerView;
 use crate::ui::{self, Preview, PreviewConfig};
@@ -11,7 +12,7 @@ use egui::{
     widgets::text_edit::TextEdit,
     Frame, Layout, Margin, Pos2, ScrollArea, Sense, TextBuffer,
 };
-use enostr::{FilledKeypair, FullKeypair, NoteId, Pubkey, RelayPool};
+use enostr::{FullKeypair, KeypairUnowned, NoteId, Pubkey};
 use nostrdb::{Ndb, Transaction};
 use notedeck::media::gif::ensure_latest_texture;
 use notedeck::media::AnimationMode;
@@ -20,7 +21,8 @@ use notedeck::platform::android::try_open_file_picker;
 use notedeck::platform::get_next_selected_file;
 use notedeck::{get_render_state, JobsCache, PixelDimensions, RenderState};
 use notedeck::{
-    name::get_display_name, supported_mime_hosted_at_url, tr, Localization, NoteAction, NoteContext,
+    name::get_display_name, supported_mime_hosted_at_url, tr, Accounts, Localization, NoteAction,
+    NoteContext, Outbox,
 };
 use notedeck_ui::{
     app_images,
@@ -29,6 +31,7 @@ use notedeck_ui::{
     NoteOptions, ProfilePic,
 };
 use tracing::error;
+use uuid::Uuid;
 #[cfg(not(target_os = "android"))]
 use {notedeck::platform::file::emit_selected_file, notedeck::platform::file::SelectedMedia};
 
@@ -36,7 +39,7 @@ pub struct PostView<'a, 'd> {
     note_context: &'a mut NoteContext<'d>,
     draft: &'a mut Draft,
     post_type: PostType,
-    poster: FilledKeypair<'a>,
+    poster: KeypairUnowned<'a>,
     inner_rect: egui::Rect,
     note_options: NoteOptions,
     jobs: &'a mut JobsCache,
@@ -48,6 +51,13 @@ pub enum PostType {
     New,
     Quote(NoteId),
     Reply(NoteId),
+    /// A new top-level message posted from a channel's chat composer
+    Channel(Uuid),
+    /// A NIP-28 message posted from a public chat channel's composer. The
+    /// note id is the channel's kind 40 creation event.
+    PublicChat(Uuid, NoteId),
+    /// A NIP-29 message posted from a group channel's composer
+    Group(Uuid, GroupRef),
 }
 
 pub enum PostAction {
@@ -61,38 +71,65 @@ pub enum PostAction {
 pub struct NewPostAction {
     post_type: PostType,
     post: NewPost,
+    /// Publish only to these relays instead of every relay in the pool
+    relays: Vec<String>,
 }
 
 impl NewPostAction {
     pub fn new(post_type: PostType, post: NewPost) -> Self {
-        NewPostAction { post_type, post }
+        NewPostAction {
+            post_type,
+            post,
+            relays: vec![],
+        }
+    }
+
+    /// Publish only to `relays`, used by channels with their own relays
+    pub fn relays(mut self, relays: Vec<String>) -> Self {
+        self.relays = relays;
+        self
     }
 
+    /// Sign the post with the account's signer and publish it once signed.
+    /// The draft is kept until then.
     pub fn execute(
         &self,
         ndb: &Ndb,
         txn: &Transaction,
-        pool: &mut RelayPool,
+        accounts: &mut Accounts,
+        outbox: &mut Outbox,
         drafts: &mut Drafts,
     ) -> Result<()> {
-        let seckey = self.post.account.secret_key.to_secret_bytes();
+        let note = match &self.post_type {
+            PostType::New | PostType::Channel(_) => self.post.to_note(),
+
+            PostType::PublicChat(_, channel_id) => self.post.to_channel_message(channel_id),
 
-        let note = match self.post_type {
-            PostType::New => self.post.to_note(&seckey),
+            PostType::Group(_, group) => self.post.to_group_message(group),
 
             PostType::Reply(target) => {
                 let replying_to = ndb.get_note_by_id(txn, target.bytes())?;
-                self.post.to_reply(&seckey, &replying_to)
+                self.post.to_reply(&replying_to)
             }
 
             PostType::Quote(target) => {
                 let quoting = ndb.get_note_by_id(txn, target.bytes())?;
-                self.post.to_quote(&seckey, &quoting)
+                self.post.to_quote(&quoting)
             }
         };
 
-        pool.send(&enostr::ClientMessage::event(&note)?);
-        drafts.get_from_post_type(&self.post_type).clear();
+        let signed = accounts.sign(note);
+        let relays = match &self.post_type {
+            // group messages never leave the group's relay
+            PostType::Group(_, group) => vec![group.relay.clone()],
+            _ => self.relays.clone(),
+        };
+
+        // the draft is cleared by `Drafts::update` once the signer signed
+        // it, so nothing is lost when a remote signer refuses
+        let draft = drafts.get_from_post_type(&self.post_type);
+        draft.signing = Some(outbox.sign_and_track(signed, relays));
+        draft.sign_error = None;
 
         Ok(())
     }
@@ -109,7 +146,7 @@ impl<'a, 'd> PostView<'a, 'd> {
         note_context: &'a mut NoteContext<'d>,
         draft: &'a mut Draft,
         post_type: PostType,
-        poster: FilledKeypair<'a>,
+        poster: KeypairUnowned<'a>,
         inner_rect: egui::Rect,
         note_options: NoteOptions,
         jobs: &'a mut JobsCache,
@@ -249,96 +286,20 @@ impl<'a, 'd> PostView<'a, 'd> {
         cursor_index: usize,
         textedit_output: &TextEditOutput,
     ) -> Option<egui::Id> {
-        let mention = self.draft.buffer.get_mention(cursor_index)?;
-
-        if mention.info.mention_type != MentionType::Pending {
-            return None;
-        }
-
-        if ui.ctx().input(|r| r.key_pressed(egui::Key::Escape)) {
-            self.draft.buffer.delete_mention(mention.index);
-            return None;
-        }
-
-        let mention_str = self.draft.buffer.get_mention_string(&mention);
-
-        if !mention_str.is_empty() {
-            if let Some(mention_hint) = &mut self.draft.cur_mention_hint {
-                if mention_hint.index != mention.index {
-                    mention_hint.index = mention.index;
-                    mention_hint.pos =
-                        calculate_mention_hints_pos(textedit_output, mention.info.start_index);
-                }
-                mention_hint.text = mention_str.to_owned();
-            } else {
-                self.draft.cur_mention_hint = Some(MentionHint {
-                    index: mention.index,
-                    text: mention_str.to_owned(),
-                    pos: calculate_mention_hints_pos(textedit_output, mention.info.start_index),
-                });
-            }
-        }
-
-        let hint_rect = {
-            let Some(hint) = &self.draft.cur_mention_hint else {
-                return None;
-            };
-
-            let mut hint_rect = self.inner_rect;
-            hint_rect.set_top(hint.pos.y);
-            hint_rect
-        };
-
-        let res = self
-            .note_context
-            .ndb
-            .search_profile(txn, mention_str, 10)
-            .ok()?;
-
-        let resp = MentionPickerView::new(
-            self.note_context.img_cache,
-            self.note_context.ndb,
+        let inner_rect = self.inner_rect;
+        show_mention_hints(
+            self.note_context,
+            self.draft,
             txn,
-            &res,
+            ui,
+            cursor_index,
+            textedit_output,
+            |hint| {
+                let mut hint_rect = inner_rect;
+                hint_rect.set_top(hint.pos.y);
+                hint_rect
+            },
         )
-        .show_in_rect(hint_rect, ui);
-
-        let mut selection_made = None;
-
-        let Some(out) = resp.output else {
-            return resp.drag_id;
-        };
-
-        match out {
-            ui::mentions_picker::MentionPickerResponse::SelectResult(selection) => {
-                if let Some(hint_index) = selection {
-                    if let Some(pk) = res.get(hint_index) {
-                        let record = self.note_context.ndb.get_profile_by_pubkey(txn, pk);
-
-                        if let Some(made_selection) =
-                            self.draft.buffer.select_mention_and_replace_name(
-                                mention.index,
-                                get_display_name(record.ok().as_ref()).name(),
-                                Pubkey::new(**pk),
-                            )
-                        {
-                            selection_made = Some(made_selection);
-                        }
-                        self.draft.cur_mention_hint = None;
-                    }
-                }
-            }
-
-            ui::mentions_picker::MentionPickerResponse::DeleteMention => {
-                self.draft.buffer.delete_mention(mention.index)
-            }
-        }
-
-        if let Some(selection) = selection_made {
-            selection.process(ui.ctx(), textedit_output);
-        }
-
-        resp.drag_id
     }
 
     fn focused(&self, ui: &egui::Ui) -> bool {
@@ -376,10 +337,13 @@ impl<'a, 'd> PostView<'a, 'd> {
         while let Some(selected_file) = get_next_selected_file() {
             match selected_file {
                 Ok(selected_media) => {
-                    let promise = nostrbuild_nip96_upload(
-                        self.poster.secret_key.secret_bytes(),
-                        selected_media,
-                    );
+                    // uploads are authorized with a NIP-98 event, signed
+                    // locally
+                    let Some(secret_key) = self.poster.secret_key else {
+                        continue;
+                    };
+                    let promise =
+                        nostrbuild_nip96_upload(secret_key.secret_bytes(), selected_media);
                     self.draft.uploading_media.push(promise);
                 }
                 Err(e) => {
@@ -480,15 +444,20 @@ impl<'a, 'd> PostView<'a, 'd> {
     }
 
     fn input_buttons(&mut self, ui: &mut egui::Ui) -> Option<NewPostAction> {
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::BOTTOM), |ui| {
-            self.show_upload_media_button(ui);
-        });
+        if self.poster.secret_key.is_some() {
+            ui.with_layout(egui::Layout::left_to_right(egui::Align::BOTTOM), |ui| {
+                self.show_upload_media_button(ui);
+            });
+        }
 
         ui.with_layout(egui::Layout::right_to_left(egui::Align::BOTTOM), |ui| {
             let post_button_clicked = ui
                 .add_sized(
                     [91.0, 32.0],
-                    post_button(self.note_context.i18n, !self.draft.buffer.is_empty()),
+                    post_button(
+                        self.note_context.i18n,
+                        !self.draft.buffer.is_empty() && !self.draft.is_signing(),
+                    ),
                 )
                 .clicked();
 
@@ -497,12 +466,15 @@ impl<'a, 'd> PostView<'a, 'd> {
             });
 
             if post_button_clicked
-                || (!self.draft.buffer.is_empty() && shortcut_pressed && self.focused(ui))
+                || (!self.draft.buffer.is_empty()
+                    && !self.draft.is_signing()
+                    && shortcut_pressed
+                    && self.focused(ui))
             {
                 let output = self.draft.buffer.output();
                 let new_post = NewPost::new(
                     output.text,
-                    self.poster.to_full(),
+                    *self.poster.pubkey,
                     self.draft.uploaded_media.clone(),
                     output.mentions,
                 );
@@ -621,6 +593,28 @@ impl<'a, 'd> PostView<'a, 'd> {
         for i in to_remove {
             self.draft.upload_errors.remove(i);
         }
+
+        show_sign_error(ui, self.draft);
+    }
+}
+
+/// Why the signer refused the last post, until it is dismissed or the post
+/// is sent again
+pub(crate) fn show_sign_error(ui: &mut egui::Ui, draft: &mut Draft) {
+    let Some(error) = &draft.sign_error else {
+        return;
+    };
+
+    if ui
+        .add(
+            egui::Label::new(egui::RichText::new(error).color(ui.visuals().warn_fg_color))
+                .sense(Sense::click())
+                .selectable(false),
+        )
+        .on_hover_text_at_pointer("Dismiss")
+        .clicked()
+    {
+        draft.sign_error = None;
     }
 }
 
@@ -783,7 +777,98 @@ fn show_remove_upload_button(ui: &mut egui::Ui, desired_rect: egui::Rect) -> egu
     resp
 }
 
-fn get_cursor_index(cursor: &Option<CCursorRange>) -> Option<usize> {
+/// Displays the mention picker and handles when one is selected. `hint_rect`
+/// decides where the picker is placed relative to the current hint.
+/// Returns the drag id of the mention hint widget
+pub(crate) fn show_mention_hints(
+    note_context: &mut NoteContext<'_>,
+    draft: &mut Draft,
+    txn: &nostrdb::Transaction,
+    ui: &mut egui::Ui,
+    cursor_index: usize,
+    textedit_output: &TextEditOutput,
+    place_hint: impl FnOnce(&MentionHint) -> egui::Rect,
+) -> Option<egui::Id> {
+    let mention = draft.buffer.get_mention(cursor_index)?;
+
+    if mention.info.mention_type != MentionType::Pending {
+        return None;
+    }
+
+    if ui.ctx().input(|r| r.key_pressed(egui::Key::Escape)) {
+        draft.buffer.delete_mention(mention.index);
+        return None;
+    }
+
+    let mention_str = draft.buffer.get_mention_string(&mention);
+
+    if !mention_str.is_empty() {
+        if let Some(mention_hint) = &mut draft.cur_mention_hint {
+            if mention_hint.index != mention.index {
+                mention_hint.index = mention.index;
+                mention_hint.pos =
+                    calculate_mention_hints_pos(textedit_output, mention.info.start_index);
+            }
+            mention_hint.text = mention_str.to_owned();
+        } else {
+            draft.cur_mention_hint = Some(MentionHint {
+                index: mention.index,
+                text: mention_str.to_owned(),
+                pos: calculate_mention_hints_pos(textedit_output, mention.info.start_index),
+            });
+        }
+    }
+
+    let hint_rect = {
+        let Some(hint) = &draft.cur_mention_hint else {
+            return None;
+        };
+
+        place_hint(hint)
+    };
+
+    let res = note_context.ndb.search_profile(txn, mention_str, 10).ok()?;
+
+    let resp = MentionPickerView::new(note_context.img_cache, note_context.ndb, txn, &res)
+        .show_in_rect(hint_rect, ui);
+
+    let mut selection_made = None;
+
+    let Some(out) = resp.output else {
+        return resp.drag_id;
+    };
+
+    match out {
+        ui::mentions_picker::MentionPickerResponse::SelectResult(selection) => {
+            if let Some(hint_index) = selection {
+                if let Some(pk) = res.get(hint_index) {
+                    let record = note_context.ndb.get_profile_by_pubkey(txn, pk);
+
+                    if let Some(made_selection) = draft.buffer.select_mention_and_replace_name(
+                        mention.index,
+                        get_display_name(record.ok().as_ref()).name(),
+                        Pubkey::new(**pk),
+                    ) {
+                        selection_made = Some(made_selection);
+                    }
+                    draft.cur_mention_hint = None;
+                }
+            }
+        }
+
+        ui::mentions_picker::MentionPickerResponse::DeleteMention => {
+            draft.buffer.delete_mention(mention.index)
+        }
+    }
+
+    if let Some(selection) = selection_made {
+        selection.process(ui.ctx(), textedit_output);
+    }
+
+    resp.drag_id
+}
+
+pub(crate) fn get_cursor_index(cursor: &Option<CCursorRange>) -> Option<usize> {
     let range = cursor.as_ref()?;
 
     if range.primary.index == range.secondary.index {
@@ -809,7 +894,7 @@ fn calculate_mention_hints_pos(out: &TextEditOutput, char_pos: usize) -> egui::P
     out.text_clip_rect.left_bottom()
 }
 
-fn text_edit_default_layout(ui: &egui::Ui, text: String, wrap_width: f32) -> LayoutJob {
+pub(crate) fn text_edit_default_layout(ui: &egui::Ui, text: String, wrap_width: f32) -> LayoutJob {
     LayoutJob::simple(
         text,
         egui::FontSelection::default().resolve(ui.style()),
@@ -880,13 +965,15 @@ mod preview {
                 unknown_ids: app.unknown_ids,
                 clipboard: app.clipboard,
                 i18n: app.i18n,
+                outbox: app.outbox,
+                deletions: app.deletions,
             };
 
             PostView::new(
                 &mut note_context,
                 &mut self.draft,
                 PostType::New,
-                self.poster.to_filled(),
+                (&self.poster.to_filled()).into(),
                 ui.available_rect_before_wrap(),
                 NoteOptions::default(),
                 &mut self.jobs,
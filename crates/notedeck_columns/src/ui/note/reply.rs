@@ -6,13 +6,13 @@ use crate::ui::{
 };
 
 use egui::{Rect, Response, ScrollArea, Ui};
-use enostr::{FilledKeypair, NoteId};
+use enostr::{KeypairUnowned, NoteId};
 use notedeck::{JobsCache, NoteContext};
 use notedeck_ui::{NoteOptions, NoteView, ProfilePic};
 
 pub struct PostReplyView<'a, 'd> {
     note_context: &'a mut NoteContext<'d>,
-    poster: FilledKeypair<'a>,
+    poster: KeypairUnowned<'a>,
     draft: &'a mut Draft,
     note: &'a nostrdb::Note<'a>,
     scroll_id: egui::Id,
@@ -25,7 +25,7 @@ impl<'a, 'd> PostReplyView<'a, 'd> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         note_context: &'a mut NoteContext<'d>,
-        poster: FilledKeypair<'a>,
+        poster: KeypairUnowned<'a>,
         draft: &'a mut Draft,
         note: &'a nostrdb::Note<'a>,
         inner_rect: egui::Rect,
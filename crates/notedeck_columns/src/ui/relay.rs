@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crate::nav::BodyResponse;
 use crate::ui::{Preview, PreviewConfig};
 use egui::{Align, Button, CornerRadius, Frame, Id, Layout, Margin, Rgba, RichText, Ui, Vec2};
-use enostr::{RelayPool, RelayStatus};
+use enostr::{RelayAuthState, RelayHealth, RelayInformation, RelayPool, RelayStatus};
 use notedeck::{tr, Localization, NotedeckTextStyle, RelayAction};
 use notedeck_ui::app_images;
 use notedeck_ui::{colors::PINK, padding};
@@ -96,7 +96,8 @@ impl<'a> RelayView<'a> {
                                         .id_salt(index)
                                         .max_width(
                                             ui.max_rect().width()
-                                                - get_right_side_width(relay_info.status),
+                                                - get_right_side_width(relay_info.status)
+                                                - get_auth_width(relay_info.auth),
                                         ) // TODO: refactor to dynamically check the size of the 'right to left' portion and set the max width to be the screen width minus padding minus 'right to left' width
                                         .show(ui, |ui| {
                                             ui.label(
@@ -122,8 +123,20 @@ impl<'a> RelayView<'a> {
                             };
 
                             show_connection_status(ui, self.i18n, relay_info.status);
+
+                            if let Some(auth) = relay_info.auth {
+                                show_auth_status(ui, self.i18n, auth);
+                            }
                         });
                     });
+
+                    if let Some(document) = relay_info.document {
+                        show_relay_document(ui, self.i18n, document);
+                    }
+
+                    if let Some(health) = relay_info.health {
+                        show_health_stats(ui, self.i18n, health);
+                    }
                 });
             });
         }
@@ -220,6 +233,13 @@ fn get_right_side_width(status: RelayStatus) -> f32 {
     }
 }
 
+fn get_auth_width(auth: Option<&RelayAuthState>) -> f32 {
+    match auth {
+        None | Some(RelayAuthState::None) => 0.0,
+        Some(_) => 130.0,
+    }
+}
+
 fn delete_button(dark_mode: bool) -> egui::Button<'static> {
     let img = if dark_mode {
         app_images::delete_dark_image()
@@ -264,6 +284,232 @@ fn show_connection_status(ui: &mut Ui, i18n: &mut Localization, status: RelaySta
     });
 }
 
+/// NIP-42 auth badge, only shown for relays that asked us to authenticate
+fn show_auth_status(ui: &mut Ui, i18n: &mut Localization, auth: &RelayAuthState) {
+    let (label_text, fg_color) = match auth {
+        RelayAuthState::None => return,
+        RelayAuthState::Challenged(_) => (
+            tr!(
+                i18n,
+                "Auth required",
+                "Status label for relay asking us to authenticate"
+            ),
+            ui.visuals().warn_fg_color,
+        ),
+        RelayAuthState::Pending { .. } => (
+            tr!(
+                i18n,
+                "Authenticating...",
+                "Status label for relay we are authenticating with"
+            ),
+            ui.visuals().warn_fg_color,
+        ),
+        RelayAuthState::Authenticated => (
+            tr!(
+                i18n,
+                "Authenticated",
+                "Status label for authenticated relay"
+            ),
+            ui.visuals().selection.bg_fill,
+        ),
+        RelayAuthState::Failed(_) => (
+            tr!(
+                i18n,
+                "Auth failed",
+                "Status label for relay that rejected our authentication"
+            ),
+            ui.visuals().error_fg_color,
+        ),
+    };
+    let bg_color = egui::lerp(Rgba::from(fg_color)..=Rgba::BLACK, 0.8).into();
+
+    let resp = Frame::new()
+        .corner_radius(CornerRadius::same(100))
+        .fill(bg_color)
+        .inner_margin(Margin::symmetric(12, 4))
+        .show(ui, |ui| {
+            ui.label(RichText::new(label_text).color(fg_color));
+        })
+        .response;
+
+    match auth {
+        RelayAuthState::Failed(reason) if !reason.is_empty() => {
+            resp.on_hover_text(reason);
+        }
+        RelayAuthState::Challenged(_) => {
+            resp.on_hover_text(tr!(
+                i18n,
+                "Select an account with a private key to authenticate",
+                "Hover text for relay asking us to authenticate"
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// One line of connection stats under the relay url
+fn show_health_stats(ui: &mut Ui, i18n: &mut Localization, health: &RelayHealth) {
+    let mut stats = vec![];
+
+    if let Some(latency) = health.latency() {
+        stats.push(tr!(
+            i18n,
+            "Ping {ms} ms",
+            "Relay round trip time in the relay list",
+            ms = latency.as_millis() as u64
+        ));
+    }
+
+    if let Some(eose_time) = health.eose_time() {
+        stats.push(tr!(
+            i18n,
+            "Loads in {ms} ms",
+            "Average time for a relay to answer a subscription, in the relay list",
+            ms = eose_time.as_millis() as u64
+        ));
+    }
+
+    if health.connect_failures() > 0 {
+        stats.push(tr!(
+            i18n,
+            "{count} failed connects",
+            "Number of failed connection attempts to a relay, in the relay list",
+            count = health.connect_failures()
+        ));
+    }
+
+    let error_rate = health.error_rate();
+    if error_rate > 0.0 {
+        stats.push(tr!(
+            i18n,
+            "{percent}% refused",
+            "Share of events and subscriptions a relay refused, in the relay list",
+            percent = (error_rate * 100.0).round() as u32
+        ));
+    }
+
+    if stats.is_empty() {
+        return;
+    }
+
+    let color = if health.is_failing() {
+        ui.visuals().error_fg_color
+    } else {
+        ui.visuals().weak_text_color()
+    };
+
+    ui.add_space(4.0);
+    let resp = ui.label(RichText::new(stats.join(" · ")).size(12.0).color(color));
+
+    if health.is_failing() {
+        resp.on_hover_text(tr!(
+            i18n,
+            "This relay keeps failing, it is skipped when fetching notes",
+            "Hover text for the stats of a chronically failing relay"
+        ));
+    }
+}
+
+/// What the relay says about itself in its NIP-11 document: name,
+/// description, and the limits we have to respect
+fn show_relay_document(ui: &mut Ui, i18n: &mut Localization, document: &RelayInformation) {
+    let about: Vec<&str> = [document.name.as_deref(), document.description.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    if !about.is_empty() {
+        ui.add_space(4.0);
+        ui.add(
+            egui::Label::new(RichText::new(about.join(" · ")).size(12.0))
+                .truncate()
+                .show_tooltip_when_elided(true),
+        );
+    }
+
+    let limitation = &document.limitation;
+    let mut notes = vec![];
+
+    if limitation.payment_required {
+        notes.push(tr!(
+            i18n,
+            "Paid",
+            "Relay requires payment, in the relay list"
+        ));
+    }
+
+    if limitation.auth_required {
+        notes.push(tr!(
+            i18n,
+            "Login required",
+            "Relay requires authentication before use, in the relay list"
+        ));
+    }
+
+    if limitation.restricted_writes {
+        notes.push(tr!(
+            i18n,
+            "Restricted writes",
+            "Relay only accepts some events, in the relay list"
+        ));
+    }
+
+    if let Some(max) = limitation.max_subscriptions {
+        notes.push(tr!(
+            i18n,
+            "Max {count} subscriptions",
+            "Most subscriptions a relay allows at once, in the relay list",
+            count = max
+        ));
+    }
+
+    if let Some(max) = limitation.max_filters {
+        notes.push(tr!(
+            i18n,
+            "Max {count} filters",
+            "Most filters a relay allows per subscription, in the relay list",
+            count = max
+        ));
+    }
+
+    if !document.supported_nips.is_empty() {
+        notes.push(tr!(
+            i18n,
+            "{count} NIPs",
+            "Number of NIPs a relay supports, in the relay list",
+            count = document.supported_nips.len()
+        ));
+    }
+
+    if notes.is_empty() {
+        return;
+    }
+
+    ui.add_space(4.0);
+    let resp = ui.label(
+        RichText::new(notes.join(" · "))
+            .size(12.0)
+            .color(ui.visuals().weak_text_color()),
+    );
+
+    if !document.supported_nips.is_empty() {
+        let nips = document
+            .supported_nips
+            .iter()
+            .map(|nip| nip.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        resp.on_hover_text(tr!(
+            i18n,
+            "Supported NIPs: {nips}",
+            "Hover text listing the NIPs a relay supports, in the relay list",
+            nips = nips
+        ));
+    }
+}
+
 fn get_connection_icon(status: RelayStatus) -> egui::Image<'static> {
     match status {
         RelayStatus::Connected => app_images::connected_image(),
@@ -275,6 +521,9 @@ fn get_connection_icon(status: RelayStatus) -> egui::Image<'static> {
 struct RelayInfo<'a> {
     pub relay_url: &'a str,
     pub status: RelayStatus,
+    pub auth: Option<&'a RelayAuthState>,
+    pub health: Option<&'a RelayHealth>,
+    pub document: Option<&'a RelayInformation>,
 }
 
 fn get_relay_infos(pool: &RelayPool) -> Vec<RelayInfo<'_>> {
@@ -283,6 +532,9 @@ fn get_relay_infos(pool: &RelayPool) -> Vec<RelayInfo<'_>> {
         .map(|relay| RelayInfo {
             relay_url: relay.url(),
             status: relay.status(),
+            auth: relay.auth_state(),
+            health: relay.health(),
+            document: relay.info(),
         })
         .collect()
 }
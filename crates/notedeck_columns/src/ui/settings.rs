@@ -3,18 +3,20 @@ use egui::{
     ScrollArea, TextEdit, ThemePreference,
 };
 use egui_extras::{Size, StripBuilder};
-use enostr::NoteId;
+use enostr::{NoteId, Pubkey};
 use nostrdb::Transaction;
 use notedeck::{
+    name::get_display_name,
     tr,
     ui::{is_narrow, richtext_small},
-    Images, JobsCache, LanguageIdentifier, Localization, NoteContext, NotedeckTextStyle, Settings,
-    SettingsHandler, DEFAULT_NOTE_BODY_FONT_SIZE,
+    Accounts, Images, JobsCache, LanguageIdentifier, Localization, MuteItem, NoteContext,
+    NotedeckTextStyle, Outbox, Settings, SettingsHandler, DEFAULT_NOTE_BODY_FONT_SIZE,
 };
 use notedeck_ui::{
     app_images::{copy_to_clipboard_dark_image, copy_to_clipboard_image},
     AnimationHelper, NoteOptions, NoteView,
 };
+use tracing::error;
 
 use crate::{
     nav::{BodyResponse, RouterAction},
@@ -38,15 +40,22 @@ pub enum SettingsAction {
     OpenRelays,
     OpenCacheFolder,
     ClearCacheFolder,
+    SetPassphrase(Option<String>),
+    LockKeys,
+    Mute { item: MuteItem, private: bool },
+    Unmute(MuteItem),
 }
 
 impl SettingsAction {
+    #[allow(clippy::too_many_arguments)]
     pub fn process_settings_action<'a>(
         self,
         app: &mut Damus,
         settings: &'a mut SettingsHandler,
         i18n: &'a mut Localization,
         img_cache: &mut Images,
+        accounts: &mut Accounts,
+        outbox: &mut Outbox,
         ctx: &egui::Context,
     ) -> Option<RouterAction> {
         let mut route_action: Option<RouterAction> = None;
@@ -79,6 +88,20 @@ impl SettingsAction {
             Self::ClearCacheFolder => {
                 let _ = img_cache.clear_folder_contents();
             }
+            Self::SetPassphrase(passphrase) => {
+                if let Err(err) = accounts.set_passphrase(passphrase.as_deref()) {
+                    error!("could not change the keystore passphrase: {err}");
+                }
+            }
+            Self::LockKeys => {
+                accounts.lock();
+            }
+            Self::Mute { item, private } => {
+                accounts.mute_item(outbox, item, private);
+            }
+            Self::Unmute(item) => {
+                accounts.unmute_item(outbox, &item);
+            }
             Self::SetNoteBodyFontSize(size) => {
                 let mut style = (*ctx.style()).clone();
                 style.text_styles.insert(
@@ -622,6 +645,259 @@ impl<'a> SettingsView<'a> {
         });
     }
 
+    fn passphrase_section(&mut self, ui: &mut egui::Ui) -> Option<SettingsAction> {
+        let accounts = self.note_context.accounts;
+        if !accounts.can_store_keys() {
+            return None;
+        }
+
+        let mut action = None;
+        let title = tr!(
+            self.note_context.i18n,
+            "Passphrase",
+            "label for the keystore passphrase setting section"
+        );
+
+        settings_group(ui, title, |ui| {
+            if accounts.has_passphrase() {
+                ui.label(richtext_small(tr!(
+                    self.note_context.i18n,
+                    "Your secret keys are locked with a passphrase.",
+                    "Explanation when the keystore has a passphrase"
+                )));
+                ui.label(richtext_small(tr!(
+                    self.note_context.i18n,
+                    "Notedeck asks for it on startup and after a while without input.",
+                    "Explanation of when the keystore passphrase is needed"
+                )));
+
+                ui.horizontal_wrapped(|ui| {
+                    if ui
+                        .button(richtext_small(tr!(
+                            self.note_context.i18n,
+                            "Lock now",
+                            "Button to lock the keystore right away"
+                        )))
+                        .clicked()
+                    {
+                        action = Some(SettingsAction::LockKeys);
+                    }
+
+                    if ui
+                        .button(richtext_small(tr!(
+                            self.note_context.i18n,
+                            "Remove passphrase",
+                            "Button to store secret keys without a passphrase again"
+                        )))
+                        .clicked()
+                    {
+                        action = Some(SettingsAction::SetPassphrase(None));
+                    }
+                });
+                return;
+            }
+
+            ui.label(richtext_small(tr!(
+                self.note_context.i18n,
+                "Lock your secret keys with a passphrase.",
+                "Explanation for setting a keystore passphrase"
+            )));
+            ui.label(richtext_small(tr!(
+                self.note_context.i18n,
+                "Notedeck asks for it on startup and after a while without input.",
+                "Explanation of when the keystore passphrase is needed"
+            )));
+
+            let id = ui.id().with("new-passphrase");
+            let (mut passphrase, mut confirm) = ui
+                .ctx()
+                .data_mut(|d| d.get_temp::<(String, String)>(id))
+                .unwrap_or_default();
+
+            ui.add(
+                TextEdit::singleline(&mut passphrase)
+                    .password(true)
+                    .hint_text(tr!(
+                        self.note_context.i18n,
+                        "Passphrase",
+                        "Hint for the new keystore passphrase field"
+                    )),
+            );
+            ui.add(
+                TextEdit::singleline(&mut confirm)
+                    .password(true)
+                    .hint_text(tr!(
+                        self.note_context.i18n,
+                        "Repeat passphrase",
+                        "Hint for the field confirming the new keystore passphrase"
+                    )),
+            );
+
+            let matches = !passphrase.is_empty() && passphrase == confirm;
+            if !confirm.is_empty() && !matches {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    richtext_small(tr!(
+                        self.note_context.i18n,
+                        "Passphrases don't match",
+                        "Error when the repeated keystore passphrase is different"
+                    )),
+                );
+            }
+
+            let set = ui.add_enabled(
+                matches,
+                Button::new(richtext_small(tr!(
+                    self.note_context.i18n,
+                    "Set passphrase",
+                    "Button to lock secret keys with the new passphrase"
+                ))),
+            );
+            if set.clicked() {
+                action = Some(SettingsAction::SetPassphrase(Some(passphrase.clone())));
+                ui.ctx().data_mut(|d| d.remove::<(String, String)>(id));
+            } else {
+                ui.ctx()
+                    .data_mut(|d| d.insert_temp(id, (passphrase, confirm)));
+            }
+        });
+
+        action
+    }
+
+    /// The account's NIP-51 mute list. Needs an account we can sign for,
+    /// since every change publishes the list again.
+    fn muted_section(&mut self, ui: &mut egui::Ui) -> Option<SettingsAction> {
+        let accounts = self.note_context.accounts;
+        if accounts.selected_poster().is_none() {
+            return None;
+        }
+
+        let mut action = None;
+        let muted = accounts.mute();
+        let can_mute_privately = accounts.can_mute_privately();
+        let title = tr!(
+            self.note_context.i18n,
+            "Muted",
+            "label for the mute list setting section"
+        );
+
+        settings_group(ui, title, |ui| {
+            let items = muted.items();
+            if items.is_empty() {
+                ui.label(richtext_small(tr!(
+                    self.note_context.i18n,
+                    "You haven't muted anything.",
+                    "Shown when the mute list is empty"
+                )));
+            }
+
+            let txn = Transaction::new(self.note_context.ndb).ok();
+            for item in items {
+                let label = match &item {
+                    MuteItem::Pubkey(pk) => txn
+                        .as_ref()
+                        .and_then(|txn| {
+                            let profile = self.note_context.ndb.get_profile_by_pubkey(txn, pk);
+                            profile
+                                .ok()
+                                .map(|p| get_display_name(Some(&p)).name().to_owned())
+                        })
+                        .unwrap_or_else(|| Pubkey::new(*pk).npub().unwrap_or_default()),
+                    MuteItem::Thread(id) => {
+                        let thread = NoteId::new(*id).to_bech().unwrap_or_default();
+                        tr!(
+                            self.note_context.i18n,
+                            "Thread {thread}",
+                            "A muted thread in the mute list",
+                            thread = thread
+                        )
+                    }
+                    MuteItem::Hashtag(hashtag) => format!("#{hashtag}"),
+                    MuteItem::Word(word) => format!("\"{word}\""),
+                };
+
+                item_frame(ui).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Label::new(richtext_small(label)).truncate());
+
+                        ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui
+                                .button(richtext_small(tr!(
+                                    self.note_context.i18n,
+                                    "Unmute",
+                                    "Button to take an entry off the mute list"
+                                )))
+                                .clicked()
+                            {
+                                action = Some(SettingsAction::Unmute(item.clone()));
+                            }
+
+                            if muted.is_private(&item) {
+                                ui.label(
+                                    richtext_small(tr!(
+                                        self.note_context.i18n,
+                                        "private",
+                                        "Marks a mute list entry only the user can see"
+                                    ))
+                                    .color(ui.visuals().gray_out(ui.visuals().text_color())),
+                                );
+                            }
+                        });
+                    });
+                });
+            }
+
+            let id = ui.id().with("new-mute");
+            let (mut input, mut private) = ui
+                .ctx()
+                .data_mut(|d| d.get_temp::<(String, bool)>(id))
+                .unwrap_or_default();
+
+            ui.add(
+                TextEdit::singleline(&mut input)
+                    .hint_text(tr!(
+                        self.note_context.i18n,
+                        "npub, #hashtag, word or phrase",
+                        "Hint for the field adding an entry to the mute list"
+                    ))
+                    .desired_width(f32::INFINITY),
+            );
+
+            let new_item = parse_mute_input(&input);
+            ui.horizontal(|ui| {
+                if can_mute_privately {
+                    ui.checkbox(
+                        &mut private,
+                        richtext_small(tr!(
+                            self.note_context.i18n,
+                            "Private",
+                            "Checkbox to encrypt a new mute list entry"
+                        )),
+                    );
+                }
+
+                let add = ui.add_enabled(
+                    new_item.is_some(),
+                    Button::new(richtext_small(tr!(
+                        self.note_context.i18n,
+                        "Mute",
+                        "Button to add an entry to the mute list"
+                    ))),
+                );
+
+                if let (true, Some(item)) = (add.clicked(), new_item) {
+                    action = Some(SettingsAction::Mute { item, private });
+                    input.clear();
+                }
+            });
+
+            ui.ctx().data_mut(|d| d.insert_temp(id, (input, private)));
+        });
+
+        action
+    }
+
     fn manage_relays_section(&mut self, ui: &mut egui::Ui) -> Option<SettingsAction> {
         let mut action = None;
 
@@ -664,6 +940,18 @@ impl<'a> SettingsView<'a> {
 
                     ui.add_space(5.0);
 
+                    if let Some(new_action) = self.passphrase_section(ui) {
+                        action = Some(new_action);
+                    }
+
+                    ui.add_space(5.0);
+
+                    if let Some(new_action) = self.muted_section(ui) {
+                        action = Some(new_action);
+                    }
+
+                    ui.add_space(5.0);
+
                     if let Some(new_action) = self.other_options_section(ui) {
                         action = Some(new_action);
                     }
@@ -682,6 +970,30 @@ impl<'a> SettingsView<'a> {
     }
 }
 
+/// What the mute list field holds: a user's npub or hex key, a hashtag
+/// starting with `#`, or a word or phrase
+fn parse_mute_input(input: &str) -> Option<MuteItem> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    if let Ok(pk) = Pubkey::parse(input) {
+        return Some(MuteItem::Pubkey(*pk.bytes()));
+    }
+
+    let item = if input.starts_with('#') {
+        MuteItem::hashtag(input)
+    } else {
+        MuteItem::word(input)
+    };
+
+    match &item {
+        MuteItem::Hashtag(name) | MuteItem::Word(name) if name.is_empty() => None,
+        _ => Some(item),
+    }
+}
+
 pub fn format_size(size_bytes: u64) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;
@@ -181,7 +181,7 @@ fn show_notes(
         .ok()
         .is_some_and(|root_id| is_muted(&note.note, root_id.bytes()));
 
-        if muted {
+        if muted || note_context.deletions.is_deleted(&note.note) {
             return 1;
         }
 
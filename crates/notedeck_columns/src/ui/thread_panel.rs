@@ -1,19 +1,77 @@
-use egui::{Color32, RichText, Sense, Vec2};
+use egui::{Color32, Margin, RichText, Sense, Vec2};
+use enostr::{NoteId, Pubkey};
+use nostrdb::Transaction;
 use notedeck::{tr, JobsCache, NoteAction, NoteContext};
 use notedeck_ui::NoteOptions;
+use tracing::warn;
 
+use crate::draft::{Draft, Drafts};
 use crate::timeline::thread::Threads;
+use crate::ui::chat_composer::{ChatComposer, ChatComposerResponse};
+use crate::ui::note::{NewPostAction, PostType};
 use crate::ui::ThreadView;
 
 pub const THREAD_PANEL_WIDTH: f32 = 420.0;
 
+/// Repliers shown in a reply summary
+const MAX_SUMMARY_PARTICIPANTS: usize = 3;
+
+/// How many replies a message has, who wrote the latest ones and when the
+/// newest was posted
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ReplySummary {
+    pub count: usize,
+    /// Newest repliers first, each once
+    pub participants: Vec<Pubkey>,
+    pub last_reply: u64,
+}
+
+/// Summarize replies given as their author and creation time, in any
+/// order. None when there are no replies.
+pub(crate) fn summarize_replies(
+    replies: impl IntoIterator<Item = ([u8; 32], u64)>,
+) -> Option<ReplySummary> {
+    let mut replies: Vec<([u8; 32], u64)> = replies.into_iter().collect();
+    replies.sort_by(|a, b| b.1.cmp(&a.1));
+    let last_reply = replies.first()?.1;
+
+    let mut participants: Vec<Pubkey> = Vec::with_capacity(MAX_SUMMARY_PARTICIPANTS);
+    for (author, _) in &replies {
+        if participants.len() == MAX_SUMMARY_PARTICIPANTS {
+            break;
+        }
+
+        let author = Pubkey::new(*author);
+        if !participants.contains(&author) {
+            participants.push(author);
+        }
+    }
+
+    Some(ReplySummary {
+        count: replies.len(),
+        participants,
+        last_reply,
+    })
+}
+
 pub struct ThreadPanel {
     pub is_open: bool,
     pub selected_thread_id: Option<[u8; 32]>,
+    /// Focus the reply composer the next time the panel is shown
+    focus_composer: bool,
 }
 
 pub enum ThreadPanelAction {
     Close,
+    /// A reply was composed in the panel
+    Post(NewPostAction),
+}
+
+#[derive(Default)]
+pub struct ThreadPanelResponse {
+    pub action: Option<ThreadPanelAction>,
+    pub note_action: Option<NoteAction>,
+    pub can_take_drag_from: Vec<egui::Id>,
 }
 
 impl ThreadPanel {
@@ -21,6 +79,7 @@ impl ThreadPanel {
         Self {
             is_open: false,
             selected_thread_id: None,
+            focus_composer: false,
         }
     }
 
@@ -29,25 +88,36 @@ impl ThreadPanel {
         self.selected_thread_id = Some(thread_id);
     }
 
+    /// Open the thread and put the cursor in its reply composer
+    pub fn reply(&mut self, thread_id: [u8; 32]) {
+        self.open(thread_id);
+        self.focus_composer = true;
+    }
+
     pub fn close(&mut self) {
         self.is_open = false;
     }
 
+    /// `reply_relays` are the relays replies are published to, taken from
+    /// the channel the thread was opened from. Empty means every relay in
+    /// the pool, and `None` means replies can't be sent from the channel.
+    #[allow(clippy::too_many_arguments)]
     pub fn show<'a, 'd>(
         &mut self,
         ui: &mut egui::Ui,
         threads: &'a mut Threads,
+        drafts: &'a mut Drafts,
+        reply_relays: Option<Vec<String>>,
         note_options: NoteOptions,
         note_context: &'a mut NoteContext<'d>,
         jobs: &'a mut JobsCache,
         col: usize,
-    ) -> (Option<ThreadPanelAction>, Option<NoteAction>) {
-        if !self.is_open || self.selected_thread_id.is_none() {
-            return (None, None);
-        }
+    ) -> ThreadPanelResponse {
+        let mut response = ThreadPanelResponse::default();
 
-        let mut panel_action: Option<ThreadPanelAction> = None;
-        let mut note_action: Option<NoteAction> = None;
+        let Some(thread_id) = self.selected_thread_id.filter(|_| self.is_open) else {
+            return response;
+        };
 
         let screen_rect = ui.ctx().screen_rect();
         let panel_width = THREAD_PANEL_WIDTH;
@@ -65,16 +135,17 @@ impl ThreadPanel {
         );
 
         // Draw overlay
-        ui.painter().rect_filled(
-            overlay_rect,
-            0.0,
-            Color32::from_black_alpha(100),
-        );
+        ui.painter()
+            .rect_filled(overlay_rect, 0.0, Color32::from_black_alpha(100));
 
         // Handle click on overlay to close
-        let overlay_response = ui.interact(overlay_rect, egui::Id::new("thread_panel_overlay"), Sense::click());
+        let overlay_response = ui.interact(
+            overlay_rect,
+            egui::Id::new("thread_panel_overlay"),
+            Sense::click(),
+        );
         if overlay_response.clicked() {
-            panel_action = Some(ThreadPanelAction::Close);
+            response.action = Some(ThreadPanelAction::Close);
         }
 
         // Draw the panel
@@ -106,33 +177,65 @@ impl ThreadPanel {
                                 ui.add_space(16.0);
 
                                 ui.label(
-                                    RichText::new(tr!(note_context.i18n, "Thread", "Thread panel header"))
-                                        .size(16.0)
-                                        .strong(),
+                                    RichText::new(tr!(
+                                        note_context.i18n,
+                                        "Thread",
+                                        "Thread panel header"
+                                    ))
+                                    .size(16.0)
+                                    .strong(),
                                 );
 
-                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                    ui.add_space(16.0);
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        ui.add_space(16.0);
 
-                                    // Close button (X)
-                                    let close_btn = ui.button(RichText::new("✕").size(20.0));
+                                        // Close button (X)
+                                        let close_btn = ui.button(RichText::new("✕").size(20.0));
 
-                                    if close_btn.clicked() {
-                                        panel_action = Some(ThreadPanelAction::Close);
-                                    }
-                                });
+                                        if close_btn.clicked() {
+                                            response.action = Some(ThreadPanelAction::Close);
+                                        }
+                                    },
+                                );
                             });
 
                             ui.add_space(8.0);
                             ui.separator();
                             ui.add_space(8.0);
 
-                            // Thread content
-                            if let Some(thread_id) = &self.selected_thread_id {
+                            // Reply composer docked to the bottom, the
+                            // thread fills the rest of the panel
+                            let body_rect =
+                                egui::Rect::from_min_max(ui.cursor().min, panel_rect.max);
+                            ui.allocate_new_ui(egui::UiBuilder::new().max_rect(body_rect), |ui| {
+                                egui::TopBottomPanel::bottom(egui::Id::new((
+                                    "thread_reply_composer",
+                                    thread_id,
+                                )))
+                                .frame(egui::Frame::new().inner_margin(Margin::symmetric(16, 12)))
+                                .show_inside(ui, |ui| {
+                                    let draft = thread_draft(drafts, &thread_id);
+                                    let composer_resp = self.composer_ui(
+                                        ui,
+                                        note_context,
+                                        draft,
+                                        &thread_id,
+                                        reply_relays,
+                                    );
+                                    response
+                                        .can_take_drag_from
+                                        .extend(composer_resp.mention_hints_drag_id);
+                                    if let Some(action) = composer_resp.action {
+                                        response.action = Some(ThreadPanelAction::Post(action));
+                                    }
+                                });
+
                                 // ThreadView will handle the case where thread doesn't exist
                                 let thread_resp = ThreadView::new(
                                     threads,
-                                    thread_id,
+                                    &thread_id,
                                     note_options,
                                     note_context,
                                     jobs,
@@ -140,16 +243,77 @@ impl ThreadPanel {
                                 )
                                 .ui(ui);
 
-                                if let Some(action) = thread_resp.output {
-                                    note_action = Some(action);
-                                }
-                            }
+                                response.can_take_drag_from.extend(thread_resp.drag_id);
+                                response.note_action = thread_resp.output;
+                            });
                         });
                     });
             });
 
-        (panel_action, note_action)
+        response
     }
+
+    fn composer_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_context: &mut NoteContext<'_>,
+        draft: &mut Draft,
+        thread_id: &[u8; 32],
+        reply_relays: Option<Vec<String>>,
+    ) -> ChatComposerResponse {
+        let Some(relays) = reply_relays else {
+            ui.label(
+                RichText::new(tr!(
+                    note_context.i18n,
+                    "Replies can't be sent from this channel",
+                    "Shown in place of the thread reply composer for read-only channels"
+                ))
+                .color(ui.visuals().weak_text_color()),
+            );
+            return ChatComposerResponse::default();
+        };
+
+        let accounts = note_context.accounts;
+        let Some(poster) = accounts.selected_poster() else {
+            ui.label(
+                RichText::new(tr!(
+                    note_context.i18n,
+                    "Add an account with a private key to reply",
+                    "Shown in place of the thread reply composer for read-only accounts"
+                ))
+                .color(ui.visuals().weak_text_color()),
+            );
+            return ChatComposerResponse::default();
+        };
+
+        let Ok(txn) = Transaction::new(note_context.ndb) else {
+            warn!("Failed to create transaction for thread reply composer");
+            return ChatComposerResponse::default();
+        };
+
+        let hint_text = tr!(
+            note_context.i18n,
+            "Reply in thread",
+            "Placeholder for the thread reply input field"
+        );
+
+        ChatComposer::new(
+            note_context,
+            draft,
+            PostType::Reply(NoteId::new(*thread_id)),
+            poster,
+        )
+        .relays(relays)
+        .hint_text(hint_text)
+        .request_focus(std::mem::take(&mut self.focus_composer))
+        .ui(&txn, ui)
+    }
+}
+
+/// The docked composer's draft. There's one per thread root, the same one
+/// posting the [`PostType::Reply`] clears.
+fn thread_draft<'a>(drafts: &'a mut Drafts, thread_id: &[u8; 32]) -> &'a mut Draft {
+    drafts.get_from_post_type(&PostType::Reply(NoteId::new(*thread_id)))
 }
 
 impl Default for ThreadPanel {
@@ -157,3 +321,49 @@ impl Default for ThreadPanel {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::TextBuffer;
+
+    #[test]
+    fn test_no_replies() {
+        assert_eq!(summarize_replies(Vec::<([u8; 32], u64)>::new()), None);
+    }
+
+    #[test]
+    fn test_summarize_replies() {
+        let (a, b, c, d) = ([1; 32], [2; 32], [3; 32], [4; 32]);
+        let summary = summarize_replies([(a, 10), (b, 30), (a, 40), (c, 20), (d, 5), (b, 35)])
+            .expect("summary");
+
+        assert_eq!(summary.count, 6);
+        assert_eq!(summary.last_reply, 40);
+        // newest repliers first, without repeats, and only the first few
+        assert_eq!(
+            summary.participants,
+            vec![Pubkey::new(a), Pubkey::new(b), Pubkey::new(c)]
+        );
+    }
+
+    #[test]
+    fn test_one_draft_per_thread() {
+        let mut drafts = Drafts::default();
+        let (a, b) = ([1; 32], [2; 32]);
+
+        thread_draft(&mut drafts, &a).buffer.insert_text("gm", 0);
+        assert!(thread_draft(&mut drafts, &b).buffer.is_empty());
+
+        // switching threads and back keeps the text
+        thread_draft(&mut drafts, &b).buffer.insert_text("gn", 0);
+        assert_eq!(thread_draft(&mut drafts, &a).buffer.as_str(), "gm");
+
+        // posting the reply clears the draft it was written in
+        drafts
+            .get_from_post_type(&PostType::Reply(NoteId::new(a)))
+            .clear();
+        assert!(thread_draft(&mut drafts, &a).buffer.is_empty());
+        assert_eq!(thread_draft(&mut drafts, &b).buffer.as_str(), "gn");
+    }
+}
@@ -468,7 +468,7 @@ impl<'a, 'd> TimelineTabView<'a, 'd> {
         )
         .is_ok_and(|root_id| mute.is_muted(&underlying_note, root_id.bytes()));
 
-        if muted {
+        if muted || self.note_context.deletions.is_deleted(&underlying_note) {
             return RenderEntryResponse::Success(None);
         }
 
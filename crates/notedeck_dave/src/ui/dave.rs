@@ -223,6 +223,8 @@ impl<'a> DaveUi<'a> {
             clipboard: ctx.clipboard,
             i18n: ctx.i18n,
             global_wallet: ctx.global_wallet,
+            outbox: ctx.outbox,
+            deletions: ctx.deletions,
         };
 
         let txn = Transaction::new(note_context.ndb).unwrap();
@@ -1,6 +1,6 @@
 use egui::{Rect, Vec2};
-use nostrdb::NoteKey;
-use notedeck::{tr, BroadcastContext, Localization, NoteContextSelection};
+use nostrdb::{Note, NoteKey};
+use notedeck::{tr, BroadcastContext, Localization, MuteItem, NoteContextSelection};
 
 use crate::context_menu::{context_button, stationary_arbitrary_menu_button};
 
@@ -60,11 +60,16 @@ impl NoteContextButton {
         context_button(ui, id, put_at)
     }
 
+    /// `thread` is the root of the note's thread, muted by "Mute thread".
+    /// Our own notes can be deleted, but can't mute their author.
     #[profiling::function]
     pub fn menu(
         ui: &mut egui::Ui,
         i18n: &mut Localization,
         button_response: egui::Response,
+        note: &Note,
+        thread: &[u8; 32],
+        is_own_note: bool,
     ) -> Option<NoteContextSelection> {
         let mut context_selection: Option<NoteContextSelection> = None;
 
@@ -154,8 +159,118 @@ impl NoteContextButton {
                 ));
                 ui.close_menu();
             }
+
+            ui.separator();
+
+            if is_own_note
+                && ui
+                    .button(tr!(
+                        i18n,
+                        "Delete",
+                        "Ask relays to delete the user's own note"
+                    ))
+                    .clicked()
+            {
+                context_selection = Some(NoteContextSelection::Delete);
+                ui.close_menu();
+            }
+
+            if let Some(item) = mute_menu(ui, i18n, note, thread, is_own_note) {
+                context_selection = Some(NoteContextSelection::Mute(item));
+                ui.close_menu();
+            }
         });
 
         context_selection
     }
 }
+
+fn mute_menu(
+    ui: &mut egui::Ui,
+    i18n: &mut Localization,
+    note: &Note,
+    thread: &[u8; 32],
+    is_own_note: bool,
+) -> Option<MuteItem> {
+    let mut item = None;
+
+    if !is_own_note
+        && ui
+            .button(tr!(i18n, "Mute user", "Mute the author of the note"))
+            .clicked()
+    {
+        item = Some(MuteItem::Pubkey(*note.pubkey()));
+    }
+
+    if ui
+        .button(tr!(
+            i18n,
+            "Mute thread",
+            "Mute the thread the note belongs to"
+        ))
+        .clicked()
+    {
+        item = Some(MuteItem::Thread(*thread));
+    }
+
+    let mut hashtags: Vec<MuteItem> = note
+        .tags()
+        .into_iter()
+        .filter(|tag| tag.count() >= 2 && tag.get_str(0) == Some("t"))
+        .filter_map(|tag| tag.get_str(1).map(MuteItem::hashtag))
+        .collect();
+    hashtags.sort();
+    hashtags.dedup();
+
+    if !hashtags.is_empty() {
+        ui.menu_button(
+            tr!(i18n, "Mute hashtag", "Mute one of the note's hashtags"),
+            |ui| {
+                for hashtag in hashtags {
+                    let MuteItem::Hashtag(name) = &hashtag else {
+                        continue;
+                    };
+
+                    if ui.button(format!("#{name}")).clicked() {
+                        item = Some(hashtag);
+                        ui.close_menu();
+                    }
+                }
+            },
+        );
+    }
+
+    ui.menu_button(
+        tr!(i18n, "Mute word", "Mute notes containing a word or phrase"),
+        |ui| {
+            let id = ui.id().with(("mute_word", note.id()));
+            let mut word: String = ui.data_mut(|d| d.get_temp(id)).unwrap_or_default();
+
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut word)
+                    .hint_text(tr!(i18n, "Word or phrase", "Hint for the word to mute"))
+                    .desired_width(160.0),
+            );
+            let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+            let mute_word = MuteItem::word(&word);
+            let can_mute = !matches!(&mute_word, MuteItem::Word(w) if w.is_empty());
+            let clicked = ui
+                .add_enabled(
+                    can_mute,
+                    egui::Button::new(tr!(i18n, "Mute", "Button to mute the word")),
+                )
+                .clicked();
+
+            if can_mute && (submitted || clicked) {
+                item = Some(mute_word);
+                word.clear();
+                ui.close_menu();
+            }
+
+            ui.data_mut(|d| d.insert_temp(id, word));
+        },
+    );
+
+    item
+}
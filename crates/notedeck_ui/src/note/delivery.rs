@@ -0,0 +1,59 @@
+use egui::{Label, RichText, Sense};
+use notedeck::{tr, tr_plural, DeliveryStatus, Localization};
+
+/// Small status mark shown next to notes we published: a clock while no
+/// relay has confirmed it yet, a check once one has, and a warning when
+/// every relay rejected it. Hovering shows the per-relay details.
+pub fn delivery_indicator(
+    ui: &mut egui::Ui,
+    i18n: &mut Localization,
+    status: &DeliveryStatus,
+) -> egui::Response {
+    let (icon, color) = if status.is_failed() {
+        ("⚠", ui.visuals().error_fg_color)
+    } else if status.is_delivered() {
+        ("✔", ui.visuals().weak_text_color())
+    } else {
+        ("🕓", ui.visuals().weak_text_color())
+    };
+
+    let mut hover = if status.is_failed() {
+        tr!(
+            i18n,
+            "Not delivered",
+            "Hover text of the delivery indicator when every relay rejected a note"
+        )
+    } else if status.is_delivered() {
+        tr_plural!(
+            i18n,
+            "Delivered to {count} relay",
+            "Delivered to {count} relays",
+            "Hover text of the delivery indicator on a note relays accepted",
+            status.accepted,
+        )
+    } else {
+        tr!(
+            i18n,
+            "Sending…",
+            "Hover text of the delivery indicator while no relay has confirmed a note"
+        )
+    };
+
+    if status.is_delivered() && status.pending > 0 {
+        hover.push('\n');
+        hover.push_str(&tr_plural!(
+            i18n,
+            "Waiting on {count} relay",
+            "Waiting on {count} relays",
+            "Hover text of the delivery indicator, relays that haven't confirmed a note yet",
+            status.pending,
+        ));
+    }
+
+    for (relay, reason) in &status.rejected {
+        hover.push_str(&format!("\n{relay}: {reason}"));
+    }
+
+    ui.add(Label::new(RichText::new(icon).size(11.0).color(color)).sense(Sense::hover()))
+        .on_hover_text(hover)
+}
@@ -1,5 +1,6 @@
 pub mod contents;
 pub mod context;
+pub mod delivery;
 pub mod media;
 pub mod options;
 pub mod reply_description;
@@ -9,10 +10,12 @@ use crate::{widgets::x_button, ProfilePic, ProfilePreview, PulseAlpha, Username}
 
 pub use contents::{render_note_preview, NoteContents};
 pub use context::NoteContextButton;
+pub use delivery::delivery_indicator;
 use notedeck::get_current_wallet;
 use notedeck::note::{reaction_sent_id, ZapTargetAmount};
 use notedeck::ui::is_narrow;
 use notedeck::Accounts;
+use notedeck::DeliveryStatus;
 use notedeck::GlobalWallet;
 use notedeck::Images;
 use notedeck::JobsCache;
@@ -345,14 +348,19 @@ impl<'a, 'd> NoteView<'a, 'd> {
         note: &Note,
         profile: &Result<nostrdb::ProfileRecord<'_>, nostrdb::Error>,
         flags: NoteOptions,
+        delivery: Option<&DeliveryStatus>,
     ) {
         let horiz_resp = ui
             .horizontal_wrapped(|ui| {
                 ui.spacing_mut().item_spacing.x = if is_narrow(ui.ctx()) { 1.0 } else { 2.0 };
-                let response = ui
+                let mut response = ui
                     .add(Username::new(i18n, profile.as_ref().ok(), note.pubkey()).abbreviated(20));
                 if !flags.contains(NoteOptions::FullCreatedDate) {
-                    return render_notetime(ui, i18n, note.created_at(), true);
+                    response = render_notetime(ui, i18n, note.created_at(), true);
+                }
+                if let Some(status) = delivery {
+                    ui.add_space(4.0);
+                    delivery_indicator(ui, i18n, status);
                 }
                 response
             })
@@ -378,6 +386,8 @@ impl<'a, 'd> NoteView<'a, 'd> {
         note_key: NoteKey,
         profile: &Result<ProfileRecord, nostrdb::Error>,
     ) -> egui::InnerResponse<NoteUiResponse> {
+        let delivery = self.note_context.outbox.status(self.note.id());
+
         ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
             let mut note_action: Option<NoteAction> = None;
             let mut pfp_rect = None;
@@ -403,6 +413,7 @@ impl<'a, 'd> NoteView<'a, 'd> {
                                         self.note,
                                         profile,
                                         self.flags,
+                                        delivery.as_ref(),
                                     );
                                 })
                                 .response
@@ -499,6 +510,8 @@ impl<'a, 'd> NoteView<'a, 'd> {
         note_key: NoteKey,
         profile: &Result<ProfileRecord, nostrdb::Error>,
     ) -> egui::InnerResponse<NoteUiResponse> {
+        let delivery = self.note_context.outbox.status(self.note.id());
+
         // main design
         ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
             let (mut note_action, pfp_rect) =
@@ -519,6 +532,7 @@ impl<'a, 'd> NoteView<'a, 'd> {
                         self.note,
                         profile,
                         self.flags,
+                        delivery.as_ref(),
                     );
 
                     ui.horizontal_wrapped(|ui| {
@@ -630,8 +644,27 @@ impl<'a, 'd> NoteView<'a, 'd> {
             };
 
             let resp = ui.add(NoteContextButton::new(note_key).place_at(context_pos));
-            if let Some(action) = NoteContextButton::menu(ui, self.note_context.i18n, resp.clone())
-            {
+
+            // a note without a root starts its own thread
+            let thread = self
+                .note_context
+                .note_cache
+                .cached_note_or_insert_mut(note_key, self.note)
+                .reply
+                .borrow(self.note.tags())
+                .root()
+                .map_or(*self.note.id(), |root| *root.id);
+            let is_own_note =
+                self.note_context.accounts.selected_account_pubkey().bytes() == self.note.pubkey();
+
+            if let Some(action) = NoteContextButton::menu(
+                ui,
+                self.note_context.i18n,
+                resp.clone(),
+                self.note,
+                &thread,
+                is_own_note,
+            ) {
                 note_action = Some(NoteAction::Context(ContextSelection { note_key, action }));
             }
         }
@@ -655,12 +688,11 @@ fn get_zapper<'a>(
     zaps: &'a Zaps,
 ) -> Option<Zapper<'a>> {
     let has_wallet = get_current_wallet(accounts, global_wallet).is_some();
-    let cur_acc = accounts.get_selected_account();
 
-    has_wallet.then_some(Zapper {
-        zaps,
-        cur_acc: cur_acc.keypair(),
-    })
+    // zap requests are signed by the account's secret key or remote signer
+    let cur_acc = accounts.selected_poster()?;
+
+    has_wallet.then_some(Zapper { zaps, cur_acc })
 }
 
 pub fn get_reposted_note<'a>(ndb: &Ndb, txn: &'a Transaction, note: &Note) -> Option<Note<'a>> {
@@ -840,8 +872,6 @@ fn zap_actionbar_button(
         zap_recipient: Pubkey::new(*note_pubkey),
     };
 
-    cur_acc.secret_key.as_ref()?;
-
     match zap_state {
         Ok(any_zap_state) => {
             let zap_resp = ui.add(zap_button(i18n, any_zap_state, note_id));